@@ -0,0 +1,71 @@
+// Times styling a large generated page, with and without the rule hash, the ancestor filter and
+// style sharing. Run with: cargo run --release --example style_bench
+
+use parsnip::parsing::css_parser::CssParser;
+use parsnip::parsing::html_parser::HtmlParser;
+use parsnip::style::style_tree::{style_tree, style_tree_unoptimized};
+use parsnip::style::values::Viewport;
+use std::time::Instant;
+
+/// Creates a page of nested sections, each with a list of items.
+fn page(sections: usize, items: usize) -> String {
+    let mut html = String::from("<html><body>");
+    for s in 0..sections {
+        html.push_str(&format!(
+            "<div class=\"section s{}\" id=\"section{s}\">",
+            s % 10
+        ));
+        html.push_str(&format!(
+            "<h2 class=\"title\">Section {s}</h2><ul class=\"list\">"
+        ));
+        for i in 0..items {
+            html.push_str(&format!(
+                "<li class=\"item i{}\"><span class=\"label\">Item {i}</span></li>",
+                i % 20
+            ));
+        }
+        html.push_str("</ul></div>");
+    }
+    html.push_str("</body></html>");
+    html
+}
+
+/// Creates a stylesheet with many rules, most of which don't match any given element.
+fn stylesheet(rules: usize) -> String {
+    let mut css = String::new();
+    for r in 0..rules {
+        css.push_str(&match r % 5 {
+            0 => format!(".c{r} {{ color: red; }}\n"),
+            1 => format!("#id{r} {{ margin: {r}px; }}\n"),
+            2 => format!("div.section .i{} {{ padding: 1px; }}\n", r % 20),
+            3 => format!(".other{r} .label {{ font-weight: bold; }}\n"),
+            _ => format!("ul > .i{} {{ margin-left: 2px; }}\n", r % 20),
+        });
+    }
+    css
+}
+
+fn main() {
+    let dom = HtmlParser::parse(page(200, 50));
+    let sheets = vec![CssParser::parse(stylesheet(2000))];
+
+    let runs = 3;
+    let time = |style: &dyn Fn()| {
+        let start = Instant::now();
+        for _ in 0..runs {
+            style();
+        }
+        start.elapsed() / runs
+    };
+
+    let optimized = time(&|| drop(style_tree(&dom, &sheets, Viewport::default())));
+    let unoptimized = time(&|| drop(style_tree_unoptimized(&dom, &sheets, Viewport::default())));
+    println!("Styled in {optimized:?} per run");
+    println!(
+        "Styled without the rule hash, ancestor filter and sharing in {unoptimized:?} per run"
+    );
+    println!(
+        "{:.1}x faster",
+        unoptimized.as_secs_f64() / optimized.as_secs_f64()
+    );
+}
@@ -0,0 +1,662 @@
+// Flex layout, where the children of a flex container are laid out in a row or a column, and grow
+// or shrink to fill it.
+// https://www.w3.org/TR/css-flexbox-1/#layout-algorithm
+
+use crate::box_model::{content_size, distribute, Dimensions, LayoutBox, Rect};
+use crate::style::computed::{
+    Alignment, ComputedStyle, FlexBasis, FlexDirection, FlexWrap, Length, Overflow,
+};
+use crate::text::FontDatabase;
+
+/// A flex container, which its items are laid out in.
+struct FlexContainer<'a, 'f> {
+    fonts: &'f FontDatabase,
+    style: &'a ComputedStyle,
+    /// The content area, which percentages of the items are of.
+    area: Rect,
+    /// The Some height of the content area, or None if it depends on the items.
+    height: Option<f32>,
+    /// Is the main axis horizontal?
+    row: bool,
+}
+
+/// The sizes of a flex item along the main and cross axes. Sizes are of the content box, and the
+/// margins are None if they're `auto`.
+struct FlexItem {
+    /// The index of the item in the container's children.
+    index: usize,
+    grow: f32,
+    shrink: f32,
+    align: Alignment,
+    /// The padding and borders on both sides, along the main and cross axes.
+    main_edges: f32,
+    cross_edges: f32,
+    /// The margins at the main start and end, and the cross start and end.
+    margins: [Option<f32>; 4],
+    /// The flex base size, and the hypothetical main size, which is kept between the minimum and
+    /// the maximum.
+    base: f32,
+    hypothetical: f32,
+    min: f32,
+    max: f32,
+    /// The Some cross size the style sets, or None if it's `auto`, and its minimum and maximum.
+    cross_size: Option<f32>,
+    min_cross: f32,
+    max_cross: f32,
+    /// The used main and cross sizes.
+    main: f32,
+    cross: f32,
+    /// The distance from the cross start of the margin box to the first baseline, for items which
+    /// are aligned by their baselines.
+    ascent: f32,
+}
+
+impl FlexItem {
+    fn margin(&self, i: usize) -> f32 {
+        self.margins[i].unwrap_or(0.0)
+    }
+
+    /// The size of the margin box along the main axis, with `auto` margins as zero.
+    fn outer_main(&self) -> f32 {
+        self.main + self.main_edges + self.margin(0) + self.margin(1)
+    }
+
+    fn outer_cross(&self) -> f32 {
+        self.cross + self.cross_edges + self.margin(2) + self.margin(3)
+    }
+
+    /// Is the item stretched to the cross size of its line?
+    fn stretches(&self) -> bool {
+        self.align == Alignment::Stretch
+            && self.cross_size.is_none()
+            && self.margins[2].is_some()
+            && self.margins[3].is_some()
+    }
+}
+
+/// A line of flex items, with the indices of its first and last items in the items.
+struct FlexLine {
+    start: usize,
+    end: usize,
+    /// The cross size, and where it starts in the container's content area.
+    cross: f32,
+    position: f32,
+}
+
+/// Returns the size of a gap, with percentages of the Some `base` size, which are zero if it isn't
+/// known. A `normal` gap is zero.
+fn gap(gap: &Length, base: Option<f32>) -> f32 {
+    match (gap, base) {
+        (Length::Px(n), _) => *n,
+        (_, Some(base)) => gap.to_px_of(base),
+        _ => 0.0,
+    }
+}
+
+/// Sets the used main sizes of the items on a line, which share the `available` space between
+/// them by their flex factors, and are kept between their minimums and maximums. The items keep
+/// their hypothetical main sizes when the space isn't known.
+/// https://www.w3.org/TR/css-flexbox-1/#resolve-flexible-lengths
+fn resolve_flexible_lengths(items: &mut [FlexItem], available: Option<f32>) {
+    for item in items.iter_mut() {
+        item.main = item.hypothetical;
+    }
+    let Some(available) = available else {
+        return;
+    };
+
+    // The items grow if they don't fill the line, and shrink if they overflow it.
+    let outer: f32 = items.iter().map(FlexItem::outer_main).sum();
+    let grow = outer < available;
+    let factor = |item: &FlexItem| match grow {
+        true => item.grow,
+        false => item.shrink,
+    };
+
+    // Items which can't flex, or which would flex the wrong way, keep their hypothetical size.
+    let mut frozen: Vec<bool> = items
+        .iter()
+        .map(|item| {
+            factor(item) == 0.0
+                || (grow && item.base > item.hypothetical)
+                || (!grow && item.base < item.hypothetical)
+        })
+        .collect();
+    let free_space = |items: &[FlexItem], frozen: &[bool]| {
+        available
+            - items
+                .iter()
+                .zip(frozen)
+                .map(|(item, frozen)| {
+                    let size = if *frozen { item.main } else { item.base };
+                    item.outer_main() - item.main + size
+                })
+                .sum::<f32>()
+    };
+    let initial_free = free_space(items, &frozen);
+
+    while frozen.contains(&false) {
+        let unfrozen = || items.iter().zip(&frozen).filter(|(_, f)| !**f);
+        let factors: f32 = unfrozen().map(|(item, _)| factor(item)).sum();
+        let mut free = free_space(items, &frozen);
+        // Items whose factors add up to less than one only take that fraction of the space.
+        if factors < 1.0 && (initial_free * factors).abs() < free.abs() {
+            free = initial_free * factors;
+        }
+
+        // Shrinking is also in proportion to the base sizes, so small items shrink less.
+        let scaled: f32 = unfrozen().map(|(item, _)| item.shrink * item.base).sum();
+        let mut violations = vec![0.0; items.len()];
+        for (i, item) in items.iter_mut().enumerate() {
+            if frozen[i] {
+                continue;
+            }
+            let share = match grow {
+                true => item.grow / factors,
+                false if scaled > 0.0 => item.shrink * item.base / scaled,
+                false => 0.0,
+            };
+            let target = item.base + free * share;
+            item.main = target.min(item.max).max(item.min).max(0.0);
+            violations[i] = item.main - target;
+        }
+
+        // Items which were made bigger to their minimums are frozen if the sizes had to be made
+        // bigger overall, and the ones made smaller to their maximums if they had to be smaller.
+        let total: f32 = violations.iter().sum();
+        for (i, violation) in violations.into_iter().enumerate() {
+            frozen[i] = frozen[i]
+                || total == 0.0
+                || (total > 0.0 && violation > 0.0)
+                || (total < 0.0 && violation < 0.0);
+        }
+    }
+}
+
+impl<'a> FlexContainer<'a, '_> {
+    /// Returns a flex item for a child of the container, with its flex base size and its
+    /// hypothetical main size. The child is laid out if its size depends on its content.
+    /// https://www.w3.org/TR/css-flexbox-1/#algo-main-item
+    fn item(&self, child: &mut LayoutBox<'a>, index: usize) -> FlexItem {
+        let area = self.area;
+        let main_available = match self.row {
+            true => Some(area.width),
+            false => self.height,
+        };
+        let mut item = FlexItem {
+            index,
+            grow: 0.0,
+            shrink: 1.0,
+            align: self.style.align_items,
+            main_edges: 0.0,
+            cross_edges: 0.0,
+            margins: [Some(0.0); 4],
+            base: 0.0,
+            hypothetical: 0.0,
+            min: 0.0,
+            max: f32::INFINITY,
+            cross_size: None,
+            min_cross: 0.0,
+            max_cross: f32::INFINITY,
+            main: 0.0,
+            cross: 0.0,
+            ascent: 0.0,
+        };
+
+        // An anonymous item has no style of its own, so its properties are their initial values.
+        let (mut basis, mut main_size, mut min) = (None, None, None);
+        let mut content_min = true;
+        if let Some(style) = child.style_node().map(|n| &n.style) {
+            let w = area.width;
+            let horizontal = style.padding.left.to_px_of(w)
+                + style.padding.right.to_px_of(w)
+                + style.border_width.left
+                + style.border_width.right;
+            let vertical = style.padding.top.to_px_of(w)
+                + style.padding.bottom.to_px_of(w)
+                + style.border_width.top
+                + style.border_width.bottom;
+            let m = &style.margin;
+            // Percentages for vertical margins are still relative to the container's width.
+            let (h_margins, v_margins) = (
+                [m.left.resolve(w), m.right.resolve(w)],
+                [m.top.resolve(w), m.bottom.resolve(w)],
+            );
+            let width = |l: &Length| content_size(l, Some(w), horizontal, style.box_sizing);
+            let height = |l: &Length| content_size(l, self.height, vertical, style.box_sizing);
+            let (width_sizes, height_sizes) = (
+                [&style.width, &style.min_width, &style.max_width].map(width),
+                [&style.height, &style.min_height, &style.max_height].map(height),
+            );
+
+            let (main_sizes, cross_sizes, main_margins, cross_margins, edges, cross_edges) =
+                match self.row {
+                    true => (
+                        width_sizes,
+                        height_sizes,
+                        h_margins,
+                        v_margins,
+                        horizontal,
+                        vertical,
+                    ),
+                    false => (
+                        height_sizes,
+                        width_sizes,
+                        v_margins,
+                        h_margins,
+                        vertical,
+                        horizontal,
+                    ),
+                };
+            item.grow = style.flex_grow;
+            item.shrink = style.flex_shrink;
+            if style.align_self != Alignment::Auto {
+                item.align = style.align_self;
+            }
+            item.main_edges = edges;
+            item.cross_edges = cross_edges;
+            item.margins = [
+                main_margins[0],
+                main_margins[1],
+                cross_margins[0],
+                cross_margins[1],
+            ];
+            [main_size, min] = [main_sizes[0], main_sizes[1]];
+            item.max = main_sizes[2].unwrap_or(f32::INFINITY);
+            item.cross_size = cross_sizes[0];
+            item.min_cross = cross_sizes[1].unwrap_or(0.0);
+            item.max_cross = cross_sizes[2].unwrap_or(f32::INFINITY);
+
+            basis = match &style.flex_basis {
+                FlexBasis::Auto => main_size,
+                FlexBasis::Content => None,
+                FlexBasis::Size(l) => content_size(l, main_available, edges, style.box_sizing),
+            };
+            // An item which clips its overflow has no minimum size of its own.
+            content_min =
+                style.overflow_x == Overflow::Visible && style.overflow_y == Overflow::Visible;
+        }
+        if item.align == Alignment::Normal {
+            item.align = Alignment::Stretch;
+        }
+
+        // The minimum and maximum content sizes. A column's items are laid out at their cross
+        // size, and are as tall as their content.
+        let content = match self.row {
+            true => child.item_widths(self.fonts, self.style, self.area.width),
+            false => {
+                item.cross = self.column_item_width(child, &item);
+                child.layout_item(
+                    self.fonts,
+                    self.style,
+                    self.area,
+                    self.height,
+                    item.cross,
+                    None,
+                );
+                (child.dims.content.height, child.dims.content.height)
+            }
+        };
+
+        // Unless the style sets a minimum, an item can't be smaller than its content, or the size
+        // its style sets if that's smaller.
+        // https://www.w3.org/TR/css-flexbox-1/#min-size-auto
+        item.min = min.unwrap_or(match content_min {
+            true => main_size.map_or(content.0, |size| size.min(content.0)),
+            false => 0.0,
+        });
+        item.min = item.min.min(item.max);
+        item.base = basis.unwrap_or(content.1);
+        item.hypothetical = item.base.min(item.max).max(item.min);
+        item
+    }
+
+    /// Returns the width of an item in a column, which fills the width of the container if the
+    /// item is stretched, and otherwise fits its content.
+    fn column_item_width(&self, child: &mut LayoutBox<'a>, item: &FlexItem) -> f32 {
+        let available = self.area.width - item.margin(2) - item.margin(3) - item.cross_edges;
+        let width = match item.cross_size {
+            Some(width) => width,
+            // Items in a column which can wrap are stretched to their line once it's known how
+            // wide it is.
+            None if item.stretches() && self.style.flex_wrap == FlexWrap::Nowrap => available,
+            None => {
+                let (min, max) = child.item_widths(self.fonts, self.style, self.area.width);
+                max.min(available).max(min)
+            }
+        };
+        width.min(item.max_cross).max(item.min_cross)
+    }
+
+    /// Lays out an item at its used main size, and Some cross size, or None if it fits its
+    /// content, and sets the item's used cross size.
+    fn layout_item(&self, child: &mut LayoutBox<'a>, item: &mut FlexItem, cross: Option<f32>) {
+        if self.row {
+            child.layout_item(
+                self.fonts,
+                self.style,
+                self.area,
+                self.height,
+                item.main,
+                cross.or(item.cross_size),
+            );
+            item.cross = child.dims.content.height;
+            // Only rows of items are aligned by their baselines, which are horizontal.
+            let top = child.margin_box().y;
+            let baseline = child.first_baseline();
+            item.ascent = baseline.unwrap_or(top + child.margin_box().height) - top;
+        } else {
+            item.cross = cross.unwrap_or(item.cross);
+            child.layout_item(
+                self.fonts,
+                self.style,
+                self.area,
+                self.height,
+                item.cross,
+                Some(item.main),
+            );
+        }
+    }
+}
+
+impl<'a> LayoutBox<'a> {
+    /// Lays out the flex items of a flex container, whose content area has been placed, and which
+    /// is Some `height` tall, or None if its height depends on its items. Its content height is set
+    /// to the height of the items if it depends on them.
+    /// https://www.w3.org/TR/css-flexbox-1/#layout-algorithm
+    pub(super) fn layout_flex_items(
+        &mut self,
+        fonts: &FontDatabase,
+        style: &'a ComputedStyle,
+        height: Option<f32>,
+    ) {
+        let area = self.dims.content;
+        let flex = FlexContainer {
+            fonts,
+            style,
+            area,
+            height,
+            row: matches!(
+                style.flex_direction,
+                FlexDirection::Row | FlexDirection::RowReverse
+            ),
+        };
+        let (main_available, cross_available) = match flex.row {
+            true => (Some(area.width), height),
+            false => (height, Some(area.width)),
+        };
+        let (main_gap, cross_gap) = match flex.row {
+            true => (
+                gap(&style.column_gap, Some(area.width)),
+                gap(&style.row_gap, height),
+            ),
+            false => (
+                gap(&style.row_gap, height),
+                gap(&style.column_gap, Some(area.width)),
+            ),
+        };
+
+        // Absolutely positioned children aren't flex items. Their static position is the start
+        // of the content area.
+        let mut items = Vec::new();
+        for (i, c) in self.children.iter_mut().enumerate() {
+            if c.is_absolutely_positioned() {
+                c.dims = Dimensions {
+                    content: Rect {
+                        x: area.x,
+                        y: area.y,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                };
+                continue;
+            }
+            items.push(flex.item(c, i));
+        }
+        // The items are in the order their `order` gives, and then in the order of the document.
+        let children = &mut self.children;
+        items.sort_by_key(|item| {
+            children[item.index]
+                .style_node()
+                .map_or(0, |n| n.style.order)
+        });
+
+        // The items are broken into lines where the next item doesn't fit, if they can wrap.
+        let mut lines: Vec<FlexLine> = Vec::new();
+        let mut line_main = 0.0;
+        for (i, item) in items.iter().enumerate() {
+            let outer = item.hypothetical + item.main_edges + item.margin(0) + item.margin(1);
+            match lines.last_mut() {
+                Some(line)
+                    if style.flex_wrap == FlexWrap::Nowrap
+                        || main_available.is_none_or(|a| line_main + main_gap + outer <= a) =>
+                {
+                    line.end = i + 1;
+                    line_main += main_gap + outer;
+                }
+                _ => {
+                    lines.push(FlexLine {
+                        start: i,
+                        end: i + 1,
+                        cross: 0.0,
+                        position: 0.0,
+                    });
+                    line_main = outer;
+                }
+            }
+        }
+
+        // The items share the space on their line, and are then laid out at their main sizes to
+        // find their cross sizes.
+        for line in &mut lines {
+            let line_items = &mut items[line.start..line.end];
+            let gaps = main_gap * (line_items.len() - 1) as f32;
+            resolve_flexible_lengths(line_items, main_available.map(|a| a - gaps));
+            for item in line_items.iter_mut() {
+                flex.layout_item(&mut children[item.index], item, None);
+            }
+
+            // A single line is as big as the container, if its size is known. Otherwise a line is
+            // as big as its items, with the items aligned by their baselines sharing one.
+            let baseline = |item: &&FlexItem| flex.row && item.align == Alignment::Baseline;
+            let ascent = line_items.iter().filter(baseline).map(|i| i.ascent);
+            let descent = line_items
+                .iter()
+                .filter(baseline)
+                .map(|i| i.outer_cross() - i.ascent);
+            let baselines = ascent.fold(0.0f32, f32::max) + descent.fold(0.0f32, f32::max);
+            line.cross = match cross_available {
+                Some(cross) if style.flex_wrap == FlexWrap::Nowrap => cross,
+                _ => line_items
+                    .iter()
+                    .map(|item| item.outer_cross())
+                    .fold(baselines, f32::max),
+            };
+        }
+
+        // The lines are aligned in the container, and stretched to fill it by default.
+        let gaps = cross_gap * lines.len().saturating_sub(1) as f32;
+        let lines_cross = lines.iter().map(|l| l.cross).sum::<f32>() + gaps;
+        let free = cross_available.map_or(0.0, |cross| cross - lines_cross);
+        let (mut position, between) = match style.align_content {
+            Alignment::Normal | Alignment::Stretch if free > 0.0 => {
+                let extra = free / lines.len() as f32;
+                for line in &mut lines {
+                    line.cross += extra;
+                }
+                (0.0, 0.0)
+            }
+            _ if style.flex_wrap == FlexWrap::Nowrap => (0.0, 0.0),
+            alignment => distribute(alignment, free, lines.len()),
+        };
+        for line in &mut lines {
+            line.position = position;
+            position += line.cross + between + cross_gap;
+        }
+
+        let main_size = match main_available {
+            Some(main) => main,
+            None => lines
+                .iter()
+                .map(|line| {
+                    let line_items = &items[line.start..line.end];
+                    let gaps = main_gap * (line_items.len() - 1) as f32;
+                    line_items.iter().map(FlexItem::outer_main).sum::<f32>() + gaps
+                })
+                .fold(0.0, f32::max),
+        };
+        let cross_size = cross_available.unwrap_or(lines_cross);
+        let height = match flex.row {
+            true => cross_size,
+            false => main_size,
+        };
+
+        for line in &lines {
+            let line_items = &mut items[line.start..line.end];
+
+            // Items are stretched to the cross size of their line.
+            for item in line_items.iter_mut().filter(|item| item.stretches()) {
+                let cross = line.cross - item.margin(2) - item.margin(3) - item.cross_edges;
+                let cross = cross.min(item.max_cross).max(item.min_cross);
+                if cross != item.cross {
+                    flex.layout_item(&mut children[item.index], item, Some(cross));
+                }
+            }
+
+            // The free space on the line goes to the `auto` margins on the main axis if there
+            // are any, and is otherwise distributed as `justify-content` says.
+            let gaps = main_gap * (line_items.len() - 1) as f32;
+            let used = line_items.iter().map(FlexItem::outer_main).sum::<f32>() + gaps;
+            let free = main_size - used;
+            let auto_margins = line_items
+                .iter()
+                .flat_map(|item| &item.margins[..2])
+                .filter(|m| m.is_none())
+                .count();
+            let (mut position, between) = match auto_margins {
+                0 => distribute(style.justify_content, free, line_items.len()),
+                _ => (0.0, 0.0),
+            };
+            let ascent = line_items
+                .iter()
+                .filter(|item| flex.row && item.align == Alignment::Baseline)
+                .map(|item| item.ascent)
+                .fold(0.0, f32::max);
+
+            for item in line_items.iter_mut() {
+                let auto = free.max(0.0) / auto_margins.max(1) as f32;
+                let main_margins = [item.margins[0], item.margins[1]].map(|m| m.unwrap_or(auto));
+
+                // `auto` margins on the cross axis take up the free space in the line, so
+                // both of them centre the item.
+                let free = line.cross - item.outer_cross();
+                let cross_margins = match [item.margins[2], item.margins[3]] {
+                    [None, None] => [free.max(0.0) / 2.0; 2],
+                    [None, Some(end)] => [free.max(0.0), end],
+                    [Some(start), None] => [start, free.max(0.0)],
+                    [Some(start), Some(end)] => [start, end],
+                };
+                let offset = match item.align {
+                    _ if item.margins[2..].contains(&None) => 0.0,
+                    Alignment::End => free,
+                    Alignment::Center => free / 2.0,
+                    Alignment::Baseline if flex.row => ascent - item.ascent,
+                    _ => 0.0,
+                };
+
+                // The margin box's offsets from the start of the content area, along each axis,
+                // which are mirrored when the items or the lines go in the reverse direction.
+                let outer_main = item.main + item.main_edges + main_margins[0] + main_margins[1];
+                let outer_cross =
+                    item.cross + item.cross_edges + cross_margins[0] + cross_margins[1];
+                let mut main = position;
+                position += outer_main + between + main_gap;
+                let mut cross = line.position + offset;
+                if matches!(
+                    style.flex_direction,
+                    FlexDirection::RowReverse | FlexDirection::ColumnReverse
+                ) {
+                    main = main_size - main - outer_main;
+                }
+                if style.flex_wrap == FlexWrap::WrapReverse {
+                    cross = cross_size - cross - outer_cross;
+                }
+
+                let c = &mut children[item.index];
+                let d = &mut c.dims;
+                let (x, y, margins) = match flex.row {
+                    true => (main, cross, [main_margins, cross_margins]),
+                    false => (cross, main, [cross_margins, main_margins]),
+                };
+                [d.margin.left, d.margin.right] = margins[0];
+                [d.margin.top, d.margin.bottom] = margins[1];
+                let (dx, dy) = (
+                    area.x + x + d.margin.left + d.border.left + d.padding.left - d.content.x,
+                    area.y + y + d.margin.top + d.border.top + d.padding.top - d.content.y,
+                );
+                c.translate(dx, dy);
+
+                // A relatively positioned item is moved from where it's placed.
+                let (dx, dy) = c.relative_offset(area.width, flex.height);
+                if (dx, dy) != (0.0, 0.0) {
+                    c.translate(dx, dy);
+                }
+            }
+        }
+
+        self.dims.content.height = height;
+    }
+
+    /// Returns the minimum and maximum widths of the content of a flex container, which are the
+    /// widths of the margin boxes of its items side by side in a row, or the widest of them in a
+    /// column. The minimum is the widest item's if a row can wrap.
+    /// https://www.w3.org/TR/css-flexbox-1/#intrinsic-sizes
+    pub(super) fn flex_widths(
+        &mut self,
+        fonts: &FontDatabase,
+        style: &'a ComputedStyle,
+        cb_width: f32,
+    ) -> (f32, f32) {
+        let row = matches!(
+            style.flex_direction,
+            FlexDirection::Row | FlexDirection::RowReverse
+        );
+        let gap = gap(&style.column_gap, Some(cb_width));
+        let (mut min, mut max, mut count) = (0.0f32, 0.0f32, 0usize);
+        for c in self
+            .children
+            .iter_mut()
+            .filter(|c| !c.is_absolutely_positioned())
+        {
+            let (item_min, item_max) = c.outer_item_widths(fonts, style, cb_width);
+            if row {
+                min = match style.flex_wrap {
+                    FlexWrap::Nowrap => min + item_min,
+                    _ => min.max(item_min),
+                };
+                max += item_max;
+                count += 1;
+            } else {
+                min = min.max(item_min);
+                max = max.max(item_max);
+            }
+        }
+        let gaps = gap * count.saturating_sub(1) as f32;
+        match style.flex_wrap {
+            FlexWrap::Nowrap => (min + gaps, max + gaps),
+            _ => (min, max + gaps),
+        }
+    }
+
+    /// Returns the Some y position of the first baseline in the box, or None if it has no lines.
+    pub(super) fn first_baseline(&self) -> Option<f32> {
+        match self.lines.first() {
+            Some(line) => Some(line.baseline),
+            None => self
+                .children
+                .iter()
+                .filter(|c| !c.is_out_of_flow())
+                .find_map(LayoutBox::first_baseline),
+        }
+    }
+}
@@ -0,0 +1,139 @@
+// Floats, which are moved to the left or right of their containing block, with the content after
+// them flowing around them.
+// https://www.w3.org/TR/CSS2/visuren.html#floats
+
+use crate::box_model::{CollapsedMargin, Dimensions, LayoutBox, Rect};
+use crate::style::computed::{Clear, ComputedStyle, Float};
+use crate::text::FontDatabase;
+
+/// The floats of a block formatting context. Line boxes are shortened beside them, and boxes
+/// which clear them are moved below them.
+#[derive(Debug, Default)]
+pub(super) struct Floats {
+    /// The side each float is on, and its margin box.
+    boxes: Vec<(Float, Rect)>,
+    /// The top of the last float, which the floats after it can't be above.
+    top: f32,
+}
+
+impl Floats {
+    /// Returns the floats which are beside a band from `y` which is `height` tall. A band with no
+    /// height is beside the floats it's in.
+    fn beside(&self, y: f32, height: f32) -> impl Iterator<Item = &(Float, Rect)> {
+        self.boxes
+            .iter()
+            .filter(move |(_, r)| r.y + r.height > y && (r.y < y + height || r.y <= y))
+    }
+
+    /// Returns the left and right of the space between `left` and `right` which isn't taken up by
+    /// the floats beside a band from `y` which is `height` tall.
+    pub(super) fn space(&self, y: f32, height: f32, left: f32, right: f32) -> (f32, f32) {
+        self.beside(y, height)
+            .fold((left, right), |(l, r), (side, rect)| match side {
+                Float::Left => (l.max(rect.x + rect.width), r),
+                _ => (l, r.min(rect.x)),
+            })
+    }
+
+    /// Returns the top of the first band at or below `y` which is `height` tall, where `width`
+    /// fits between `left` and `right` beside the floats, and the left and right of the space
+    /// there. Below all the floats, the band is as wide as it can be even if `width` doesn't fit.
+    pub(super) fn find_space(
+        &self,
+        mut y: f32,
+        width: f32,
+        height: f32,
+        left: f32,
+        right: f32,
+    ) -> (f32, f32, f32) {
+        loop {
+            let (l, r) = self.space(y, height, left, right);
+            // The space gets wider below the bottom of one of the floats beside it.
+            let next = self
+                .beside(y, height)
+                .map(|(_, r)| r.y + r.height)
+                .reduce(f32::min);
+            match next {
+                Some(next) if r - l < width => y = next,
+                _ => return (y, l, r),
+            }
+        }
+    }
+
+    /// Returns the Some bottom of the floats which a box with `clear` has to be below, or None if
+    /// there aren't any.
+    pub(super) fn clearance(&self, clear: Clear) -> Option<f32> {
+        self.boxes
+            .iter()
+            .filter(|(side, _)| match clear {
+                Clear::None => false,
+                Clear::Left => *side == Float::Left,
+                Clear::Right => *side == Float::Right,
+                Clear::Both => true,
+            })
+            .map(|(_, r)| r.y + r.height)
+            .reduce(f32::max)
+    }
+
+    /// Returns the Some bottom of the lowest float, or None if there aren't any.
+    pub(super) fn bottom(&self) -> Option<f32> {
+        self.clearance(Clear::Both)
+    }
+
+    /// Places a float with a margin box at as high a place at or below `y` as it fits in, and as
+    /// far to its side of the containing block's content `area` as it can go, and adds it to the
+    /// floats. It can't be above the floats before it, or the floats it clears.
+    /// https://www.w3.org/TR/CSS2/visuren.html#float-position
+    ///
+    /// Returns how far to move the float to get it there.
+    pub(super) fn place(
+        &mut self,
+        style: &ComputedStyle,
+        margin_box: Rect,
+        y: f32,
+        area: Rect,
+    ) -> (f32, f32) {
+        let y = y
+            .max(self.top)
+            .max(self.clearance(style.clear).unwrap_or(y));
+        let (y, left, right) = self.find_space(
+            y,
+            margin_box.width,
+            margin_box.height,
+            area.x,
+            area.x + area.width,
+        );
+        let x = match style.float {
+            Float::Right => right - margin_box.width,
+            _ => left,
+        };
+
+        self.top = y;
+        self.boxes.push((style.float, Rect { x, y, ..margin_box }));
+        (x - margin_box.x, y - margin_box.y)
+    }
+}
+
+impl<'a> LayoutBox<'a> {
+    /// Lays out a float at the top of its containing block, in a block formatting context of its
+    /// own. It's moved into place once it's known where it goes, with [Floats::place].
+    pub(super) fn layout_float(
+        &mut self,
+        fonts: &FontDatabase,
+        style: &'a ComputedStyle,
+        containing_block: Dimensions,
+        cb_height: Option<f32>,
+    ) {
+        let mut containing_block = containing_block;
+        containing_block.content.height = 0.0;
+        self.layout_block(
+            fonts,
+            style,
+            containing_block,
+            cb_height,
+            CollapsedMargin::default(),
+            &mut Floats::default(),
+            true,
+        );
+    }
+}
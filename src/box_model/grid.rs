@@ -0,0 +1,1043 @@
+// Grid layout, where the children of a grid container are placed in the areas of a grid of rows and
+// columns, whose sizes can depend on the sizes of the items in them.
+// https://www.w3.org/TR/css-grid-1/#layout-algorithm
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use crate::box_model::{content_size, distribute, Dimensions, LayoutBox, Rect};
+use crate::style::computed::{
+    Alignment, ComputedStyle, GridLine, Length, TrackBreadth, TrackList, TrackSize,
+};
+use crate::text::FontDatabase;
+
+/// The tracks along one axis of a grid, which are its rows or its columns.
+struct Axis {
+    /// The tracks of the explicit grid which the template sets, and the implicit tracks, which are
+    /// repeated as many times as they're needed.
+    template: Vec<TrackSize>,
+    implicit: Vec<TrackSize>,
+    /// The number of tracks in the explicit grid, which can have more tracks than the template
+    /// if the template areas do.
+    explicit: usize,
+    /// The lines with each name, as indices from the first line of the explicit grid.
+    names: HashMap<String, Vec<i32>>,
+    /// The tracks repeated by `auto-fit`, as indices from the first line of the explicit grid.
+    /// They collapse if there aren't any items in them.
+    auto_fit: Range<usize>,
+    /// Has each track collapsed, once the items are placed?
+    collapsed: Vec<bool>,
+    /// The index of the first line of the explicit grid, once the items are placed, as there can
+    /// be implicit tracks before it.
+    start: usize,
+    /// The number of tracks once the items are placed.
+    count: usize,
+    gap: f32,
+}
+
+/// Where an item is placed along one axis of a grid, before the grid is known.
+#[derive(Debug, Clone, Copy)]
+enum Placement {
+    /// Between two lines, as indices from the first line of the explicit grid.
+    Definite(i32, i32),
+    /// Wherever there's space for it, across a number of tracks.
+    Auto(usize),
+}
+
+/// An item which is placed in a grid, between lines which are indices from the first line of the
+/// grid.
+struct GridItem {
+    /// The index of the item in the container's children.
+    index: usize,
+    rows: Range<usize>,
+    columns: Range<usize>,
+    /// The alignment of the item in its area along the rows, and along the columns.
+    justify: Alignment,
+    align: Alignment,
+}
+
+/// The rows and columns of a grid container, and the items placed in them.
+struct Grid {
+    rows: Axis,
+    columns: Axis,
+    items: Vec<GridItem>,
+}
+
+/// Returns the Some size of a length, or None if it's a percentage of a `base` size which isn't
+/// known.
+fn fixed_size(length: &Length, base: Option<f32>) -> Option<f32> {
+    match (length, base) {
+        (Length::Px(n), _) => Some(*n),
+        (_, Some(base)) => length.resolve(base),
+        _ => None,
+    }
+}
+
+/// Returns the Some size of a track sizing function, or None if it depends on the content or the
+/// free space.
+fn fixed_breadth(breadth: &TrackBreadth, base: Option<f32>) -> Option<f32> {
+    match breadth {
+        TrackBreadth::Length(length) => fixed_size(length, base),
+        _ => None,
+    }
+}
+
+/// Adds some tracks and the names of the lines around them to the end of others. The first names
+/// are of the line the tracks start at, which is the last line of the others.
+fn extend_tracks(
+    (tracks, names): (&mut Vec<TrackSize>, &mut Vec<Vec<String>>),
+    more: &[TrackSize],
+    more_names: &[Vec<String>],
+) {
+    let (first, rest) = more_names
+        .split_first()
+        .expect("The names of the first line");
+    names
+        .last_mut()
+        .expect("A first line")
+        .extend(first.iter().cloned());
+    tracks.extend(more.iter().cloned());
+    names.extend(rest.iter().cloned());
+}
+
+impl Axis {
+    /// Creates the explicit grid along an axis from a template, which has `auto-fill` and
+    /// `auto-fit` tracks repeated as many times as fit in the Some `available` size, or once if
+    /// it isn't known. `areas` are the lines each named template area is between.
+    fn new(
+        list: &TrackList,
+        implicit: &[TrackSize],
+        areas: &HashMap<String, Range<usize>>,
+        available: Option<f32>,
+        gap: f32,
+    ) -> Self {
+        let (mut template, mut line_names) = (Vec::new(), vec![Vec::new()]);
+        let mut auto_fit = 0..0;
+        match &list.auto_repeat {
+            None => extend_tracks(
+                (&mut template, &mut line_names),
+                &list.tracks,
+                &list.line_names,
+            ),
+            Some(repeat) => {
+                // A track is as big as its maximum if that's fixed, and otherwise its minimum.
+                let size = |t: &TrackSize| {
+                    fixed_breadth(&t.max, available).or(fixed_breadth(&t.min, available))
+                };
+                let others = list.tracks.iter().map(size).sum::<Option<f32>>();
+                let repeated = repeat.tracks.iter().map(size).sum::<Option<f32>>();
+                let count = match (available, others, repeated) {
+                    (Some(available), Some(others), Some(repeated)) => {
+                        let gaps = gap * list.tracks.len().saturating_sub(1) as f32;
+                        let each = repeated + gap * repeat.tracks.len() as f32;
+                        match each > 0.0 {
+                            true => ((available - others - gaps) / each).floor().max(1.0) as usize,
+                            false => 1,
+                        }
+                    }
+                    _ => 1,
+                };
+
+                let (line, before) = (repeat.line, (&mut template, &mut line_names));
+                extend_tracks(before, &list.tracks[..line], &list.line_names[..=line]);
+                for _ in 0..count {
+                    let tracks = (&mut template, &mut line_names);
+                    extend_tracks(tracks, &repeat.tracks, &repeat.line_names);
+                }
+                let after = (&mut template, &mut line_names);
+                extend_tracks(after, &list.tracks[line..], &list.line_names[line..]);
+                if repeat.fit {
+                    auto_fit = line..line + count * repeat.tracks.len();
+                }
+            }
+        }
+
+        // The areas have lines at their starts and ends, named after them.
+        let mut names: HashMap<String, Vec<i32>> = HashMap::new();
+        for (i, line) in line_names.into_iter().enumerate() {
+            for name in line {
+                names.entry(name).or_default().push(i as i32);
+            }
+        }
+        for (name, lines) in areas {
+            let mut add = |side, line: usize| {
+                let lines = names.entry(format!("{name}-{side}")).or_default();
+                lines.push(line as i32);
+            };
+            add("start", lines.start);
+            add("end", lines.end);
+        }
+        for lines in names.values_mut() {
+            lines.sort_unstable();
+            lines.dedup();
+        }
+
+        let explicit = areas
+            .values()
+            .map(|lines| lines.end)
+            .fold(template.len(), usize::max);
+        Axis {
+            template,
+            implicit: implicit.to_vec(),
+            explicit,
+            names,
+            auto_fit,
+            collapsed: Vec::new(),
+            start: 0,
+            count: explicit,
+            gap,
+        }
+    }
+
+    /// Returns the index of the nth line with a name, counting from the start if n is positive,
+    /// and from the end if it's negative. If there aren't that many, the implicit lines are
+    /// counted as if they all had the name.
+    fn named_line(&self, name: &str, n: i32) -> i32 {
+        let lines = self.names.get(name).map_or(&[][..], Vec::as_slice);
+        let count = lines.len() as i32;
+        match n > 0 {
+            true if n <= count => lines[n as usize - 1],
+            true => self.explicit as i32 + n - count,
+            false if -n <= count => lines[(count + n) as usize],
+            false => n + count,
+        }
+    }
+
+    /// Returns the Some line a placement property sets, or None if it's `auto` or a span. `side`
+    /// is whether it's the start or the end of the item.
+    fn line(&self, line: &GridLine, side: &str) -> Option<i32> {
+        match line {
+            GridLine::Line(n, None) if *n > 0 => Some(n - 1),
+            GridLine::Line(n, None) => Some(self.explicit as i32 + 1 + n),
+            GridLine::Line(n, Some(name)) => Some(self.named_line(name, *n)),
+            GridLine::Named(name) => Some(match self.names.get(&format!("{name}-{side}")) {
+                Some(lines) => lines[0],
+                None => self.named_line(name, 1),
+            }),
+            GridLine::Auto | GridLine::Span(..) => None,
+        }
+    }
+
+    /// Returns the line a span from a line reaches, going forward or back. A span of a name
+    /// counts only the lines with the name, as if all the implicit lines had it.
+    fn span(&self, from: i32, span: &GridLine, forward: bool) -> i32 {
+        let (n, name) = match span {
+            GridLine::Span(n, name) => (*n, name.as_ref()),
+            _ => (1, None),
+        };
+        let Some(name) = name else {
+            return match forward {
+                true => from + n as i32,
+                false => from - n as i32,
+            };
+        };
+        let lines = self.names.get(name).map_or(&[][..], Vec::as_slice);
+        match forward {
+            true => {
+                let after: Vec<_> = lines.iter().filter(|l| **l > from).collect();
+                match after.get(n - 1) {
+                    Some(line) => **line,
+                    None => (self.explicit as i32).max(from) + (n - after.len()) as i32,
+                }
+            }
+            false => {
+                let before: Vec<_> = lines.iter().rev().filter(|l| **l < from).collect();
+                match before.get(n - 1) {
+                    Some(line) => **line,
+                    None => from.min(0) - (n - before.len()) as i32,
+                }
+            }
+        }
+    }
+
+    /// Returns where an item is placed by the properties for its start and end lines.
+    /// https://www.w3.org/TR/css-grid-1/#line-placement
+    fn placement(&self, start: &GridLine, end: &GridLine) -> Placement {
+        match (self.line(start, "start"), self.line(end, "end")) {
+            (Some(s), Some(e)) if s == e => Placement::Definite(s, s + 1),
+            (Some(s), Some(e)) => Placement::Definite(s.min(e), s.max(e)),
+            (Some(s), None) => Placement::Definite(s, self.span(s, end, true)),
+            (None, Some(e)) => Placement::Definite(self.span(e, start, false), e),
+            // A span of a name only places an item with a line, and is otherwise a span of one.
+            (None, None) => Placement::Auto(match (start, end) {
+                (GridLine::Span(n, None), _) => *n,
+                (GridLine::Span(..), _) => 1,
+                (_, GridLine::Span(n, None)) => *n,
+                _ => 1,
+            }),
+        }
+    }
+
+    /// Returns the size of a track, as an index from the first line of the grid.
+    fn size(&self, track: usize) -> TrackSize {
+        let auto = TrackSize {
+            min: TrackBreadth::Auto,
+            max: TrackBreadth::Auto,
+        };
+        let i = track as i32 - self.start as i32;
+        let template = self.template.len() as i32;
+        if (0..template).contains(&i) {
+            return self.template[i as usize].clone();
+        }
+        // The implicit tracks before the explicit grid repeat the sizes backwards from the last.
+        let n = self.implicit.len() as i32;
+        let i = if i < 0 { i } else { i - template };
+        match n {
+            0 => auto,
+            _ => self.implicit[i.rem_euclid(n) as usize].clone(),
+        }
+    }
+}
+
+impl Grid {
+    /// Creates the grid of a container, which is Some `width` wide and Some `height` tall, or None
+    /// if they depend on its items, and places the items in it.
+    /// https://www.w3.org/TR/css-grid-1/#placement
+    fn new(
+        container: &LayoutBox,
+        style: &ComputedStyle,
+        width: Option<f32>,
+        height: Option<f32>,
+    ) -> Self {
+        // Each named area is between the lines of the cells it's in.
+        let mut areas: HashMap<String, (Range<usize>, Range<usize>)> = HashMap::new();
+        for (r, row) in style.grid_template_areas.iter().enumerate() {
+            for (c, name) in row.iter().enumerate() {
+                let Some(name) = name else { continue };
+                let area = areas.entry(name.clone()).or_insert((r..r + 1, c..c + 1));
+                area.0 = area.0.start.min(r)..area.0.end.max(r + 1);
+                area.1 = area.1.start.min(c)..area.1.end.max(c + 1);
+            }
+        }
+        let row_areas = areas
+            .iter()
+            .map(|(n, a)| (n.clone(), a.0.clone()))
+            .collect();
+        let column_areas = areas.into_iter().map(|(n, a)| (n, a.1)).collect();
+
+        let gap = |gap: &Length, base| fixed_size(gap, base).unwrap_or(0.0);
+        let mut rows = Axis::new(
+            &style.grid_template_rows,
+            &style.grid_auto_rows,
+            &row_areas,
+            height,
+            gap(&style.row_gap, height),
+        );
+        let mut columns = Axis::new(
+            &style.grid_template_columns,
+            &style.grid_auto_columns,
+            &column_areas,
+            width,
+            gap(&style.column_gap, width),
+        );
+
+        // Items are placed in the order their `order` gives, and then in the order of the
+        // document. Absolutely positioned children aren't grid items.
+        let mut children: Vec<(usize, &LayoutBox)> = container
+            .children
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| !c.is_absolutely_positioned())
+            .collect();
+        children.sort_by_key(|(_, c)| c.style_node().map_or(0, |n| n.style.order));
+        let placements: Vec<_> = children
+            .iter()
+            .map(|(_, c)| match c.style_node().map(|n| &n.style) {
+                Some(s) => [
+                    rows.placement(&s.grid_row_start, &s.grid_row_end),
+                    columns.placement(&s.grid_column_start, &s.grid_column_end),
+                ],
+                None => [Placement::Auto(1), Placement::Auto(1)],
+            })
+            .collect();
+
+        let placed = place_items(&placements, [&mut rows, &mut columns], style);
+
+        // Empty tracks which `auto-fit` repeats collapse.
+        for (i, axis) in [&mut rows, &mut columns].into_iter().enumerate() {
+            let mut used = vec![false; axis.count];
+            for tracks in &placed {
+                tracks[i].clone().for_each(|t| used[t] = true);
+            }
+            let (start, auto_fit) = (axis.start, axis.auto_fit.clone());
+            axis.collapsed = (0..axis.count)
+                .map(|t| !used[t] && t >= start && auto_fit.contains(&(t - start)))
+                .collect();
+        }
+
+        // An item's alignment is its container's if it's `auto`, and stretches it if it's
+        // `normal`.
+        let alignment = |own: Alignment, items: Alignment| match own {
+            Alignment::Auto => items,
+            own => own,
+        };
+        let items = children
+            .iter()
+            .zip(placed)
+            .map(|((index, c), [rows, columns])| {
+                let own = c.style_node().map(|n| &n.style);
+                let [justify, align] = [
+                    alignment(
+                        own.map_or(Alignment::Auto, |s| s.justify_self),
+                        style.justify_items,
+                    ),
+                    alignment(
+                        own.map_or(Alignment::Auto, |s| s.align_self),
+                        style.align_items,
+                    ),
+                ]
+                .map(|a| match a {
+                    Alignment::Normal => Alignment::Stretch,
+                    a => a,
+                });
+                GridItem {
+                    index: *index,
+                    rows,
+                    columns,
+                    justify,
+                    align,
+                }
+            })
+            .collect();
+        Grid {
+            rows,
+            columns,
+            items,
+        }
+    }
+}
+
+/// Places items along the rows and columns of a grid, given where each is placed along each
+/// axis. The items which are placed in both are placed first, then the ones which are placed in
+/// the direction the container's `grid-auto-flow` doesn't go in, and then the rest, in the first
+/// space after the item before them, or in the first space at all if the flow is `dense`. The
+/// grid grows implicit tracks to fit them, and its axes are told where the explicit grid starts.
+/// https://www.w3.org/TR/css-grid-1/#auto-placement-algo
+///
+/// Returns the tracks each item is in, along the rows and then the columns.
+fn place_items(
+    placements: &[[Placement; 2]],
+    axes: [&mut Axis; 2],
+    style: &ComputedStyle,
+) -> Vec<[Range<usize>; 2]> {
+    // The grid grows along the major axis, which is the rows unless the flow is in columns.
+    let flow = style.grid_auto_flow;
+    let (major, minor) = match flow.column {
+        true => (1, 0),
+        false => (0, 1),
+    };
+
+    // Implicit tracks are added before the explicit grid for the items placed before it.
+    let mut start = [0i32; 2];
+    for p in placements {
+        for axis in 0..2 {
+            if let Placement::Definite(s, _) = p[axis] {
+                start[axis] = start[axis].max(-s);
+            }
+        }
+    }
+    let definite = |p: Placement, axis: usize| match p {
+        Placement::Definite(s, e) => Some((s + start[axis]) as usize..(e + start[axis]) as usize),
+        Placement::Auto(_) => None,
+    };
+    let span = |p: Placement| match p {
+        Placement::Definite(s, e) => (e - s) as usize,
+        Placement::Auto(n) => n,
+    };
+
+    // The minor axis has as many tracks as any item needs, so each auto item fits across it.
+    let mut minor_count = placements
+        .iter()
+        .map(|p| match definite(p[minor], minor) {
+            Some(tracks) => tracks.end,
+            None => span(p[minor]),
+        })
+        .fold(start[minor] as usize + axes[minor].explicit, usize::max);
+
+    let mut occupied: HashSet<(usize, usize)> = HashSet::new();
+    let fits = |occupied: &HashSet<_>, major: &Range<usize>, minor: &Range<usize>| {
+        major
+            .clone()
+            .all(|a| minor.clone().all(|b| !occupied.contains(&(a, b))))
+    };
+    let mut placed: Vec<Option<[Range<usize>; 2]>> = vec![None; placements.len()];
+    let mut place = |i: usize, a: Range<usize>, b: Range<usize>, occupied: &mut HashSet<_>| {
+        for x in a.clone() {
+            for y in b.clone() {
+                occupied.insert((x, y));
+            }
+        }
+        let mut tracks = [a.clone(), b.clone()];
+        if major == 1 {
+            tracks = [b, a];
+        }
+        placed[i] = Some(tracks);
+    };
+
+    // Items placed along both axes.
+    for (i, p) in placements.iter().enumerate() {
+        if let (Some(a), Some(b)) = (definite(p[major], major), definite(p[minor], minor)) {
+            place(i, a, b, &mut occupied);
+        }
+    }
+
+    // Items placed along the major axis only, each after the items before it in its tracks.
+    let mut cursors: HashMap<usize, usize> = HashMap::new();
+    for (i, p) in placements.iter().enumerate() {
+        let (Some(a), None) = (definite(p[major], major), definite(p[minor], minor)) else {
+            continue;
+        };
+        let n = span(p[minor]);
+        let mut b = match flow.dense {
+            true => 0,
+            false => cursors.get(&a.start).copied().unwrap_or(0),
+        };
+        while !fits(&occupied, &a, &(b..b + n)) {
+            b += 1;
+        }
+        cursors.insert(a.start, b + n);
+        minor_count = minor_count.max(b + n);
+        place(i, a, b..b + n, &mut occupied);
+    }
+
+    // The rest are placed from a cursor, which moves on past each of them unless the flow is
+    // dense.
+    let mut cursor = (0, 0);
+    for (i, p) in placements.iter().enumerate() {
+        if definite(p[major], major).is_some() {
+            continue;
+        }
+        let m = span(p[major]);
+        let (mut a, mut b) = match flow.dense {
+            true => (0, 0),
+            false => cursor,
+        };
+        match definite(p[minor], minor) {
+            Some(tracks) => {
+                if !flow.dense && tracks.start < b {
+                    a += 1;
+                }
+                while !fits(&occupied, &(a..a + m), &tracks) {
+                    a += 1;
+                }
+                b = tracks.start;
+                place(i, a..a + m, tracks, &mut occupied);
+            }
+            None => {
+                let n = span(p[minor]);
+                loop {
+                    if b + n > minor_count {
+                        (a, b) = (a + 1, 0);
+                    } else if !fits(&occupied, &(a..a + m), &(b..b + n)) {
+                        b += 1;
+                    } else {
+                        break;
+                    }
+                }
+                place(i, a..a + m, b..b + n, &mut occupied);
+                b += n;
+            }
+        }
+        cursor = (a, b);
+    }
+
+    let placed: Vec<[Range<usize>; 2]> = placed.into_iter().flatten().collect();
+    for (axis, grid_axis) in axes.into_iter().enumerate() {
+        grid_axis.start = start[axis] as usize;
+        grid_axis.count = placed
+            .iter()
+            .map(|p| p[axis].end)
+            .fold(grid_axis.start + grid_axis.explicit, usize::max);
+        if axis == minor {
+            grid_axis.count = grid_axis.count.max(minor_count);
+        }
+    }
+    placed
+}
+
+impl Axis {
+    /// The size of the gaps between the tracks which haven't collapsed.
+    fn gaps(&self) -> f32 {
+        let visible = self.collapsed.iter().filter(|c| !**c).count();
+        self.gap * visible.saturating_sub(1) as f32
+    }
+}
+
+/// Grows some of the tracks by an `extra` size, which they share equally, if it's positive.
+fn grow(sizes: &mut [f32], tracks: &[usize], extra: f32) {
+    if extra <= 0.0 || tracks.is_empty() {
+        return;
+    }
+    for t in tracks {
+        sizes[*t] += extra / tracks.len() as f32;
+    }
+}
+
+/// Returns the sizes of the tracks along an axis of a grid, which is Some `available` size, or
+/// None if the tracks are as big as their content. `contributions` are the tracks each item is
+/// in, and the minimum and maximum sizes of its margin box along the axis. `stretch` is whether
+/// the tracks with an `auto` maximum are stretched to fill the axis.
+/// https://www.w3.org/TR/css-grid-1/#algo-track-sizing
+fn size_tracks(
+    axis: &Axis,
+    contributions: &[(Range<usize>, f32, f32)],
+    available: Option<f32>,
+    stretch: bool,
+) -> Vec<f32> {
+    let count = axis.count;
+    let sizes: Vec<TrackSize> = (0..count).map(|t| axis.size(t)).collect();
+    let fr = |t: usize| match sizes[t].max {
+        TrackBreadth::Flex(fr) if !axis.collapsed[t] => Some(fr),
+        _ => None,
+    };
+    let sum = |sizes: &[f32], tracks: &[usize]| tracks.iter().map(|t| sizes[*t]).sum::<f32>();
+
+    // Each track starts at its fixed minimum, and can grow to its fixed maximum.
+    let mut base = vec![0.0; count];
+    let mut limit = vec![f32::INFINITY; count];
+    for t in (0..count).filter(|t| !axis.collapsed[*t]) {
+        base[t] = fixed_breadth(&sizes[t].min, available).unwrap_or(0.0);
+        if let Some(max) = fixed_breadth(&sizes[t].max, available) {
+            limit[t] = max.max(base[t]);
+        }
+    }
+    for t in (0..count).filter(|t| axis.collapsed[*t]) {
+        limit[t] = 0.0;
+    }
+
+    // Tracks which are as big as their content grow to fit the items in them, starting with the
+    // items which are in the fewest tracks. Items in flexible tracks only make those bigger.
+    let mut items: Vec<_> = contributions.iter().collect();
+    items.sort_by_key(|(tracks, ..)| tracks.len());
+    for (tracks, min, max) in items {
+        let tracks: Vec<usize> = tracks.clone().filter(|t| !axis.collapsed[*t]).collect();
+        let gaps = axis.gap * tracks.len().saturating_sub(1) as f32;
+        let flexible: Vec<usize> = tracks
+            .iter()
+            .copied()
+            .filter(|t| fr(*t).is_some())
+            .collect();
+        if !flexible.is_empty() {
+            let extra = min - gaps - sum(&base, &tracks);
+            grow(&mut base, &flexible, extra);
+            continue;
+        }
+
+        let intrinsic_min: Vec<usize> = tracks
+            .iter()
+            .copied()
+            .filter(|t| !matches!(sizes[*t].min, TrackBreadth::Length(_)))
+            .collect();
+        let target = match intrinsic_min
+            .iter()
+            .all(|t| sizes[*t].min == TrackBreadth::MaxContent)
+        {
+            true => *max,
+            false => *min,
+        };
+        let extra = target - gaps - sum(&base, &tracks);
+        grow(&mut base, &intrinsic_min, extra);
+
+        let intrinsic_max: Vec<usize> = tracks
+            .iter()
+            .copied()
+            .filter(|t| !matches!(sizes[*t].max, TrackBreadth::Length(_)))
+            .collect();
+        let target = match (tracks.as_slice(), intrinsic_max.first()) {
+            ([_], Some(t)) => match &sizes[*t].max {
+                TrackBreadth::MinContent => *min,
+                TrackBreadth::FitContent(l) => {
+                    max.min(fixed_size(l, available).unwrap_or(*max)).max(*min)
+                }
+                _ => *max,
+            },
+            _ => *max,
+        };
+        let current: f32 = tracks
+            .iter()
+            .map(|t| match limit[*t].is_finite() {
+                true => limit[*t],
+                false => base[*t],
+            })
+            .sum();
+        let extra = target - gaps - current;
+        if extra > 0.0 {
+            for t in &intrinsic_max {
+                if limit[*t].is_infinite() {
+                    limit[*t] = base[*t];
+                }
+            }
+            grow(&mut limit, &intrinsic_max, extra);
+        }
+    }
+    for t in 0..count {
+        if limit[t].is_infinite() || limit[t] < base[t] {
+            limit[t] = base[t];
+        }
+    }
+
+    // The tracks grow to their limits while there's free space, or all the way if they're as big
+    // as their content.
+    match available {
+        Some(available) => loop {
+            let free = available - axis.gaps() - base.iter().sum::<f32>();
+            let growable: Vec<usize> = (0..count).filter(|t| limit[*t] > base[*t]).collect();
+            if free <= 0.0 || growable.is_empty() {
+                break;
+            }
+            let share = free / growable.len() as f32;
+            let mut capped = false;
+            for t in growable {
+                capped |= base[t] + share >= limit[t];
+                base[t] = limit[t].min(base[t] + share);
+            }
+            if !capped {
+                break;
+            }
+        },
+        None => base.clone_from(&limit),
+    }
+
+    // Flexible tracks share the space that's left by their flex factors, but aren't made smaller
+    // than they are. Without any space, an fr is as big as it needs to be for the tracks and the
+    // items in them to fit their content.
+    // https://www.w3.org/TR/css-grid-1/#algo-flex-tracks
+    let flexible: Vec<usize> = (0..count).filter(|t| fr(*t).is_some()).collect();
+    if !flexible.is_empty() {
+        let factor = |t: usize| fr(t).unwrap_or(0.0);
+        let fr_size = match available {
+            Some(available) => {
+                let mut inflexible = vec![true; count];
+                flexible.iter().for_each(|t| inflexible[*t] = false);
+                loop {
+                    let fixed: Vec<usize> = (0..count).filter(|t| inflexible[*t]).collect();
+                    let leftover = available - axis.gaps() - sum(&base, &fixed);
+                    let factors: f32 = flexible
+                        .iter()
+                        .filter(|t| !inflexible[**t])
+                        .map(|t| factor(*t))
+                        .sum();
+                    let size = leftover / factors.max(1.0);
+                    let too_small = flexible
+                        .iter()
+                        .filter(|t| !inflexible[**t] && size * factor(**t) < base[**t]);
+                    let too_small: Vec<usize> = too_small.copied().collect();
+                    if too_small.is_empty() {
+                        break size;
+                    }
+                    too_small.into_iter().for_each(|t| inflexible[t] = true);
+                }
+            }
+            None => {
+                let tracks = flexible.iter().map(|t| base[*t] / factor(*t).max(1.0));
+                let items = contributions.iter().filter_map(|(tracks, _, max)| {
+                    let tracks: Vec<usize> = tracks.clone().collect();
+                    let factors: f32 = tracks.iter().map(|t| factor(*t)).sum();
+                    let fixed: Vec<usize> = tracks
+                        .iter()
+                        .copied()
+                        .filter(|t| fr(*t).is_none())
+                        .collect();
+                    let gaps = axis.gap * tracks.len().saturating_sub(1) as f32;
+                    let space = max - gaps - sum(&base, &fixed);
+                    (factors > 0.0).then(|| space / factors.max(1.0))
+                });
+                tracks.chain(items).fold(0.0, f32::max)
+            }
+        };
+        for t in flexible {
+            base[t] = base[t].max(fr_size * factor(t));
+        }
+    }
+
+    // Tracks with an `auto` maximum share the space that's left.
+    if let Some(available) = available.filter(|_| stretch) {
+        let auto: Vec<usize> = (0..count)
+            .filter(|t| sizes[*t].max == TrackBreadth::Auto && !axis.collapsed[*t])
+            .collect();
+        let free = available - axis.gaps() - base.iter().sum::<f32>();
+        grow(&mut base, &auto, free);
+    }
+    base
+}
+
+/// Returns where each track along an axis starts, given their sizes, with the tracks aligned in
+/// the Some `available` size as `alignment` says.
+/// https://www.w3.org/TR/css-align-3/#align-justify-content
+fn track_starts(
+    axis: &Axis,
+    sizes: &[f32],
+    available: Option<f32>,
+    alignment: Alignment,
+) -> Vec<f32> {
+    let total = sizes.iter().sum::<f32>() + axis.gaps();
+    let visible = axis.collapsed.iter().filter(|c| !**c).count();
+    let free = available.map_or(0.0, |available| available - total);
+    let (mut position, between) = distribute(alignment, free, visible);
+
+    let mut starts = Vec::new();
+    for (size, collapsed) in sizes.iter().zip(&axis.collapsed) {
+        starts.push(position);
+        position += size;
+        if !collapsed {
+            position += axis.gap + between;
+        }
+    }
+    starts
+}
+
+/// Returns the start and the size of the span of some tracks.
+fn span(starts: &[f32], sizes: &[f32], tracks: &Range<usize>) -> (f32, f32) {
+    let start = starts[tracks.start];
+    let last = tracks.end - 1;
+    (start, starts[last] + sizes[last] - start)
+}
+
+/// Returns the offset of the margin box of an item in its area, and its margins at the start and
+/// end, given the `free` space around it in the area with `auto` margins as zero. `auto` margins
+/// take up the free space, and otherwise the item is aligned as `alignment` says.
+fn align_in_area(free: f32, alignment: Alignment, margins: [Option<f32>; 2]) -> (f32, [f32; 2]) {
+    match margins {
+        [None, None] => (0.0, [free.max(0.0) / 2.0; 2]),
+        [None, Some(end)] => (0.0, [free.max(0.0), end]),
+        [Some(start), None] => (0.0, [start, free.max(0.0)]),
+        [Some(start), Some(end)] => {
+            let offset = match alignment {
+                Alignment::End => free,
+                Alignment::Center => free / 2.0,
+                _ => 0.0,
+            };
+            (offset, [start, end])
+        }
+    }
+}
+
+impl<'a> LayoutBox<'a> {
+    /// Lays out the grid items of a grid container, whose content area has been placed, and which
+    /// is Some `height` tall, or None if its height depends on its items. Its content height is set
+    /// to the height of the rows if it depends on them.
+    /// https://www.w3.org/TR/css-grid-1/#layout-algorithm
+    pub(super) fn layout_grid_items(
+        &mut self,
+        fonts: &FontDatabase,
+        style: &'a ComputedStyle,
+        height: Option<f32>,
+    ) {
+        let area = self.dims.content;
+        let grid = Grid::new(self, style, Some(area.width), height);
+
+        // Absolutely positioned children aren't grid items. Their static position is the start of
+        // the content area.
+        for c in self
+            .children
+            .iter_mut()
+            .filter(|c| c.is_absolutely_positioned())
+        {
+            c.dims = Dimensions {
+                content: Rect {
+                    x: area.x,
+                    y: area.y,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+        }
+
+        // The columns are sized first, as the heights of the items depend on their widths. Tracks
+        // are stretched unless the content is aligned otherwise.
+        let stretch = |a| matches!(a, Alignment::Normal | Alignment::Stretch);
+        let children = &mut self.children;
+        let contributions: Vec<_> = grid
+            .items
+            .iter()
+            .map(|item| {
+                let (min, max) = children[item.index].outer_item_widths(fonts, style, area.width);
+                (item.columns.clone(), min, max)
+            })
+            .collect();
+        let widths = size_tracks(
+            &grid.columns,
+            &contributions,
+            Some(area.width),
+            stretch(style.justify_content),
+        );
+        let columns = track_starts(
+            &grid.columns,
+            &widths,
+            Some(area.width),
+            style.justify_content,
+        );
+
+        let cell = |item: &GridItem, rows: Option<(&[f32], &[f32])>| {
+            let (x, width) = span(&columns, &widths, &item.columns);
+            let (y, height) = rows.map_or((0.0, 0.0), |(starts, sizes)| {
+                span(starts, sizes, &item.rows)
+            });
+            Rect {
+                x: area.x + x,
+                y: area.y + y,
+                width,
+                height,
+            }
+        };
+        let contributions: Vec<_> = grid
+            .items
+            .iter()
+            .map(|item| {
+                let c = &mut children[item.index];
+                c.layout_grid_item(
+                    fonts,
+                    style,
+                    cell(item, None),
+                    None,
+                    [item.justify, item.align],
+                );
+                let height = c.margin_box().height;
+                (item.rows.clone(), height, height)
+            })
+            .collect();
+        let heights = size_tracks(
+            &grid.rows,
+            &contributions,
+            height,
+            stretch(style.align_content),
+        );
+        let rows = track_starts(&grid.rows, &heights, height, style.align_content);
+
+        for item in &grid.items {
+            let cell = cell(item, Some((&rows, &heights)));
+            let c = &mut children[item.index];
+            c.layout_grid_item(
+                fonts,
+                style,
+                cell,
+                Some(cell.height),
+                [item.justify, item.align],
+            );
+
+            // A relatively positioned item is moved from where it's placed.
+            let (dx, dy) = c.relative_offset(cell.width, Some(cell.height));
+            if (dx, dy) != (0.0, 0.0) {
+                c.translate(dx, dy);
+            }
+        }
+
+        self.dims.content.height = heights.iter().sum::<f32>() + grid.rows.gaps();
+    }
+
+    /// Lays out a grid item in its grid `area`, aligned along the rows and the columns as
+    /// `justify` and `align` say, with its `auto` margins taking up the free space in the area.
+    /// The area's height is None while the rows are sized, when the item is as tall as its
+    /// content and isn't aligned.
+    ///
+    /// TODO: Align items by their baselines. They're aligned at the start.
+    fn layout_grid_item(
+        &mut self,
+        fonts: &FontDatabase,
+        container: &'a ComputedStyle,
+        area: Rect,
+        area_height: Option<f32>,
+        [justify, align]: [Alignment; 2],
+    ) {
+        // An anonymous item has no style of its own, so it has no edges or sizes.
+        let w = area.width;
+        let (margins, edges, widths, heights) = match self.style_node().map(|n| &n.style) {
+            Some(s) => {
+                let (p, b, m) = (&s.padding, &s.border_width, &s.margin);
+                let edges = [
+                    p.left.to_px_of(w) + p.right.to_px_of(w) + b.left + b.right,
+                    p.top.to_px_of(w) + p.bottom.to_px_of(w) + b.top + b.bottom,
+                ];
+                let width = |l: &Length| content_size(l, Some(w), edges[0], s.box_sizing);
+                let height = |l: &Length| content_size(l, area_height, edges[1], s.box_sizing);
+                (
+                    [
+                        m.left.resolve(w),
+                        m.right.resolve(w),
+                        m.top.resolve(w),
+                        m.bottom.resolve(w),
+                    ],
+                    edges,
+                    [&s.width, &s.min_width, &s.max_width].map(width),
+                    [&s.height, &s.min_height, &s.max_height].map(height),
+                )
+            }
+            None => ([Some(0.0); 4], [0.0; 2], [None; 3], [None; 3]),
+        };
+        let margin = |i: usize| margins[i].unwrap_or(0.0);
+
+        // An item is stretched to fill its area, unless it has a size or `auto` margins, and is
+        // otherwise as wide as its content.
+        let available = w - margin(0) - margin(1) - edges[0];
+        let stretch_width =
+            justify == Alignment::Stretch && margins[..2].iter().all(Option::is_some);
+        let width = match widths[0] {
+            Some(width) => width,
+            None if stretch_width => available,
+            None => {
+                let (min, max) = self.item_widths(fonts, container, w);
+                max.min(available).max(min)
+            }
+        };
+        let width = width
+            .min(widths[2].unwrap_or(f32::INFINITY))
+            .max(widths[1].unwrap_or(0.0));
+        let stretch_height = align == Alignment::Stretch
+            && heights[0].is_none()
+            && margins[2..].iter().all(Option::is_some);
+        let height = match area_height {
+            Some(h) if stretch_height => Some(h - margin(2) - margin(3) - edges[1]),
+            _ => heights[0],
+        };
+        self.layout_item(fonts, container, area, area_height, width, height);
+        if area_height.is_none() {
+            return;
+        }
+
+        let outer = self.margin_box();
+        let (dx, [left, right]) =
+            align_in_area(area.width - outer.width, justify, [margins[0], margins[1]]);
+        let (dy, [top, bottom]) =
+            align_in_area(area.height - outer.height, align, [margins[2], margins[3]]);
+        let border_box = self.border_box();
+        let d = &mut self.dims;
+        (d.margin.left, d.margin.right, d.margin.top, d.margin.bottom) = (left, right, top, bottom);
+        self.translate(
+            area.x + dx + left - border_box.x,
+            area.y + dy + top - border_box.y,
+        );
+    }
+
+    /// Returns the minimum and maximum widths of the content of a grid container, which are the
+    /// widths of its columns when they're as narrow as their items can be, and as wide as their
+    /// items want to be.
+    /// https://www.w3.org/TR/css-grid-1/#intrinsic-sizes
+    pub(super) fn grid_widths(
+        &mut self,
+        fonts: &FontDatabase,
+        style: &'a ComputedStyle,
+        cb_width: f32,
+    ) -> (f32, f32) {
+        let grid = Grid::new(self, style, None, None);
+        let contributions: Vec<_> = grid
+            .items
+            .iter()
+            .map(|item| {
+                let c = &mut self.children[item.index];
+                let (min, max) = c.outer_item_widths(fonts, style, cb_width);
+                (item.columns.clone(), min, max)
+            })
+            .collect();
+        let minimums: Vec<_> = contributions
+            .iter()
+            .map(|(tracks, min, _)| (tracks.clone(), *min, *min))
+            .collect();
+
+        let columns = &grid.columns;
+        let total = |widths: Vec<f32>| widths.iter().sum::<f32>() + columns.gaps();
+        (
+            total(size_tracks(columns, &minimums, None, false)),
+            total(size_tracks(columns, &contributions, None, false)),
+        )
+    }
+}
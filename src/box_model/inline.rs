@@ -0,0 +1,1055 @@
+// Inline formatting contexts, where inline boxes and text are laid out in lines.
+// https://www.w3.org/TR/CSS2/visuren.html#inline-formatting
+// https://www.w3.org/TR/CSS2/visudet.html#line-height
+
+use crate::box_model::float::Floats;
+use crate::box_model::line_break::{break_opportunities, Break};
+use crate::box_model::{first_line_style, BoxType, Dimensions, EdgeSizes, LayoutBox, Rect};
+use crate::dom::NodeType;
+use crate::style::computed::{
+    ComputedStyle, Overflow, TextAlign, TextTransform, VerticalAlign, WhiteSpace,
+};
+use crate::style::css::StyledNode;
+use crate::text::FontDatabase;
+use std::ops::Range;
+
+/// A line of inline content.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineBox {
+    pub rect: Rect,
+    /// The y position of the line's baseline.
+    pub baseline: f32,
+}
+
+/// The part of an inline box on one line. An inline box which is broken across lines has a
+/// fragment on each of them, and only the first and last have margins, borders and padding on the
+/// sides where the box starts and ends.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Fragment {
+    pub dims: Dimensions,
+    /// The y position of the fragment's baseline.
+    pub baseline: f32,
+    /// The text on the line, for the fragments of text.
+    pub text: String,
+}
+
+/// Returns the top and bottom of the area an inline box takes up in its line, relative to its
+/// baseline. This is its line height, split evenly (as half-leading) above and below its glyphs.
+fn line_extents(fonts: &FontDatabase, style: &ComputedStyle) -> (f32, f32) {
+    let m = fonts.metrics(style);
+    let half_leading = (fonts.line_height(style) - m.ascent - m.descent) / 2.0;
+    (-m.ascent - half_leading, m.descent + half_leading)
+}
+
+/// Returns the Some distance to move a box's baseline down from its parent's baseline, or None if
+/// it's aligned with the top or bottom of the line instead. `(top, bottom)` are the box's extents
+/// in the line, relative to its baseline.
+fn baseline_shift(
+    fonts: &FontDatabase,
+    style: &ComputedStyle,
+    parent: &ComputedStyle,
+    (top, bottom): (f32, f32),
+) -> Option<f32> {
+    let pm = fonts.metrics(parent);
+
+    Some(match &style.vertical_align {
+        VerticalAlign::Baseline => 0.0,
+        VerticalAlign::Sub => parent.font_size / 5.0,
+        VerticalAlign::Super => -parent.font_size / 3.0,
+        // The top of the box is aligned with the top of the parent's glyphs.
+        VerticalAlign::TextTop => -pm.ascent - top,
+        VerticalAlign::TextBottom => pm.descent - bottom,
+        // The middle of the box is aligned with the middle of the parent's lowercase letters. The
+        // half-leading is the same above and below, so this is the middle of its glyphs too.
+        VerticalAlign::Middle => -pm.x_height / 2.0 - (top + bottom) / 2.0,
+        // Percentages are relative to the box's line height.
+        VerticalAlign::Length(l) => -l.to_px_of(fonts.line_height(style)),
+        VerticalAlign::Top | VerticalAlign::Bottom => return None,
+    })
+}
+
+/// Applies `text-transform` to some text.
+fn transform(text: &str, transform: TextTransform) -> String {
+    match transform {
+        TextTransform::None => text.to_string(),
+        TextTransform::Uppercase => text.to_uppercase(),
+        TextTransform::Lowercase => text.to_lowercase(),
+        TextTransform::Capitalize => {
+            let mut word_start = true;
+            text.chars()
+                .flat_map(|c| {
+                    let upper = word_start && c.is_alphanumeric();
+                    word_start = c.is_whitespace();
+                    match upper {
+                        true => c.to_uppercase().collect::<Vec<_>>(),
+                        false => vec![c],
+                    }
+                })
+                .collect()
+        }
+    }
+}
+
+/// An inline-level box of a formatting context.
+struct InlineBox<'a> {
+    node: &'a StyledNode<'a>,
+    /// The Some index of the inline box it's in, or None if it's directly in the block container.
+    parent: Option<usize>,
+    /// The edges of the box, where `auto` margins are zero.
+    margin: EdgeSizes,
+    border: EdgeSizes,
+    padding: EdgeSizes,
+    /// The Some margin box of a float, as it's laid out at the top of the container before it's
+    /// placed, or None for an inline box.
+    float: Option<Rect>,
+    /// The Some margin box of an atomic inline, as it's laid out at the top of the container
+    /// before it's placed, and the distance from its top to its baseline.
+    atomic: Option<(Rect, f32)>,
+    /// How far the box is moved once it's laid out, if it's relatively positioned.
+    offset: (f32, f32),
+}
+
+impl InlineBox<'_> {
+    fn start_width(&self) -> f32 {
+        self.margin.left + self.border.left + self.padding.left
+    }
+
+    fn end_width(&self) -> f32 {
+        self.padding.right + self.border.right + self.margin.right
+    }
+}
+
+/// The inline content of a formatting context, flattened into a sequence of items. Each refers to
+/// the index of its box.
+#[derive(Debug, Clone)]
+enum Item {
+    /// The start of an inline box, where its left margin, border and padding are.
+    Start(usize),
+    End(usize),
+    /// The text of a box, at a range of the context's text.
+    Text(usize, Range<usize>),
+    /// A forced line break. Eg: <br>
+    Break(usize),
+    /// A float, which is placed beside the line it's on if it fits.
+    Float(usize),
+    /// An absolutely positioned box, whose static position is where it is on its line.
+    Absolute(usize),
+    /// An atomic inline, which is placed in the line as a whole. Eg: an inline-block
+    Atomic(usize),
+}
+
+/// A sequence of items which can't be broken across lines.
+#[derive(Default)]
+struct Chunk {
+    items: Vec<Item>,
+    /// Must the line be broken after the chunk?
+    mandatory: bool,
+}
+
+/// Ends a chunk, if it has any items. A mandatory break after an empty chunk is moved to the
+/// chunk before it.
+fn end_chunk(chunk: &mut Chunk, chunks: &mut Vec<Chunk>, mandatory: bool) {
+    if chunk.items.is_empty() {
+        if let Some(last) = chunks.last_mut().filter(|_| mandatory) {
+            last.mandatory = true;
+        }
+        return;
+    }
+    chunk.mandatory = mandatory;
+    chunks.push(std::mem::take(chunk));
+}
+
+/// Where a box of an inline formatting context goes: its fragments, and how far to move it once
+/// they're placed. A float has no fragments, and an absolutely positioned box has one at its
+/// static position.
+type Placed = (Vec<Fragment>, (f32, f32));
+
+/// The items on a line.
+#[derive(Default)]
+struct Line {
+    items: Vec<Item>,
+    /// Was the line ended by a forced break?
+    forced: bool,
+}
+
+/// Where an inline box is on a line, as it's laid out.
+#[derive(Default, Clone)]
+struct Placement {
+    on_line: bool,
+    /// The Some left and right of the content, if the box starts or ends on the line.
+    left: Option<f32>,
+    right: Option<f32>,
+    text: String,
+}
+
+/// Lays out the inline-level boxes of a block container in lines.
+struct InlineContext<'a, 'f> {
+    fonts: &'f FontDatabase,
+    /// The style of the block container, which the lines are in.
+    container: &'a ComputedStyle,
+    /// The Some style of the container's first line, while it's laid out.
+    first_line: Option<&'a ComputedStyle>,
+    /// The content area of the container.
+    area: Rect,
+    boxes: Vec<InlineBox<'a>>,
+    /// The items, with the position in the text where each is.
+    items: Vec<(usize, Item)>,
+    /// The text of every box, once white space is processed.
+    text: String,
+    /// Whether the line can be wrapped after each byte of the text.
+    wraps: Vec<bool>,
+    /// Will a collapsible space be removed if it's next? Eg: after another space
+    space_collapses: bool,
+}
+
+impl<'a> LayoutBox<'a> {
+    /// Lays out the inline-level children of a block container in lines, stacked from the top of
+    /// this box's content area, which must already be positioned. `container` is the block the
+    /// lines are in, and `first_line` is the Some style of its first line, if they start with it.
+    /// The lines are shortened beside `floats`, and the floats in them are added to those.
+    pub(super) fn layout_lines(
+        &mut self,
+        fonts: &FontDatabase,
+        container: &'a ComputedStyle,
+        first_line: Option<&'a ComputedStyle>,
+        floats: &mut Floats,
+    ) {
+        let mut context = InlineContext::new(fonts, container, self.dims.content);
+        context.collect(&mut self.children, None);
+
+        let (lines, placed) = context.layout(first_line, floats);
+        self.dims.content.height = lines
+            .last()
+            .map_or(0.0, |l| l.rect.y + l.rect.height - self.dims.content.y);
+        self.lines = lines;
+
+        place_fragments(&mut self.children, &mut placed.into_iter());
+    }
+
+    /// Returns the minimum and maximum widths of the lines of a block container's inline-level
+    /// children, in a container which is `width` wide. The lines are wrapped wherever they can
+    /// be for the minimum, and only at forced breaks for the maximum.
+    pub(super) fn line_widths(
+        &mut self,
+        fonts: &FontDatabase,
+        container: &'a ComputedStyle,
+        width: f32,
+    ) -> (f32, f32) {
+        let area = Rect {
+            width,
+            ..Default::default()
+        };
+        let mut context = InlineContext::new(fonts, container, area);
+        context.collect(&mut self.children, None);
+
+        let (mut min, mut max, mut line) = (0.0f32, 0.0f32, 0.0f32);
+        for chunk in context.chunks() {
+            let (width, hanging) = context.chunk_width(&chunk.items);
+            let floats = chunk.items.iter().filter_map(|i| match i {
+                Item::Float(id) => context.boxes[*id].float.map(|r| r.width),
+                _ => None,
+            });
+            for float in floats {
+                min = min.max(float);
+                line += float;
+            }
+            min = min.max(width - hanging);
+            line += width;
+            max = max.max(line - hanging);
+            if chunk.mandatory {
+                line = 0.0;
+            }
+        }
+        (min, max)
+    }
+
+    /// Returns the Some y position of the last baseline in the box, or None if it has no lines.
+    fn last_baseline(&self) -> Option<f32> {
+        match self.lines.last() {
+            Some(line) => Some(line.baseline),
+            None => self
+                .children
+                .iter()
+                .rev()
+                .filter(|c| !c.is_out_of_flow())
+                .find_map(LayoutBox::last_baseline),
+        }
+    }
+}
+
+/// Gives the inline boxes their fragments, and moves the floats and atomic inlines to where they
+/// were placed and the relatively positioned boxes by their offsets. The boxes are visited in the
+/// order they were collected.
+fn place_fragments<'a>(boxes: &mut [LayoutBox<'a>], fragments: &mut impl Iterator<Item = Placed>) {
+    for b in boxes {
+        let (f, (dx, dy)) = fragments.next().expect("Every box should be laid out");
+        if b.is_float() || b.is_atomic_inline() {
+            b.translate(dx, dy);
+            continue;
+        }
+        b.dims = f.first().map(|f| f.dims).unwrap_or_default();
+        // The boxes in an absolutely positioned box aren't laid out until its containing block is.
+        if b.is_absolutely_positioned() {
+            continue;
+        }
+        b.fragments = f;
+        place_fragments(&mut b.children, fragments);
+        if (dx, dy) != (0.0, 0.0) {
+            b.translate(dx, dy);
+        }
+    }
+}
+
+impl<'a, 'f> InlineContext<'a, 'f> {
+    fn new(fonts: &'f FontDatabase, container: &'a ComputedStyle, area: Rect) -> Self {
+        InlineContext {
+            fonts,
+            container,
+            first_line: None,
+            area,
+            boxes: Vec::new(),
+            items: Vec::new(),
+            text: String::new(),
+            wraps: Vec::new(),
+            // White space at the start of the lines is removed.
+            space_collapses: true,
+        }
+    }
+
+    /// Adds the items of some inline-level boxes, and their descendants. Floats are laid out as
+    /// they're added, but not the boxes in them, which are in a formatting context of their own.
+    fn collect(&mut self, boxes: &mut [LayoutBox<'a>], parent: Option<usize>) {
+        for b in boxes {
+            let id = self.boxes.len();
+            let (node, is_text) = match b.box_type {
+                BoxType::BlockNode(node) | BoxType::FlexNode(node) | BoxType::GridNode(node)
+                    if b.is_float() =>
+                {
+                    let containing_block = Dimensions {
+                        content: self.area,
+                        ..Default::default()
+                    };
+                    b.layout_float(self.fonts, &node.style, containing_block, None);
+                    self.boxes.push(InlineBox {
+                        node,
+                        parent,
+                        margin: EdgeSizes::default(),
+                        border: EdgeSizes::default(),
+                        padding: EdgeSizes::default(),
+                        float: Some(b.margin_box()),
+                        atomic: None,
+                        offset: b.relative_offset(self.area.width, None),
+                    });
+                    self.items.push((self.text.len(), Item::Float(id)));
+                    continue;
+                }
+                BoxType::BlockNode(node) | BoxType::FlexNode(node) | BoxType::GridNode(node)
+                    if b.is_absolutely_positioned() =>
+                {
+                    self.boxes.push(InlineBox {
+                        node,
+                        parent,
+                        margin: EdgeSizes::default(),
+                        border: EdgeSizes::default(),
+                        padding: EdgeSizes::default(),
+                        float: None,
+                        atomic: None,
+                        offset: (0.0, 0.0),
+                    });
+                    self.items.push((self.text.len(), Item::Absolute(id)));
+                    continue;
+                }
+                BoxType::BlockNode(node) | BoxType::FlexNode(node) | BoxType::GridNode(node)
+                    if b.is_atomic_inline() =>
+                {
+                    let containing_block = Dimensions {
+                        content: self.area,
+                        ..Default::default()
+                    };
+                    b.layout_float(self.fonts, &node.style, containing_block, None);
+                    self.push_atomic(id, b, node, parent);
+                    continue;
+                }
+                BoxType::InlineNode(node) => (node, false),
+                BoxType::AnonymousInline(node) => (node, true),
+                BoxType::BlockNode(_)
+                | BoxType::FlexNode(_)
+                | BoxType::GridNode(_)
+                | BoxType::AnonymousBlock => {
+                    panic!("Blocks can't be in inline formatting contexts")
+                }
+            };
+
+            let style = &node.style;
+            let width = self.area.width;
+            // Text has no edges or offset of its own. The text of a block-level pseudo-element is
+            // in an anonymous inline box with the pseudo-element's style, whose box has them.
+            if is_text {
+                self.boxes.push(InlineBox {
+                    node,
+                    parent,
+                    margin: EdgeSizes::default(),
+                    border: EdgeSizes::default(),
+                    padding: EdgeSizes::default(),
+                    float: None,
+                    atomic: None,
+                    offset: (0.0, 0.0),
+                });
+                if let Some(text) = node.text() {
+                    self.push_text(id, text, style);
+                }
+                continue;
+            }
+            let mut inline_box = InlineBox {
+                node,
+                parent,
+                // Vertical margins don't apply to inline boxes.
+                margin: EdgeSizes {
+                    left: style.margin.left.to_px_of(width),
+                    right: style.margin.right.to_px_of(width),
+                    ..Default::default()
+                },
+                border: EdgeSizes {
+                    left: style.border_width.left,
+                    right: style.border_width.right,
+                    top: style.border_width.top,
+                    bottom: style.border_width.bottom,
+                },
+                padding: EdgeSizes {
+                    left: style.padding.left.to_px_of(width),
+                    right: style.padding.right.to_px_of(width),
+                    top: style.padding.top.to_px_of(width),
+                    bottom: style.padding.bottom.to_px_of(width),
+                },
+                float: None,
+                atomic: None,
+                offset: b.relative_offset(width, None),
+            };
+            // A box split around a block only has edges where the box starts and ends.
+            if b.split.before {
+                inline_box.margin.left = 0.0;
+                inline_box.border.left = 0.0;
+                inline_box.padding.left = 0.0;
+            }
+            if b.split.after {
+                inline_box.margin.right = 0.0;
+                inline_box.border.right = 0.0;
+                inline_box.padding.right = 0.0;
+            }
+            self.boxes.push(inline_box);
+
+            let is_br = matches!(&node.node.node_type, NodeType::Element(e) if e.tag_name.eq_ignore_ascii_case("br"));
+
+            if is_br {
+                self.push_item(Item::Break(id), "\n");
+                continue;
+            }
+            self.items.push((self.text.len(), Item::Start(id)));
+            if let Some(text) = node.text() {
+                self.push_text(id, text, style);
+            }
+            self.collect(&mut b.children, Some(id));
+            self.items.push((self.text.len(), Item::End(id)));
+        }
+    }
+
+    /// Adds an atomic inline, which has been laid out at the top of the container. It's placed in
+    /// the line like a character which the line can be wrapped around, if its white space allows.
+    fn push_atomic(
+        &mut self,
+        id: usize,
+        b: &LayoutBox<'a>,
+        node: &'a StyledNode<'a>,
+        parent: Option<usize>,
+    ) {
+        let style = &node.style;
+        let margin_box = b.margin_box();
+        // The baseline of an inline-block is that of its last line, and of a flex or grid
+        // container that of its first. A box without lines, or which clips its content, is
+        // aligned by the bottom of its margin box.
+        // https://www.w3.org/TR/CSS2/visudet.html#leading
+        let visible =
+            style.overflow_x == Overflow::Visible && style.overflow_y == Overflow::Visible;
+        let baseline = match b.box_type {
+            BoxType::BlockNode(_) if visible => b.last_baseline(),
+            BoxType::BlockNode(_) => None,
+            _ => b.first_baseline(),
+        };
+        let baseline = baseline.map_or(margin_box.height, |y| y - margin_box.y);
+
+        self.boxes.push(InlineBox {
+            node,
+            parent,
+            margin: EdgeSizes::default(),
+            border: EdgeSizes::default(),
+            padding: EdgeSizes::default(),
+            float: None,
+            atomic: Some((margin_box, baseline)),
+            offset: b.relative_offset(self.area.width, None),
+        });
+        self.items.push((self.text.len(), Item::Atomic(id)));
+        // An object replacement character stands for the box when the text is broken into lines.
+        self.text.push('\u{FFFC}');
+        let wraps = !matches!(style.white_space, WhiteSpace::Nowrap | WhiteSpace::Pre);
+        self.wraps.resize(self.text.len(), wraps);
+        self.space_collapses = false;
+    }
+
+    /// Adds an item which is a line of its own, or ends one. White space before it is removed.
+    fn push_item(&mut self, item: Item, text: &str) {
+        self.trim_collapsible_space();
+        self.items.push((self.text.len(), item));
+        self.text.push_str(text);
+        self.wraps.resize(self.text.len(), true);
+        self.space_collapses = true;
+    }
+
+    /// Adds the text of a box, with its white space collapsed or preserved.
+    /// https://www.w3.org/TR/css-text-3/#white-space-processing
+    fn push_text(&mut self, id: usize, text: &str, style: &ComputedStyle) {
+        let (collapse_spaces, keep_newlines) = match style.white_space {
+            WhiteSpace::Normal | WhiteSpace::Nowrap => (true, false),
+            WhiteSpace::PreLine => (true, true),
+            WhiteSpace::Pre | WhiteSpace::PreWrap | WhiteSpace::BreakSpaces => (false, true),
+        };
+        let wraps = !matches!(style.white_space, WhiteSpace::Nowrap | WhiteSpace::Pre);
+
+        let mut start = self.text.len();
+        for c in transform(text, style.text_transform).chars() {
+            match c {
+                '\n' if keep_newlines => {
+                    // Spaces around a preserved newline are removed.
+                    if collapse_spaces {
+                        self.trim_collapsible_space();
+                        start = start.min(self.text.len());
+                    }
+                    self.text.push('\n');
+                    self.space_collapses = collapse_spaces;
+                }
+                ' ' | '\t' | '\n' | '\r' | '\u{C}' if collapse_spaces => {
+                    if !self.space_collapses {
+                        self.text.push(' ');
+                        self.space_collapses = true;
+                    }
+                }
+                '\r' => {}
+                // Tabs move to the next multiple of 8 spaces.
+                '\t' => {
+                    let line_start = self.text.rfind('\n').map_or(0, |i| i + 1);
+                    let column = self.text[line_start..].chars().count();
+                    self.text.push_str(&" ".repeat(8 - column % 8));
+                }
+                c => {
+                    self.text.push(c);
+                    self.space_collapses = false;
+                }
+            }
+        }
+
+        self.wraps.resize(self.text.len(), wraps);
+        if start < self.text.len() {
+            self.items
+                .push((start, Item::Text(id, start..self.text.len())));
+        }
+    }
+
+    /// Removes a collapsible space at the end of the text, from the item it was in.
+    fn trim_collapsible_space(&mut self) {
+        if !(self.space_collapses && self.text.ends_with(' ')) {
+            return;
+        }
+        self.text.pop();
+        self.wraps.truncate(self.text.len());
+
+        let len = self.text.len();
+        for (pos, item) in self.items.iter_mut().rev() {
+            *pos = (*pos).min(len);
+            if let Item::Text(_, range) = item {
+                range.end = range.end.min(len);
+                break;
+            }
+        }
+        self.items
+            .retain(|(_, item)| !matches!(item, Item::Text(_, r) if r.is_empty()));
+    }
+
+    /// Splits the items into chunks at the places where lines may be broken.
+    fn chunks(&self) -> Vec<Chunk> {
+        let mut breaks = break_opportunities(&self.text)
+            .into_iter()
+            // Lines can only be wrapped after text which allows it.
+            .filter(|(pos, b)| *b == Break::Mandatory || self.wraps[pos - 1])
+            .peekable();
+
+        let mut chunks: Vec<Chunk> = Vec::new();
+        let mut chunk = Chunk::default();
+
+        for (pos, item) in &self.items {
+            // The ends of boxes stay on the line with the content before them.
+            if let Item::End(_) = item {
+                chunk.items.push(item.clone());
+                continue;
+            }
+
+            let (start, end) = match item {
+                Item::Text(_, range) => (range.start, range.end),
+                _ => (*pos, *pos),
+            };
+            while let Some(&(p, b)) = breaks.peek().filter(|(p, _)| *p <= start) {
+                breaks.next();
+                if p == start {
+                    end_chunk(&mut chunk, &mut chunks, b == Break::Mandatory);
+                }
+            }
+
+            match item {
+                Item::Text(id, range) => {
+                    let mut start = range.start;
+                    while let Some(&(p, b)) = breaks.peek().filter(|(p, _)| *p < end) {
+                        breaks.next();
+                        chunk.items.push(Item::Text(*id, start..p));
+                        end_chunk(&mut chunk, &mut chunks, b == Break::Mandatory);
+                        start = p;
+                    }
+                    chunk.items.push(Item::Text(*id, start..end));
+                }
+                _ => chunk.items.push(item.clone()),
+            }
+        }
+        end_chunk(&mut chunk, &mut chunks, false);
+
+        chunks
+    }
+
+    /// Returns the style of a box, which on the first line is the style it has there.
+    fn style(&self, id: usize) -> &'a ComputedStyle {
+        let node = self.boxes[id].node;
+        match self.first_line {
+            Some(_) => first_line_style(node),
+            None => &node.style,
+        }
+    }
+
+    /// Returns the style of the container, which on the first line is its ::first-line style.
+    fn container_style(&self) -> &'a ComputedStyle {
+        self.first_line.unwrap_or(self.container)
+    }
+
+    /// Returns the top and bottom of the area a box takes up in its line, relative to its baseline.
+    /// An atomic inline takes up its margin box.
+    fn extents(&self, id: usize) -> (f32, f32) {
+        match self.boxes[id].atomic {
+            Some((margin_box, baseline)) => (-baseline, margin_box.height - baseline),
+            None => line_extents(self.fonts, self.style(id)),
+        }
+    }
+
+    /// Returns the width of some text of a box.
+    fn text_width(&self, text: &str, id: usize) -> f32 {
+        self.fonts.shape(text, self.style(id)).width
+    }
+
+    /// Returns the text of an item, without a newline at its end.
+    fn item_text(&self, range: &Range<usize>) -> &str {
+        let text = &self.text[range.clone()];
+        text.strip_suffix('\n').unwrap_or(text)
+    }
+
+    /// Returns the width of an item.
+    fn item_width(&self, item: &Item) -> f32 {
+        match item {
+            Item::Start(id) => self.boxes[*id].start_width(),
+            Item::End(id) => self.boxes[*id].end_width(),
+            Item::Text(id, range) => self.text_width(self.item_text(range), *id),
+            Item::Atomic(id) => self.boxes[*id].atomic.map_or(0.0, |(r, _)| r.width),
+            Item::Break(_) | Item::Float(_) | Item::Absolute(_) => 0.0,
+        }
+    }
+
+    /// Returns the width of a chunk's items, and the width of the spaces at its end which would
+    /// hang past the end of a line.
+    fn chunk_width(&self, items: &[Item]) -> (f32, f32) {
+        let width = items.iter().map(|i| self.item_width(i)).sum();
+        let hanging = match Self::last_text(items).map(|i| &items[i]) {
+            Some(Item::Text(id, range)) => self.trim_hanging(*id, range).1,
+            _ => 0.0,
+        };
+        (width, hanging)
+    }
+
+    /// Returns the range of the text which is left at the end of a line, and the width of the
+    /// spaces after it, which hang past the end of the line. Eg: "word  " has two hanging spaces
+    fn trim_hanging(&self, id: usize, range: &Range<usize>) -> (Range<usize>, f32) {
+        let style = self.style(id);
+        let text = self.item_text(range);
+        let trimmed = match style.white_space {
+            // Lines ended by a preserved newline keep it, and the spaces before it.
+            _ if text.len() < range.len() => return (range.clone(), 0.0),
+            WhiteSpace::Pre | WhiteSpace::BreakSpaces => text,
+            _ => text.trim_end_matches(' '),
+        };
+        let hanging = self.text_width(&text[trimmed.len()..], id);
+        (range.start..range.start + trimmed.len(), hanging)
+    }
+
+    /// Returns the Some index of the last text item, if only box ends and boxes out of the flow
+    /// come after it.
+    fn last_text(items: &[Item]) -> Option<usize> {
+        let i = items
+            .iter()
+            .rposition(|i| !matches!(i, Item::End(_) | Item::Float(_) | Item::Absolute(_)))?;
+        matches!(items[i], Item::Text(..)).then_some(i)
+    }
+
+    /// Lays out the items in lines, which are filled with chunks and broken before chunks which
+    /// wouldn't fit. Each line is shortened beside the floats next to it, and moved down below
+    /// them if its first chunk doesn't fit beside them. The floats in the items are placed beside
+    /// the line they're reached on if they fit there, and below it if they don't.
+    ///
+    /// The first line is laid out with the Some `first_line` style of the container, and the
+    /// style its content has on it, if the lines start with the container's first line.
+    ///
+    /// Returns the lines, and where each box goes.
+    fn layout(
+        &mut self,
+        first_line: Option<&'a ComputedStyle>,
+        floats: &mut Floats,
+    ) -> (Vec<LineBox>, Vec<Placed>) {
+        let mut indent = match first_line {
+            Some(_) => self.container.text_indent.to_px_of(self.area.width),
+            None => 0.0,
+        };
+        self.first_line = first_line;
+        let (area_left, area_right) = (self.area.x, self.area.x + self.area.width);
+        // It isn't known how tall a line is until it's laid out, so the space beside the floats
+        // is found for a line as tall as the container's strut.
+        let strut = self.fonts.line_height(self.container);
+
+        let mut line_boxes = Vec::new();
+        let mut fragments = vec![Vec::new(); self.boxes.len()];
+        let mut moves: Vec<_> = self.boxes.iter().map(|b| b.offset).collect();
+        let mut placed = vec![false; self.boxes.len()];
+        let mut y = self.area.y;
+
+        let mut chunks = self.chunks().into_iter().peekable();
+        while chunks.peek().is_some() {
+            let mut line = Line::default();
+            let mut width = 0.0;
+            let (mut left, mut right) = floats.space(y, strut, area_left, area_right);
+            let mut below = Vec::new();
+
+            while let Some(chunk) = chunks.peek() {
+                for item in &chunk.items {
+                    let Item::Float(id) = *item else { continue };
+                    if std::mem::replace(&mut placed[id], true) {
+                        continue;
+                    }
+                    let b = &self.boxes[id];
+                    let margin_box = b.float.expect("Floats should be laid out");
+                    if line.items.is_empty() || width + margin_box.width <= right - left - indent {
+                        let (dx, dy) = floats.place(&b.node.style, margin_box, y, self.area);
+                        moves[id] = (b.offset.0 + dx, b.offset.1 + dy);
+                        (left, right) = floats.space(y, strut, area_left, area_right);
+                    } else {
+                        below.push(id);
+                    }
+                }
+
+                // Chunks which are too wide for any line overflow a line of their own.
+                let (chunk_width, hanging) = self.chunk_width(&chunk.items);
+                if width + chunk_width - hanging > right - left - indent {
+                    if !line.items.is_empty() {
+                        break;
+                    }
+                    let needed = chunk_width - hanging + indent;
+                    let (top, l, r) = floats.find_space(y, needed, strut, area_left, area_right);
+                    if top > y {
+                        (y, left, right) = (top, l, r);
+                        continue;
+                    }
+                }
+
+                let chunk = chunks.next().unwrap();
+                width += chunk_width;
+                line.items.extend(chunk.items);
+                if chunk.mandatory {
+                    line.forced = true;
+                    break;
+                }
+            }
+
+            // Justified text is aligned to the start on the last line, and before forced breaks.
+            let justify = chunks.peek().is_some() && !line.forced;
+            let band = Rect {
+                x: left,
+                y,
+                width: right - left,
+                height: 0.0,
+            };
+            let line_box =
+                self.layout_line(&mut line, band, indent, justify, &mut fragments, &mut moves);
+            indent = 0.0;
+            self.first_line = None;
+
+            // Lines without any content or edges take up no space, so aren't kept.
+            if let Some(line_box) = line_box {
+                y += line_box.rect.height;
+                line_boxes.push(line_box);
+            }
+
+            for id in below {
+                let b = &self.boxes[id];
+                let margin_box = b.float.expect("Floats should be laid out");
+                let (dx, dy) = floats.place(&b.node.style, margin_box, y, self.area);
+                moves[id] = (b.offset.0 + dx, b.offset.1 + dy);
+            }
+        }
+
+        (line_boxes, fragments.into_iter().zip(moves).collect())
+    }
+
+    /// Lays out a line at the top of a `band` of the container's content area, adding the
+    /// fragments of its boxes, and how far its atomic inlines are moved. Returns the Some line box,
+    /// or None if the line is a phantom line, which has no height.
+    fn layout_line(
+        &self,
+        line: &mut Line,
+        band: Rect,
+        indent: f32,
+        justify: bool,
+        fragments: &mut [Vec<Fragment>],
+        moves: &mut [(f32, f32)],
+    ) -> Option<LineBox> {
+        let y = band.y;
+        // Spaces at the end of the line hang past it, so aren't aligned or shown.
+        if let Some(i) = Self::last_text(&line.items) {
+            if let Item::Text(id, range) = &line.items[i] {
+                let (range, _) = self.trim_hanging(*id, range);
+                match range.is_empty() {
+                    true => _ = line.items.remove(i),
+                    false => line.items[i] = Item::Text(*id, range),
+                }
+            }
+        }
+
+        let width: f32 = line.items.iter().map(|i| self.item_width(i)).sum();
+        let extra = (band.width - indent - width).max(0.0);
+        let spaces = line
+            .items
+            .iter()
+            .map(|i| match i {
+                Item::Text(_, range) => self.item_text(range).matches(' ').count(),
+                _ => 0,
+            })
+            .sum::<usize>();
+
+        let (offset, space_width) = match self.container.text_align {
+            TextAlign::Start | TextAlign::Left => (0.0, 0.0),
+            TextAlign::End | TextAlign::Right => (extra, 0.0),
+            TextAlign::Center => (extra / 2.0, 0.0),
+            TextAlign::Justify if justify && spaces > 0 => (0.0, extra / spaces as f32),
+            TextAlign::Justify => (0.0, 0.0),
+        };
+
+        // Place the items along the line.
+        let mut placements = vec![Placement::default(); self.boxes.len()];
+        let line_left = band.x + indent + offset;
+        let mut x = line_left;
+        let mut phantom = true;
+        for item in &line.items {
+            match item {
+                Item::Start(id) => {
+                    let b = &self.boxes[*id];
+                    x += b.start_width();
+                    placements[*id].left = Some(x);
+                    phantom &= b.start_width() == 0.0;
+                }
+                Item::End(id) => {
+                    let b = &self.boxes[*id];
+                    placements[*id].right = Some(x);
+                    x += b.end_width();
+                    phantom &= b.end_width() == 0.0;
+                }
+                Item::Text(id, range) => {
+                    let text = self.item_text(range);
+                    let p = &mut placements[*id];
+                    p.left.get_or_insert(x);
+                    x +=
+                        self.text_width(text, *id) + space_width * text.matches(' ').count() as f32;
+                    p.right = Some(x);
+                    p.text.push_str(text);
+                    // Preserved newlines aren't shown, but give the line height.
+                    phantom &= range.is_empty();
+                }
+                Item::Break(id) => {
+                    placements[*id].left = Some(x);
+                    placements[*id].right = Some(x);
+                    phantom = false;
+                }
+                Item::Atomic(id) => {
+                    let p = &mut placements[*id];
+                    p.left = Some(x);
+                    x += self.item_width(item);
+                    p.right = Some(x);
+                    phantom = false;
+                }
+                Item::Float(_) => {}
+                Item::Absolute(id) => {
+                    let content = Rect {
+                        x,
+                        y,
+                        ..Default::default()
+                    };
+                    fragments[*id].push(Fragment {
+                        dims: Dimensions {
+                            content,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+        let line_right = x;
+
+        // Every box with an item on the line is on it, along with the boxes it's in.
+        for item in &line.items {
+            let (Item::Start(id)
+            | Item::End(id)
+            | Item::Text(id, _)
+            | Item::Break(id)
+            | Item::Atomic(id)) = item
+            else {
+                continue;
+            };
+            let mut id = Some(*id);
+            while let Some(i) = id.filter(|i| !placements[*i].on_line) {
+                placements[i].on_line = true;
+                id = self.boxes[i].parent;
+            }
+        }
+
+        // Align the boxes vertically, relative to the baseline of the container's strut. Boxes
+        // aligned to the top or bottom of the line (and the boxes in them) are aligned relative to
+        // their own baseline until the line's height is known.
+        let (mut top, mut bottom) = line_extents(self.fonts, self.container_style());
+        let mut offsets = vec![0.0; self.boxes.len()];
+        let mut aligned_to: Vec<Option<usize>> = vec![None; self.boxes.len()];
+        let mut aligned_extents: Vec<(usize, f32, f32)> = Vec::new();
+
+        for id in (0..self.boxes.len()).filter(|i| placements[*i].on_line) {
+            let b = &self.boxes[id];
+            let style = self.style(id);
+            let parent_style = b.parent.map_or(self.container_style(), |p| self.style(p));
+            let (parent_offset, parent_aligned_to) = match b.parent {
+                Some(p) => (offsets[p], aligned_to[p]),
+                None => (0.0, None),
+            };
+
+            let (box_top, box_bottom) = self.extents(id);
+            match baseline_shift(self.fonts, style, parent_style, (box_top, box_bottom)) {
+                Some(shift) => {
+                    offsets[id] = parent_offset + shift;
+                    aligned_to[id] = parent_aligned_to;
+                }
+                None => {
+                    aligned_to[id] = Some(id);
+                    aligned_extents.push((id, 0.0, 0.0));
+                }
+            }
+
+            let (box_top, box_bottom) = (offsets[id] + box_top, offsets[id] + box_bottom);
+            match aligned_to[id] {
+                Some(root) => {
+                    let extents = aligned_extents.iter_mut().find(|e| e.0 == root).unwrap();
+                    extents.1 = extents.1.min(box_top);
+                    extents.2 = extents.2.max(box_bottom);
+                }
+                None => {
+                    top = top.min(box_top);
+                    bottom = bottom.max(box_bottom);
+                }
+            }
+        }
+
+        // The line is made tall enough for the boxes aligned to its top or bottom.
+        for &(id, box_top, box_bottom) in &aligned_extents {
+            let height = box_bottom - box_top;
+            if bottom - top < height {
+                match self.style(id).vertical_align {
+                    VerticalAlign::Top => bottom = top + height,
+                    _ => top = bottom - height,
+                }
+            }
+        }
+        for &(id, box_top, box_bottom) in &aligned_extents {
+            let baseline = match self.style(id).vertical_align {
+                VerticalAlign::Top => top - box_top,
+                _ => bottom - box_bottom,
+            };
+            for i in (id..self.boxes.len()).filter(|i| aligned_to[*i] == Some(id)) {
+                offsets[i] += baseline;
+            }
+        }
+
+        if phantom {
+            (top, bottom) = (0.0, 0.0);
+        }
+        let baseline = y - top;
+
+        for (id, p) in placements.iter().enumerate().filter(|(_, p)| p.on_line) {
+            let b = &self.boxes[id];
+            // An atomic inline has no fragments, but is moved to its place on the line.
+            if let Some((margin_box, box_baseline)) = b.atomic {
+                let (dx, dy) = (
+                    p.left.unwrap_or(line_left) - margin_box.x,
+                    baseline + offsets[id] - box_baseline - margin_box.y,
+                );
+                moves[id] = (b.offset.0 + dx, b.offset.1 + dy);
+                continue;
+            }
+            let m = self.fonts.metrics(self.style(id));
+            let (starts, ends) = (p.left.is_some(), p.right.is_some());
+            let left = p.left.unwrap_or(line_left);
+            let right = p.right.unwrap_or(line_right);
+
+            // Only the content area of an inline box is used to place its vertical edges.
+            let mut dims = Dimensions {
+                content: Rect {
+                    x: left,
+                    y: baseline + offsets[id] - m.ascent,
+                    width: right - left,
+                    height: m.ascent + m.descent,
+                },
+                ..Default::default()
+            };
+            let (d, pad, border, margin) = (&mut dims, b.padding, b.border, b.margin);
+            (d.padding.top, d.padding.bottom) = (pad.top, pad.bottom);
+            (d.border.top, d.border.bottom) = (border.top, border.bottom);
+            if starts {
+                (d.margin.left, d.border.left, d.padding.left) =
+                    (margin.left, border.left, pad.left);
+            }
+            if ends {
+                (d.margin.right, d.border.right, d.padding.right) =
+                    (margin.right, border.right, pad.right);
+            }
+
+            fragments[id].push(Fragment {
+                dims,
+                baseline: baseline + offsets[id],
+                text: p.text.clone(),
+            });
+        }
+
+        (!phantom).then_some(LineBox {
+            rect: Rect {
+                x: band.x,
+                y,
+                width: band.width,
+                height: bottom - top,
+            },
+            baseline,
+        })
+    }
+}
@@ -0,0 +1,209 @@
+// Finding where lines of text may be broken.
+// https://www.unicode.org/reports/tr14/
+
+/// A place where a line may or must be broken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Break {
+    /// The line must be broken. Eg: after a newline
+    Mandatory,
+    /// The line may be broken. Eg: after a space
+    Allowed,
+}
+
+/// The line breaking classes of characters, from UAX #14.
+/// Classes which only apply to scripts we don't support are treated as alphabetic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Hard,
+    CarriageReturn,
+    LineFeed,
+    NextLine,
+    Space,
+    ZeroWidthSpace,
+    ZeroWidthJoiner,
+    CombiningMark,
+    WordJoiner,
+    /// Non-breaking. Eg: a no-break space
+    Glue,
+    /// Characters which allow a break after them. Eg: a tab
+    BreakAfter,
+    BreakBefore,
+    Hyphen,
+    /// An object in the text, which may be broken around.
+    Contingent,
+    Open,
+    Close,
+    CloseParenthesis,
+    Quotation,
+    Exclamation,
+    /// A separator within numbers. Eg: ,
+    Infix,
+    /// Symbols allowing a break after. Eg: /
+    Symbol,
+    Inseparable,
+    /// Characters which can't start a line. Eg: small kana
+    NonStarter,
+    Ideographic,
+    Numeric,
+    /// Eg: $
+    Prefix,
+    /// Eg: %
+    Postfix,
+    Alphabetic,
+}
+
+/// Returns the line breaking class of a character.
+fn class(c: char) -> Class {
+    use Class::*;
+    match c {
+        '\u{B}' | '\u{C}' | '\u{2028}' | '\u{2029}' => Hard,
+        '\r' => CarriageReturn,
+        '\n' => LineFeed,
+        '\u{85}' => NextLine,
+        ' ' => Space,
+        '\u{200B}' => ZeroWidthSpace,
+        '\u{200D}' => ZeroWidthJoiner,
+        '\u{2060}' | '\u{FEFF}' => WordJoiner,
+        '\u{A0}' | '\u{202F}' | '\u{2007}' | '\u{2011}' | '\u{F0C}' => Glue,
+        '\t' | '\u{AD}' | '\u{2010}' | '\u{2012}' | '\u{2013}' | '|' => BreakAfter,
+        '\u{B4}' | '\u{2C8}' | '\u{2CC}' => BreakBefore,
+        '-' => Hyphen,
+        '\u{FFFC}' => Contingent,
+        '(' | '[' | '{' | '\u{A1}' | '\u{BF}' | '\u{2018}' | '\u{201C}' | '\u{3008}'
+        | '\u{300A}' | '\u{300C}' | '\u{300E}' | '\u{3010}' | '\u{FF08}' => Open,
+        '}' | '\u{3001}' | '\u{3002}' | '\u{3009}' | '\u{300B}' | '\u{300D}' | '\u{300F}'
+        | '\u{3011}' | '\u{FF0C}' | '\u{FF0E}' => Close,
+        ')' | ']' | '\u{FF09}' => CloseParenthesis,
+        '"' | '\'' | '\u{AB}' | '\u{BB}' | '\u{2019}' | '\u{201D}' => Quotation,
+        '!' | '?' | '\u{FF01}' | '\u{FF1F}' => Exclamation,
+        ',' | '.' | ':' | ';' => Infix,
+        '/' => Symbol,
+        '\u{2024}' | '\u{2025}' | '\u{2026}' => Inseparable,
+        '\u{3005}' | '\u{303B}' | '\u{30FC}' | '\u{3041}' | '\u{3043}' | '\u{3045}'
+        | '\u{3047}' | '\u{3049}' | '\u{3063}' | '\u{30A1}' | '\u{30A3}' | '\u{30A5}'
+        | '\u{30A7}' | '\u{30A9}' | '\u{30C3}' => NonStarter,
+        '0'..='9' => Numeric,
+        '$' | '+' | '\\' | '\u{A3}' | '\u{A5}' | '\u{20AC}' => Prefix,
+        '%' | '\u{A2}' | '\u{B0}' | '\u{2030}' => Postfix,
+        '\u{300}'..='\u{36F}' | '\u{FE00}'..='\u{FE0F}' | '\u{20D0}'..='\u{20FF}' => CombiningMark,
+        '\u{2E80}'..='\u{2FFF}'
+        | '\u{3040}'..='\u{30FF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{AC00}'..='\u{D7AF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{FF00}'..='\u{FF60}'
+        | '\u{1F300}'..='\u{1FAFF}'
+        | '\u{20000}'..='\u{3FFFD}' => Ideographic,
+        _ => Alphabetic,
+    }
+}
+
+/// Returns the positions in `text` (as byte offsets) before which the line may or must be broken,
+/// in order. The end of the text isn't included.
+pub fn break_opportunities(text: &str) -> Vec<(usize, Break)> {
+    use Class::*;
+
+    let mut breaks = Vec::new();
+    let mut chars = text.char_indices();
+    let Some((_, first)) = chars.next() else {
+        return breaks;
+    };
+
+    // The class of the previous character, and the class before any spaces which follow it.
+    let mut prev = match class(first) {
+        // A combining mark with nothing to combine with is alphabetic.
+        CombiningMark | ZeroWidthJoiner => Alphabetic,
+        c => c,
+    };
+    let mut before_spaces = prev;
+    // Zero width joiners are combined into the previous character, but still prevent breaks.
+    let mut after_zwj = class(first) == ZeroWidthJoiner;
+
+    for (i, c) in chars {
+        let current = class(c);
+
+        // Combining marks take the class of the character they're attached to.
+        if matches!(current, CombiningMark | ZeroWidthJoiner)
+            && !matches!(
+                prev,
+                Hard | CarriageReturn | LineFeed | NextLine | Space | ZeroWidthSpace
+            )
+        {
+            after_zwj = current == ZeroWidthJoiner;
+            continue;
+        }
+        let current = match current {
+            CombiningMark | ZeroWidthJoiner => Alphabetic,
+            c => c,
+        };
+
+        if let Some(b) = pair_break(prev, before_spaces, current, after_zwj) {
+            breaks.push((i, b));
+        }
+
+        after_zwj = false;
+        if current != Space {
+            before_spaces = current;
+        }
+        prev = current;
+    }
+
+    breaks
+}
+
+/// Returns the Some break between two characters, or None if the line can't be broken there.
+/// `before_spaces` is the class of the last character which isn't a space, for the rules which
+/// apply across spaces. Eg: `( a` can't be broken after the space.
+fn pair_break(prev: Class, before_spaces: Class, current: Class, after_zwj: bool) -> Option<Break> {
+    use Class::*;
+
+    // The rules are applied in order, and the first which matches decides the break.
+    let allowed = match (prev, current) {
+        // LB4, LB5: Always break after hard line breaks, but not within CR LF.
+        (Hard | LineFeed | NextLine, _) => return Some(Break::Mandatory),
+        (CarriageReturn, LineFeed) => false,
+        (CarriageReturn, _) => return Some(Break::Mandatory),
+        // LB6, LB7: Don't break before hard line breaks, spaces or zero width spaces.
+        (_, Hard | CarriageReturn | LineFeed | NextLine | Space | ZeroWidthSpace) => false,
+        // LB8: Break after zero width spaces, even if spaces follow.
+        _ if before_spaces == ZeroWidthSpace => true,
+        // LB8a: Don't break after a zero width joiner.
+        _ if after_zwj => false,
+        // LB11, LB12, LB12a: Word joiners and non-breaking characters.
+        (WordJoiner, _) | (_, WordJoiner) | (Glue, _) => false,
+        (p, Glue) if !matches!(p, Space | BreakAfter | Hyphen) => false,
+        // LB13: Don't break before closing punctuation, even after spaces.
+        (_, Close | CloseParenthesis | Exclamation | Infix | Symbol) => false,
+        // LB14, LB15, LB16: Don't break after opening punctuation, even across spaces.
+        _ if before_spaces == Open => false,
+        (_, Open) if before_spaces == Quotation => false,
+        (_, NonStarter) if matches!(before_spaces, Close | CloseParenthesis) => false,
+        // LB18: Break after spaces.
+        (Space, _) => true,
+        // LB19: Don't break around quotation marks.
+        (Quotation, _) | (_, Quotation) => false,
+        // LB20: Break around contingent breaks.
+        (Contingent, _) | (_, Contingent) => true,
+        // LB21: Don't break before hyphens or small kana, or after break before characters.
+        (_, BreakAfter | Hyphen | NonStarter) | (BreakBefore, _) => false,
+        // LB22: Don't break before inseparable characters.
+        (_, Inseparable) => false,
+        // LB23, LB23a, LB24: Don't break within words, numbers and their prefixes or suffixes.
+        (Alphabetic, Numeric) | (Numeric, Alphabetic) => false,
+        (Prefix, Ideographic) | (Ideographic, Postfix) => false,
+        (Prefix | Postfix, Alphabetic) | (Alphabetic, Prefix | Postfix) => false,
+        // LB25: Don't break within numbers. Eg: $(12.35)
+        (Close | CloseParenthesis | Numeric, Postfix | Prefix)
+        | (Postfix | Prefix, Open | Numeric) => false,
+        (Hyphen | Infix | Numeric | Symbol, Numeric) => false,
+        // LB28, LB29: Don't break between letters. Eg: e.g.
+        (Alphabetic | Infix, Alphabetic) => false,
+        // LB30: Don't break between letters and brackets. Eg: a(b)
+        (Alphabetic | Numeric, Open) | (CloseParenthesis, Alphabetic | Numeric) => false,
+        // LB31: Break everywhere else.
+        _ => true,
+    };
+
+    allowed.then_some(Break::Allowed)
+}
@@ -0,0 +1,1276 @@
+// CSS box model. All sizes are in px.
+
+mod flex;
+mod float;
+mod grid;
+pub mod inline;
+mod line_break;
+mod position;
+mod stacking;
+
+use crate::box_model::float::Floats;
+use crate::box_model::inline::{Fragment, LineBox};
+use crate::dom::NodeType;
+use crate::style::computed::{
+    Alignment, BoxSizing, ComputedStyle, Float, Length, ListStylePosition, Overflow, Position,
+    WhiteSpace,
+};
+use crate::style::css::{PseudoElement, StyledNode};
+use crate::text::{fonts, FontDatabase};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Dimensions {
+    /// Position of the content area relative to the document origin
+    pub content: Rect,
+
+    // Surrounding edges
+    pub padding: EdgeSizes,
+    pub border: EdgeSizes,
+    pub margin: EdgeSizes,
+}
+
+/// Rect is short for Rectangle :)
+/// It is a cartesian shape :)
+/// This is a largely immutable object :) TODO: Verify
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct EdgeSizes {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
+}
+
+/// Represents a "box" in the dom box model.
+pub struct LayoutBox<'a> {
+    /// The used sizes of the box, once it is laid out.
+    pub dims: Dimensions,
+    pub box_type: BoxType<'a>,
+    pub children: Vec<LayoutBox<'a>>,
+    /// The lines of an anonymous block, which its inline boxes are laid out in.
+    pub lines: Vec<LineBox>,
+    /// The parts of an inline box on each line it's on. Its `dims` are those of the first.
+    pub fragments: Vec<Fragment>,
+    /// Where an inline box is split around the blocks in it.
+    pub split: Split,
+    /// The Some box of a list item's outside ::marker, which is beside its first line, outside
+    /// its content, rather than in its lines. Eg: the bullet of <li> in a <ul>
+    pub marker: Option<Box<LayoutBox<'a>>>,
+}
+
+/// The parts an inline box is split into around the blocks in it, which are each in the lines of
+/// a different anonymous block. Eg: `<em>a<p>b</p>c</em>` has a part before and after the <p>
+/// https://www.w3.org/TR/CSS2/visuren.html#anonymous-block-level
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Split {
+    /// Is there a part of the same box before this one?
+    pub before: bool,
+    /// Is there a part of the same box after this one?
+    pub after: bool,
+}
+
+impl Dimensions {
+    /// Dimensions with only a content area, at the document origin. Eg: the viewport
+    pub fn of_size(width: f32, height: f32) -> Self {
+        Dimensions {
+            content: Rect {
+                width,
+                height,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    // The area covered by the content area & its padding.
+    pub fn padding_box(self) -> Rect {
+        self.content.expanded_by(self.padding)
+    }
+
+    // Area covered by the content area plus padding and borders.
+    pub fn border_box(self) -> Rect {
+        self.padding_box().expanded_by(self.border)
+    }
+
+    // The area covered by the content area plus padding, borders, and margin.
+    pub fn margin_box(self) -> Rect {
+        self.border_box().expanded_by(self.margin)
+    }
+}
+
+impl Rect {
+    pub fn expanded_by(self, edge: EdgeSizes) -> Rect {
+        Rect {
+            x: self.x - edge.left,
+            y: self.y - edge.top,
+            width: self.width + edge.left + edge.right,
+            height: self.height + edge.top + edge.bottom,
+        }
+    }
+}
+
+impl<'a> LayoutBox<'a> {
+    fn new(box_type: BoxType<'a>) -> Self {
+        Self {
+            box_type,
+            dims: Default::default(),
+            children: Vec::new(),
+            lines: Vec::new(),
+            fragments: Vec::new(),
+            split: Split::default(),
+            marker: None,
+        }
+    }
+
+    /// Returns the Some node the box was generated for, or None for an anonymous block.
+    pub fn style_node(&self) -> Option<&'a StyledNode<'a>> {
+        match self.box_type {
+            BoxType::BlockNode(n)
+            | BoxType::FlexNode(n)
+            | BoxType::GridNode(n)
+            | BoxType::InlineNode(n)
+            | BoxType::AnonymousInline(n) => Some(n),
+            BoxType::AnonymousBlock => None,
+        }
+    }
+
+    /// Is the box laid out in lines, rather than as a block?
+    pub fn is_inline_level(&self) -> bool {
+        matches!(
+            self.box_type,
+            BoxType::InlineNode(_) | BoxType::AnonymousInline(_)
+        ) || self.is_atomic_inline()
+    }
+
+    /// Is the box laid out as a block, but placed in its line as a whole? Eg: an inline-block
+    pub fn is_atomic_inline(&self) -> bool {
+        let (BoxType::BlockNode(n) | BoxType::FlexNode(n) | BoxType::GridNode(n)) = self.box_type
+        else {
+            return false;
+        };
+        n.style.display.is_atomic_inline() && !self.is_out_of_flow()
+    }
+
+    /// Is the box floated, and so taken out of the flow of the boxes around it?
+    pub fn is_float(&self) -> bool {
+        let (BoxType::BlockNode(n) | BoxType::FlexNode(n) | BoxType::GridNode(n)) = self.box_type
+        else {
+            return false;
+        };
+        n.style.float != Float::None && !self.is_absolutely_positioned()
+    }
+
+    /// Is the box positioned with `absolute` or `fixed`, and so taken out of the flow entirely?
+    pub fn is_absolutely_positioned(&self) -> bool {
+        let (BoxType::BlockNode(n) | BoxType::FlexNode(n) | BoxType::GridNode(n)) = self.box_type
+        else {
+            return false;
+        };
+        matches!(n.style.position, Position::Absolute | Position::Fixed)
+    }
+
+    /// Is the box out of the flow of the boxes around it, so it's neither inline-level nor laid
+    /// out as a block between them?
+    fn is_out_of_flow(&self) -> bool {
+        self.is_float() || self.is_absolutely_positioned()
+    }
+
+    /// Are the children of a block container laid out in lines? Its children are then all
+    /// inline-level boxes or out of the flow.
+    fn has_lines(&self) -> bool {
+        self.children.iter().any(LayoutBox::is_inline_level)
+    }
+
+    /// The position and size of the content area.
+    pub fn content_box(&self) -> Rect {
+        self.dims.content
+    }
+
+    pub fn padding_box(&self) -> Rect {
+        self.dims.padding_box()
+    }
+
+    pub fn border_box(&self) -> Rect {
+        self.dims.border_box()
+    }
+
+    pub fn margin_box(&self) -> Rect {
+        self.dims.margin_box()
+    }
+
+    /// An anonymous block has no style of its own, so it fills the width of its container, and is
+    /// as tall as the lines its inline boxes are laid out in. The lines use the style of the
+    /// `parent` block, and `first_line` is the Some style of the parent's first line, if they
+    /// start with it.
+    fn layout_anonymous_block(
+        &mut self,
+        fonts: &FontDatabase,
+        containing_block: Dimensions,
+        parent: &'a ComputedStyle,
+        first_line: Option<&'a ComputedStyle>,
+        floats: &mut Floats,
+    ) {
+        let d = &mut self.dims;
+        d.content.width = containing_block.content.width;
+        d.content.x = containing_block.content.x;
+        d.content.y = containing_block.content.y + containing_block.content.height;
+
+        self.layout_lines(fonts, parent, first_line, floats);
+    }
+
+    /// Moves the box and everything in it.
+    fn translate(&mut self, dx: f32, dy: f32) {
+        let move_rect = |r: &mut Rect| {
+            r.x += dx;
+            r.y += dy;
+        };
+        move_rect(&mut self.dims.content);
+        for line in &mut self.lines {
+            move_rect(&mut line.rect);
+            line.baseline += dy;
+        }
+        for fragment in &mut self.fragments {
+            move_rect(&mut fragment.dims.content);
+            fragment.baseline += dy;
+        }
+        for child in &mut self.children {
+            child.translate(dx, dy);
+        }
+        if let Some(marker) = &mut self.marker {
+            marker.translate(dx, dy);
+        }
+    }
+
+    /// Lays out a block with a style below the content already in its container. `cb_height` is
+    /// the Some height of the containing block, or None if it depends on its content. `margin` is
+    /// the margins above the block which haven't been used yet, which its top margin collapses
+    /// with. `floats` are the floats of the block formatting context it's in, and `new_context` is
+    /// whether it starts a new one, which keeps its children's margins and floats inside it.
+    ///
+    /// Returns the margins below it, and whether its top and bottom margins collapse together,
+    /// in which case they're included in the margins below it.
+    #[allow(clippy::too_many_arguments)]
+    fn layout_block(
+        &mut self,
+        fonts: &FontDatabase,
+        style: &'a ComputedStyle,
+        containing_block: Dimensions,
+        cb_height: Option<f32>,
+        margin: CollapsedMargin,
+        floats: &mut Floats,
+        new_context: bool,
+    ) -> (CollapsedMargin, bool) {
+        // Child width can depend on parent width, so we need to calculate this box's width before laying out its children.
+        self.calculate_block_width(fonts, style, containing_block);
+
+        // Determine where the box is located within its container.
+        let margin =
+            self.calculate_block_position(style, containing_block, margin, new_context, floats);
+
+        // Percentage heights of the children are relative to this box's height, if it doesn't
+        // depend on them.
+        let (height, min_height, max_height) = self.height_constraints(style, cb_height);
+        let height = height.map(|h| h.min(max_height).max(min_height));
+
+        // Recursively lay out the children of this box.
+        let mut own_floats = Floats::default();
+        let floats = match new_context {
+            true => &mut own_floats,
+            false => floats,
+        };
+        // The box may have been laid out before, at another size. Eg: a flex item
+        self.dims.content.height = 0.0;
+        let (margin, placed) = self.layout_contents(fonts, style, height, margin, floats);
+
+        // Parent height can depend on child height, so `calculate_height` must be called *after* the children are laid out.
+        let floats_bottom = own_floats.bottom();
+        let after = self.calculate_block_height(
+            style,
+            cb_height,
+            margin,
+            placed,
+            new_context,
+            floats_bottom,
+        );
+
+        self.layout_marker(fonts);
+
+        // A positioned box is the containing block of the absolutely positioned boxes in it.
+        if style.position != Position::Static {
+            self.layout_absolute_descendants(fonts, self.padding_box());
+        }
+        after
+    }
+
+    /// Lays out the outside marker of a list item, once its content is laid out. The marker is as
+    /// wide as its content and ends where the list item's content starts, with its baseline on
+    /// the first line of the list item, or its top at the top of the list item if it has none.
+    /// https://www.w3.org/TR/css-lists-3/#list-style-position-property
+    fn layout_marker(&mut self, fonts: &FontDatabase) {
+        let baseline = self.first_baseline();
+        let content = self.dims.content;
+        let Some(marker) = &mut self.marker else {
+            return;
+        };
+        let style = &marker.style_node().expect("Markers have a node").style;
+
+        let (_, width) = marker.content_widths(fonts, style, content.width);
+        let containing_block = Dimensions {
+            content: Rect {
+                x: content.x - width,
+                y: content.y,
+                width,
+                height: 0.0,
+            },
+            ..Default::default()
+        };
+        marker.layout_float(fonts, style, containing_block, None);
+        if let (Some(baseline), Some(own)) = (baseline, marker.first_baseline()) {
+            marker.translate(0.0, baseline - own);
+        }
+    }
+
+    /// Sets the width and horizontal edges of the box, so they add up to the width of the
+    /// containing block, with the width kept between its minimum and maximum. A float is only as
+    /// wide as its content, unless that's wider than the containing block.
+    /// https://www.w3.org/TR/CSS2/visudet.html#min-max-widths
+    /// https://www.w3.org/TR/CSS2/visudet.html#float-width
+    fn calculate_block_width(
+        &mut self,
+        fonts: &FontDatabase,
+        style: &'a ComputedStyle,
+        containing_block: Dimensions,
+    ) {
+        let cb_width = containing_block.content.width;
+
+        let margin_left = style.margin.left.resolve(cb_width);
+        let margin_right = style.margin.right.resolve(cb_width);
+
+        let border_left = style.border_width.left;
+        let border_right = style.border_width.right;
+
+        let padding_left = style.padding.left.to_px_of(cb_width);
+        let padding_right = style.padding.right.to_px_of(cb_width);
+
+        let edges = border_left + border_right + padding_left + padding_right;
+        let size = |l: &Length| content_size(l, Some(cb_width), edges, style.box_sizing);
+
+        // The `auto` margins of floats and atomic inlines are zero, and their `auto` width shrinks
+        // to fit their content.
+        let shrink_to_fit = match style.float != Float::None || style.display.is_atomic_inline() {
+            false => None,
+            true if size(&style.width).is_some() => Some(0.0),
+            true => {
+                let (min, max) = self.content_widths(fonts, style, cb_width);
+                let available =
+                    cb_width - margin_left.unwrap_or(0.0) - margin_right.unwrap_or(0.0) - edges;
+                Some(max.min(available).max(min))
+            }
+        };
+        let solve = |width: Option<f32>| match shrink_to_fit {
+            None => solve_block_width(cb_width, width, margin_left, margin_right, edges),
+            Some(shrunk) => (
+                width.unwrap_or(shrunk),
+                margin_left.unwrap_or(0.0),
+                margin_right.unwrap_or(0.0),
+            ),
+        };
+
+        // The width is worked out again if it's too wide, and then if it's too narrow.
+        let mut used = solve(size(&style.width));
+        if let Some(max_width) = size(&style.max_width).filter(|max| used.0 > *max) {
+            used = solve(Some(max_width));
+        }
+        if let Some(min_width) = size(&style.min_width).filter(|min| used.0 < *min) {
+            used = solve(Some(min_width));
+        }
+        let (width, margin_left, margin_right) = used;
+
+        let d = &mut self.dims;
+        d.content.width = width;
+
+        d.padding.left = padding_left;
+        d.padding.right = padding_right;
+
+        d.border.left = border_left;
+        d.border.right = border_right;
+
+        d.margin.left = margin_left;
+        d.margin.right = margin_right;
+    }
+
+    /// Returns the minimum and maximum widths of the content of a block container, which are the
+    /// widths it takes up when every line is wrapped where it can be, and when none are.
+    /// https://www.w3.org/TR/css-sizing-3/#intrinsic-sizes
+    fn content_widths(
+        &mut self,
+        fonts: &FontDatabase,
+        style: &'a ComputedStyle,
+        cb_width: f32,
+    ) -> (f32, f32) {
+        match self.box_type {
+            BoxType::FlexNode(_) => return self.flex_widths(fonts, style, cb_width),
+            BoxType::GridNode(_) => return self.grid_widths(fonts, style, cb_width),
+            _ => {}
+        }
+        if self.has_lines() {
+            return self.line_widths(fonts, style, cb_width);
+        }
+        let mut widths = (0.0f32, 0.0f32);
+        for c in &mut self.children {
+            let (min, max) = match c.box_type {
+                BoxType::BlockNode(node) | BoxType::FlexNode(node) | BoxType::GridNode(node) => {
+                    c.outer_widths(fonts, &node.style, cb_width)
+                }
+                BoxType::AnonymousBlock => c.line_widths(fonts, style, cb_width),
+                BoxType::InlineNode(_) | BoxType::AnonymousInline(_) => {
+                    panic!("Inline boxes should be wrapped in anonymous blocks")
+                }
+            };
+            widths = (widths.0.max(min), widths.1.max(max));
+        }
+        widths
+    }
+
+    /// Returns the minimum and maximum widths of the margin box of a block, for the width of the
+    /// content of the block it's in. Percentages are of the width of its containing block.
+    fn outer_widths(
+        &mut self,
+        fonts: &FontDatabase,
+        style: &'a ComputedStyle,
+        cb_width: f32,
+    ) -> (f32, f32) {
+        let edges = style.border_width.left
+            + style.border_width.right
+            + style.padding.left.to_px_of(cb_width)
+            + style.padding.right.to_px_of(cb_width);
+        let margins = style.margin.left.resolve(cb_width).unwrap_or(0.0)
+            + style.margin.right.resolve(cb_width).unwrap_or(0.0);
+        let size = |l: &Length| content_size(l, Some(cb_width), edges, style.box_sizing);
+
+        let (min, max) = match size(&style.width) {
+            Some(width) => (width, width),
+            None => self.content_widths(fonts, style, cb_width),
+        };
+        let clamp = |width: f32| {
+            let width = size(&style.max_width).map_or(width, |max| width.min(max));
+            let width = size(&style.min_width).map_or(width, |min| width.max(min));
+            width + edges + margins
+        };
+        (clamp(min), clamp(max))
+    }
+
+    /// Sets the vertical edges and the position of the box. The margins above it collapse with its
+    /// top margin, which collapses with its first child's too unless something separates them.
+    /// https://www.w3.org/TR/CSS2/box.html#collapsing-margins
+    ///
+    /// Returns the Some margins its first child's top margin collapses with, or None if they're
+    /// separated from it.
+    fn calculate_block_position(
+        &mut self,
+        style: &ComputedStyle,
+        containing_block: Dimensions,
+        margin: CollapsedMargin,
+        new_context: bool,
+        floats: &Floats,
+    ) -> Option<CollapsedMargin> {
+        let d = &mut self.dims;
+
+        // Percentages for vertical edges are still relative to the containing block's width.
+        let cb_width = containing_block.content.width;
+
+        // If margin-top or margin-bottom is `auto`, the used value is zero.
+        d.margin.top = style.margin.top.to_px_of(cb_width);
+        d.margin.bottom = style.margin.bottom.to_px_of(cb_width);
+
+        d.border.top = style.border_width.top;
+        d.border.bottom = style.border_width.bottom;
+
+        d.padding.top = style.padding.top.to_px_of(cb_width);
+        d.padding.bottom = style.padding.bottom.to_px_of(cb_width);
+
+        d.content.x = containing_block.content.x + d.margin.left + d.border.left + d.padding.left;
+
+        // Position the box below all the previous boxes in the container.
+        d.content.y = containing_block.content.y + containing_block.content.height;
+
+        let margin = margin.adjoin(d.margin.top);
+
+        // A box which clears floats is moved below them if its border top would be above them.
+        // This clearance separates its margins from the ones above it.
+        // https://www.w3.org/TR/CSS2/visuren.html#flow-control
+        let border_top = d.content.y + margin.resolve();
+        if let Some(bottom) = floats
+            .clearance(style.clear)
+            .filter(|bottom| border_top < *bottom)
+        {
+            d.content.y = bottom + d.border.top + d.padding.top;
+            return None;
+        }
+
+        if new_context || d.border.top != 0.0 || d.padding.top != 0.0 {
+            d.content.y += margin.resolve() + d.border.top + d.padding.top;
+            None
+        } else {
+            // The box is moved down once it's known how far its children's margins reach.
+            Some(margin)
+        }
+    }
+
+    /// Lays out the children of the box, which are flex or grid items if it's a flex or grid
+    /// container, and are otherwise laid out with [LayoutBox::layout_block_children], which the
+    /// arguments and the result are the same as. The margins of items don't collapse with any
+    /// others.
+    fn layout_contents(
+        &mut self,
+        fonts: &FontDatabase,
+        style: &'a ComputedStyle,
+        height: Option<f32>,
+        margin: Option<CollapsedMargin>,
+        floats: &mut Floats,
+    ) -> (CollapsedMargin, bool) {
+        match self.box_type {
+            BoxType::FlexNode(_) => {
+                self.layout_flex_items(fonts, style, height);
+                (CollapsedMargin::default(), true)
+            }
+            BoxType::GridNode(_) => {
+                self.layout_grid_items(fonts, style, height);
+                (CollapsedMargin::default(), true)
+            }
+            _ => self.layout_block_children(fonts, style, height, margin, floats),
+        }
+    }
+
+    /// Lays out a flex or grid item with its margin box at the top left of `area`, with `auto`
+    /// margins as zero. Its content box is `width` wide, and Some `height` tall, or None if it's
+    /// as tall as its content, between its minimum and maximum heights. Percentages are of the
+    /// width of `area`, and of the Some `cb_height`. The lines of an anonymous item use the style
+    /// of its `container`. It's moved into place once it's known where it goes.
+    fn layout_item(
+        &mut self,
+        fonts: &FontDatabase,
+        container: &'a ComputedStyle,
+        area: Rect,
+        cb_height: Option<f32>,
+        width: f32,
+        height: Option<f32>,
+    ) {
+        let node = match self.box_type {
+            BoxType::BlockNode(node) | BoxType::FlexNode(node) | BoxType::GridNode(node) => node,
+            BoxType::AnonymousBlock => {
+                self.dims = Dimensions {
+                    content: Rect {
+                        width,
+                        height: 0.0,
+                        ..area
+                    },
+                    ..Default::default()
+                };
+                self.layout_lines(fonts, container, Some(container), &mut Floats::default());
+                if let Some(height) = height {
+                    self.dims.content.height = height;
+                }
+                return;
+            }
+            BoxType::InlineNode(_) | BoxType::AnonymousInline(_) => {
+                panic!("Flex and grid items should be blocks")
+            }
+        };
+
+        let style = &node.style;
+        let w = area.width;
+        let d = &mut self.dims;
+        d.margin.left = style.margin.left.to_px_of(w);
+        d.margin.right = style.margin.right.to_px_of(w);
+        d.margin.top = style.margin.top.to_px_of(w);
+        d.margin.bottom = style.margin.bottom.to_px_of(w);
+        d.border.left = style.border_width.left;
+        d.border.right = style.border_width.right;
+        d.border.top = style.border_width.top;
+        d.border.bottom = style.border_width.bottom;
+        d.padding.left = style.padding.left.to_px_of(w);
+        d.padding.right = style.padding.right.to_px_of(w);
+        d.padding.top = style.padding.top.to_px_of(w);
+        d.padding.bottom = style.padding.bottom.to_px_of(w);
+        d.content = Rect {
+            x: area.x + d.margin.left + d.border.left + d.padding.left,
+            y: area.y + d.margin.top + d.border.top + d.padding.top,
+            width,
+            height: 0.0,
+        };
+
+        // An item starts a new formatting context, so it contains its floats, and its
+        // children's margins don't collapse with its own.
+        let mut floats = Floats::default();
+        let (margin, _) = self.layout_contents(fonts, style, height, None, &mut floats);
+        let d = &mut self.dims;
+        d.content.height += margin.resolve();
+        if let Some(bottom) = floats.bottom() {
+            d.content.height = d.content.height.max(bottom - d.content.y);
+        }
+        let (_, min_height, max_height) = self.height_constraints(style, cb_height);
+        self.dims.content.height = height
+            .unwrap_or(self.dims.content.height)
+            .min(max_height)
+            .max(min_height);
+
+        // A positioned item is the containing block of the absolutely positioned boxes in it.
+        if style.position != Position::Static {
+            self.layout_absolute_descendants(fonts, self.padding_box());
+        }
+    }
+
+    /// Returns the minimum and maximum content widths of a flex or grid item, whose percentages
+    /// are of `cb_width`. The lines of an anonymous item use the style of its `container`.
+    fn item_widths(
+        &mut self,
+        fonts: &FontDatabase,
+        container: &'a ComputedStyle,
+        cb_width: f32,
+    ) -> (f32, f32) {
+        match self.box_type {
+            BoxType::AnonymousBlock => self.line_widths(fonts, container, cb_width),
+            _ => {
+                let style = &self.style_node().expect("Items are blocks").style;
+                self.content_widths(fonts, style, cb_width)
+            }
+        }
+    }
+
+    /// Returns the minimum and maximum widths of the margin box of a flex or grid item, whose
+    /// percentages are of `cb_width`. The lines of an anonymous item use the style of its
+    /// `container`.
+    fn outer_item_widths(
+        &mut self,
+        fonts: &FontDatabase,
+        container: &'a ComputedStyle,
+        cb_width: f32,
+    ) -> (f32, f32) {
+        match self.box_type {
+            BoxType::AnonymousBlock => self.line_widths(fonts, container, cb_width),
+            _ => {
+                let style = &self.style_node().expect("Items are blocks").style;
+                self.outer_widths(fonts, style, cb_width)
+            }
+        }
+    }
+
+    /// Lays out the children below each other, in the block formatting context with `floats`.
+    /// `height` is the Some height of the box, or None if it depends on the children. `margin` is
+    /// the Some margins before the box's content top which the first child's top margin collapses
+    /// with, or None if there aren't any.
+    ///
+    /// Returns the margins after the last child, and whether the content top has been placed,
+    /// which happens when the first child which doesn't collapse through is.
+    fn layout_block_children(
+        &mut self,
+        fonts: &FontDatabase,
+        style: &'a ComputedStyle,
+        height: Option<f32>,
+        margin: Option<CollapsedMargin>,
+        floats: &mut Floats,
+    ) -> (CollapsedMargin, bool) {
+        // A block with inline-level children lays them out in its own lines. Lines separate the
+        // margins before and after them, so they're placed below the margins.
+        if self.has_lines() {
+            let top = self.dims.content.y;
+            self.dims.content.y += margin.map_or(0.0, CollapsedMargin::resolve);
+            let first_line = self.style_node().map_or(style, first_line_style);
+            self.layout_lines(fonts, style, Some(first_line), floats);
+            if self.lines.is_empty() {
+                self.dims.content.y = top;
+                return (margin.unwrap_or_default(), margin.is_none());
+            }
+            return (CollapsedMargin::default(), true);
+        }
+
+        // The first line is in the first child, if it's an anonymous block.
+        let first_line = self.style_node().map_or(style, first_line_style);
+        let d = &mut self.dims;
+        // Until the content top is placed, it moves down with the first child.
+        let mut placed = margin.is_none();
+        let mut margin = margin.unwrap_or_default();
+
+        for (i, c) in self.children.iter_mut().enumerate() {
+            // The content before the child ends here, but the margins after it haven't been used.
+            let y = d.content.y + d.content.height + margin.resolve();
+
+            let collapses_through = match c.box_type {
+                // Only the static position of an absolutely positioned box is known until its
+                // containing block is laid out, which is where its margin box's top left would be.
+                BoxType::BlockNode(_) | BoxType::FlexNode(_) | BoxType::GridNode(_)
+                    if c.is_absolutely_positioned() =>
+                {
+                    c.dims = Dimensions {
+                        content: Rect {
+                            x: d.content.x,
+                            y,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    };
+                    true
+                }
+                // Floats are out of the flow, so the content after them is placed as if they
+                // weren't there, apart from flowing around them.
+                BoxType::BlockNode(node) | BoxType::FlexNode(node) | BoxType::GridNode(node)
+                    if c.is_float() =>
+                {
+                    c.layout_float(fonts, &node.style, *d, height);
+                    let (dx, dy) = floats.place(&node.style, c.margin_box(), y, d.content);
+                    c.translate(dx, dy);
+                    true
+                }
+                BoxType::AnonymousBlock => {
+                    let mut containing_block = *d;
+                    containing_block.content.height += margin.resolve();
+                    let first_line = (i == 0).then_some(first_line);
+                    c.layout_anonymous_block(fonts, containing_block, style, first_line, floats);
+                    // Lines separate the margins before and after them.
+                    if c.lines.is_empty() {
+                        true
+                    } else {
+                        margin = CollapsedMargin::default();
+                        false
+                    }
+                }
+                BoxType::BlockNode(node) | BoxType::FlexNode(node) | BoxType::GridNode(node) => {
+                    // Clearance separates the child's margins from the ones before it, so the
+                    // content top is placed above them.
+                    if !placed && floats.clearance(node.style.clear).is_some_and(|b| y < b) {
+                        d.content.y = y;
+                        margin = CollapsedMargin::default();
+                        placed = true;
+                    }
+
+                    // A block which starts a new formatting context is placed beside the floats
+                    // before it, rather than over them.
+                    // TODO: Move it below the floats if it's too wide to fit beside them.
+                    let new_context = starts_formatting_context(&node.style);
+                    let mut containing_block = *d;
+                    if new_context {
+                        let y = floats.clearance(node.style.clear).map_or(y, |b| y.max(b));
+                        let (left, right) =
+                            floats.space(y, 0.0, d.content.x, d.content.x + d.content.width);
+                        containing_block.content.x = left;
+                        containing_block.content.width = right - left;
+                    }
+                    let (after, collapses_through) = c.layout_block(
+                        fonts,
+                        &node.style,
+                        containing_block,
+                        height,
+                        margin,
+                        floats,
+                        new_context,
+                    );
+                    margin = after;
+                    collapses_through
+                }
+                BoxType::InlineNode(_) | BoxType::AnonymousInline(_) => {
+                    panic!("Inline boxes should be wrapped in anonymous blocks")
+                }
+            };
+            if !collapses_through {
+                // Track the height so each child is laid out below the previous content.
+                let border_box = c.dims.border_box();
+                if !placed {
+                    d.content.y = border_box.y;
+                    placed = true;
+                }
+                d.content.height = border_box.y + border_box.height - d.content.y;
+            }
+
+            // A relatively positioned box is moved after the content after it is placed as if it
+            // hadn't been.
+            let (dx, dy) = c.relative_offset(d.content.width, height);
+            if (dx, dy) != (0.0, 0.0) {
+                c.translate(dx, dy);
+            }
+        }
+
+        (margin, placed)
+    }
+
+    /// Returns the content height the style of the box sets, and its minimum and maximum, given
+    /// the Some height of the containing block, or None if it depends on its content.
+    /// The height is None if it's `auto`, or a percentage of a height which isn't known.
+    fn height_constraints(
+        &self,
+        style: &ComputedStyle,
+        cb_height: Option<f32>,
+    ) -> (Option<f32>, f32, f32) {
+        let d = self.dims;
+        let edges = d.border.top + d.border.bottom + d.padding.top + d.padding.bottom;
+        let size = |l: &Length| content_size(l, cb_height, edges, style.box_sizing);
+
+        (
+            size(&style.height),
+            size(&style.min_height).unwrap_or(0.0),
+            size(&style.max_height).unwrap_or(f32::INFINITY),
+        )
+    }
+
+    /// Sets the height of the box, given the margins after its last child. The last child's bottom
+    /// margin collapses with the box's, unless something separates them. The height is kept
+    /// between its minimum and maximum.
+    /// https://www.w3.org/TR/CSS2/visudet.html#min-max-heights
+    ///
+    /// A box which starts a new formatting context is tall enough to contain the Some bottom of
+    /// its floats, if it has any.
+    /// https://www.w3.org/TR/CSS2/visudet.html#root-height
+    ///
+    /// Returns the margins below the box, and whether its top and bottom margins collapse together.
+    fn calculate_block_height(
+        &mut self,
+        style: &ComputedStyle,
+        cb_height: Option<f32>,
+        margin: CollapsedMargin,
+        placed: bool,
+        new_context: bool,
+        floats_bottom: Option<f32>,
+    ) -> (CollapsedMargin, bool) {
+        let (height, min_height, max_height) = self.height_constraints(style, cb_height);
+        let d = &mut self.dims;
+
+        let separated = new_context || d.border.bottom != 0.0 || d.padding.bottom != 0.0;
+        let collapses_bottom = height.is_none() && !separated;
+
+        if !placed {
+            // None of the margins were used, so the box's content top is after all of them.
+            d.content.y += margin.resolve();
+            // The margins collapse through a box with nothing in it, unless it has a height.
+            if !separated && height.unwrap_or(0.0) == 0.0 && min_height == 0.0 {
+                return (margin.adjoin(d.margin.bottom), true);
+            }
+        } else if !collapses_bottom {
+            d.content.height += margin.resolve();
+        }
+        if let Some(bottom) = floats_bottom {
+            d.content.height = d.content.height.max(bottom - d.content.y);
+        }
+
+        // An `auto` height is the height of the content.
+        d.content.height = height
+            .unwrap_or(d.content.height)
+            .min(max_height)
+            .max(min_height);
+
+        let margin = match collapses_bottom && placed {
+            true => margin.adjoin(d.margin.bottom),
+            false => CollapsedMargin::new(d.margin.bottom),
+        };
+        (margin, false)
+    }
+}
+
+/// Returns the Some content size a width or height sets, or None if it's `auto`. Percentages are
+/// of the Some `base` size, and are None if it isn't known. `edges` is the padding and borders on
+/// both sides, which a `border-box` size includes.
+fn content_size(
+    size: &Length,
+    base: Option<f32>,
+    edges: f32,
+    box_sizing: BoxSizing,
+) -> Option<f32> {
+    let size = match (size, base) {
+        (Length::Px(n), _) => *n,
+        (_, Some(base)) => size.resolve(base)?,
+        _ => return None,
+    };
+    Some(match box_sizing {
+        BoxSizing::ContentBox => size,
+        // The content can't be smaller than nothing, even if the edges are bigger than the size.
+        BoxSizing::BorderBox => (size - edges).max(0.0),
+    })
+}
+
+/// Returns the used width and left and right margins of a block, which are the Some sizes given,
+/// with `auto` sizes (None) making up the rest of the containing block's width.
+/// `edges` is the padding and borders on both sides.
+/// https://www.w3.org/TR/CSS2/visudet.html#blockwidth
+fn solve_block_width(
+    cb_width: f32,
+    width: Option<f32>,
+    mut margin_left: Option<f32>,
+    mut margin_right: Option<f32>,
+    edges: f32,
+) -> (f32, f32, f32) {
+    // `auto` sizes are counted as 0.
+    let total: f32 = [margin_left, margin_right, width]
+        .iter()
+        .flatten()
+        .sum::<f32>()
+        + edges;
+
+    // If width is not auto and the total is wider than the container, treat auto margins as 0.
+    if width.is_some() && total > cb_width {
+        margin_left = margin_left.or(Some(0.0));
+        margin_right = margin_right.or(Some(0.0));
+    }
+
+    let underflow = cb_width - total;
+
+    match (width, margin_left, margin_right) {
+        // If the values are overconstrained, calculate margin_right.
+        (Some(w), Some(l), Some(r)) => (w, l, r + underflow),
+
+        // If exactly one size is auto, its used value follows from the equality.
+        (Some(w), Some(l), None) => (w, l, underflow),
+        (Some(w), None, Some(r)) => (w, underflow, r),
+
+        // If width is set to auto, any other auto values become 0.
+        (None, l, r) => {
+            let (l, r) = (l.unwrap_or(0.0), r.unwrap_or(0.0));
+
+            if underflow >= 0.0 {
+                // Expand width to fill the underflow.
+                (underflow, l, r)
+            } else {
+                // Width can't be negative. Adjust the right margin instead.
+                (0.0, l, r + underflow)
+            }
+        }
+
+        // If margin-left and margin-right are both auto, their used values are equal.
+        (Some(w), None, None) => (w, underflow / 2.0, underflow / 2.0),
+    }
+}
+
+/// Returns the offset of the first box from the start of the space, and the space between each of
+/// the `count` boxes, which distribute the `free` space as `alignment` says. Alignments which
+/// distribute space fall back to the start or centre when there's none to distribute.
+/// https://www.w3.org/TR/css-align-3/#distribution-values
+fn distribute(alignment: Alignment, free: f32, count: usize) -> (f32, f32) {
+    let count = count as f32;
+    match alignment {
+        Alignment::End => (free, 0.0),
+        Alignment::Center => (free / 2.0, 0.0),
+        Alignment::SpaceBetween if free > 0.0 && count > 1.0 => (0.0, free / (count - 1.0)),
+        Alignment::SpaceAround if free > 0.0 => (free / count / 2.0, free / count),
+        Alignment::SpaceEvenly if free > 0.0 => (free / (count + 1.0), free / (count + 1.0)),
+        Alignment::SpaceAround | Alignment::SpaceEvenly => (free / 2.0, 0.0),
+        _ => (0.0, 0.0),
+    }
+}
+
+/// Vertical margins which are adjoining, and so collapse into one margin, which is the largest
+/// positive margin plus the most negative margin.
+/// https://www.w3.org/TR/CSS2/box.html#collapsing-margins
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct CollapsedMargin {
+    positive: f32,
+    negative: f32,
+}
+
+impl CollapsedMargin {
+    fn new(margin: f32) -> Self {
+        CollapsedMargin::default().adjoin(margin)
+    }
+
+    /// Adds a margin which is adjoining these.
+    fn adjoin(self, margin: f32) -> Self {
+        CollapsedMargin {
+            positive: self.positive.max(margin),
+            negative: self.negative.min(margin),
+        }
+    }
+
+    /// The size of the collapsed margin.
+    fn resolve(self) -> f32 {
+        self.positive + self.negative
+    }
+}
+
+/// Does a block start a new block formatting context, which its children are laid out in?
+/// https://www.w3.org/TR/CSS2/visuren.html#block-formatting
+fn starts_formatting_context(style: &ComputedStyle) -> bool {
+    matches!(
+        style.display,
+        Display::FlowRoot | Display::Flex | Display::Grid
+    ) || style.display.is_atomic_inline()
+        || style.overflow_x != Overflow::Visible
+        || style.overflow_y != Overflow::Visible
+        || style.float != Float::None
+        || matches!(style.position, Position::Absolute | Position::Fixed)
+}
+
+/// How should the box formatted?
+pub enum BoxType<'a> {
+    BlockNode(&'a StyledNode<'a>),
+    /// A block-level flex container, whose children are flex items.
+    /// https://www.w3.org/TR/css-flexbox-1/#flex-containers
+    FlexNode(&'a StyledNode<'a>),
+    /// A block-level grid container, whose children are grid items.
+    /// https://www.w3.org/TR/css-grid-1/#grid-containers
+    GridNode(&'a StyledNode<'a>),
+    InlineNode(&'a StyledNode<'a>),
+    /// Text, which is in an inline box of its own with the style it inherits.
+    AnonymousInline(&'a StyledNode<'a>),
+    /// A block which holds the inline-level boxes next to a block, so a block's children are
+    /// either all blocks or all laid out in lines.
+    AnonymousBlock,
+}
+
+/// The values for the display property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Display {
+    Inline,
+    Block,
+    /// A block which is placed in a line as a whole, like a word. (An atomic inline)
+    InlineBlock,
+    /// A block which starts a new block formatting context. Eg: to contain its floats
+    FlowRoot,
+    /// A block whose children are laid out as flex items.
+    Flex,
+    /// A flex container which is placed in a line as a whole.
+    InlineFlex,
+    /// A block whose children are laid out as grid items.
+    Grid,
+    /// A grid container which is placed in a line as a whole.
+    InlineGrid,
+    ListItem,
+    None,
+}
+
+impl Display {
+    /// Is a box with this display laid out as a block, but placed in a line as a whole?
+    /// https://www.w3.org/TR/css-display-3/#atomic-inline
+    pub fn is_atomic_inline(self) -> bool {
+        matches!(
+            self,
+            Display::InlineBlock | Display::InlineFlex | Display::InlineGrid
+        )
+    }
+
+    /// Returns the display of a box which is made block-level, as floats, absolutely positioned
+    /// boxes, flex and grid items and the root are. Eg: inline-flex -> flex
+    /// https://www.w3.org/TR/css-display-3/#blockify
+    pub fn blockified(self) -> Display {
+        match self {
+            Display::Inline | Display::InlineBlock => Display::Block,
+            Display::InlineFlex => Display::Flex,
+            Display::InlineGrid => Display::Grid,
+            display => display,
+        }
+    }
+}
+
+/// Build a tree of [LayoutBox]s. Not performing any calculations yet.
+/// Returns None if the root isn't displayed.
+fn build_layout_tree<'a>(root: &'a StyledNode<'a>) -> Option<LayoutBox<'a>> {
+    // The root is always a block. https://www.w3.org/TR/css-display-3/#transformations
+    match root.display() {
+        Display::None => None,
+        _ => Some(build_block_level(root)),
+    }
+}
+
+/// Builds the box of a block-level element, which is a flex or grid container, or a block. An
+/// atomic inline's box is built the same way, as it's laid out as a block.
+fn build_block_level<'a>(node: &'a StyledNode<'a>) -> LayoutBox<'a> {
+    match node.display().blockified() {
+        Display::Flex => build_container(LayoutBox::new(BoxType::FlexNode(node)), node),
+        Display::Grid => build_container(LayoutBox::new(BoxType::GridNode(node)), node),
+        _ => build_block(node),
+    }
+}
+
+/// Builds the boxes of the children of a flex or grid container. Each child element is an item,
+/// which is a block even if the element is inline, and each run of text between them is wrapped
+/// in an anonymous block, which is an item too. Runs of only collapsible white space are removed.
+/// https://www.w3.org/TR/css-flexbox-1/#flex-items
+/// https://www.w3.org/TR/css-grid-1/#grid-items
+fn build_container<'a>(mut container: LayoutBox<'a>, node: &'a StyledNode<'a>) -> LayoutBox<'a> {
+    let mut run: Vec<_> = build_generated_text(node).into_iter().collect();
+    for c in &node.children {
+        if c.pseudo.is_none() && matches!(c.node.node_type, NodeType::Text(_)) {
+            build_boxes(c, &mut run);
+            continue;
+        }
+        push_anonymous_block(&mut container.children, &mut run);
+        if c.display() != Display::None {
+            container.children.push(build_block_level(c));
+        }
+    }
+    push_anonymous_block(&mut container.children, &mut run);
+    container
+}
+
+/// Builds the box of a block, and the boxes of its children. If it has both block-level and
+/// inline-level children, each run of inline-level boxes is wrapped in an anonymous block. Boxes
+/// out of the flow are in the lines of the inline-level boxes next to them.
+/// https://www.w3.org/TR/CSS2/visuren.html#anonymous-block-level
+fn build_block<'a>(node: &'a StyledNode<'a>) -> LayoutBox<'a> {
+    let mut block = LayoutBox::new(BoxType::BlockNode(node));
+    let mut children: Vec<_> = build_generated_text(node).into_iter().collect();
+    for c in &node.children {
+        // An outside marker isn't in the lines of the list item.
+        if c.pseudo == Some(PseudoElement::Marker)
+            && c.style.list_style_position == ListStylePosition::Outside
+            && c.display() != Display::None
+        {
+            block.marker = Some(Box::new(build_block(c)));
+            continue;
+        }
+        build_boxes(c, &mut children);
+    }
+
+    let is_block = |c: &LayoutBox| !c.is_inline_level() && !c.is_out_of_flow();
+    if !children.iter().any(LayoutBox::is_inline_level) || !children.iter().any(is_block) {
+        block.children = children;
+        return block;
+    }
+
+    let mut run = Vec::new();
+    for c in children {
+        if !is_block(&c) {
+            run.push(c);
+            continue;
+        }
+        push_anonymous_block(&mut block.children, &mut run);
+        block.children.push(c);
+    }
+    push_anonymous_block(&mut block.children, &mut run);
+    block
+}
+
+/// Returns the style of a node's content on the first line of its block container, which it has
+/// from the container's ::first-line pseudo-element.
+/// https://www.w3.org/TR/css-pseudo-4/#first-line-inheritance
+fn first_line_style<'a>(node: &'a StyledNode) -> &'a ComputedStyle {
+    node.pseudo_styles
+        .get(&PseudoElement::FirstLine)
+        .unwrap_or(&node.style)
+}
+
+/// Returns the Some box of the text of a pseudo-element's generated content, which is in the box
+/// of the pseudo-element like an element's text is in the element's, or None if it has none.
+/// An inline pseudo-element's text is laid out with its inline box, so it only needs this box if
+/// it's a block or a container. Eg: p::before { content: "xyz"; display: block }
+fn build_generated_text<'a>(node: &'a StyledNode<'a>) -> Option<LayoutBox<'a>> {
+    node.pseudo?;
+    node.generated_text.as_ref()?;
+    Some(LayoutBox::new(BoxType::AnonymousInline(node)))
+}
+
+/// Wraps a run of inline-level boxes and boxes out of the flow in an anonymous block, and adds it
+/// to the children of a block. Runs of only collapsible white space are removed, as they would
+/// have no lines, and the boxes out of the flow in them are added to the block on their own.
+fn push_anonymous_block<'a>(children: &mut Vec<LayoutBox<'a>>, run: &mut Vec<LayoutBox<'a>>) {
+    let is_space = |b: &LayoutBox| match b.box_type {
+        BoxType::AnonymousInline(n) => {
+            matches!(n.style.white_space, WhiteSpace::Normal | WhiteSpace::Nowrap)
+                && n.text()
+                    .is_some_and(|t| t.chars().all(|c| c.is_ascii_whitespace()))
+        }
+        _ => false,
+    };
+    if run.iter().all(|b| b.is_out_of_flow() || is_space(b)) {
+        children.extend(run.drain(..).filter(LayoutBox::is_out_of_flow));
+        return;
+    }
+
+    let mut block = LayoutBox::new(BoxType::AnonymousBlock);
+    block.children = std::mem::take(run);
+    children.push(block);
+}
+
+/// Adds the boxes a node generates to the boxes of its parent. An inline box with blocks in it is
+/// split into parts around them, and the blocks are added to the parent between the parts. The
+/// boxes out of the flow in it stay in it, as they're placed from its lines.
+fn build_boxes<'a>(node: &'a StyledNode<'a>, boxes: &mut Vec<LayoutBox<'a>>) {
+    match node.display() {
+        Display::None => {}
+        Display::Block | Display::FlowRoot | Display::Flex | Display::Grid | Display::ListItem => {
+            boxes.push(build_block_level(node))
+        }
+        // Floats and absolutely positioned boxes are always blocks.
+        // https://www.w3.org/TR/CSS2/visuren.html#dis-pos-flo
+        Display::Inline
+            if node.style.float != Float::None
+                || matches!(node.style.position, Position::Absolute | Position::Fixed) =>
+        {
+            boxes.push(build_block(node))
+        }
+        // Atomic inlines are blocks too, which are placed in their line. A floated or absolutely
+        // positioned one is laid out as a block-level box.
+        Display::InlineBlock | Display::InlineFlex | Display::InlineGrid => {
+            boxes.push(build_block_level(node))
+        }
+        Display::Inline
+            if node.pseudo.is_none() && matches!(node.node.node_type, NodeType::Text(_)) =>
+        {
+            boxes.push(LayoutBox::new(BoxType::AnonymousInline(node)))
+        }
+        Display::Inline => {
+            let mut children = Vec::new();
+            for c in &node.children {
+                build_boxes(c, &mut children);
+            }
+
+            let mut part = LayoutBox::new(BoxType::InlineNode(node));
+            for c in children {
+                if c.is_inline_level() || c.is_out_of_flow() {
+                    part.children.push(c);
+                    continue;
+                }
+                let mut next = LayoutBox::new(BoxType::InlineNode(node));
+                next.split.before = true;
+                part.split.after = true;
+                boxes.push(std::mem::replace(&mut part, next));
+                boxes.push(c);
+            }
+            boxes.push(part);
+        }
+    }
+}
+
+/// Lays out a styled tree in the viewport, returning the Some tree of boxes with their used sizes
+/// and positions, or None if the root has `display: none`. Sticky boxes are placed for the
+/// document and its scroll containers scrolled to the start.
+pub fn layout_tree<'a>(node: &'a StyledNode<'a>, viewport: Dimensions) -> Option<LayoutBox<'a>> {
+    // Boxes are placed below the content already in their container, so the viewport's height
+    // mustn't count as content. Only percentage heights of the root depend on it.
+    let mut containing_block = viewport;
+    containing_block.content.height = 0.0;
+
+    let mut root = build_layout_tree(node)?;
+    // The fonts are only locked once, for the whole of the layout. Formatting contexts in other
+    // formatting contexts, (Eg: a float in a line), use the same lock.
+    let lock = fonts().read().unwrap();
+    let fonts: &FontDatabase = &lock;
+
+    // Percentage heights of the root are relative to the viewport, and it starts a new block
+    // formatting context, so its margins don't collapse with its children's.
+    let cb_height = Some(viewport.content.height);
+    root.layout_block(
+        fonts,
+        &node.style,
+        containing_block,
+        cb_height,
+        CollapsedMargin::default(),
+        &mut Floats::default(),
+        true,
+    );
+    let viewport = viewport.content;
+    let (dx, dy) = root.relative_offset(viewport.width, Some(viewport.height));
+    root.translate(dx, dy);
+
+    // The viewport is the containing block of the fixed boxes, and of the absolutely positioned
+    // boxes which aren't in a positioned block.
+    if node.style.position == Position::Static {
+        root.layout_absolute_descendants(fonts, viewport);
+    }
+    root.layout_fixed_descendants(fonts, viewport);
+    root.apply_sticky_offsets(viewport);
+    Some(root)
+}
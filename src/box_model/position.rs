@@ -0,0 +1,336 @@
+// Positioned boxes, which are moved from where they'd be in the flow, or taken out of it.
+// https://www.w3.org/TR/CSS2/visuren.html#positioning-scheme
+// https://www.w3.org/TR/css-position-3/#sticky-pos
+// The order positioned boxes are painted in is in stacking.rs.
+
+use crate::box_model::float::Floats;
+use crate::box_model::{content_size, BoxType, LayoutBox, Rect};
+use crate::style::computed::{ComputedStyle, Length, Overflow, Position};
+use crate::text::FontDatabase;
+
+/// Returns the Some size of an inset, or None if it's `auto`, or a percentage of a Some `base`
+/// size which isn't known.
+fn inset(inset: &Length, base: Option<f32>) -> Option<f32> {
+    match (inset, base) {
+        (Length::Px(n), _) => Some(*n),
+        (_, Some(base)) => inset.resolve(base),
+        _ => None,
+    }
+}
+
+/// The sizes along one axis of an absolutely positioned box, which are None if they're `auto`.
+/// The start and end are its insets from the containing block, and `edges` is its padding and
+/// borders on both sides.
+struct AbsoluteAxis {
+    start: Option<f32>,
+    size: Option<f32>,
+    end: Option<f32>,
+    margin_start: Option<f32>,
+    margin_end: Option<f32>,
+    edges: f32,
+}
+
+impl AbsoluteAxis {
+    /// Returns the used inset at the start, the size and the margins, which add up to the size of
+    /// the containing block. `static_start` is where the box's margin edge would be if it was in
+    /// the flow, and `fit` returns the size the content fits in, given the space available.
+    /// https://www.w3.org/TR/CSS2/visudet.html#abs-non-replaced-width
+    fn solve(
+        self,
+        cb_size: f32,
+        static_start: f32,
+        fit: &mut impl FnMut(f32) -> f32,
+    ) -> (f32, f32, f32, f32) {
+        let AbsoluteAxis {
+            start,
+            size,
+            end,
+            margin_start,
+            margin_end,
+            edges,
+        } = self;
+
+        if let (Some(start), Some(size), Some(end)) = (start, size, end) {
+            let rest = cb_size - start - size - end - edges;
+            let (margin_start, margin_end) = match (margin_start, margin_end) {
+                // `auto` margins on both sides centre the box, unless it doesn't fit.
+                (None, None) if rest >= 0.0 => (rest / 2.0, rest / 2.0),
+                (None, None) => (0.0, rest),
+                (None, Some(end)) => (rest - end, end),
+                (Some(start), None) => (start, rest - start),
+                // If the values are overconstrained, the end inset is ignored.
+                (Some(start), Some(end)) => (start, end),
+            };
+            return (start, size, margin_start, margin_end);
+        }
+
+        // Otherwise `auto` margins are zero.
+        let (margin_start, margin_end) = (margin_start.unwrap_or(0.0), margin_end.unwrap_or(0.0));
+        let outer = edges + margin_start + margin_end;
+        let (start, size) = match (start, size, end) {
+            (None, None, None) => (static_start, fit(cb_size - static_start - outer)),
+            (None, None, Some(end)) => {
+                let size = fit(cb_size - end - outer);
+                (cb_size - end - outer - size, size)
+            }
+            (None, Some(size), None) => (static_start, size),
+            (Some(start), None, None) => (start, fit(cb_size - start - outer)),
+            (None, Some(size), Some(end)) => (cb_size - end - outer - size, size),
+            (Some(start), None, Some(end)) => (start, (cb_size - start - end - outer).max(0.0)),
+            (Some(start), Some(size), None) => (start, size),
+            (Some(_), Some(_), Some(_)) => unreachable!(),
+        };
+        (start, size, margin_start, margin_end)
+    }
+}
+
+/// Returns how far to move a sticky box along one axis, to keep the start and size of its border
+/// box the Some insets from the start and end of the scrollport. It can only move `back` and
+/// `forward` as far as it can before its margin box leaves its containing block.
+fn sticky_offset(
+    (start, size): (f32, f32),
+    (back, forward): (f32, f32),
+    (port_start, port_size): (f32, f32),
+    insets: (Option<f32>, Option<f32>),
+) -> f32 {
+    match insets {
+        (Some(before), _) if start < port_start + before => {
+            (port_start + before - start).min(forward).max(0.0)
+        }
+        (_, Some(after)) if start + size > port_start + port_size - after => {
+            (port_start + port_size - after - start - size)
+                .max(back)
+                .min(0.0)
+        }
+        _ => 0.0,
+    }
+}
+
+impl<'a> LayoutBox<'a> {
+    /// Returns how far a relatively positioned box is moved from where it's laid out, by its `left`
+    /// or else `right`, and its `top` or else `bottom`. Percentages are of the containing block's
+    /// width, and of its Some height if that's known.
+    /// https://www.w3.org/TR/CSS2/visuren.html#relative-positioning
+    pub(super) fn relative_offset(&self, cb_width: f32, cb_height: Option<f32>) -> (f32, f32) {
+        let Some(style) = self
+            .style_node()
+            .map(|n| &n.style)
+            .filter(|s| s.position == Position::Relative)
+        else {
+            return (0.0, 0.0);
+        };
+
+        let i = &style.inset;
+        let dx = match (
+            inset(&i.left, Some(cb_width)),
+            inset(&i.right, Some(cb_width)),
+        ) {
+            (Some(left), _) => left,
+            (None, Some(right)) => -right,
+            (None, None) => 0.0,
+        };
+        let dy = match (inset(&i.top, cb_height), inset(&i.bottom, cb_height)) {
+            (Some(top), _) => top,
+            (None, Some(bottom)) => -bottom,
+            (None, None) => 0.0,
+        };
+        (dx, dy)
+    }
+
+    /// Lays out an absolutely positioned box in the padding box of its containing block. Its
+    /// content area's position must be its static position, which is where the top left of its
+    /// margin box would be if it was in the flow.
+    /// https://www.w3.org/TR/CSS2/visudet.html#abs-non-replaced-width
+    /// https://www.w3.org/TR/CSS2/visudet.html#abs-non-replaced-height
+    fn layout_absolute(
+        &mut self,
+        fonts: &FontDatabase,
+        style: &'a ComputedStyle,
+        containing_block: Rect,
+    ) {
+        let cb = containing_block;
+        let (static_x, static_y) = (self.dims.content.x, self.dims.content.y);
+
+        let padding_left = style.padding.left.to_px_of(cb.width);
+        let padding_right = style.padding.right.to_px_of(cb.width);
+        let (border_left, border_right) = (style.border_width.left, style.border_width.right);
+        let edges = padding_left + padding_right + border_left + border_right;
+
+        // The width is worked out again if it's too wide, and then if it's too narrow. An `auto`
+        // width shrinks to fit the content.
+        let size = |l: &Length| content_size(l, Some(cb.width), edges, style.box_sizing);
+        let horizontal = |width| AbsoluteAxis {
+            start: inset(&style.inset.left, Some(cb.width)),
+            size: width,
+            end: inset(&style.inset.right, Some(cb.width)),
+            margin_start: style.margin.left.resolve(cb.width),
+            margin_end: style.margin.right.resolve(cb.width),
+            edges,
+        };
+        let mut shrink_to_fit = |available: f32| {
+            let (min, max) = self.content_widths(fonts, style, cb.width);
+            max.min(available).max(min)
+        };
+        let solve = |width, fit: &mut _| horizontal(width).solve(cb.width, static_x - cb.x, fit);
+
+        let mut used = solve(size(&style.width), &mut shrink_to_fit);
+        if let Some(max_width) = size(&style.max_width).filter(|max| used.1 > *max) {
+            used = solve(Some(max_width), &mut shrink_to_fit);
+        }
+        if let Some(min_width) = size(&style.min_width).filter(|min| used.1 < *min) {
+            used = solve(Some(min_width), &mut shrink_to_fit);
+        }
+        let (left, width, margin_left, margin_right) = used;
+
+        let d = &mut self.dims;
+        (d.padding.left, d.padding.right) = (padding_left, padding_right);
+        (d.border.left, d.border.right) = (border_left, border_right);
+        (d.margin.left, d.margin.right) = (margin_left, margin_right);
+        d.content.width = width;
+        d.content.x = cb.x + left + margin_left + border_left + padding_left;
+
+        // Percentages for vertical edges are still relative to the containing block's width.
+        d.padding.top = style.padding.top.to_px_of(cb.width);
+        d.padding.bottom = style.padding.bottom.to_px_of(cb.width);
+        (d.border.top, d.border.bottom) = (style.border_width.top, style.border_width.bottom);
+        let edges = d.padding.top + d.padding.bottom + d.border.top + d.border.bottom;
+
+        // The children are laid out from the static position, as the height can depend on them,
+        // and then moved with the box once it's placed. The box starts a new block formatting
+        // context, so it contains its floats.
+        d.content.y = static_y;
+        d.content.height = 0.0;
+        let (height, min_height, max_height) = self.height_constraints(style, Some(cb.height));
+        let mut floats = Floats::default();
+        let height = height.map(|h| h.min(max_height).max(min_height));
+        self.layout_contents(fonts, style, height, None, &mut floats);
+
+        let d = &mut self.dims;
+        let content_height = floats
+            .bottom()
+            .map_or(d.content.height, |b| d.content.height.max(b - d.content.y));
+
+        let size = |l: &Length| content_size(l, Some(cb.height), edges, style.box_sizing);
+        let vertical = |height| AbsoluteAxis {
+            start: inset(&style.inset.top, Some(cb.height)),
+            size: height,
+            end: inset(&style.inset.bottom, Some(cb.height)),
+            margin_start: style.margin.top.resolve(cb.width),
+            margin_end: style.margin.bottom.resolve(cb.width),
+            edges,
+        };
+        // An `auto` height is the height of the content.
+        let mut fit_content = |_| content_height;
+        let solve = |height, fit: &mut _| vertical(height).solve(cb.height, static_y - cb.y, fit);
+
+        let mut used = solve(size(&style.height), &mut fit_content);
+        if let Some(max_height) = size(&style.max_height).filter(|max| used.1 > *max) {
+            used = solve(Some(max_height), &mut fit_content);
+        }
+        if let Some(min_height) = size(&style.min_height).filter(|min| used.1 < *min) {
+            used = solve(Some(min_height), &mut fit_content);
+        }
+        let (top, height, margin_top, margin_bottom) = used;
+
+        (d.margin.top, d.margin.bottom) = (margin_top, margin_bottom);
+        d.content.height = height;
+        let y = cb.y + top + margin_top + d.border.top + d.padding.top;
+        self.translate(0.0, y - static_y);
+
+        self.layout_absolute_descendants(fonts, self.padding_box());
+    }
+
+    /// Lays out the absolutely positioned boxes which are in this box, but not in another
+    /// positioned block, which would be their containing block instead. This box is their
+    /// containing block, and `containing_block` is its padding box.
+    ///
+    /// TODO: A positioned inline box is the containing block of the absolutely positioned boxes in
+    /// it, from the fragments of its first and last lines.
+    pub(super) fn layout_absolute_descendants(
+        &mut self,
+        fonts: &FontDatabase,
+        containing_block: Rect,
+    ) {
+        for c in &mut self.children {
+            match c.box_type {
+                BoxType::BlockNode(node) | BoxType::FlexNode(node) | BoxType::GridNode(node)
+                    if node.style.position == Position::Absolute =>
+                {
+                    c.layout_absolute(fonts, &node.style, containing_block)
+                }
+                BoxType::BlockNode(node) | BoxType::FlexNode(node) | BoxType::GridNode(node)
+                    if node.style.position != Position::Static => {}
+                _ => c.layout_absolute_descendants(fonts, containing_block),
+            }
+        }
+    }
+
+    /// Lays out the boxes with `position: fixed` which are in this box, in the `viewport`. They
+    /// stay where they are when the document is scrolled.
+    pub(super) fn layout_fixed_descendants(&mut self, fonts: &FontDatabase, viewport: Rect) {
+        for c in &mut self.children {
+            if let BoxType::BlockNode(node) | BoxType::FlexNode(node) | BoxType::GridNode(node) =
+                c.box_type
+            {
+                if node.style.position == Position::Fixed {
+                    c.layout_absolute(fonts, &node.style, viewport);
+                }
+            }
+            c.layout_fixed_descendants(fonts, viewport);
+        }
+    }
+
+    /// Moves the stickily positioned boxes in this box, which are positioned relative to the
+    /// `scrollport` of their nearest scroll container, or the viewport if they aren't in one. Each
+    /// box is moved as little as it can be to keep its insets from the edges of the scrollport,
+    /// but stays in its containing block.
+    ///
+    /// TODO: Move the boxes again when a scroll container is scrolled. They're placed for the
+    /// scroll position at the start.
+    pub(super) fn apply_sticky_offsets(&mut self, scrollport: Rect) {
+        let cb = self.dims.content;
+        for c in &mut self.children {
+            let Some(style) = c.style_node().map(|n| &n.style) else {
+                c.apply_sticky_offsets(scrollport);
+                continue;
+            };
+
+            if style.position == Position::Sticky {
+                let (border_box, margin_box) = (c.border_box(), c.margin_box());
+                let (port, i) = (scrollport, &style.inset);
+                let (w, h) = (Some(port.width), Some(port.height));
+                let dx = sticky_offset(
+                    (border_box.x, border_box.width),
+                    (
+                        cb.x - margin_box.x,
+                        cb.x + cb.width - margin_box.x - margin_box.width,
+                    ),
+                    (port.x, port.width),
+                    (inset(&i.left, w), inset(&i.right, w)),
+                );
+                let dy = sticky_offset(
+                    (border_box.y, border_box.height),
+                    (
+                        cb.y - margin_box.y,
+                        cb.y + cb.height - margin_box.y - margin_box.height,
+                    ),
+                    (port.y, port.height),
+                    (inset(&i.top, h), inset(&i.bottom, h)),
+                );
+                c.translate(dx, dy);
+            }
+
+            // A box which clips its overflow is a scroll container for the boxes in it.
+            let scroll_container = matches!(
+                c.box_type,
+                BoxType::BlockNode(_) | BoxType::FlexNode(_) | BoxType::GridNode(_)
+            ) && (style.overflow_x != Overflow::Visible
+                || style.overflow_y != Overflow::Visible);
+            let scrollport = match scroll_container {
+                true => c.padding_box(),
+                false => scrollport,
+            };
+            c.apply_sticky_offsets(scrollport);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+// The order boxes are painted in, which stacking contexts and z-index decide.
+// https://www.w3.org/TR/CSS2/zindex.html
+// https://www.w3.org/TR/css-position-3/#painting-order
+
+use crate::box_model::{BoxType, LayoutBox};
+use crate::style::computed::Position;
+
+/// How a box takes part in the painting of the stacking context it's in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stacking {
+    /// The box starts a stacking context of its own, which is painted at its z-index.
+    Context(i32),
+    /// A positioned box with `z-index: auto` is painted as if it started a stacking context, but
+    /// its positioned descendants are in the context it's in.
+    Positioned,
+    /// A float is painted like a positioned box with `z-index: auto`, in a layer of its own.
+    Float,
+    /// An atomic inline is painted with the inline content, but as a whole, like a float.
+    Atomic,
+    Block,
+    Inline,
+}
+
+/// Returns how a box is stacked. `in_container` is whether it's in a flex or grid container, whose
+/// items can have a z-index without being positioned.
+fn stacking(b: &LayoutBox, in_container: bool) -> Stacking {
+    let Some(style) = b.style_node().map(|n| &n.style) else {
+        return Stacking::Block;
+    };
+
+    // Fixed and sticky boxes always start a stacking context. Other positioned boxes, and flex
+    // and grid items, start one if they have a z-index, and so does any transparent box.
+    let positioned = style.position != Position::Static;
+    match style.z_index {
+        _ if matches!(style.position, Position::Fixed | Position::Sticky) => {
+            return Stacking::Context(style.z_index.unwrap_or(0))
+        }
+        Some(z) if positioned || in_container => return Stacking::Context(z),
+        _ if style.opacity < 1.0 => return Stacking::Context(0),
+        _ => {}
+    }
+
+    match b.box_type {
+        _ if positioned => Stacking::Positioned,
+        _ if b.is_float() => Stacking::Float,
+        _ if b.is_atomic_inline() => Stacking::Atomic,
+        BoxType::InlineNode(_) | BoxType::AnonymousInline(_) => Stacking::Inline,
+        _ => Stacking::Block,
+    }
+}
+
+/// The boxes of a stacking context, or a box painted like one, sorted into the layers they're
+/// painted in. Boxes in the same layer are painted in tree order.
+#[derive(Default)]
+struct Layers<'l, 'a> {
+    /// Stacking contexts with a negative z-index.
+    negative: Vec<(i32, &'l LayoutBox<'a>)>,
+    blocks: Vec<&'l LayoutBox<'a>>,
+    floats: Vec<&'l LayoutBox<'a>>,
+    inlines: Vec<(Stacking, &'l LayoutBox<'a>)>,
+    /// Positioned boxes with `z-index: auto`, and stacking contexts with a z-index of 0.
+    positioned: Vec<(Stacking, &'l LayoutBox<'a>)>,
+    /// Stacking contexts with a positive z-index.
+    positive: Vec<(i32, &'l LayoutBox<'a>)>,
+}
+
+impl<'l, 'a> Layers<'l, 'a> {
+    /// Sorts the boxes in `parent`, and their descendants, into layers. `flow` is whether to add
+    /// the boxes painted in the parent's own layers, and `positioned` whether to add positioned
+    /// boxes and stacking contexts, which are in the nearest stacking context.
+    fn collect(&mut self, parent: &'l LayoutBox<'a>, flow: bool, positioned: bool) {
+        let in_container = matches!(parent.box_type, BoxType::FlexNode(_) | BoxType::GridNode(_));
+        // An outside marker is painted with the content of its list item.
+        let marker = parent.marker.as_deref().into_iter();
+
+        for b in marker.chain(&parent.children) {
+            match stacking(b, in_container) {
+                Stacking::Context(_) | Stacking::Positioned if !positioned => {}
+                Stacking::Context(z) if z < 0 => self.negative.push((z, b)),
+                Stacking::Context(z) if z > 0 => self.positive.push((z, b)),
+                s @ Stacking::Context(_) => self.positioned.push((s, b)),
+                s @ Stacking::Positioned => {
+                    self.positioned.push((s, b));
+                    self.collect(b, false, true);
+                }
+                Stacking::Float => {
+                    if flow {
+                        self.floats.push(b);
+                    }
+                    self.collect(b, false, positioned);
+                }
+                Stacking::Block => {
+                    if flow {
+                        self.blocks.push(b);
+                    }
+                    self.collect(b, flow, positioned);
+                }
+                s @ Stacking::Atomic => {
+                    if flow {
+                        self.inlines.push((s, b));
+                    }
+                    self.collect(b, false, positioned);
+                }
+                s @ Stacking::Inline => {
+                    if flow {
+                        self.inlines.push((s, b));
+                    }
+                    self.collect(b, flow, positioned);
+                }
+            }
+        }
+    }
+
+    /// Adds the boxes to `order`, layer by layer.
+    fn paint(mut self, order: &mut Vec<&'l LayoutBox<'a>>) {
+        // Stacking contexts with the same z-index stay in tree order.
+        self.negative.sort_by_key(|(z, _)| *z);
+        self.positive.sort_by_key(|(z, _)| *z);
+
+        for (_, b) in self.negative {
+            paint_stacking_context(b, order);
+        }
+        order.extend(self.blocks);
+        for b in self.floats {
+            paint_layer(b, order);
+        }
+        for (stacking, b) in self.inlines {
+            match stacking {
+                Stacking::Atomic => paint_layer(b, order),
+                _ => order.push(b),
+            }
+        }
+        for (stacking, b) in self.positioned {
+            match stacking {
+                Stacking::Context(_) => paint_stacking_context(b, order),
+                _ => paint_layer(b, order),
+            }
+        }
+        for (_, b) in self.positive {
+            paint_stacking_context(b, order);
+        }
+    }
+}
+
+/// Adds a stacking context, and everything in it, to the painting `order`.
+fn paint_stacking_context<'l, 'a>(root: &'l LayoutBox<'a>, order: &mut Vec<&'l LayoutBox<'a>>) {
+    order.push(root);
+    let mut layers = Layers::default();
+    layers.collect(root, true, true);
+    layers.paint(order);
+}
+
+/// Adds a box which is painted like a stacking context to the painting `order`, without its
+/// positioned descendants, which are painted in the stacking context it's in.
+fn paint_layer<'l, 'a>(root: &'l LayoutBox<'a>, order: &mut Vec<&'l LayoutBox<'a>>) {
+    order.push(root);
+    let mut layers = Layers::default();
+    layers.collect(root, true, false);
+    layers.paint(order);
+}
+
+impl<'a> LayoutBox<'a> {
+    /// Returns this box and every box in it, in the order they're painted, from back to front.
+    /// The box is the root of a stacking context, and each box paints its own background, borders
+    /// and text. Stacking contexts are painted in the order of their z-index, and the boxes in a
+    /// stacking context are painted together.
+    /// https://www.w3.org/TR/CSS2/zindex.html#painting-order
+    pub fn paint_order(&self) -> Vec<&LayoutBox<'a>> {
+        let mut order = Vec::new();
+        paint_stacking_context(self, &mut order);
+        order
+    }
+}
@@ -0,0 +1,130 @@
+// Loading a html document, with the stylesheets it contains and links to.
+// https://html.spec.whatwg.org/multipage/semantics.html#the-style-element
+
+use crate::dom::{ElementData, Node, NodeType};
+use crate::loader::{resolve_url, Loader};
+use crate::parsing::css_parser::CssParser;
+use crate::parsing::html_parser::HtmlParser;
+use crate::style::css::{Origin, StyledNode, Stylesheet};
+use crate::style::style_tree::style_tree;
+use crate::style::values::Viewport;
+
+/// A html document, and its stylesheets.
+pub struct Document {
+    /// The url of the document, which the urls within it are relative to.
+    pub url: String,
+    pub root: Node,
+    /// The stylesheets of the <style> and <link> elements, in the order they appear.
+    pub stylesheets: Vec<Stylesheet>,
+}
+
+impl Document {
+    /// Returns the Some document at a url, or None if it can't be loaded.
+    pub fn load(url: &str, loader: &dyn Loader) -> Option<Self> {
+        Some(Document::parse(loader.load(url)?, url, loader))
+    }
+
+    /// Parses a document from a url, loading the stylesheets it links to with a [Loader].
+    /// Stylesheets which can't be loaded are ignored.
+    pub fn parse(html: String, url: &str, loader: &dyn Loader) -> Self {
+        let root = HtmlParser::parse(html);
+        let mut stylesheets = Vec::new();
+        collect_stylesheets(&root, url, loader, &mut stylesheets);
+
+        Document {
+            url: url.to_string(),
+            root,
+            stylesheets,
+        }
+    }
+
+    /// Styles the document for a viewport. The style attributes of elements apply on top of the
+    /// stylesheets.
+    pub fn style(&self, viewport: Viewport) -> StyledNode<'_> {
+        style_tree(&self.root, &self.stylesheets, viewport)
+    }
+}
+
+/// Adds the stylesheets of a node and its descendants, in document order.
+fn collect_stylesheets(node: &Node, url: &str, loader: &dyn Loader, sheets: &mut Vec<Stylesheet>) {
+    let NodeType::Element(ref elem) = node.node_type else {
+        return;
+    };
+
+    let sheet = match &*elem.tag_name.to_lowercase() {
+        "style" if is_enabled_css(elem) => {
+            let css: String = node
+                .children
+                .iter()
+                .filter_map(|c| match &c.node_type {
+                    NodeType::Text(text) => Some(text.as_str()),
+                    NodeType::Element(_) => None,
+                })
+                .collect();
+            Some(CssParser::parse_with_loader(
+                css,
+                Origin::Author,
+                url,
+                loader,
+            ))
+        }
+        "link" if is_enabled_css(elem) && is_stylesheet_link(elem) => {
+            elem.attr("href").and_then(|href| {
+                let href = resolve_url(url, href);
+                let css = loader.load(&href)?;
+                Some(CssParser::parse_with_loader(
+                    css,
+                    Origin::Author,
+                    &href,
+                    loader,
+                ))
+            })
+        }
+        _ => None,
+    };
+
+    if let Some(mut sheet) = sheet {
+        // The rules only apply when the element's media queries match.
+        if let Some(media) = elem.attr("media") {
+            let media = CssParser::parse_media(media);
+            for rule in &mut sheet.rules {
+                rule.media.insert(0, media.clone());
+            }
+            for font_face in &mut sheet.font_faces {
+                font_face.media.insert(0, media.clone());
+            }
+            for page in &mut sheet.pages {
+                page.media.insert(0, media.clone());
+            }
+            for keyframes in &mut sheet.keyframes {
+                keyframes.media.insert(0, media.clone());
+            }
+        }
+        sheets.push(sheet);
+    }
+
+    for child in &node.children {
+        collect_stylesheets(child, url, loader, sheets);
+    }
+}
+
+/// Is the element a css stylesheet which isn't disabled? Elements without a type are css.
+fn is_enabled_css(elem: &ElementData) -> bool {
+    let css = elem
+        .attr("type")
+        .is_none_or(|t| t.is_empty() || t.eq_ignore_ascii_case("text/css"));
+    css && elem.attr("disabled").is_none()
+}
+
+/// Does a <link> link to a stylesheet? Alternative stylesheets aren't used unless they're chosen.
+/// Eg: <link rel="stylesheet" href="style.css">
+fn is_stylesheet_link(elem: &ElementData) -> bool {
+    let Some(rel) = elem.attr("rel") else {
+        return false;
+    };
+    let has = |keyword| {
+        rel.split_whitespace()
+            .any(|r| r.eq_ignore_ascii_case(keyword))
+    };
+    has("stylesheet") && !has("alternate")
+}
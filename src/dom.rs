@@ -1,3 +1,5 @@
+use crate::style::restyle::RestyleData;
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 
@@ -8,6 +10,8 @@ pub type AttrMap = HashMap<String, String>;
 pub struct Node {
     pub children: Vec<Node>,
     pub node_type: NodeType,
+    /// The node's style, and what has changed since it was styled.
+    pub(crate) restyle: RefCell<RestyleData>,
 }
 
 /// A node can be either an element (like a html tag), or text.
@@ -19,7 +23,7 @@ pub enum NodeType {
 
 /// Holds the data of an element. Eg: <div class="salad"> has
 /// [ElementData::tag_name] of div, and [ElementData::attributes] of class: salad.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ElementData {
     pub tag_name: String,
     attributes: AttrMap,
@@ -30,6 +34,7 @@ pub fn text(data: String) -> Node {
     Node {
         children: Vec::new(),
         node_type: NodeType::Text(data),
+        restyle: Default::default(),
     }
 }
 
@@ -41,10 +46,73 @@ pub fn elem(name: String, attributes: AttrMap, children: Vec<Node>) -> Node {
             tag_name: name,
             attributes,
         }),
+        restyle: Default::default(),
+    }
+}
+
+/// Changes to the DOM, which mark what has to be restyled.
+impl Node {
+    /// Sets an attribute of an element. Panics if the node is text.
+    pub fn set_attr(&mut self, name: &str, value: &str) {
+        self.element_mut()
+            .attributes
+            .insert(name.to_string(), value.to_string());
+    }
+
+    /// Removes an attribute of an element, returning its Some value, or None if it wasn't set.
+    /// Panics if the node is text.
+    pub fn remove_attr(&mut self, name: &str) -> Option<String> {
+        self.element_mut().attributes.remove(name)
+    }
+
+    /// Returns the element to change, remembering how it was when it was last styled.
+    fn element_mut(&mut self) -> &mut ElementData {
+        let NodeType::Element(ref mut elem) = self.node_type else {
+            panic!("Text nodes have no attributes");
+        };
+        self.restyle.get_mut().attributes_changing(elem);
+        elem
+    }
+
+    /// Inserts a child at an index, shifting the children after it.
+    pub fn insert_child(&mut self, index: usize, mut child: Node) {
+        // The child may have been styled somewhere else in the document.
+        child.forget_style();
+        self.children.insert(index, child);
+        self.restyle.get_mut().children_changed = true;
+    }
+
+    /// Adds a child after the other children.
+    pub fn append_child(&mut self, child: Node) {
+        self.insert_child(self.children.len(), child);
+    }
+
+    /// Removes the child at an index, returning it.
+    pub fn remove_child(&mut self, index: usize) -> Node {
+        self.restyle.get_mut().children_changed = true;
+        self.children.remove(index)
+    }
+
+    /// Forgets the styles of the node and its children, so they are styled from scratch.
+    fn forget_style(&mut self) {
+        *self.restyle.get_mut() = RestyleData::default();
+        for c in &mut self.children {
+            c.forget_style();
+        }
     }
 }
 
 impl ElementData {
+    /// Returns the Some value of an attribute of the element, or None.
+    pub fn attr(&self, name: &str) -> Option<&String> {
+        self.attributes.get(name)
+    }
+
+    /// Returns every attribute of the element.
+    pub fn attributes(&self) -> &AttrMap {
+        &self.attributes
+    }
+
     /// Returns the Some id of the element, or None.
     pub fn id(&self) -> Option<&String> {
         self.attributes.get("id")
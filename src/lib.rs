@@ -0,0 +1,7 @@
+pub mod box_model;
+pub mod document;
+pub mod dom;
+pub mod loader;
+pub mod parsing;
+pub mod style;
+pub mod text;
@@ -0,0 +1,85 @@
+// Loading resources, (Eg: stylesheets), by their url.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Loads the text of resources by their url.
+pub trait Loader {
+    /// Returns the Some text of the resource at a url, or None if it can't be loaded.
+    fn load(&self, url: &str) -> Option<String>;
+}
+
+/// Loads local files. Relative urls are relative to a base directory.
+pub struct FileLoader {
+    pub base: PathBuf,
+}
+
+impl FileLoader {
+    pub fn new(base: impl Into<PathBuf>) -> Self {
+        Self { base: base.into() }
+    }
+}
+
+impl Loader for FileLoader {
+    fn load(&self, url: &str) -> Option<String> {
+        let path = url.strip_prefix("file://").unwrap_or(url);
+        fs::read_to_string(self.base.join(path)).ok()
+    }
+}
+
+/// Resources held in memory, by their url.
+impl Loader for HashMap<String, String> {
+    fn load(&self, url: &str) -> Option<String> {
+        self.get(url).cloned()
+    }
+}
+
+/// Returns a url relative to the url of the resource it appears in, with its . and .. segments
+/// collapsed, so that the same resource always has the same url.
+/// Eg: fonts.css in css/theme.css is css/fonts.css, and ../fonts.css in css/theme.css is fonts.css
+pub fn resolve_url(base: &str, url: &str) -> String {
+    if url.starts_with('/') || url.contains("://") {
+        return remove_dot_segments(url);
+    }
+
+    match base.rfind('/') {
+        Some(i) => remove_dot_segments(&format!("{}{url}", &base[..=i])),
+        None => remove_dot_segments(url),
+    }
+}
+
+/// Returns a url with its . and .. segments collapsed. A relative url keeps the .. segments which
+/// go above where it starts. Eg: css/./a/../b.css is css/b.css
+/// https://www.rfc-editor.org/rfc/rfc3986#section-5.2.4
+fn remove_dot_segments(url: &str) -> String {
+    // The scheme and host aren't part of the path.
+    let path_start = match url.find("://") {
+        Some(i) => url[i + 3..].find('/').map_or(url.len(), |j| i + 3 + j),
+        None => 0,
+    };
+    let (prefix, path) = url.split_at(path_start);
+
+    let parts: Vec<&str> = path.split('/').collect();
+    let mut segments: Vec<&str> = Vec::new();
+    for (i, &part) in parts.iter().enumerate() {
+        match part {
+            "." => {}
+            ".." => match segments.last() {
+                // The root of an absolute path has no parent.
+                Some(&"") if segments.len() == 1 => {}
+                Some(&last) if last != ".." => {
+                    segments.pop();
+                }
+                _ => segments.push(".."),
+            },
+            _ => segments.push(part),
+        }
+        // A url ending in a dot segment is a directory.
+        if matches!(part, "." | "..") && i == parts.len() - 1 {
+            segments.push("");
+        }
+    }
+
+    format!("{prefix}{}", segments.join("/"))
+}
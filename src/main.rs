@@ -1,15 +1,27 @@
-mod dom;
-mod parsing;
-mod style;
-mod box_model;
-
-use crate::parsing::html_parser::HtmlParser;
-use std::fs;
+use parsnip::box_model::{layout_tree, Dimensions};
+use parsnip::document::Document;
+use parsnip::loader::FileLoader;
+use parsnip::style::values::Viewport;
+use std::env;
 
 fn main() {
-    let file_contents = fs::read_to_string("./test/test.html").expect("Couldn't read file!");
+    // Urls in the document are relative to the document's path.
+    let path = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "./test/test.html".to_string());
+    let document = Document::load(&path, &FileLoader::new(".")).expect("Couldn't read file!");
 
-    let p = HtmlParser::parse(file_contents);
+    println!("File content:\n{}", document.root);
+    println!("Stylesheets: {}", document.stylesheets.len());
 
-    println!("File content:\n{p}");
+    let viewport = Viewport::default();
+    let styled = document.style(viewport);
+    let layout = layout_tree(
+        &styled,
+        Dimensions::of_size(viewport.width, viewport.height),
+    );
+    match layout {
+        Some(layout) => println!("Page height: {}px", layout.margin_box().height),
+        None => println!("The page isn't displayed"),
+    }
 }
@@ -1,16 +1,44 @@
-use crate::parsing::parser::{standard_char, Parser};
-use crate::style::css::{Colour, Declaration, Rule, Selector, SimpleSelector, Unit, Value};
+use crate::loader::{resolve_url, Loader};
+use crate::parsing::parser::{identifier_char, standard_char, Parser};
+use crate::style::colour::{colour_function, is_colour_function};
+use crate::style::css::{
+    AttributeOperator, AttributeSelector, Colour, Combinator, Declaration, FontFace, FontSource,
+    Import, Keyframe, Keyframes, Origin, PageRule, PropertyMap, PseudoElement, Rule, Selector,
+    SimpleSelector, Stylesheet, Token, TokenList, Unit, Value,
+};
+use crate::style::media::{Comparison, MediaCondition, MediaFeature, MediaQuery, MediaQueryList};
+use crate::style::properties::{self, is_custom_property, CSS_WIDE_KEYWORDS};
+use crate::style::shorthand::expand_shorthand;
+use crate::style::values::CalcExpr;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 pub struct CssParser {
     p: Parser,
 }
 
 impl CssParser {
-    fn parse_simple_selector(&mut self) -> SimpleSelector {
+    /// Consumes whitespace and comments.
+    fn consume_whitespace(&mut self) {
+        loop {
+            self.p.consume_whitespace();
+            if !self.p.string("/*") {
+                break;
+            }
+            while !self.p.eof() && !self.p.string("*/") {
+                self.p.consume_char();
+            }
+        }
+    }
+
+    /// Parses a simple selector. Returns None if it is invalid, or has a pseudo-class or
+    /// pseudo-element which isn't supported. Eg: div.note#intro::before
+    fn parse_simple_selector(&mut self) -> Option<SimpleSelector> {
         let mut selector = SimpleSelector {
             tag_name: None,
             id: None,
             class: Vec::new(),
+            attributes: Vec::new(),
+            pseudo_element: None,
         };
 
         // TODO: Make this not error prone.
@@ -18,15 +46,26 @@ impl CssParser {
             match self.p.next_char() {
                 '#' => {
                     self.p.consume_char();
-                    selector.id = Some(self.p.parse_standard_word());
+                    selector.id = Some(self.p.parse_identifier());
                 }
                 '.' => {
                     self.p.consume_char();
-                    selector.class.push(self.p.parse_standard_word());
+                    selector.class.push(self.p.parse_identifier());
                 }
                 '*' => {
                     self.p.consume_char();
                 }
+                '[' => {
+                    let attribute = self.parse_attribute_selector()?;
+                    selector.attributes.push(attribute);
+                }
+                ':' => {
+                    self.p.consume_char();
+                    // Pseudo-elements are written with two colons, except for the legacy few.
+                    let legacy = !self.p.string(":");
+                    let name = self.p.parse_identifier();
+                    selector.pseudo_element = Some(PseudoElement::from_name(&name, legacy)?);
+                }
                 c if standard_char(c) => {
                     selector.tag_name = Some(self.p.parse_standard_word());
                 }
@@ -34,104 +73,1736 @@ impl CssParser {
             }
         }
 
-        selector
+        Some(selector)
     }
 
-    fn parse_rule(&mut self) -> Rule {
-        Rule {
-            selectors: self.parse_selectors(),
+    /// Parses an attribute selector, returning None if it is invalid. Eg: [type="checkbox" i]
+    fn parse_attribute_selector(&mut self) -> Option<AttributeSelector> {
+        assert_eq!(self.p.consume_char(), '[');
+        self.consume_whitespace();
+        let name = self.p.parse_identifier();
+        self.consume_whitespace();
+
+        let mut selector = AttributeSelector {
+            name,
+            matcher: None,
+            case_insensitive: false,
+        };
+
+        if !self.p.eof() && self.p.next_char() != ']' {
+            let operator = self
+                .p
+                .consume_while(|c| matches!(c, '=' | '~' | '|' | '^' | '$' | '*'));
+            let operator = AttributeOperator::from_symbol(&operator)?;
+            self.consume_whitespace();
+
+            let value = match self.p.peek_char(0)? {
+                '"' | '\'' => self.parse_string(),
+                _ => self.p.parse_identifier(),
+            };
+            selector.matcher = Some((operator, value));
+            self.consume_whitespace();
+
+            // The `i` flag matches the value ignoring case, and `s` matches it exactly.
+            if !self.p.eof() && self.p.next_char() != ']' {
+                let flag = self.p.parse_identifier();
+                selector.case_insensitive = flag.eq_ignore_ascii_case("i");
+                self.consume_whitespace();
+            }
+        }
+
+        self.p.string("]").then_some(selector)
+    }
+
+    /// Parses a rule. Returns None if any of its selectors are invalid, when the whole rule is
+    /// skipped. https://www.w3.org/TR/selectors-4/#invalid
+    fn parse_rule(&mut self) -> Option<Rule> {
+        let start = self.p.pos;
+        let Some(selectors) = self.parse_selectors() else {
+            self.p.pos = start;
+            self.skip_rule();
+            return None;
+        };
+
+        Some(Rule {
+            selectors,
             declarations: self.parse_declarations(),
+            layer: None,
+            media: Vec::new(),
+        })
+    }
+
+    /// Skips a rule up to the end of its block, or the end of input.
+    fn skip_rule(&mut self) {
+        while !self.p.eof() && self.p.next_char() != '{' {
+            match self.p.next_char() {
+                '"' | '\'' => {
+                    self.parse_string();
+                }
+                _ => {
+                    self.p.consume_char();
+                }
+            }
+        }
+        self.skip_at_rule();
+    }
+
+    /// Parses rules into a stylesheet, until the end of input, or the end of the block they are
+    /// `nested` in. The rules are put in the layer and media of the at-rules around them.
+    fn parse_rules(&mut self, sheet: &mut Stylesheet, conditions: &Conditions, nested: bool) {
+        loop {
+            self.consume_whitespace();
+            // A block which isn't closed ends with the input.
+            if self.p.eof() {
+                break;
+            }
+
+            match self.p.next_char() {
+                '}' if nested => break,
+                '@' => {
+                    self.parse_at_rule(sheet, conditions, nested);
+                }
+                _ => {
+                    if let Some(rule) = self.parse_rule() {
+                        sheet.rules.push(Rule {
+                            layer: conditions.layer.clone(),
+                            media: conditions.media.clone(),
+                            ..rule
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Parses the rules in a block. Returns false if there isn't a block, when nothing is parsed.
+    /// Eg: { p { color: red } }
+    fn parse_nested_rules(&mut self, sheet: &mut Stylesheet, conditions: &Conditions) -> bool {
+        if !self.p.string("{") {
+            return false;
+        }
+        self.parse_rules(sheet, conditions, true);
+        self.p.string("}");
+        true
+    }
+
+    /// Parses an at-rule. Returns false if it is invalid or unknown, when it is skipped.
+    /// Eg: @media print { ... }
+    fn parse_at_rule(
+        &mut self,
+        sheet: &mut Stylesheet,
+        conditions: &Conditions,
+        nested: bool,
+    ) -> bool {
+        assert_eq!(self.p.consume_char(), '@');
+        let name = self.p.parse_identifier().to_lowercase();
+        self.consume_whitespace();
+
+        let start = self.p.pos;
+        let valid = match &*name {
+            "media" => {
+                let mut conditions = conditions.clone();
+                conditions.media.push(self.parse_media_query_list());
+                self.parse_nested_rules(sheet, &conditions)
+            }
+            "supports" => match self.parse_supports_condition() {
+                Some(true) => {
+                    self.consume_whitespace();
+                    self.parse_nested_rules(sheet, conditions)
+                }
+                // The rules may use syntax which isn't supported, so aren't parsed.
+                Some(false) => {
+                    self.skip_at_rule();
+                    true
+                }
+                None => false,
+            },
+            "layer" => self.parse_layer_rule(sheet, conditions),
+            // Imports have to come before any other rules.
+            "import" if !nested && sheet.rules.is_empty() => match self.parse_import() {
+                Some(import) => {
+                    sheet.layers.extend(import.layer.clone());
+                    sheet.imports.push(import);
+                    true
+                }
+                None => false,
+            },
+            // These are in the layer and media of the rules around them, like style rules.
+            "font-face" => match self.parse_font_face() {
+                Some(font_face) => {
+                    sheet.font_faces.push(FontFace {
+                        layer: conditions.layer.clone(),
+                        media: conditions.media.clone(),
+                        ..font_face
+                    });
+                    true
+                }
+                None => false,
+            },
+            "page" => match self.parse_page_rule() {
+                Some(page) => {
+                    sheet.pages.push(PageRule {
+                        layer: conditions.layer.clone(),
+                        media: conditions.media.clone(),
+                        ..page
+                    });
+                    true
+                }
+                None => false,
+            },
+            "keyframes" | "-webkit-keyframes" => match self.parse_keyframes() {
+                Some(keyframes) => {
+                    sheet.keyframes.push(Keyframes {
+                        layer: conditions.layer.clone(),
+                        media: conditions.media.clone(),
+                        ..keyframes
+                    });
+                    true
+                }
+                None => false,
+            },
+            // Unknown at-rules are ignored. Eg: @charset, @namespace
+            _ => false,
+        };
+
+        // An invalid at-rule is skipped whole, up to the end of its block or its semicolon.
+        if !valid {
+            self.p.pos = start;
+            self.skip_at_rule();
+        }
+        valid
+    }
+
+    /// Skips the rest of an at-rule, which either ends with a semicolon or a block. A `}` which
+    /// isn't in the at-rule ends the block the at-rule is in, so isn't skipped.
+    fn skip_at_rule(&mut self) {
+        let mut depth = 0;
+        while !self.p.eof() {
+            match self.p.next_char() {
+                '"' | '\'' => {
+                    self.parse_string();
+                    continue;
+                }
+                ';' if depth == 0 => {
+                    self.p.consume_char();
+                    return;
+                }
+                '}' if depth == 0 => return,
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.p.consume_char();
+                        return;
+                    }
+                }
+                _ => {}
+            }
+            self.p.consume_char();
+        }
+    }
+
+    /// Parses a layer name, which may be nested in other layers. Returns None if any part of it is
+    /// empty. Eg: base.reset
+    fn parse_layer_name(&mut self) -> Option<String> {
+        let mut name = self.p.parse_identifier();
+        while self.p.string(".") {
+            name.push('.');
+            name.push_str(&self.p.parse_identifier());
+        }
+        (!name.split('.').any(str::is_empty)).then_some(name)
+    }
+
+    /// Parses an @layer rule, which either declares the order of layers, or holds the rules of a
+    /// layer. Returns false if it is invalid. Eg: @layer base, theme; or @layer base { ... }
+    fn parse_layer_rule(&mut self, sheet: &mut Stylesheet, conditions: &Conditions) -> bool {
+        let mut names = Vec::new();
+        if self.starts_with_identifier_char(0) {
+            loop {
+                let Some(name) = self.parse_layer_name() else {
+                    return false;
+                };
+                names.push(name);
+                self.consume_whitespace();
+                if !self.p.string(",") {
+                    break;
+                }
+                self.consume_whitespace();
+            }
+        }
+
+        // Layers are nested in the layer the rule is in.
+        let full_name = |name: String| match &conditions.layer {
+            Some(parent) => format!("{parent}.{name}"),
+            None => name,
+        };
+
+        if !names.is_empty() && (self.p.string(";") || self.p.eof()) {
+            sheet.layers.extend(names.into_iter().map(full_name));
+            return true;
+        }
+
+        // A layer block can only have one name.
+        if names.len() > 1 || self.p.peek_char(0) != Some('{') {
+            return false;
+        }
+        let name = full_name(names.pop().unwrap_or_else(anonymous_layer));
+        sheet.layers.push(name.clone());
+
+        let conditions = Conditions {
+            layer: Some(name),
+            ..conditions.clone()
+        };
+        self.parse_nested_rules(sheet, &conditions)
+    }
+
+    /// Parses an @import rule, returning None if it is invalid, or its supports() condition isn't
+    /// supported. Eg: @import url(theme.css) layer(theme) supports(display: grid) screen;
+    fn parse_import(&mut self) -> Option<Import> {
+        let url = match self.p.peek_char(0)? {
+            '"' | '\'' => self.parse_string(),
+            _ if self.starts_with_keyword("url(") => {
+                self.p.parse_identifier();
+                self.parse_url()?
+            }
+            _ => return None,
+        };
+        self.consume_whitespace();
+
+        let mut layer = None;
+        if self.p.string("layer(") {
+            self.consume_whitespace();
+            layer = Some(self.parse_layer_name()?);
+            self.consume_whitespace();
+            if !self.p.string(")") {
+                return None;
+            }
+        } else if self.p.starts_with("layer") && !self.starts_with_identifier_char(5) {
+            self.p.string("layer");
+            layer = Some(anonymous_layer());
+        }
+        self.consume_whitespace();
+
+        let mut supported = true;
+        if self.p.string("supports(") {
+            supported = self.parse_supports_declaration_or_condition();
+            self.consume_whitespace();
+            if !self.p.string(")") {
+                return None;
+            }
+        }
+
+        // The semicolon may be left out at the end of input.
+        let media = self.parse_media_query_list();
+        if !self.p.string(";") && !self.p.eof() {
+            return None;
+        }
+
+        supported.then_some(Import {
+            url,
+            layer,
+            media: (!media.0.is_empty()).then_some(media),
+        })
+    }
+
+    /// Is the character `n` characters ahead part of an identifier?
+    fn starts_with_identifier_char(&self, n: usize) -> bool {
+        self.p.peek_char(n).is_some_and(identifier_char)
+    }
+
+    /// Parses a list of media queries, up to a block or semicolon. A query which is invalid is
+    /// replaced with `not all`, so never matches. Eg: screen, (orientation: portrait)
+    fn parse_media_query_list(&mut self) -> MediaQueryList {
+        let mut queries = Vec::new();
+
+        loop {
+            self.consume_whitespace();
+            if self.p.eof() || matches!(self.p.next_char(), '{' | ';' | '}') {
+                break;
+            }
+
+            let start = self.p.pos;
+            let query = self.parse_media_query();
+            self.consume_whitespace();
+            let ended = self
+                .p
+                .peek_char(0)
+                .is_none_or(|c| matches!(c, ',' | '{' | ';' | '}'));
+            match query {
+                Some(query) if ended => queries.push(query),
+                _ => {
+                    self.p.pos = start;
+                    self.skip_media_query();
+                    queries.push(MediaQuery::not_all());
+                }
+            }
+            self.p.string(",");
+        }
+
+        MediaQueryList(queries)
+    }
+
+    /// Skips a media query, up to the comma, block or semicolon after it.
+    fn skip_media_query(&mut self) {
+        let mut depth = 0_usize;
+        while let Some(c) = self.p.peek_char(0) {
+            match c {
+                '{' | ';' | '}' => break,
+                ',' if depth == 0 => break,
+                '(' => depth += 1,
+                ')' => depth = depth.saturating_sub(1),
+                '"' | '\'' => {
+                    self.parse_string();
+                    continue;
+                }
+                _ => {}
+            }
+            self.p.consume_char();
+        }
+    }
+
+    /// Parses a media query, returning None if it is invalid. Eg: only screen and (min-width: 40em)
+    fn parse_media_query(&mut self) -> Option<MediaQuery> {
+        let mut query = MediaQuery {
+            negated: false,
+            media_type: None,
+            condition: None,
+        };
+
+        if self.p.peek_char(0) == Some('(') || self.starts_with_keyword("not (") {
+            query.condition = Some(self.parse_media_condition()?);
+            return Some(query);
+        }
+
+        let mut word = self.p.parse_identifier().to_lowercase();
+        if word == "not" || word == "only" {
+            query.negated = word == "not";
+            self.consume_whitespace();
+            word = self.p.parse_identifier().to_lowercase();
+        }
+        if matches!(&*word, "" | "not" | "only" | "and" | "or") {
+            return None;
+        }
+        query.media_type = Some(word);
+        self.consume_whitespace();
+
+        if self.starts_with_keyword("and") {
+            self.p.parse_identifier();
+            self.consume_whitespace();
+            query.condition = Some(self.parse_media_condition()?);
+        }
+        Some(query)
+    }
+
+    /// Does the input start with a keyword, ignoring case? The keyword may end with a `(`.
+    fn starts_with_keyword(&self, keyword: &str) -> bool {
+        let rest = &self.p.input[self.p.pos..];
+        rest.get(..keyword.len())
+            .is_some_and(|s| s.eq_ignore_ascii_case(keyword))
+            && (keyword.ends_with('(') || !self.starts_with_identifier_char(keyword.len()))
+    }
+
+    /// Parses media conditions joined by `and` or `or`, or a negated condition. Returns None if
+    /// it is invalid, (Eg: if `and` and `or` are mixed). Eg: (color) and (min-width: 40em)
+    fn parse_media_condition(&mut self) -> Option<MediaCondition> {
+        if self.starts_with_keyword("not") {
+            self.p.parse_identifier();
+            self.consume_whitespace();
+            return Some(MediaCondition::Not(Box::new(self.parse_media_in_parens()?)));
+        }
+
+        let mut conditions = vec![self.parse_media_in_parens()?];
+        let mut or = None;
+        loop {
+            self.consume_whitespace();
+            if self.starts_with_keyword("and") || self.starts_with_keyword("or") {
+                let is_or = self.p.parse_identifier().eq_ignore_ascii_case("or");
+                if *or.get_or_insert(is_or) != is_or {
+                    return None;
+                }
+                self.consume_whitespace();
+                conditions.push(self.parse_media_in_parens()?);
+            } else {
+                break;
+            }
+        }
+
+        Some(match (conditions.len(), or) {
+            (1, _) => conditions.pop().unwrap(),
+            (_, Some(true)) => MediaCondition::Or(conditions),
+            (_, _) => MediaCondition::And(conditions),
+        })
+    }
+
+    /// Parses a media feature or a condition in parentheses, returning None if it is invalid.
+    /// Eg: (min-width: 40em)
+    fn parse_media_in_parens(&mut self) -> Option<MediaCondition> {
+        if !self.p.string("(") {
+            return None;
+        }
+        self.consume_whitespace();
+
+        let condition = if self.p.peek_char(0) == Some('(') || self.starts_with_keyword("not") {
+            self.parse_media_condition()?
+        } else {
+            self.parse_media_feature()?
+        };
+
+        self.consume_whitespace();
+        self.p.string(")").then_some(condition)
+    }
+
+    /// Parses a media feature, after its opening parenthesis. Returns None if it is invalid.
+    /// Eg: color, min-width: 40em, width >= 40em, 40em <= width < 60em
+    fn parse_media_feature(&mut self) -> Option<MediaCondition> {
+        use MediaFeature::Range;
+
+        // A range with the value first. Eg: 40em <= width < 60em
+        if !self.p.peek_char(0)?.is_ascii_alphabetic() {
+            let value = self.parse_media_value();
+            let comparison = self.parse_comparison()?.flip();
+            let name = self.p.parse_identifier().to_lowercase();
+            if name.is_empty() {
+                return None;
+            }
+            let first = MediaCondition::Feature(Range(name.clone(), comparison, value));
+
+            self.consume_whitespace();
+            if self.p.peek_char(0) == Some(')') {
+                return Some(first);
+            }
+            let comparison = self.parse_comparison()?;
+            let second = MediaCondition::Feature(Range(name, comparison, self.parse_media_value()));
+            return Some(MediaCondition::And(vec![first, second]));
+        }
+
+        let name = self.p.parse_identifier().to_lowercase();
+        self.consume_whitespace();
+
+        let feature = match self.p.peek_char(0)? {
+            ')' => MediaFeature::Boolean(name),
+            ':' => {
+                self.p.consume_char();
+                let value = self.parse_media_value();
+                match (name.strip_prefix("min-"), name.strip_prefix("max-")) {
+                    (Some(name), _) => Range(name.to_string(), Comparison::GreaterOrEqual, value),
+                    (_, Some(name)) => Range(name.to_string(), Comparison::LessOrEqual, value),
+                    _ => Range(name, Comparison::Equal, value),
+                }
+            }
+            _ => {
+                let comparison = self.parse_comparison()?;
+                Range(name, comparison, self.parse_media_value())
+            }
+        };
+        Some(MediaCondition::Feature(feature))
+    }
+
+    /// Parses a comparison in a media feature range, returning None if there isn't one. Eg: <=
+    fn parse_comparison(&mut self) -> Option<Comparison> {
+        self.consume_whitespace();
+        let comparison = if self.p.string("<=") {
+            Comparison::LessOrEqual
+        } else if self.p.string("<") {
+            Comparison::Less
+        } else if self.p.string(">=") {
+            Comparison::GreaterOrEqual
+        } else if self.p.string(">") {
+            Comparison::Greater
+        } else if self.p.string("=") {
+            Comparison::Equal
+        } else {
+            return None;
+        };
+        self.consume_whitespace();
+        Some(comparison)
+    }
+
+    /// Parses the value of a media feature, which may be a ratio. An invalid value is kept as an
+    /// empty keyword, which never matches. Eg: 40em, 16/9
+    fn parse_media_value(&mut self) -> Value {
+        let invalid = || Value::Keyword(String::new());
+        self.consume_whitespace();
+        let value = self.parse_component_value().unwrap_or_else(invalid);
+        self.consume_whitespace();
+
+        if !self.p.string("/") {
+            return value;
+        }
+        self.consume_whitespace();
+        let denominator = self.parse_component_value().unwrap_or_else(invalid);
+        self.consume_whitespace();
+        Value::List(vec![value, Value::Slash, denominator])
+    }
+
+    /// Parses a supports condition, returning whether it is supported, or None if it is invalid.
+    /// Eg: (display: grid) and (not (display: inline-grid))
+    fn parse_supports_condition(&mut self) -> Option<bool> {
+        if self.starts_with_keyword("not") {
+            self.p.parse_identifier();
+            self.consume_whitespace();
+            return Some(!self.parse_supports_in_parens()?);
+        }
+
+        let mut supported = self.parse_supports_in_parens()?;
+        loop {
+            self.consume_whitespace();
+            if self.starts_with_keyword("and") {
+                self.p.parse_identifier();
+                self.consume_whitespace();
+                supported &= self.parse_supports_in_parens()?;
+            } else if self.starts_with_keyword("or") {
+                self.p.parse_identifier();
+                self.consume_whitespace();
+                supported |= self.parse_supports_in_parens()?;
+            } else {
+                break Some(supported);
+            }
+        }
+    }
+
+    /// Parses a supports condition in parentheses, or a supports function. Returns None if there
+    /// isn't one. Eg: (display: grid), selector(a > b)
+    fn parse_supports_in_parens(&mut self) -> Option<bool> {
+        if self.starts_with_keyword("selector(") {
+            self.p.parse_identifier();
+            let selector = self.consume_parenthesised()?;
+            return Some(is_supported_selector(&selector));
+        }
+
+        // Other functions test features which aren't supported. Eg: font-tech(color-COLRv1)
+        if self.starts_with_identifier_char(0) {
+            self.p.parse_identifier();
+            self.consume_parenthesised()?;
+            return Some(false);
+        }
+
+        // What's in the parentheses isn't supported if it can't be parsed.
+        let start = self.p.pos;
+        if !self.p.string("(") {
+            return None;
+        }
+        self.consume_whitespace();
+        let supported = self.parse_supports_declaration_or_condition();
+        self.consume_whitespace();
+        if !self.p.string(")") {
+            self.p.pos = start;
+            self.consume_parenthesised();
+            return Some(false);
+        }
+        Some(supported)
+    }
+
+    /// Parses what is inside the parentheses of a supports condition, which is either a
+    /// declaration or another condition. Eg: display: grid
+    fn parse_supports_declaration_or_condition(&mut self) -> bool {
+        if self.p.peek_char(0) == Some('(') || self.starts_with_keyword("not") {
+            return self.parse_supports_condition().unwrap_or(false);
+        }
+
+        let Some(declaration) = self.parse_supports_declaration() else {
+            return false;
+        };
+        let longhands = expand_shorthand(&declaration.name, &declaration.value)
+            .unwrap_or_else(|| vec![declaration]);
+        longhands.iter().all(properties::is_valid)
+    }
+
+    /// Parses a declaration in a supports condition, which doesn't end with a semicolon. Returns
+    /// None if it is invalid.
+    fn parse_supports_declaration(&mut self) -> Option<Declaration> {
+        let name = self.p.parse_identifier();
+        self.consume_whitespace();
+        if !self.p.string(":") {
+            return None;
+        }
+        let value = self.parse_comma_separated_value()?;
+        self.consume_whitespace();
+
+        let important = self.p.string("!");
+        if important {
+            self.consume_whitespace();
+            self.p.parse_identifier();
+        }
+
+        Some(Declaration {
+            name,
+            value,
+            important,
+        })
+    }
+
+    /// Consumes parentheses and everything between them, returning the Some text between them, or
+    /// None if the input doesn't start with a parenthesis.
+    fn consume_parenthesised(&mut self) -> Option<String> {
+        if !self.p.string("(") {
+            return None;
+        }
+        let mut depth = 1;
+        let mut contents = String::new();
+
+        while !self.p.eof() {
+            match self.p.consume_char() {
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+            contents.push(self.p.input[..self.p.pos].chars().next_back().unwrap());
+        }
+
+        Some(contents)
+    }
+
+    /// Parses a block of declarations as they were written, without checking they are valid
+    /// properties. Returns None if there isn't a block. Eg: the descriptors of @font-face
+    fn parse_descriptors(&mut self) -> Option<Vec<Declaration>> {
+        if !self.p.string("{") {
+            return None;
+        }
+        let mut declarations = Vec::new();
+        loop {
+            self.consume_whitespace();
+            // A block which isn't closed ends with the input.
+            if self.p.eof() || self.p.string("}") {
+                break;
+            }
+            declarations.extend(self.parse_declaration());
+        }
+        Some(declarations)
+    }
+
+    /// Parses an @font-face rule, returning None if it has no block, font-family or src.
+    fn parse_font_face(&mut self) -> Option<FontFace> {
+        let mut descriptors: PropertyMap = self
+            .parse_descriptors()?
+            .into_iter()
+            .map(|d| (d.name.to_lowercase(), d.value))
+            .collect();
+
+        let family = font_family_name(&descriptors.remove("font-family")?);
+        let sources = match descriptors.remove("src")? {
+            Value::CommaList(sources) => sources.iter().filter_map(font_source).collect(),
+            source => font_source(&source).into_iter().collect(),
+        };
+
+        Some(FontFace {
+            family,
+            sources,
+            descriptors,
+            layer: None,
+            media: Vec::new(),
+        })
+    }
+
+    /// Parses an @page rule, returning None if it has no block.
+    /// Eg: @page :first { margin: 1in; @top-center { content: "Title" } }
+    fn parse_page_rule(&mut self) -> Option<PageRule> {
+        let selectors = self.p.consume_while(|c| !matches!(c, '{' | ';' | '}'));
+        let selectors = selectors
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        if !self.p.string("{") {
+            return None;
+        }
+        let mut rule = PageRule {
+            selectors,
+            declarations: Vec::new(),
+            margin_rules: Vec::new(),
+            layer: None,
+            media: Vec::new(),
+        };
+
+        loop {
+            self.consume_whitespace();
+            // A block which isn't closed ends with the input.
+            match self.p.peek_char(0) {
+                None => break,
+                Some('}') => {
+                    self.p.consume_char();
+                    break;
+                }
+                Some('@') => {
+                    self.p.consume_char();
+                    let name = self.p.parse_identifier().to_lowercase();
+                    self.consume_whitespace();
+                    match self.parse_descriptors() {
+                        Some(declarations) => rule.margin_rules.push((name, declarations)),
+                        None => self.skip_at_rule(),
+                    }
+                }
+                Some(_) => rule.declarations.extend(self.parse_declaration()),
+            }
+        }
+
+        Some(rule)
+    }
+
+    /// Parses an @keyframes rule, returning None if it has no name or block. A keyframe with an
+    /// invalid selector is skipped.
+    /// Eg: @keyframes fade { from { opacity: 0 } 50%, to { opacity: 1 } }
+    fn parse_keyframes(&mut self) -> Option<Keyframes> {
+        let name = match self.p.peek_char(0)? {
+            '"' | '\'' => self.parse_string(),
+            _ => self.p.parse_identifier(),
+        };
+        self.consume_whitespace();
+        if name.is_empty() || !self.p.string("{") {
+            return None;
+        }
+
+        let mut frames = Vec::new();
+        loop {
+            self.consume_whitespace();
+            // A block which isn't closed ends with the input.
+            if self.p.eof() || self.p.string("}") {
+                break;
+            }
+
+            let Some(offsets) = self.parse_keyframe_selectors() else {
+                self.skip_rule();
+                continue;
+            };
+
+            // Important declarations are ignored in keyframes.
+            let declarations = self.parse_declarations();
+            frames.push(Keyframe {
+                offsets,
+                declarations: declarations.into_iter().filter(|d| !d.important).collect(),
+            });
+        }
+
+        Some(Keyframes {
+            name,
+            frames,
+            layer: None,
+            media: Vec::new(),
+        })
+    }
+
+    /// Parses the selectors of a keyframe into offsets from 0 to 1, up to its block. Returns None
+    /// if any of them are invalid. Eg: from, 50%
+    fn parse_keyframe_selectors(&mut self) -> Option<Vec<f32>> {
+        let mut offsets = Vec::new();
+        loop {
+            self.consume_whitespace();
+            let offset = match self.p.peek_char(0)? {
+                c if c.is_ascii_digit() || c == '.' => match self.parse_length()? {
+                    Value::Percentage(p) if (0.0..=100.0).contains(&p) => p / 100.0,
+                    _ => return None,
+                },
+                _ => match &*self.p.parse_identifier().to_lowercase() {
+                    "from" => 0.0,
+                    "to" => 1.0,
+                    _ => return None,
+                },
+            };
+            offsets.push(offset);
+
+            self.consume_whitespace();
+            if !self.p.string(",") {
+                return (self.p.peek_char(0) == Some('{')).then_some(offsets);
+            }
         }
     }
 
-    fn parse_selectors(&mut self) -> Vec<Selector> {
+    /// Parses a selector, which may contain combinators. Returns None if it is invalid.
+    /// Eg: ul > li a, h1 + p
+    fn parse_selector(&mut self) -> Option<Selector> {
+        let mut ancestors = Vec::new();
+        let mut subject = self.parse_compound_selector()?;
+
+        loop {
+            self.consume_whitespace();
+            let combinator = match self.p.peek_char(0)? {
+                ',' | '{' => break,
+                c @ ('>' | '+' | '~') => {
+                    self.p.consume_char();
+                    self.consume_whitespace();
+                    match c {
+                        '>' => Combinator::Child,
+                        '+' => Combinator::NextSibling,
+                        _ => Combinator::SubsequentSibling,
+                    }
+                }
+                _ => Combinator::Descendant,
+            };
+
+            ancestors.push((subject, combinator));
+            subject = self.parse_compound_selector()?;
+        }
+
+        if ancestors.is_empty() {
+            Some(Selector::Simple(subject))
+        } else {
+            Some(Selector::Complex(ancestors, subject))
+        }
+    }
+
+    /// Parses a simple selector, which mustn't be empty.
+    fn parse_compound_selector(&mut self) -> Option<SimpleSelector> {
+        let start = self.p.pos;
+        let selector = self.parse_simple_selector()?;
+        (self.p.pos != start).then_some(selector)
+    }
+
+    /// Parses a list of selectors, up to the block of their rule. Returns None if any of them are
+    /// invalid.
+    fn parse_selectors(&mut self) -> Option<Vec<Selector>> {
         let mut selectors = Vec::new();
 
         loop {
-            selectors.push(Selector::Simple(self.parse_simple_selector()));
-            self.p.consume_whitespace();
+            selectors.push(self.parse_selector()?);
 
             match self.p.next_char() {
                 ',' => {
                     self.p.consume_char();
-                    self.p.consume_whitespace();
+                    self.consume_whitespace();
                 }
                 '{' => break,
-                c => panic!("Unexpected character {c} in selector list"),
+                _ => return None,
             }
         }
 
         selectors.sort_by_key(|x| std::cmp::Reverse(x.specificity()));
-        selectors
+        Some(selectors)
     }
 
+    /// Parses a block of declarations. Eg: { color: red; margin: 0 }
     fn parse_declarations(&mut self) -> Vec<Declaration> {
         assert_eq!(self.p.consume_char(), '{');
+        let declarations = self.parse_declaration_list();
+        // A block which isn't closed ends with the input.
+        self.p.string("}");
+        declarations
+    }
+
+    /// Parses declarations until the end of a block, or the end of input.
+    fn parse_declaration_list(&mut self) -> Vec<Declaration> {
         let mut declarations = Vec::new();
         loop {
-            self.p.consume_whitespace();
-            if self.p.next_char() == '}' {
-                self.p.consume_char();
+            self.consume_whitespace();
+            if self.p.eof() || self.p.next_char() == '}' {
                 break;
             }
-            declarations.push(self.parse_declaration());
+            let Some(declaration) = self.parse_declaration() else {
+                continue;
+            };
+            let important = declaration.important;
+            let longhands = match expand_shorthand(&declaration.name, &declaration.value) {
+                Some(longhands) => longhands,
+                None => vec![declaration],
+            };
+
+            // Unsupported properties and invalid values are dropped.
+            declarations.extend(
+                longhands
+                    .into_iter()
+                    .map(|d| Declaration { important, ..d })
+                    .filter(properties::is_valid),
+            );
         }
         declarations
     }
 
-    fn parse_declaration(&mut self) -> Declaration {
-        let key = self.p.parse_standard_word();
-        self.p.consume_whitespace();
-        assert_eq!(self.p.consume_char(), ':');
-        self.p.consume_whitespace();
-        let val = self.parse_value();
-        self.p.consume_whitespace();
-        assert_eq!(self.p.consume_char(), ';');
+    /// Parses a declaration. Returns None if it is invalid, when it is skipped up to the semicolon
+    /// after it, or the end of its block. Eg: margin: 0 auto !important;
+    fn parse_declaration(&mut self) -> Option<Declaration> {
+        let start = self.p.pos;
+        let declaration = self.parse_name_and_value();
+        if declaration.is_none() {
+            self.p.pos = start;
+            self.skip_declaration();
+        }
+        declaration
+    }
 
-        Declaration {
+    /// Parses a declaration, returning None if it is invalid.
+    fn parse_name_and_value(&mut self) -> Option<Declaration> {
+        let mut key = self.p.parse_identifier();
+        // Property names are case-insensitive, but custom property names aren't.
+        if !key.starts_with("--") {
+            key.make_ascii_lowercase();
+        }
+        self.consume_whitespace();
+        if key.is_empty() || !self.p.string(":") {
+            return None;
+        }
+        self.consume_whitespace();
+        let val = self.parse_declared_value(&key)?;
+        self.consume_whitespace();
+
+        let important = self.p.string("!");
+        if important {
+            self.consume_whitespace();
+            let keyword = self.p.parse_identifier();
+            if !keyword.eq_ignore_ascii_case("important") {
+                return None;
+            }
+            self.consume_whitespace();
+        }
+
+        // The final declaration in a block doesn't need a semicolon.
+        if !self.p.eof() && self.p.next_char() != '}' && !self.p.string(";") {
+            return None;
+        }
+
+        Some(Declaration {
             name: key,
             value: val,
+            important,
+        })
+    }
+
+    /// Parses the value of a property, up to the end of its declaration. Custom properties, and
+    /// values with var(), aren't parsed until they are used. Returns None if it is invalid.
+    fn parse_declared_value(&mut self, name: &str) -> Option<Value> {
+        let start = self.p.pos;
+        let tokens = self.parse_tokens()?;
+        if is_custom_property(name) {
+            return Some(custom_property_value(tokens));
+        }
+        if tokens.has_var() {
+            return Some(Value::Unparsed(tokens, None));
+        }
+
+        // The whole of the value has to be parsed.
+        let end = self.p.pos;
+        self.p.pos = start;
+        let value = self.parse_comma_separated_value()?;
+        self.consume_whitespace();
+        (self.p.pos == end).then_some(value)
+    }
+
+    /// Skips an invalid declaration, up to the semicolon after it, or the end of its block.
+    fn skip_declaration(&mut self) {
+        let mut depth = 0usize;
+        while !self.p.eof() {
+            match self.p.next_char() {
+                '"' | '\'' => {
+                    self.parse_string();
+                    continue;
+                }
+                ';' if depth == 0 => {
+                    self.p.consume_char();
+                    return;
+                }
+                '}' if depth == 0 => return,
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            self.p.consume_char();
         }
     }
 
-    fn parse_value(&mut self) -> Value {
+    /// Parses the tokens of a declaration's value, up to the end of the declaration. Returns None
+    /// if a url is invalid. Eg: 1px solid var(--colour)
+    fn parse_tokens(&mut self) -> Option<TokenList> {
+        let mut tokens = Vec::new();
+        // The closing brackets of the blocks and functions the tokens are in.
+        let mut closing = Vec::new();
+
+        loop {
+            let start = self.p.pos;
+            self.consume_whitespace();
+            if self.p.pos != start && !tokens.is_empty() {
+                tokens.push(Token::Whitespace);
+            }
+            if self.p.eof() {
+                break;
+            }
+
+            let token = match self.p.next_char() {
+                ';' | '!' | '}' if closing.is_empty() => break,
+                '"' | '\'' => Token::Str(self.parse_string()),
+                '#' => {
+                    self.p.consume_char();
+                    Token::Hash(self.p.consume_while(identifier_char))
+                }
+                c if c.is_ascii_digit() || self.starts_with_number() => {
+                    let mut number = String::new();
+                    if matches!(c, '-' | '+') {
+                        number.push(self.p.consume_char());
+                    }
+                    number.push_str(&self.p.consume_while(|c| matches!(c, '0'..='9' | '.')));
+                    if self.p.string("%") {
+                        number.push('%');
+                    } else {
+                        number.push_str(&self.p.consume_while(|c| c.is_ascii_alphabetic()));
+                    }
+                    Token::Number(number)
+                }
+                c if identifier_char(c) => {
+                    let name = self.p.parse_identifier();
+                    if self.p.eof() || self.p.next_char() != '(' {
+                        Token::Ident(name)
+                    } else if name.eq_ignore_ascii_case("url")
+                        && !matches!(self.p.peek_char(1), Some('"' | '\''))
+                    {
+                        Token::Url(self.parse_url()?)
+                    } else {
+                        self.p.consume_char();
+                        closing.push(')');
+                        Token::Function(name)
+                    }
+                }
+                c => {
+                    self.p.consume_char();
+                    match c {
+                        '(' => closing.push(')'),
+                        '[' => closing.push(']'),
+                        '{' => closing.push('}'),
+                        c if closing.last() == Some(&c) => {
+                            closing.pop();
+                        }
+                        _ => {}
+                    }
+                    Token::Delim(c)
+                }
+            };
+            tokens.push(token);
+        }
+
+        if tokens.last() == Some(&Token::Whitespace) {
+            tokens.pop();
+        }
+        Some(TokenList(tokens))
+    }
+
+    /// Does the input start with a signed number or a number without an integer part? Eg: -1, .5
+    fn starts_with_number(&self) -> bool {
+        let digit = |n| self.p.peek_char(n).is_some_and(|c| c.is_ascii_digit());
         match self.p.next_char() {
-            '0'..='9' => self.parse_length(),
+            '-' | '+' => digit(1) || (self.p.peek_char(1) == Some('.') && digit(2)),
+            '.' => digit(1),
+            _ => false,
+        }
+    }
+
+    /// Parses a declaration's value, which may be a comma separated list of values. Returns None
+    /// if it is invalid.
+    fn parse_comma_separated_value(&mut self) -> Option<Value> {
+        let mut values = vec![self.parse_value()?];
+
+        while !self.p.eof() && self.p.next_char() == ',' {
+            self.p.consume_char();
+            values.push(self.parse_value()?);
+        }
+
+        if values.len() == 1 {
+            Some(values.swap_remove(0))
+        } else {
+            Some(Value::CommaList(values))
+        }
+    }
+
+    /// Parses a value, which may be a space separated list of component values. Returns None if
+    /// any of them are invalid.
+    fn parse_value(&mut self) -> Option<Value> {
+        let mut values = Vec::new();
+
+        loop {
+            self.consume_whitespace();
+            if self.p.eof() || matches!(self.p.next_char(), ';' | '}' | ',' | ')' | '!') {
+                break;
+            }
+            values.push(self.parse_component_value()?);
+        }
+
+        if values.len() == 1 {
+            Some(values.swap_remove(0))
+        } else {
+            Some(Value::List(values))
+        }
+    }
+
+    /// Parses a single value, returning None if it is invalid. Eg: 10px, "text", attr(title)
+    fn parse_component_value(&mut self) -> Option<Value> {
+        Some(match self.p.peek_char(0)? {
+            '0'..='9' | '.' => self.parse_length()?,
+            '-' | '+'
+                if self
+                    .p
+                    .peek_char(1)
+                    .is_some_and(|c| c.is_ascii_digit() || c == '.') =>
+            {
+                self.parse_length()?
+            }
             '#' => self.parse_colour(),
-            _ => Value::Keyword(self.p.parse_standard_word()),
+            '"' | '\'' => Value::Str(self.parse_string()),
+            '/' => {
+                self.p.consume_char();
+                Value::Slash
+            }
+            '[' => Value::LineNames(self.parse_line_names()?),
+            c if identifier_char(c) => {
+                let name = self.p.parse_identifier();
+                if !self.p.eof() && self.p.next_char() == '(' {
+                    let name = name.to_lowercase();
+                    if is_math_function(&name) {
+                        return Some(Value::Calc(Box::new(self.parse_math_function(&name)?)));
+                    }
+                    if name == "url" {
+                        return Some(Value::Function(name, vec![Value::Str(self.parse_url()?)]));
+                    }
+
+                    let args = self.parse_arguments()?;
+                    match colour_function(&name, &args) {
+                        Some(colour) if is_colour_function(&name) => Value::Colour(colour),
+                        _ => Value::Function(name, args),
+                    }
+                } else {
+                    Value::Keyword(name)
+                }
+            }
+            _ => return None,
+        })
+    }
+
+    /// Parses the names of a grid line, including the brackets. Returns None if they are invalid.
+    /// Eg: [main-start content]
+    fn parse_line_names(&mut self) -> Option<Vec<String>> {
+        assert_eq!(self.p.consume_char(), '[');
+        let mut names = Vec::new();
+
+        loop {
+            self.consume_whitespace();
+            match self.p.peek_char(0)? {
+                ']' => {
+                    self.p.consume_char();
+                    return Some(names);
+                }
+                c if identifier_char(c) => names.push(self.p.parse_identifier()),
+                _ => return None,
+            }
         }
     }
 
-    fn parse_length(&mut self) -> Value {
-        Value::Length(self.parse_float(), self.parse_unit())
+    /// Parses the comma separated arguments of a function, including the parentheses. Returns
+    /// None if any of them are invalid, or the parentheses aren't closed.
+    fn parse_arguments(&mut self) -> Option<Vec<Value>> {
+        assert_eq!(self.p.consume_char(), '(');
+        let mut args = Vec::new();
+
+        loop {
+            self.consume_whitespace();
+            if self.p.string(")") {
+                break;
+            }
+            args.push(self.parse_value()?);
+            if !self.p.string(",") && !self.p.starts_with(")") {
+                return None;
+            }
+        }
+
+        Some(args)
     }
 
-    fn parse_float(&mut self) -> f32 {
-        let s = self.p.consume_while(|c| matches!(c, '0'..='9' | '.'));
-        s.parse().unwrap()
+    /// Parses the address within url(), which may be unquoted, including the parentheses.
+    /// Returns None if the parentheses aren't closed.
+    fn parse_url(&mut self) -> Option<String> {
+        assert_eq!(self.p.consume_char(), '(');
+        self.consume_whitespace();
+
+        let url = match self.p.peek_char(0)? {
+            '"' | '\'' => self.parse_string(),
+            _ => self.p.consume_while(|c| c != ')' && !c.is_whitespace()),
+        };
+
+        self.consume_whitespace();
+        self.p.string(")").then_some(url)
     }
 
-    fn parse_unit(&mut self) -> Unit {
-        match &*self.p.parse_standard_word().to_lowercase() {
-            "px" => Unit::Px,
-            _ => panic!("Unrecognised unit!"),
+    /// Parses a quoted string, resolving any escapes within it.
+    fn parse_string(&mut self) -> String {
+        let quote = self.p.consume_char();
+        let mut res = String::new();
+
+        while !self.p.eof() {
+            match self.p.consume_char() {
+                c if c == quote => break,
+                '\\' => {
+                    let hex = self.p.consume_while(|c| c.is_ascii_hexdigit());
+                    if hex.is_empty() {
+                        // An escape at the end of input is dropped.
+                        if !self.p.eof() {
+                            res.push(self.p.consume_char());
+                        }
+                        continue;
+                    }
+                    // A single whitespace after a hex escape is part of the escape.
+                    if !self.p.eof() && self.p.next_char().is_whitespace() {
+                        self.p.consume_char();
+                    }
+                    let code = u32::from_str_radix(&hex[..hex.len().min(6)], 16).unwrap_or(0xFFFD);
+                    res.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+                }
+                c => res.push(c),
+            }
         }
+
+        res
     }
 
+    /// Parses the arguments of a math function, Eg: calc(100% - 2em), including the parentheses.
+    /// Returns None if the expression is invalid, or has the wrong number of arguments.
+    fn parse_math_function(&mut self, name: &str) -> Option<CalcExpr> {
+        assert_eq!(self.p.consume_char(), '(');
+        let mut args = Vec::new();
+
+        loop {
+            args.push(self.parse_calc_sum()?);
+            self.consume_whitespace();
+            match self.p.peek_char(0)? {
+                ',' => {}
+                ')' => {
+                    self.p.consume_char();
+                    break;
+                }
+                _ => return None,
+            }
+            self.p.consume_char();
+        }
+
+        match (name, args.len()) {
+            ("calc", 1) => Some(args.swap_remove(0)),
+            ("min", _) => Some(CalcExpr::Min(args)),
+            ("max", _) => Some(CalcExpr::Max(args)),
+            ("clamp", 3) => {
+                let max = args.pop()?;
+                let val = args.pop()?;
+                let min = args.pop()?;
+                Some(CalcExpr::Clamp(Box::new(min), Box::new(val), Box::new(max)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Parses terms added or subtracted together within a math function.
+    fn parse_calc_sum(&mut self) -> Option<CalcExpr> {
+        let mut expr = self.parse_calc_product()?;
+
+        loop {
+            self.consume_whitespace();
+            let Some(op @ ('+' | '-')) = self.p.peek_char(0) else {
+                break Some(expr);
+            };
+            self.p.consume_char();
+
+            let rhs = Box::new(self.parse_calc_product()?);
+            expr = match op {
+                '+' => CalcExpr::Sum(Box::new(expr), rhs),
+                _ => CalcExpr::Difference(Box::new(expr), rhs),
+            };
+        }
+    }
+
+    /// Parses values multiplied or divided together within a math function.
+    fn parse_calc_product(&mut self) -> Option<CalcExpr> {
+        let mut expr = self.parse_calc_value()?;
+
+        loop {
+            self.consume_whitespace();
+            let Some(op @ ('*' | '/')) = self.p.peek_char(0) else {
+                break Some(expr);
+            };
+            self.p.consume_char();
+
+            let rhs = Box::new(self.parse_calc_value()?);
+            expr = match op {
+                '*' => CalcExpr::Product(Box::new(expr), rhs),
+                _ => CalcExpr::Quotient(Box::new(expr), rhs),
+            };
+        }
+    }
+
+    /// Parses a single value, or a parenthesised expression, within a math function. Returns None
+    /// if it is invalid.
+    fn parse_calc_value(&mut self) -> Option<CalcExpr> {
+        self.consume_whitespace();
+
+        match self.p.peek_char(0)? {
+            '(' => {
+                self.p.consume_char();
+                let expr = self.parse_calc_sum()?;
+                self.consume_whitespace();
+                self.p.string(")").then_some(expr)
+            }
+            c if c.is_ascii_digit() || matches!(c, '.' | '-' | '+') => {
+                Some(CalcExpr::Leaf(self.parse_length()?))
+            }
+            _ => {
+                let name = self.p.parse_identifier().to_lowercase();
+                match is_math_function(&name) && self.p.starts_with("(") {
+                    true => self.parse_math_function(&name),
+                    false => None,
+                }
+            }
+        }
+    }
+
+    /// Parses a number, which may be followed by a unit or a percent sign. Returns None if there
+    /// isn't a number.
+    fn parse_length(&mut self) -> Option<Value> {
+        let num = self.parse_float()?;
+        if self.p.eof() {
+            return Some(Value::Number(num));
+        }
+
+        Some(match self.p.next_char() {
+            '%' => {
+                self.p.consume_char();
+                Value::Percentage(num)
+            }
+            c if c.is_ascii_alphabetic() => {
+                let name = self.p.parse_standard_word();
+                if let Some(degrees) = angle_to_degrees(num, &name) {
+                    return Some(Value::Angle(degrees));
+                }
+                if let Some(dppx) = resolution_to_dppx(num, &name) {
+                    return Some(Value::Resolution(dppx));
+                }
+                if name.eq_ignore_ascii_case("fr") {
+                    return Some(Value::Flex(num));
+                }
+                match Unit::from_name(&name) {
+                    Some(unit) => Value::Length(num, unit),
+                    // Unknown dimensions are kept as they were written, so they are never used as lengths.
+                    None => Value::Keyword(format!("{num}{name}")),
+                }
+            }
+            _ => Value::Number(num),
+        })
+    }
+
+    /// Parses a number, returning None if it isn't one. Eg: -1.5
+    fn parse_float(&mut self) -> Option<f32> {
+        let sign = match self.p.next_char() {
+            '-' | '+' => self.p.consume_char().to_string(),
+            _ => String::new(),
+        };
+        let s = self.p.consume_while(|c| matches!(c, '0'..='9' | '.'));
+        (sign + &s).parse().ok()
+    }
+
+    /// Parses a hex colour. Eg: #f80, #ff8800cc
     fn parse_colour(&mut self) -> Value {
         assert_eq!(self.p.consume_char(), '#');
-        Value::ColourValue(Colour {
-            r: self.parse_hex_pair(),
-            g: self.parse_hex_pair(),
-            b: self.parse_hex_pair(),
-            a: 255,
-        })
+        let digits = self.p.consume_while(identifier_char);
+
+        match Colour::from_hex(&digits) {
+            Some(colour) => Value::Colour(colour),
+            // Invalid colours are kept as they were written, so they are never used as colours.
+            None => Value::Keyword(format!("#{digits}")),
+        }
+    }
+
+    /// Parse a declaration's value from a string of css, returning None if it isn't a valid
+    /// value. Eg: 1px solid black
+    pub fn parse_declaration_value(s: &str) -> Option<Value> {
+        let mut parser = CssParser {
+            p: Parser {
+                pos: 0,
+                input: s.to_string(),
+            },
+        };
+        parser.consume_whitespace();
+        let value = parser.parse_comma_separated_value()?;
+        parser.consume_whitespace();
+        parser.p.eof().then_some(value)
+    }
+
+    /// Parse the value of a property from a string of css, as it would be in a declaration.
+    /// Returns None if it isn't a single valid value. Eg: ("margin", "0 var(--gap)")
+    pub fn parse_property_value(name: &str, s: &str) -> Option<Value> {
+        let mut parser = CssParser {
+            p: Parser {
+                pos: 0,
+                input: s.to_string(),
+            },
+        };
+        parser.consume_whitespace();
+        let value = parser.parse_declared_value(name)?;
+        parser.p.eof().then_some(value)
+    }
+
+    /// Parse a list of media queries from a string of css. Eg: screen and (min-width: 40em)
+    pub fn parse_media(s: &str) -> MediaQueryList {
+        CssParser {
+            p: Parser {
+                pos: 0,
+                input: s.to_string(),
+            },
+        }
+        .parse_media_query_list()
+    }
+
+    /// Parse a [String] of css code.
+    pub fn parse(s: String) -> Stylesheet {
+        CssParser::parse_with_origin(s, Origin::Author)
+    }
+
+    /// Parse a [String] of css code, from a given [Origin].
+    /// Imported stylesheets aren't loaded, and are left in [Stylesheet::imports].
+    pub fn parse_with_origin(s: String, origin: Origin) -> Stylesheet {
+        let mut sheet = Stylesheet::new(origin);
+        CssParser {
+            p: Parser { pos: 0, input: s },
+        }
+        .parse_rules(&mut sheet, &Conditions::default(), false);
+        sheet
+    }
+
+    /// Parse a single rule from a string of css, from a given [Origin]. Returns None unless the
+    /// css is exactly one valid rule. Eg: @media print { nav { display: none } }
+    pub fn parse_rule_with_origin(s: &str, origin: Origin) -> Option<Stylesheet> {
+        let mut sheet = Stylesheet::new(origin);
+        let mut parser = CssParser {
+            p: Parser {
+                pos: 0,
+                input: s.to_string(),
+            },
+        };
+        parser.consume_whitespace();
+
+        let valid = match parser.p.peek_char(0)? {
+            '@' => parser.parse_at_rule(&mut sheet, &Conditions::default(), false),
+            _ => match parser.parse_rule() {
+                Some(rule) => {
+                    sheet.rules.push(rule);
+                    true
+                }
+                None => false,
+            },
+        };
+        parser.consume_whitespace();
+        (valid && parser.p.eof()).then_some(sheet)
+    }
+
+    /// Parse a [String] of css code from a url, loading the stylesheets it imports with a
+    /// [Loader]. Imports which can't be loaded are ignored.
+    pub fn parse_with_loader(
+        s: String,
+        origin: Origin,
+        url: &str,
+        loader: &dyn Loader,
+    ) -> Stylesheet {
+        let sheet = CssParser::parse_with_origin(s, origin);
+        load_imports(sheet, url, loader, &mut vec![url.to_string()])
+    }
+
+    /// Parse the declarations of a style attribute. Invalid declarations are dropped, up to the
+    /// semicolon after them. Eg: color: red; margin: 0
+    pub fn parse_inline(s: &str) -> Vec<Declaration> {
+        let mut parser = CssParser {
+            p: Parser {
+                pos: 0,
+                input: s.to_string(),
+            },
+        };
+
+        let mut declarations = Vec::new();
+        loop {
+            declarations.extend(parser.parse_declaration_list());
+            // There is no block to end, so a closing brace is part of an invalid declaration.
+            if !parser.p.string("}") {
+                break declarations;
+            }
+            parser.skip_declaration();
+        }
+    }
+}
+
+/// Returns the value of a custom property from its tokens. A lone CSS-wide keyword is kept as a
+/// keyword, so that it is applied like any other property's.
+fn custom_property_value(tokens: TokenList) -> Value {
+    match tokens.0.as_slice() {
+        [Token::Ident(k)] if CSS_WIDE_KEYWORDS.contains(&k.to_lowercase().as_str()) => {
+            Value::Keyword(k.clone())
+        }
+        _ => Value::Tokens(tokens),
+    }
+}
+
+/// The at-rules that the rules being parsed are nested in.
+#[derive(Clone, Default)]
+struct Conditions {
+    layer: Option<String>,
+    media: Vec<MediaQueryList>,
+}
+
+/// Returns a unique name for a layer without one. It isn't an identifier, so can't be written.
+fn anonymous_layer() -> String {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    format!("<anonymous {}>", COUNT.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Inserts the rules of the stylesheets a stylesheet imports before its own.
+/// `loading` holds the urls of the stylesheets being loaded, so that import cycles are ignored.
+fn load_imports(
+    mut sheet: Stylesheet,
+    url: &str,
+    loader: &dyn Loader,
+    loading: &mut Vec<String>,
+) -> Stylesheet {
+    let mut imported = Stylesheet::new(sheet.origin);
+
+    for import in std::mem::take(&mut sheet.imports) {
+        let import_url = resolve_url(url, &import.url);
+        if loading.contains(&import_url) {
+            continue;
+        }
+        let Some(text) = loader.load(&import_url) else {
+            continue;
+        };
+
+        loading.push(import_url.clone());
+        let child = CssParser::parse_with_origin(text, sheet.origin);
+        let child = load_imports(child, &import_url, loader, loading);
+        loading.pop();
+
+        // The imported layers are nested in the layer of the import.
+        let layer = |name: Option<String>| match (&import.layer, name) {
+            (Some(outer), Some(inner)) => Some(format!("{outer}.{inner}")),
+            (outer, inner) => inner.or_else(|| outer.clone()),
+        };
+
+        // The imported rules only apply when the import's media queries match.
+        let media =
+            |media: Vec<MediaQueryList>| import.media.iter().cloned().chain(media).collect();
+
+        let layers = child.layers.into_iter().filter_map(|l| layer(Some(l)));
+        imported.layers.extend(layers);
+        imported
+            .rules
+            .extend(child.rules.into_iter().map(|rule| Rule {
+                layer: layer(rule.layer),
+                media: media(rule.media),
+                ..rule
+            }));
+        imported
+            .font_faces
+            .extend(child.font_faces.into_iter().map(|font_face| FontFace {
+                layer: layer(font_face.layer),
+                media: media(font_face.media),
+                ..font_face
+            }));
+        imported
+            .pages
+            .extend(child.pages.into_iter().map(|page| PageRule {
+                layer: layer(page.layer),
+                media: media(page.media),
+                ..page
+            }));
+        imported
+            .keyframes
+            .extend(child.keyframes.into_iter().map(|keyframes| Keyframes {
+                layer: layer(keyframes.layer),
+                media: media(keyframes.media),
+                ..keyframes
+            }));
+    }
+
+    imported.layers.extend(sheet.layers);
+    imported.rules.extend(sheet.rules);
+    imported.font_faces.extend(sheet.font_faces);
+    imported.pages.extend(sheet.pages);
+    imported.keyframes.extend(sheet.keyframes);
+    imported
+}
+
+/// Returns the name of a font family. Unquoted names may be made of several words.
+fn font_family_name(value: &Value) -> String {
+    match value {
+        Value::Str(s) | Value::Keyword(s) => s.clone(),
+        Value::List(words) => words
+            .iter()
+            .map(font_family_name)
+            .collect::<Vec<_>>()
+            .join(" "),
+        _ => String::new(),
+    }
+}
+
+/// Returns the Some source of an @font-face src, or None if it is invalid.
+/// Eg: url(lato.woff2) format("woff2"), local(Lato)
+fn font_source(value: &Value) -> Option<FontSource> {
+    let (source, format) = match value {
+        Value::List(parts) => match parts.as_slice() {
+            [source, Value::Function(f, args)] if f == "format" => (source, args.first()),
+            _ => return None,
+        },
+        source => (source, None),
+    };
+
+    match source {
+        Value::Function(f, args) if f == "url" => {
+            let Some(Value::Str(url)) = args.first() else {
+                return None;
+            };
+            Some(FontSource::Url(url.clone(), format.map(font_family_name)))
+        }
+        Value::Function(f, args) if f == "local" => {
+            Some(FontSource::Local(font_family_name(args.first()?)))
+        }
+        _ => None,
     }
+}
+
+/// Can the selector in a supports selector() function be parsed?
+fn is_supported_selector(selector: &str) -> bool {
+    let mut parts = selector.split(':').skip(1).peekable();
+
+    // Every pseudo-class and pseudo-element has to be supported. Eg: ::before
+    while let Some(part) = parts.next() {
+        let legacy = !part.is_empty();
+        let part = if legacy {
+            part
+        } else {
+            match parts.next() {
+                Some(part) => part,
+                None => return false,
+            }
+        };
+        let name: String = part.chars().take_while(|c| identifier_char(*c)).collect();
+        if PseudoElement::from_name(&name, legacy).is_none() {
+            return false;
+        }
+    }
+    true
+}
+
+/// Is the function a math function, whose arguments are math expressions?
+fn is_math_function(name: &str) -> bool {
+    matches!(name, "calc" | "min" | "max" | "clamp")
+}
+
+/// Returns the Some resolution in dots per px, or None if `unit` isn't a resolution unit.
+fn resolution_to_dppx(n: f32, unit: &str) -> Option<f32> {
+    match &*unit.to_lowercase() {
+        "dppx" | "x" => Some(n),
+        "dpi" => Some(n / 96.0),
+        "dpcm" => Some(n * 2.54 / 96.0),
+        _ => None,
+    }
+}
 
-    /// Parse two hexadecimal digits.
-    fn parse_hex_pair(&mut self) -> u8 {
-        let s = &self.p.input[self.p.pos..self.p.pos + 2];
-        self.p.pos += 2;
-        u8::from_str_radix(s, 16).unwrap()
+/// Returns the Some size of an angle in degrees, or None if `unit` isn't an angle unit.
+fn angle_to_degrees(n: f32, unit: &str) -> Option<f32> {
+    match &*unit.to_lowercase() {
+        "deg" => Some(n),
+        "rad" => Some(n.to_degrees()),
+        "grad" => Some(n * 0.9),
+        "turn" => Some(n * 360.0),
+        _ => None,
     }
 }
@@ -1,6 +1,15 @@
-use std::collections::HashMap;
-use crate::parsing::parser::Parser;
 use crate::dom;
+use crate::parsing::parser::Parser;
+use std::collections::HashMap;
+
+/// Elements which can't have children, so have no closing tag.
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Elements whose contents are text, rather than html.
+const RAW_TEXT_ELEMENTS: [&str; 2] = ["script", "style"];
 
 /// A parser for html.
 #[derive(Debug)]
@@ -28,23 +37,59 @@ impl HtmlParser {
         assert!(self.p.consume_char() == '<');
         let tag_name = self.p.parse_standard_word();
         let attributes = self.parse_attributes();
+
+        // Void elements have no children or closing tag. Eg: <link rel="stylesheet" href="a.css">
+        let self_closing = self.p.string("/");
         assert!(self.p.consume_char() == '>');
+        if self_closing || VOID_ELEMENTS.contains(&tag_name.to_lowercase().as_str()) {
+            return dom::elem(tag_name, attributes, Vec::new());
+        }
 
         // Parse all the children:
-        let children = self.parse_nodes();
+        let children = if RAW_TEXT_ELEMENTS.contains(&tag_name.to_lowercase().as_str()) {
+            self.parse_raw_text(&tag_name)
+        } else {
+            self.parse_nodes()
+        };
 
         // Parse closing tag:
         assert!(self.p.consume_char() == '<');
         assert!(self.p.consume_char() == '/');
-        assert!(self.p.parse_standard_word() == tag_name);
+        assert!(self.p.parse_standard_word().eq_ignore_ascii_case(&tag_name));
         assert!(self.p.consume_char() == '>');
 
         dom::elem(tag_name, attributes, children)
     }
 
+    /// Parses the text of an element whose contents aren't html, up to its closing tag.
+    /// Eg: The css of a <style> element, which may contain `<`.
+    fn parse_raw_text(&mut self, tag_name: &str) -> Vec<dom::Node> {
+        let closing = format!("</{tag_name}");
+        let start = self.p.pos;
+        // Tag names are case-insensitive. Eg: <style>...</STYLE>
+        while !self.p.eof() && !self.p.starts_with_ignore_ascii_case(&closing) {
+            self.p.consume_char();
+        }
+
+        let text = self.p.input[start..self.p.pos].to_string();
+        if text.is_empty() {
+            Vec::new()
+        } else {
+            vec![dom::text(text)]
+        }
+    }
+
     /// Parses an attribute of an element/tag.
     fn parse_attribute(&mut self) -> (String, String) {
-        let key = self.p.parse_standard_word();
+        // Attribute names may contain dashes. Eg: data-state
+        let key = self.p.parse_identifier();
+        assert!(!key.is_empty());
+
+        // Boolean attributes don't need a value. Eg: <p hidden>
+        if self.p.next_char() != '=' {
+            return (key, String::new());
+        }
+
         assert!(self.p.consume_char() == '=');
         let val = self.parse_attribute_value();
 
@@ -52,14 +97,10 @@ impl HtmlParser {
     }
 
     /// Parses an attribute value.
-    /// TODO: Replace with parse_between
     fn parse_attribute_value(&mut self) -> String {
-        let open_quote = self.p.consume_char();
+        let open_quote = self.p.next_char();
         assert!(open_quote == '"' || open_quote == '\'');
-        let val = self.p.consume_while(|x| x != open_quote);
-        assert!(self.p.consume_char() == open_quote);
-
-        val
+        self.p.parse_between(open_quote)
     }
 
     /// Parses multiple attributes.
@@ -68,7 +109,7 @@ impl HtmlParser {
 
         loop {
             self.p.consume_whitespace();
-            if self.p.next_char() == '>' {
+            if self.p.next_char() == '>' || self.p.starts_with("/>") {
                 break;
             }
             let (key, val) = self.parse_attribute();
@@ -82,8 +123,9 @@ impl HtmlParser {
     fn parse_nodes(&mut self) -> Vec<dom::Node> {
         let mut nodes = Vec::new();
 
+        // Text keeps its whitespace, even if it is only whitespace, since it separates the nodes
+        // around it. Eg: <b>a</b> <i>b</i>. Whitespace which doesn't is removed by layout.
         loop {
-            self.p.consume_whitespace();
             if self.p.eof() || self.p.starts_with("</") {
                 break;
             }
@@ -99,6 +141,8 @@ impl HtmlParser {
             p: Parser { pos: 0, input: s },
         }
         .parse_nodes();
+        // Whitespace outside of the root element isn't part of the document.
+        nodes.retain(|n| !matches!(&n.node_type, dom::NodeType::Text(t) if t.trim().is_empty()));
 
         // If the document contains a root element, return it, else create one.
         if nodes.len() == 1 {
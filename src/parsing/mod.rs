@@ -1,3 +1,3 @@
+pub mod css_parser;
 pub mod html_parser;
 mod parser;
-pub mod css_parser;
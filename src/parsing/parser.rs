@@ -10,15 +10,27 @@ impl Parser {
         self.input[self.pos..].chars().next().unwrap()
     }
 
+    /// Returns the Some char `n` chars ahead of the next char, or None if past the end of input.
+    pub fn peek_char(&self, n: usize) -> Option<char> {
+        self.input[self.pos..].chars().nth(n)
+    }
+
     /// Do the chars match the current position in string?
     pub fn starts_with(&self, s: &str) -> bool {
         self.input[self.pos..].starts_with(s)
     }
 
+    /// Do the chars match the current position in string, ignoring ASCII case?
+    pub fn starts_with_ignore_ascii_case(&self, s: &str) -> bool {
+        self.input[self.pos..]
+            .get(..s.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(s))
+    }
+
     pub fn string(&mut self, s: &str) -> bool {
         if self.starts_with(s) {
             self.pos += s.len();
-            return true
+            return true;
         }
         false
     }
@@ -60,6 +72,11 @@ impl Parser {
         self.consume_while(standard_char)
     }
 
+    /// Parses a css identifier, which can contain the characters allowed in [identifier_char].
+    pub fn parse_identifier(&mut self) -> String {
+        self.consume_while(identifier_char)
+    }
+
     /// Parse a string that is between two characters
     pub fn parse_between(&mut self, c: char) -> String {
         assert!(self.next_char() == c);
@@ -79,3 +96,8 @@ pub fn standard_char(c: char) -> bool {
         .iter()
         .fold(false, |acc, (lo, hi)| acc || ((&c >= lo) && (&c <= hi)))
 }
+
+/// Check if a character can appear in a css identifier (Eg: margin-left, --brand, my_class).
+pub fn identifier_char(c: char) -> bool {
+    standard_char(c) || matches!(c, '-' | '_') || !c.is_ascii()
+}
@@ -0,0 +1,255 @@
+// Sorting the declarations that apply to an element, to find the value of each property.
+// https://www.w3.org/TR/css-cascade-5/#cascade-sort
+
+use crate::dom::ElementData;
+use crate::style::css::{
+    Declaration, Origin, PropertyMap, PseudoElement, Rule, Specificity, Stylesheet, Value,
+};
+use crate::style::matching::{matches, MatchContext, RuleEntry, RuleMap};
+use crate::style::restyle::InvalidationMap;
+use crate::style::values::Viewport;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// The position of a declaration in the cascade. Declarations with a higher precedence win.
+/// Fields are compared in order, so the first that differs decides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Precedence {
+    /// The origin and importance of the declaration.
+    origin: usize,
+    /// Was the declaration in a style attribute?
+    inline: bool,
+    layer: usize,
+    specificity: Specificity,
+    /// The order of appearance: The index of the stylesheet, then of the rule within it.
+    order: (usize, usize),
+}
+
+/// A set of stylesheets, ready to be cascaded.
+pub struct Cascade<'a> {
+    pub stylesheets: Vec<&'a Stylesheet>,
+    /// Each origin's cascade layers, from lowest to highest precedence.
+    layers: HashMap<Origin, Vec<&'a str>>,
+    /// The rules of every stylesheet whose media queries match, indexed by the pseudo-element
+    /// they style.
+    rules: HashMap<Option<PseudoElement>, RuleMap<'a>>,
+    pub invalidations: InvalidationMap,
+    /// Are rules found by their rule hash and the ancestor filter, and styles shared between
+    /// siblings? Only turned off to compare with.
+    pub optimized: bool,
+}
+
+impl<'a> Cascade<'a> {
+    pub fn new(stylesheets: Vec<&'a Stylesheet>, viewport: &Viewport) -> Self {
+        let mut layers: HashMap<Origin, Vec<&str>> = HashMap::new();
+
+        // Layers are ordered by when they are first declared, across every stylesheet of an origin.
+        for sheet in &stylesheets {
+            let names = layers.entry(sheet.origin).or_default();
+            let declared = sheet.layers.iter().map(String::as_str);
+            let used = sheet.rules.iter().filter_map(|r| r.layer.as_deref());
+
+            for name in declared.chain(used) {
+                // A nested layer implicitly declares its parents. Eg: a.b declares a.
+                let parents = name.match_indices('.').map(|(i, _)| &name[..i]);
+                for layer in parents.chain([name]) {
+                    if !names.contains(&layer) {
+                        names.push(layer);
+                    }
+                }
+            }
+        }
+
+        // Layers are sorted by their position in the layer tree. Sublayers come before their
+        // parent, since rules directly in a layer win over its sublayers.
+        for names in layers.values_mut() {
+            let key = |name: &str| {
+                let parents = name.match_indices('.').map(|(i, _)| &name[..i]);
+                let mut path: Vec<usize> = parents
+                    .chain([name])
+                    .map(|layer| names.iter().position(|n| *n == layer).unwrap())
+                    .collect();
+                path.push(usize::MAX);
+                path
+            };
+
+            let mut sorted = names.clone();
+            sorted.sort_by_cached_key(|name| key(name));
+            *names = sorted;
+        }
+
+        // Index every selector by the pseudo-element it styles, then by its rule hash.
+        let mut rules: HashMap<Option<PseudoElement>, RuleMap> = HashMap::new();
+        let mut invalidations = InvalidationMap::default();
+        for (i, sheet) in stylesheets.iter().copied().enumerate() {
+            for (j, rule) in sheet.rules.iter().enumerate() {
+                if !rule.matches_media(viewport) {
+                    continue;
+                }
+                for selector in &rule.selectors {
+                    invalidations.insert(selector);
+                    rules
+                        .entry(selector.subject().pseudo_element)
+                        .or_default()
+                        .insert(RuleEntry::new(selector, rule, (i, j)));
+                }
+            }
+        }
+
+        Self {
+            stylesheets,
+            layers,
+            rules,
+            invalidations,
+            optimized: true,
+        }
+    }
+
+    /// Returns the declarations of every rule that matches an element, (or one of its
+    /// pseudo-elements), with their precedence.
+    pub fn matching_declarations(
+        &self,
+        elem: &ElementData,
+        context: MatchContext,
+        pseudo: Option<PseudoElement>,
+    ) -> Vec<(Precedence, &'a Declaration)> {
+        let Some(rules) = self.rules.get(&pseudo) else {
+            return Vec::new();
+        };
+
+        let is_match = |e: &&RuleEntry| matches(elem, context, e.selector, pseudo);
+        let mut matched: Vec<&RuleEntry> = match self.optimized {
+            true => rules
+                .candidates(elem)
+                .filter(|e| context.filter.might_match(e))
+                .filter(is_match)
+                .collect(),
+            false => rules.entries().filter(is_match).collect(),
+        };
+
+        // A rule is as specific as the most specific of its selectors that match.
+        matched.sort_by_key(|e| (e.order, Reverse(e.specificity)));
+        matched.dedup_by_key(|e| e.order);
+
+        matched
+            .into_iter()
+            .flat_map(|e| {
+                e.rule.declarations.iter().map(move |d| {
+                    (
+                        self.precedence(e.rule, e.specificity, e.order, d.important),
+                        d,
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the rank of a layer within its origin. Unlayered declarations win over layered
+    /// ones, unless they are important, which reverses the order of layers.
+    fn layer_rank(&self, origin: Origin, layer: Option<&str>, important: bool) -> usize {
+        let names = self.layers.get(&origin).map_or(&[][..], Vec::as_slice);
+        let rank = match layer {
+            Some(layer) => names.iter().position(|n| *n == layer).unwrap_or(0),
+            None => names.len(),
+        };
+
+        if important {
+            names.len() - rank
+        } else {
+            rank
+        }
+    }
+
+    /// Returns the precedence of a declaration in a rule.
+    /// `order` is the index of the stylesheet, and of the rule within it.
+    pub fn precedence(
+        &self,
+        rule: &Rule,
+        specificity: Specificity,
+        order: (usize, usize),
+        important: bool,
+    ) -> Precedence {
+        let origin = self.stylesheets[order.0].origin;
+
+        Precedence {
+            origin: origin_rank(origin, important),
+            inline: false,
+            layer: self.layer_rank(origin, rule.layer.as_deref(), important),
+            specificity,
+            order,
+        }
+    }
+
+    /// Returns the precedence of a declaration in a style attribute.
+    pub fn inline_precedence(&self, important: bool) -> Precedence {
+        Precedence {
+            origin: origin_rank(Origin::Author, important),
+            inline: true,
+            layer: self.layer_rank(Origin::Author, None, important),
+            specificity: (0, 0, 0),
+            order: (self.stylesheets.len(), 0),
+        }
+    }
+}
+
+/// The number of origins.
+const ORIGINS: usize = 3;
+
+/// Returns the rank of an origin. Important declarations reverse the order of origins, and
+/// win over every normal declaration.
+fn origin_rank(origin: Origin, important: bool) -> usize {
+    if important {
+        2 * ORIGINS - 1 - origin as usize
+    } else {
+        origin as usize
+    }
+}
+
+impl Precedence {
+    /// Returns the index of the declaration's origin, whether or not it is important.
+    fn origin_index(&self) -> usize {
+        self.origin.min(2 * ORIGINS - 1 - self.origin)
+    }
+
+    /// Are the declarations in the same layer of the same origin?
+    fn same_layer(&self, other: &Precedence) -> bool {
+        (self.origin, self.inline, self.layer) == (other.origin, other.inline, other.layer)
+    }
+}
+
+/// Returns the value of each property, from the declaration with the highest precedence.
+pub fn cascade(mut declarations: Vec<(Precedence, &Declaration)>) -> PropertyMap {
+    // Sorting is stable, so later declarations within a rule still win.
+    declarations.sort_by_key(|(precedence, _)| *precedence);
+
+    let mut by_name: HashMap<&str, Vec<(Precedence, &Declaration)>> = HashMap::new();
+    for (precedence, d) in declarations {
+        by_name.entry(&d.name).or_default().push((precedence, d));
+    }
+
+    by_name
+        .into_iter()
+        .filter_map(|(name, candidates)| Some((name.to_string(), cascaded_value(candidates)?)))
+        .collect()
+}
+
+/// Returns the Some value of the winning declaration for a property, or None if every declaration
+/// was reverted. `candidates` are sorted from lowest to highest precedence.
+fn cascaded_value(mut candidates: Vec<(Precedence, &Declaration)>) -> Option<Value> {
+    while let Some((precedence, declaration)) = candidates.pop() {
+        match &declaration.value {
+            // Rolls back to the previous origin, as if this origin had no declarations.
+            Value::Keyword(k) if k.eq_ignore_ascii_case("revert") => {
+                candidates.retain(|(p, _)| p.origin_index() != precedence.origin_index());
+            }
+            // Rolls back to the previous layer.
+            Value::Keyword(k) if k.eq_ignore_ascii_case("revert-layer") => {
+                candidates.retain(|(p, _)| !p.same_layer(&precedence));
+            }
+            value => return Some(value.clone()),
+        }
+    }
+
+    // Reverting past the user agent origin leaves the property unset.
+    None
+}
@@ -0,0 +1,473 @@
+// Colour values and conversions into sRGB.
+// https://www.w3.org/TR/css-color-4/
+
+use crate::style::css::{Colour, Value};
+
+/// The named colours, with their hex values.
+/// https://www.w3.org/TR/css-color-4/#named-colors
+const NAMED_COLOURS: [(&str, u32); 148] = [
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+impl Colour {
+    pub const TRANSPARENT: Colour = Colour {
+        r: 0,
+        g: 0,
+        b: 0,
+        a: 0,
+    };
+
+    pub const BLACK: Colour = Colour {
+        r: 0,
+        g: 0,
+        b: 0,
+        a: 255,
+    };
+
+    /// Creates an opaque colour from a 0xrrggbb number.
+    fn from_u32(rgb: u32) -> Self {
+        Colour {
+            r: (rgb >> 16) as u8,
+            g: (rgb >> 8) as u8,
+            b: rgb as u8,
+            a: 255,
+        }
+    }
+
+    /// Creates a colour from red, green, blue and alpha channels between 0 and 1.
+    /// Channels outside of the sRGB gamut are clipped.
+    pub fn from_unit_rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        let to_u8 = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        Colour {
+            r: to_u8(r),
+            g: to_u8(g),
+            b: to_u8(b),
+            a: to_u8(a),
+        }
+    }
+
+    /// Returns the Some colour with a given name, or None if there isn't one. Eg: rebeccapurple.
+    pub fn named(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        if name == "transparent" {
+            return Some(Colour::TRANSPARENT);
+        }
+
+        NAMED_COLOURS
+            .binary_search_by_key(&name.as_str(), |(n, _)| n)
+            .ok()
+            .map(|i| Colour::from_u32(NAMED_COLOURS[i].1))
+    }
+
+    /// Returns the Some colour of a hex colour's digits (rgb, rgba, rrggbb or rrggbbaa).
+    pub fn from_hex(digits: &str) -> Option<Self> {
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        // Short forms repeat each digit. Eg: #f80 is #ff8800.
+        let expanded: String = match digits.len() {
+            3 | 4 => digits.chars().flat_map(|c| [c, c]).collect(),
+            6 | 8 => digits.to_string(),
+            _ => return None,
+        };
+        let channel = |i: usize| u8::from_str_radix(&expanded[i..i + 2], 16).unwrap();
+
+        Some(Colour {
+            r: channel(0),
+            g: channel(2),
+            b: channel(4),
+            a: if expanded.len() == 8 { channel(6) } else { 255 },
+        })
+    }
+
+    /// Creates a colour from a hue in degrees, and saturation and lightness between 0 and 1.
+    fn from_hsl(h: f32, s: f32, l: f32, a: f32) -> Self {
+        let [r, g, b] = hsl_to_rgb(h, s, l);
+        Colour::from_unit_rgba(r, g, b, a)
+    }
+
+    /// Creates a colour from a hue in degrees, and whiteness and blackness between 0 and 1.
+    fn from_hwb(h: f32, w: f32, b: f32, a: f32) -> Self {
+        if w + b >= 1.0 {
+            let grey = w / (w + b);
+            return Colour::from_unit_rgba(grey, grey, grey, a);
+        }
+
+        let rgb = hsl_to_rgb(h, 1.0, 0.5).map(|c| c * (1.0 - w - b) + w);
+        Colour::from_unit_rgba(rgb[0], rgb[1], rgb[2], a)
+    }
+
+    /// Creates a colour from CIE Lab, (D50 white point).
+    fn from_lab(l: f32, a: f32, b: f32, alpha: f32) -> Self {
+        // Lab to XYZ, https://www.w3.org/TR/css-color-4/#color-conversion-code
+        const KAPPA: f32 = 24389.0 / 27.0;
+        const EPSILON: f32 = 216.0 / 24389.0;
+        const WHITE: [f32; 3] = [0.3457 / 0.3585, 1.0, (1.0 - 0.3457 - 0.3585) / 0.3585];
+
+        let fy = (l + 16.0) / 116.0;
+        let fx = a / 500.0 + fy;
+        let fz = fy - b / 200.0;
+
+        let inverse = |f: f32| {
+            if f.powi(3) > EPSILON {
+                f.powi(3)
+            } else {
+                (116.0 * f - 16.0) / KAPPA
+            }
+        };
+        let y = if l > KAPPA * EPSILON {
+            fy.powi(3)
+        } else {
+            l / KAPPA
+        };
+        let xyz = [inverse(fx) * WHITE[0], y * WHITE[1], inverse(fz) * WHITE[2]];
+
+        // Bradford chromatic adaptation from D50 to D65.
+        let d65 = mat_mul(
+            [
+                [0.9554734, -0.023098456, 0.063259244],
+                [-0.02836971, 1.0099953, 0.021041442],
+                [0.012314015, -0.020507649, 1.3303659],
+            ],
+            xyz,
+        );
+        Colour::from_xyz_d65(d65, alpha)
+    }
+
+    /// Creates a colour from the Oklab colour space.
+    fn from_oklab(l: f32, a: f32, b: f32, alpha: f32) -> Self {
+        // https://bottosson.github.io/posts/oklab/
+        let lms = mat_mul(
+            [
+                [1.0, 0.39633778, 0.21580376],
+                [1.0, -0.10556135, -0.06385417],
+                [1.0, -0.08948418, -1.2914855],
+            ],
+            [l, a, b],
+        )
+        .map(|c| c.powi(3));
+
+        let rgb = mat_mul(
+            [
+                [4.0767417, -3.3077116, 0.23096994],
+                [-1.268438, 2.6097574, -0.3413194],
+                [-0.0041960864, -0.7034186, 1.7076147],
+            ],
+            lms,
+        )
+        .map(gamma_encode);
+        Colour::from_unit_rgba(rgb[0], rgb[1], rgb[2], alpha)
+    }
+
+    /// Creates a colour from CIE XYZ, (D65 white point).
+    fn from_xyz_d65(xyz: [f32; 3], alpha: f32) -> Self {
+        let rgb = mat_mul(
+            [
+                [3.24097, -1.5373832, -0.49861076],
+                [-0.96924365, 1.8759675, 0.04155506],
+                [0.05563008, -0.20397696, 1.0569715],
+            ],
+            xyz,
+        )
+        .map(gamma_encode);
+        Colour::from_unit_rgba(rgb[0], rgb[1], rgb[2], alpha)
+    }
+}
+
+/// Converts hsl, with a hue in degrees, into rgb channels between 0 and 1.
+fn hsl_to_rgb(h: f32, s: f32, l: f32) -> [f32; 3] {
+    let h = h.rem_euclid(360.0);
+    let f = |n: f32| {
+        let k = (n + h / 30.0) % 12.0;
+        let a = s * l.min(1.0 - l);
+        l - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+    };
+    [f(0.0), f(8.0), f(4.0)]
+}
+
+/// Applies the sRGB transfer function to a linear channel.
+fn gamma_encode(c: f32) -> f32 {
+    if c.abs() > 0.0031308 {
+        c.signum() * (1.055 * c.abs().powf(1.0 / 2.4) - 0.055)
+    } else {
+        12.92 * c
+    }
+}
+
+fn mat_mul(m: [[f32; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+    m.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+}
+
+/// Returns a channel of a colour function as a number, where 100% is `percent_scale`.
+/// The `none` keyword is treated as zero.
+fn channel(v: &Value, percent_scale: f32) -> Option<f32> {
+    match v {
+        Value::Number(n) => Some(*n),
+        Value::Percentage(p) => Some(p / 100.0 * percent_scale),
+        Value::Keyword(k) if k.eq_ignore_ascii_case("none") => Some(0.0),
+        _ => None,
+    }
+}
+
+/// Returns a hue in degrees.
+fn hue(v: &Value) -> Option<f32> {
+    match v {
+        Value::Angle(deg) => Some(*deg),
+        v => channel(v, f32::NAN).filter(|h| !h.is_nan()),
+    }
+}
+
+/// Returns the Some colour of a colour function, Eg: rgb(0 128 255 / 50%), or None if invalid.
+/// Both the legacy (comma separated) and modern (space separated) syntaxes are accepted.
+pub fn colour_function(name: &str, args: &[Value]) -> Option<Colour> {
+    let components: Vec<&Value> = match args {
+        [single] => single.components().iter().collect(),
+        _ => legacy_components(name, args)?,
+    };
+
+    let (c, alpha) = match components.as_slice() {
+        [a, b, c] => ([*a, *b, *c], 1.0),
+        [a, b, c, Value::Slash, alpha] => ([*a, *b, *c], channel(alpha, 1.0)?),
+        _ => return None,
+    };
+
+    let colour = match name {
+        "rgb" | "rgba" => Colour::from_unit_rgba(
+            channel(c[0], 255.0)? / 255.0,
+            channel(c[1], 255.0)? / 255.0,
+            channel(c[2], 255.0)? / 255.0,
+            alpha,
+        ),
+        "hsl" | "hsla" => Colour::from_hsl(
+            hue(c[0])?,
+            channel(c[1], 100.0)? / 100.0,
+            channel(c[2], 100.0)? / 100.0,
+            alpha,
+        ),
+        "hwb" => Colour::from_hwb(
+            hue(c[0])?,
+            channel(c[1], 100.0)? / 100.0,
+            channel(c[2], 100.0)? / 100.0,
+            alpha,
+        ),
+        "lab" => Colour::from_lab(
+            channel(c[0], 100.0)?,
+            channel(c[1], 125.0)?,
+            channel(c[2], 125.0)?,
+            alpha,
+        ),
+        "lch" => {
+            let (l, chroma, h) = (channel(c[0], 100.0)?, channel(c[1], 150.0)?, hue(c[2])?);
+            let h = h.to_radians();
+            Colour::from_lab(l, chroma * h.cos(), chroma * h.sin(), alpha)
+        }
+        "oklab" => Colour::from_oklab(
+            channel(c[0], 1.0)?,
+            channel(c[1], 0.4)?,
+            channel(c[2], 0.4)?,
+            alpha,
+        ),
+        "oklch" => {
+            let (l, chroma, h) = (channel(c[0], 1.0)?, channel(c[1], 0.4)?, hue(c[2])?);
+            let h = h.to_radians();
+            Colour::from_oklab(l, chroma * h.cos(), chroma * h.sin(), alpha)
+        }
+        _ => return None,
+    };
+
+    Some(colour)
+}
+
+/// Returns the Some components of a colour function in the legacy syntax, which separates them
+/// and the alpha with commas, or None if it isn't valid. Eg: rgba(0, 128, 255, 0.5)
+/// https://www.w3.org/TR/css-color-4/#typedef-legacy-rgb-syntax
+fn legacy_components<'a>(name: &str, args: &'a [Value]) -> Option<Vec<&'a Value>> {
+    let is = |range: &[Value], f: fn(&Value) -> bool| range.iter().all(f);
+    let number = |v: &Value| matches!(v, Value::Number(_));
+    let percentage = |v: &Value| matches!(v, Value::Percentage(_));
+
+    let (channels, alpha) = match args {
+        [a, b, c] => ([a, b, c], None),
+        [a, b, c, alpha] => ([a, b, c], Some(alpha)),
+        _ => return None,
+    };
+    let valid = match name {
+        // The channels are either all numbers or all percentages.
+        "rgb" | "rgba" => is(&args[..3], number) || is(&args[..3], percentage),
+        "hsl" | "hsla" => {
+            matches!(channels[0], Value::Number(_) | Value::Angle(_)) && is(&args[1..3], percentage)
+        }
+        _ => false,
+    };
+    // There is no `none` in the legacy syntax.
+    if !valid || alpha.is_some_and(|a| !number(a) && !percentage(a)) {
+        return None;
+    }
+
+    let mut components = channels.to_vec();
+    if let Some(alpha) = alpha {
+        components.extend([&Value::Slash, alpha]);
+    }
+    Some(components)
+}
+
+/// Is the function one which produces a colour?
+pub fn is_colour_function(name: &str) -> bool {
+    matches!(
+        name,
+        "rgb" | "rgba" | "hsl" | "hsla" | "hwb" | "lab" | "lch" | "oklab" | "oklch"
+    )
+}
+
+/// Does the property take a colour? Eg: background-color
+pub fn is_colour_property(name: &str) -> bool {
+    name == "color" || name.ends_with("-color")
+}
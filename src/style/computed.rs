@@ -0,0 +1,837 @@
+// Typed computed styles, so layout can read fields rather than looking up properties by name.
+// https://www.w3.org/TR/css-cascade-4/#computed
+
+use crate::box_model::Display;
+use crate::style::css::{Colour, PropertyMap, Value};
+use crate::style::values::{initial_computed_value, CalcExpr, LengthContext};
+use std::sync::Arc;
+
+/// A computed length or percentage, which may be `auto`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Length {
+    /// `auto`, or `none` for maximum sizes.
+    Auto,
+    Px(f32),
+    Percentage(f32),
+    /// A math expression which contains a percentage.
+    Calc(Box<CalcExpr>),
+}
+
+impl Length {
+    fn from_value(value: &Value) -> Self {
+        match value {
+            Value::Length(n, _) => Length::Px(*n),
+            // A unitless zero.
+            Value::Number(n) => Length::Px(*n),
+            Value::Percentage(p) => Length::Percentage(*p),
+            Value::Calc(expr) => Length::Calc(expr.clone()),
+            _ => Length::Auto,
+        }
+    }
+
+    pub fn is_auto(&self) -> bool {
+        *self == Length::Auto
+    }
+
+    /// Returns the Some size in px, with percentages relative to `base`, or None if it is `auto`.
+    pub fn resolve(&self, base: f32) -> Option<f32> {
+        match self {
+            Length::Auto => None,
+            Length::Px(n) => Some(*n),
+            Length::Percentage(p) => Some(base * p / 100.0),
+            Length::Calc(expr) => Some(expr.eval(&LengthContext::for_percentages(base))),
+        }
+    }
+
+    /// Returns the size in px, with percentages relative to `base`, and `auto` as zero.
+    pub fn to_px_of(&self, base: f32) -> f32 {
+        self.resolve(base).unwrap_or(0.0)
+    }
+}
+
+/// A value for each side of a box.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sides<T> {
+    pub top: T,
+    pub right: T,
+    pub bottom: T,
+    pub left: T,
+}
+
+impl<T> Sides<T> {
+    /// Creates the sides from the longhands of a property, given a function to make each value.
+    /// Eg: `border-{}-width` reads border-top-width, border-right-width...
+    fn from_values(values: &PropertyMap, pattern: &str, f: impl Fn(&Value) -> T) -> Self {
+        let side = |s: &str| f(&value(values, &pattern.replace("{}", s)));
+        Sides {
+            top: side("top"),
+            right: side("right"),
+            bottom: side("bottom"),
+            left: side("left"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    Static,
+    Relative,
+    Absolute,
+    Fixed,
+    Sticky,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Float {
+    None,
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Clear {
+    None,
+    Left,
+    Right,
+    Both,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoxSizing {
+    ContentBox,
+    BorderBox,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BorderStyle {
+    None,
+    Hidden,
+    Dotted,
+    Dashed,
+    Solid,
+    Double,
+    Groove,
+    Ridge,
+    Inset,
+    Outset,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    Visible,
+    Hidden,
+    Clip,
+    Scroll,
+    Auto,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    Visible,
+    Hidden,
+    Collapse,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontStyle {
+    Normal,
+    Italic,
+    Oblique,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineHeight {
+    Normal,
+    /// A multiple of the font size.
+    Number(f32),
+    Px(f32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextAlign {
+    Start,
+    End,
+    Left,
+    Right,
+    Center,
+    Justify,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextTransform {
+    None,
+    Capitalize,
+    Uppercase,
+    Lowercase,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WhiteSpace {
+    Normal,
+    Pre,
+    Nowrap,
+    PreWrap,
+    PreLine,
+    BreakSpaces,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerticalAlign {
+    Baseline,
+    Sub,
+    Super,
+    Top,
+    TextTop,
+    Middle,
+    Bottom,
+    TextBottom,
+    /// A distance to raise the box by.
+    Length(Length),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlexDirection {
+    Row,
+    RowReverse,
+    Column,
+    ColumnReverse,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlexWrap {
+    Nowrap,
+    Wrap,
+    WrapReverse,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FlexBasis {
+    /// The item's width or height, or the size of its content if that's `auto`.
+    Auto,
+    /// The size of the item's content.
+    Content,
+    Size(Length),
+}
+
+/// The alignment of boxes in their container, or of the content of a container. The `left` and
+/// `right` keywords are the start and end, as text is left to right.
+/// https://www.w3.org/TR/css-align-3/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alignment {
+    /// The alignment of the box's parent, for `align-self` and `justify-self`.
+    Auto,
+    Normal,
+    Stretch,
+    Start,
+    End,
+    Center,
+    Baseline,
+    SpaceBetween,
+    SpaceAround,
+    SpaceEvenly,
+}
+
+/// The sizes a grid track is kept between, which are the same unless it's `minmax()`.
+/// https://www.w3.org/TR/css-grid-1/#track-sizing
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackSize {
+    pub min: TrackBreadth,
+    pub max: TrackBreadth,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrackBreadth {
+    Length(Length),
+    /// A share of the free space, in fr. It's only ever a maximum.
+    Flex(f32),
+    MinContent,
+    MaxContent,
+    /// The size of the content, but no bigger than a length. It's only ever a maximum.
+    /// Eg: fit-content(100px)
+    FitContent(Length),
+    Auto,
+}
+
+/// The tracks of a grid, and the names of the lines between them.
+/// Eg: [full-start] 100px repeat(auto-fill, 50px) [full-end]
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TrackList {
+    /// The tracks, with each `repeat()` of a number of tracks repeated that many times.
+    pub tracks: Vec<TrackSize>,
+    /// The names of each line, of which there's one more than there are tracks.
+    pub line_names: Vec<Vec<String>>,
+    /// The Some `repeat()` which is repeated as many times as fits, or None if there isn't one.
+    pub auto_repeat: Option<AutoRepeat>,
+}
+
+/// A `repeat()` with `auto-fill` or `auto-fit`, whose tracks are repeated as many times as fit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AutoRepeat {
+    /// The index of the line the tracks are repeated from, in [TrackList::tracks].
+    pub line: usize,
+    /// Do the repeated tracks without items in them collapse, as they do for `auto-fit`?
+    pub fit: bool,
+    /// The tracks which are repeated, and the names of the lines around them.
+    pub tracks: Vec<TrackSize>,
+    pub line_names: Vec<Vec<String>>,
+}
+
+/// A line a grid item starts or ends at.
+/// https://www.w3.org/TR/css-grid-1/#line-placement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GridLine {
+    Auto,
+    /// The nth line from the start, or from the end if it's negative, which only counts the lines
+    /// with the Some name if there is one. Eg: 2, -1, 2 main
+    Line(i32, Option<String>),
+    /// A name, which is the start or end of the area with that name if there is one, and
+    /// otherwise the first line with it.
+    Named(String),
+    /// A span of n lines, which only counts the lines with the Some name if there is one, from
+    /// the other line the item is between. Eg: span 2
+    Span(usize, Option<String>),
+}
+
+/// How grid items which aren't placed are placed automatically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GridAutoFlow {
+    /// Are items placed in columns, rather than in rows?
+    pub column: bool,
+    /// Are items placed in the first space which fits them, even if it's before the items placed
+    /// before them?
+    pub dense: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListStylePosition {
+    Inside,
+    Outside,
+}
+
+/// The computed values of an element, with the properties that layout uses as typed fields.
+/// Nodes with identical styles share one [ComputedStyle].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComputedStyle {
+    pub display: Display,
+    pub position: Position,
+    pub float: Float,
+    pub clear: Clear,
+    pub box_sizing: BoxSizing,
+    pub width: Length,
+    pub height: Length,
+    pub min_width: Length,
+    pub min_height: Length,
+    pub max_width: Length,
+    pub max_height: Length,
+    pub margin: Sides<Length>,
+    pub padding: Sides<Length>,
+    pub border_width: Sides<f32>,
+    pub border_style: Sides<BorderStyle>,
+    pub border_colour: Sides<Colour>,
+    /// The top, right, bottom and left properties of positioned boxes.
+    pub inset: Sides<Length>,
+    /// The Some stacking level, or None if it is `auto`.
+    pub z_index: Option<i32>,
+    pub overflow_x: Overflow,
+    pub overflow_y: Overflow,
+    pub visibility: Visibility,
+    pub colour: Colour,
+    pub background_colour: Colour,
+    pub opacity: f32,
+    pub font_family: Vec<String>,
+    pub font_size: f32,
+    /// A weight from 1 to 1000, where normal is 400 and bold is 700.
+    pub font_weight: f32,
+    pub font_style: FontStyle,
+    pub line_height: LineHeight,
+    pub text_align: TextAlign,
+    pub text_indent: Length,
+    pub text_transform: TextTransform,
+    pub white_space: WhiteSpace,
+    pub vertical_align: VerticalAlign,
+    pub list_style_type: String,
+    pub list_style_position: ListStylePosition,
+    pub flex_direction: FlexDirection,
+    pub flex_wrap: FlexWrap,
+    pub flex_grow: f32,
+    pub flex_shrink: f32,
+    pub flex_basis: FlexBasis,
+    pub order: i32,
+    pub justify_content: Alignment,
+    pub align_content: Alignment,
+    pub align_items: Alignment,
+    pub align_self: Alignment,
+    pub justify_items: Alignment,
+    pub justify_self: Alignment,
+    /// The gaps between rows and columns, where `normal` is `auto`.
+    pub row_gap: Length,
+    pub column_gap: Length,
+    pub grid_template_rows: TrackList,
+    pub grid_template_columns: TrackList,
+    /// The names of the cells of each row of the grid, which are None for `.` cells.
+    pub grid_template_areas: Vec<Vec<Option<String>>>,
+    /// The sizes of the implicit tracks, which are repeated as many times as they're needed.
+    pub grid_auto_rows: Vec<TrackSize>,
+    pub grid_auto_columns: Vec<TrackSize>,
+    pub grid_auto_flow: GridAutoFlow,
+    pub grid_row_start: GridLine,
+    pub grid_row_end: GridLine,
+    pub grid_column_start: GridLine,
+    pub grid_column_end: GridLine,
+    /// Every computed value, including the properties without a typed field.
+    pub values: PropertyMap,
+}
+
+/// Returns the Some computed value of a property, which is its initial value if it isn't in
+/// `values`, or None if the property isn't registered.
+fn computed_value(values: &PropertyMap, name: &str) -> Option<Value> {
+    values
+        .get(name)
+        .cloned()
+        .or_else(|| initial_computed_value(name))
+}
+
+/// Returns the computed value of a property which is known to be registered.
+fn value(values: &PropertyMap, name: &str) -> Value {
+    computed_value(values, name).unwrap_or_else(|| panic!("{name} isn't a registered property"))
+}
+
+/// Returns the keyword of a value, or an empty string if it isn't a keyword.
+fn keyword(value: &Value) -> &str {
+    match value {
+        Value::Keyword(k) => k,
+        _ => "",
+    }
+}
+
+fn colour(value: &Value) -> Colour {
+    value.to_colour(None).unwrap_or(Colour::TRANSPARENT)
+}
+
+fn number(value: &Value) -> f32 {
+    match value {
+        Value::Number(n) => *n,
+        Value::Percentage(p) => p / 100.0,
+        _ => 0.0,
+    }
+}
+
+fn display(value: &Value) -> Display {
+    // The outer display type decides how a box takes part in layout. Eg: block flow
+    match value.components().first().map(keyword) {
+        Some("block") => Display::Block,
+        Some("inline-block") => Display::InlineBlock,
+        Some("flow-root") => Display::FlowRoot,
+        Some("flex") => Display::Flex,
+        Some("inline-flex") => Display::InlineFlex,
+        Some("grid") => Display::Grid,
+        Some("inline-grid") => Display::InlineGrid,
+        Some("list-item") => Display::ListItem,
+        // TODO: Until there are tables, their rows are blocks, with their cells beside each other
+        // as inline blocks.
+        Some(
+            "table" | "table-caption" | "table-header-group" | "table-row-group"
+            | "table-footer-group" | "table-row",
+        ) => Display::Block,
+        Some("inline-table" | "table-cell") => Display::InlineBlock,
+        Some("table-column-group" | "table-column") => Display::None,
+        Some("none") => Display::None,
+        _ => Display::Inline,
+    }
+}
+
+fn border_style(value: &Value) -> BorderStyle {
+    match keyword(value) {
+        "hidden" => BorderStyle::Hidden,
+        "dotted" => BorderStyle::Dotted,
+        "dashed" => BorderStyle::Dashed,
+        "solid" => BorderStyle::Solid,
+        "double" => BorderStyle::Double,
+        "groove" => BorderStyle::Groove,
+        "ridge" => BorderStyle::Ridge,
+        "inset" => BorderStyle::Inset,
+        "outset" => BorderStyle::Outset,
+        _ => BorderStyle::None,
+    }
+}
+
+fn overflow(value: &Value) -> Overflow {
+    match keyword(value) {
+        "hidden" => Overflow::Hidden,
+        "clip" => Overflow::Clip,
+        "scroll" => Overflow::Scroll,
+        "auto" => Overflow::Auto,
+        _ => Overflow::Visible,
+    }
+}
+
+fn alignment(value: &Value) -> Alignment {
+    // Overflow alignment (`safe` and `unsafe`) comes before the alignment, and `first` and `last`
+    // before `baseline`.
+    match value.components().last().map(keyword).unwrap_or("") {
+        "auto" => Alignment::Auto,
+        "stretch" => Alignment::Stretch,
+        "start" | "flex-start" | "self-start" | "left" => Alignment::Start,
+        "end" | "flex-end" | "self-end" | "right" => Alignment::End,
+        "center" => Alignment::Center,
+        "baseline" => Alignment::Baseline,
+        "space-between" => Alignment::SpaceBetween,
+        "space-around" => Alignment::SpaceAround,
+        "space-evenly" => Alignment::SpaceEvenly,
+        _ => Alignment::Normal,
+    }
+}
+
+/// Returns the Some size of a grid track, or None if the value isn't one.
+fn track_size(value: &Value) -> Option<TrackSize> {
+    match value {
+        Value::Function(name, args) if name == "minmax" => match args.as_slice() {
+            [min, max] => Some(TrackSize {
+                min: track_breadth(min).filter(|b| {
+                    !matches!(b, TrackBreadth::Flex(_) | TrackBreadth::FitContent(_))
+                })?,
+                max: track_breadth(max)?,
+            }),
+            _ => None,
+        },
+        // A flexible size has an `auto` minimum, and `fit-content()` only limits the maximum.
+        v => track_breadth(v).map(|max| TrackSize {
+            min: match max {
+                TrackBreadth::Flex(_) | TrackBreadth::FitContent(_) => TrackBreadth::Auto,
+                _ => max.clone(),
+            },
+            max,
+        }),
+    }
+}
+
+fn track_breadth(value: &Value) -> Option<TrackBreadth> {
+    Some(match value {
+        Value::Flex(fr) => TrackBreadth::Flex(*fr),
+        Value::Keyword(k) => match k.as_str() {
+            "min-content" => TrackBreadth::MinContent,
+            "max-content" => TrackBreadth::MaxContent,
+            "auto" => TrackBreadth::Auto,
+            _ => return None,
+        },
+        Value::Function(name, args) if name == "fit-content" => match args.as_slice() {
+            [limit] => TrackBreadth::FitContent(Length::from_value(limit)),
+            _ => return None,
+        },
+        Value::Length(..) | Value::Number(_) | Value::Percentage(_) | Value::Calc(_) => {
+            TrackBreadth::Length(Length::from_value(value))
+        }
+        _ => return None,
+    })
+}
+
+/// Adds the tracks and line names of a track list to `tracks`, and returns the Some `repeat()`
+/// with `auto-fill` or `auto-fit` in it, or None if there isn't one.
+fn add_tracks(values: &[Value], tracks: &mut TrackList) -> Option<AutoRepeat> {
+    let mut auto_repeat = None;
+    for v in values {
+        match v {
+            Value::LineNames(names) => {
+                let last = tracks
+                    .line_names
+                    .last_mut()
+                    .expect("A line before the tracks");
+                last.extend(names.iter().cloned());
+            }
+            Value::Function(name, args) if name == "repeat" && args.len() == 2 => {
+                let repeated = args[1].components();
+                match &args[0] {
+                    Value::Number(n) => {
+                        for _ in 0..(*n as usize).max(1) {
+                            add_tracks(repeated, tracks);
+                        }
+                    }
+                    Value::Keyword(k) if k == "auto-fill" || k == "auto-fit" => {
+                        let mut inner = TrackList {
+                            line_names: vec![Vec::new()],
+                            ..Default::default()
+                        };
+                        add_tracks(repeated, &mut inner);
+                        auto_repeat = Some(AutoRepeat {
+                            line: tracks.tracks.len(),
+                            fit: k == "auto-fit",
+                            tracks: inner.tracks,
+                            line_names: inner.line_names,
+                        });
+                    }
+                    _ => {}
+                }
+            }
+            v => {
+                if let Some(size) = track_size(v) {
+                    tracks.tracks.push(size);
+                    tracks.line_names.push(Vec::new());
+                }
+            }
+        }
+    }
+    auto_repeat
+}
+
+/// Returns the tracks of `grid-template-rows` or `grid-template-columns`. There are none for
+/// `none`.
+fn track_list(value: &Value) -> TrackList {
+    let mut tracks = TrackList {
+        line_names: vec![Vec::new()],
+        ..Default::default()
+    };
+    tracks.auto_repeat = add_tracks(value.components(), &mut tracks);
+    tracks
+}
+
+fn grid_line(value: &Value) -> GridLine {
+    let (mut span, mut n, mut name) = (false, None, None);
+    for c in value.components() {
+        match c {
+            Value::Keyword(k) if k == "span" => span = true,
+            Value::Keyword(k) if k == "auto" => {}
+            Value::Keyword(k) => name = Some(k.clone()),
+            Value::Number(x) => n = Some(*x as i32),
+            _ => {}
+        }
+    }
+    match (span, n, name) {
+        (true, n, name) => GridLine::Span(n.unwrap_or(1).max(1) as usize, name),
+        (false, Some(n), name) if n != 0 => GridLine::Line(n, name),
+        (false, None, Some(name)) => GridLine::Named(name),
+        _ => GridLine::Auto,
+    }
+}
+
+fn vertical_align(value: &Value) -> VerticalAlign {
+    match keyword(value) {
+        "sub" => VerticalAlign::Sub,
+        "super" => VerticalAlign::Super,
+        "top" => VerticalAlign::Top,
+        "text-top" => VerticalAlign::TextTop,
+        "middle" => VerticalAlign::Middle,
+        "bottom" => VerticalAlign::Bottom,
+        "text-bottom" => VerticalAlign::TextBottom,
+        "baseline" => VerticalAlign::Baseline,
+        _ => VerticalAlign::Length(Length::from_value(value)),
+    }
+}
+
+impl ComputedStyle {
+    /// Creates the typed style from a map of computed values.
+    pub fn new(values: PropertyMap) -> Self {
+        let get = |name: &str| value(&values, name);
+        let length = |name: &str| Length::from_value(&get(name));
+
+        ComputedStyle {
+            display: display(&get("display")),
+            position: match keyword(&get("position")) {
+                "relative" => Position::Relative,
+                "absolute" => Position::Absolute,
+                "fixed" => Position::Fixed,
+                "sticky" => Position::Sticky,
+                _ => Position::Static,
+            },
+            float: match keyword(&get("float")) {
+                "left" | "inline-start" => Float::Left,
+                "right" | "inline-end" => Float::Right,
+                _ => Float::None,
+            },
+            clear: match keyword(&get("clear")) {
+                "left" | "inline-start" => Clear::Left,
+                "right" | "inline-end" => Clear::Right,
+                "both" => Clear::Both,
+                _ => Clear::None,
+            },
+            box_sizing: match keyword(&get("box-sizing")) {
+                "border-box" => BoxSizing::BorderBox,
+                _ => BoxSizing::ContentBox,
+            },
+            width: length("width"),
+            height: length("height"),
+            min_width: length("min-width"),
+            min_height: length("min-height"),
+            max_width: length("max-width"),
+            max_height: length("max-height"),
+            margin: Sides::from_values(&values, "margin-{}", Length::from_value),
+            padding: Sides::from_values(&values, "padding-{}", Length::from_value),
+            border_width: Sides::from_values(&values, "border-{}-width", Value::to_px),
+            border_style: Sides::from_values(&values, "border-{}-style", border_style),
+            border_colour: Sides::from_values(&values, "border-{}-color", colour),
+            inset: Sides::from_values(&values, "{}", Length::from_value),
+            z_index: match get("z-index") {
+                Value::Number(n) => Some(n as i32),
+                _ => None,
+            },
+            overflow_x: overflow(&get("overflow-x")),
+            overflow_y: overflow(&get("overflow-y")),
+            visibility: match keyword(&get("visibility")) {
+                "hidden" => Visibility::Hidden,
+                "collapse" => Visibility::Collapse,
+                _ => Visibility::Visible,
+            },
+            colour: colour(&get("color")),
+            background_colour: colour(&get("background-color")),
+            opacity: number(&get("opacity")).clamp(0.0, 1.0),
+            font_family: match get("font-family") {
+                Value::CommaList(families) => families.iter().map(family_name).collect(),
+                family => vec![family_name(&family)],
+            },
+            font_size: get("font-size").to_px(),
+            font_weight: number(&get("font-weight")),
+            font_style: match get("font-style").components().first().map(keyword) {
+                Some("italic") => FontStyle::Italic,
+                Some("oblique") => FontStyle::Oblique,
+                _ => FontStyle::Normal,
+            },
+            line_height: match get("line-height") {
+                Value::Number(n) => LineHeight::Number(n),
+                Value::Length(n, _) => LineHeight::Px(n),
+                _ => LineHeight::Normal,
+            },
+            text_align: match keyword(&get("text-align")) {
+                "end" => TextAlign::End,
+                "left" => TextAlign::Left,
+                "right" => TextAlign::Right,
+                "center" => TextAlign::Center,
+                "justify" => TextAlign::Justify,
+                _ => TextAlign::Start,
+            },
+            text_indent: length("text-indent"),
+            text_transform: match keyword(&get("text-transform")) {
+                "capitalize" => TextTransform::Capitalize,
+                "uppercase" => TextTransform::Uppercase,
+                "lowercase" => TextTransform::Lowercase,
+                _ => TextTransform::None,
+            },
+            white_space: match keyword(&get("white-space")) {
+                "pre" => WhiteSpace::Pre,
+                "nowrap" => WhiteSpace::Nowrap,
+                "pre-wrap" => WhiteSpace::PreWrap,
+                "pre-line" => WhiteSpace::PreLine,
+                "break-spaces" => WhiteSpace::BreakSpaces,
+                _ => WhiteSpace::Normal,
+            },
+            vertical_align: vertical_align(&get("vertical-align")),
+            list_style_type: match get("list-style-type") {
+                Value::Str(s) => s,
+                v => keyword(&v).to_string(),
+            },
+            list_style_position: match keyword(&get("list-style-position")) {
+                "inside" => ListStylePosition::Inside,
+                _ => ListStylePosition::Outside,
+            },
+            flex_direction: match keyword(&get("flex-direction")) {
+                "row-reverse" => FlexDirection::RowReverse,
+                "column" => FlexDirection::Column,
+                "column-reverse" => FlexDirection::ColumnReverse,
+                _ => FlexDirection::Row,
+            },
+            flex_wrap: match keyword(&get("flex-wrap")) {
+                "wrap" => FlexWrap::Wrap,
+                "wrap-reverse" => FlexWrap::WrapReverse,
+                _ => FlexWrap::Nowrap,
+            },
+            flex_grow: number(&get("flex-grow")),
+            flex_shrink: number(&get("flex-shrink")),
+            flex_basis: match get("flex-basis") {
+                Value::Keyword(k) if k == "content" => FlexBasis::Content,
+                Value::Keyword(_) => FlexBasis::Auto,
+                v => FlexBasis::Size(Length::from_value(&v)),
+            },
+            order: match get("order") {
+                Value::Number(n) => n as i32,
+                _ => 0,
+            },
+            justify_content: alignment(&get("justify-content")),
+            align_content: alignment(&get("align-content")),
+            align_items: alignment(&get("align-items")),
+            align_self: alignment(&get("align-self")),
+            justify_items: alignment(&get("justify-items")),
+            justify_self: alignment(&get("justify-self")),
+            row_gap: length("row-gap"),
+            column_gap: length("column-gap"),
+            grid_template_rows: track_list(&get("grid-template-rows")),
+            grid_template_columns: track_list(&get("grid-template-columns")),
+            grid_template_areas: get("grid-template-areas")
+                .components()
+                .iter()
+                .filter_map(|row| match row {
+                    // A cell of only dots has no name.
+                    Value::Str(row) => Some(
+                        row.split_ascii_whitespace()
+                            .map(|cell| Some(cell.to_string()).filter(|c| !c.starts_with('.')))
+                            .collect(),
+                    ),
+                    _ => None,
+                })
+                .collect(),
+            grid_auto_rows: get("grid-auto-rows")
+                .components()
+                .iter()
+                .filter_map(track_size)
+                .collect(),
+            grid_auto_columns: get("grid-auto-columns")
+                .components()
+                .iter()
+                .filter_map(track_size)
+                .collect(),
+            grid_auto_flow: {
+                let flow = get("grid-auto-flow");
+                let has = |k: &str| flow.components().iter().any(|c| keyword(c) == k);
+                GridAutoFlow {
+                    column: has("column"),
+                    dense: has("dense"),
+                }
+            },
+            grid_row_start: grid_line(&get("grid-row-start")),
+            grid_row_end: grid_line(&get("grid-row-end")),
+            grid_column_start: grid_line(&get("grid-column-start")),
+            grid_column_end: grid_line(&get("grid-column-end")),
+            values,
+        }
+    }
+
+    /// Returns the Some computed value of a property, or None if the property is unknown.
+    /// Properties which aren't specified or inherited have their initial value.
+    pub fn value(&self, name: &str) -> Option<Value> {
+        computed_value(&self.values, name)
+    }
+}
+
+/// Returns the name of a font family. Names can be quoted, or a list of identifiers.
+fn family_name(value: &Value) -> String {
+    match value {
+        Value::Str(s) => s.clone(),
+        Value::List(words) => words.iter().map(keyword).collect::<Vec<_>>().join(" "),
+        v => keyword(v).to_string(),
+    }
+}
+
+/// Recently computed styles, so nodes with identical computed values share one [ComputedStyle].
+#[derive(Default)]
+pub struct StyleCache {
+    recent: Vec<Arc<ComputedStyle>>,
+}
+
+impl StyleCache {
+    /// The number of styles to remember.
+    const CAPACITY: usize = 32;
+
+    /// Returns a shared style for a map of computed values.
+    pub fn share(&mut self, values: PropertyMap) -> Arc<ComputedStyle> {
+        if let Some(i) = self.recent.iter().position(|s| s.values == values) {
+            // Keep the most recently used styles at the end.
+            let style = self.recent.remove(i);
+            self.recent.push(style.clone());
+            return style;
+        }
+
+        if self.recent.len() == Self::CAPACITY {
+            self.recent.remove(0);
+        }
+        let style = Arc::new(ComputedStyle::new(values));
+        self.recent.push(style.clone());
+        style
+    }
+}
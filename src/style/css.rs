@@ -1,10 +1,113 @@
 use crate::box_model::Display;
-use crate::dom::Node;
+use crate::dom::{Node, NodeType};
+use crate::parsing::css_parser::CssParser;
+use crate::style::computed::ComputedStyle;
+use crate::style::media::MediaQueryList;
+use crate::style::properties::is_valid;
+use crate::style::serialize::declarations_text;
+use crate::style::shorthand::{expand_shorthand, longhands};
+use crate::style::values::{CalcExpr, LengthContext, Viewport};
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
 
 /// Represents a set of styling rules. (Aka, an entires stylesheet or css file).
 pub struct Stylesheet {
     pub rules: Vec<Rule>,
+    pub origin: Origin,
+    /// The names of the stylesheet's cascade layers, in the order they are first declared.
+    pub layers: Vec<String>,
+    /// The stylesheets it imports which haven't been loaded yet.
+    pub imports: Vec<Import>,
+    pub font_faces: Vec<FontFace>,
+    pub pages: Vec<PageRule>,
+    pub keyframes: Vec<Keyframes>,
+}
+
+impl Stylesheet {
+    /// Creates a stylesheet without any rules.
+    pub fn new(origin: Origin) -> Self {
+        Self {
+            rules: Vec::new(),
+            origin,
+            layers: Vec::new(),
+            imports: Vec::new(),
+            font_faces: Vec::new(),
+            pages: Vec::new(),
+            keyframes: Vec::new(),
+        }
+    }
+
+    /// Parses a rule from css, and inserts it before the rule at `index`, returning `index`. A
+    /// rule in an @media or @layer rule is inserted with its media and layer.
+    /// https://www.w3.org/TR/cssom-1/#insert-a-css-rule
+    /// Eg: sheet.insert_rule(0, "@media print { nav { display: none } }")
+    pub fn insert_rule(&mut self, index: usize, css: &str) -> Result<usize, RuleError> {
+        if index > self.rules.len() {
+            return Err(RuleError::IndexSize);
+        }
+        let parsed =
+            CssParser::parse_rule_with_origin(css, self.origin).ok_or(RuleError::Syntax)?;
+        // Imports have to come before any other rules.
+        if !parsed.imports.is_empty() && index > 0 {
+            return Err(RuleError::HierarchyRequest);
+        }
+
+        for layer in parsed.layers {
+            if !self.layers.contains(&layer) {
+                self.layers.push(layer);
+            }
+        }
+        self.rules.splice(index..index, parsed.rules);
+        self.imports.extend(parsed.imports);
+        self.font_faces.extend(parsed.font_faces);
+        self.pages.extend(parsed.pages);
+        self.keyframes.extend(parsed.keyframes);
+        Ok(index)
+    }
+
+    /// Removes the rule at `index`, returning it.
+    pub fn delete_rule(&mut self, index: usize) -> Result<Rule, RuleError> {
+        if index >= self.rules.len() {
+            return Err(RuleError::IndexSize);
+        }
+        Ok(self.rules.remove(index))
+    }
+}
+
+/// Why a rule couldn't be inserted into or deleted from a stylesheet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleError {
+    /// There is no rule at the index.
+    IndexSize,
+    /// The css isn't exactly one valid rule.
+    Syntax,
+    /// An @import rule would come after other rules.
+    HierarchyRequest,
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::IndexSize => "the rule index is out of range",
+            Self::Syntax => "the css isn't a single valid rule",
+            Self::HierarchyRequest => "an @import rule can't come after other rules",
+        })
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+/// Where a stylesheet comes from. Normal declarations from later origins win over earlier ones,
+/// and important declarations reverse the order.
+/// https://www.w3.org/TR/css-cascade-4/#cascading-origins
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Origin {
+    /// The browser's default styles.
+    UserAgent,
+    User,
+    /// The document's own styles.
+    Author,
 }
 
 /// A rule is a set of declarations(characteristics) with a specifier that
@@ -12,23 +115,184 @@ pub struct Stylesheet {
 pub struct Rule {
     pub selectors: Vec<Selector>,
     pub declarations: Vec<Declaration>,
+    /// The Some cascade layer the rule is in, or None if it is unlayered. Eg: base.reset
+    pub layer: Option<String>,
+    /// The media queries of the @media rules it is in, which must all match for it to apply.
+    pub media: Vec<MediaQueryList>,
 }
 
-/// A rule stored with it s assosiated specificity.
-pub struct MatchedRule<'a> {
-    pub specificity: Specificity,
-    pub rule: &'a Rule,
-}
+impl Rule {
+    /// Do the media queries of every @media rule the rule is in match the viewport?
+    pub fn matches_media(&self, viewport: &Viewport) -> bool {
+        self.media.iter().all(|m| m.matches(viewport))
+    }
+
+    /// Returns the text of the rule's declarations. Eg: color: red; margin-top: 0;
+    pub fn css_text(&self) -> String {
+        declarations_text(&self.declarations)
+    }
+
+    /// Returns the Some value of a longhand property, or None if the rule doesn't declare it.
+    pub fn property_value(&self, name: &str) -> Option<&Value> {
+        self.declarations
+            .iter()
+            .rfind(|d| d.name == name)
+            .map(|d| &d.value)
+    }
+
+    /// Sets a property from css text, replacing any declarations of it. Shorthands set each of
+    /// their longhands. Returns whether the value was valid, since invalid values are ignored.
+    /// Eg: rule.set_property("margin", "1em auto", false)
+    pub fn set_property(&mut self, name: &str, value: &str, important: bool) -> bool {
+        let Some(value) = CssParser::parse_property_value(name, value) else {
+            return false;
+        };
+        let declarations = match expand_shorthand(name, &value) {
+            Some(longhands) => longhands
+                .into_iter()
+                .map(|d| Declaration { important, ..d })
+                .collect(),
+            None => vec![Declaration {
+                name: name.to_string(),
+                value,
+                important,
+            }],
+        };
+        // A shorthand is only set if every one of its longhands is valid.
+        if declarations.is_empty() || !declarations.iter().all(is_valid) {
+            return false;
+        }
+
+        for declaration in declarations {
+            // The first declaration of the property is replaced, so it keeps its place.
+            match self
+                .declarations
+                .iter()
+                .position(|d| d.name == declaration.name)
+            {
+                Some(i) => {
+                    // Later declarations of the property would win over the new one.
+                    let later = self.declarations.split_off(i + 1);
+                    let later = later.into_iter().filter(|d| d.name != declaration.name);
+                    self.declarations.extend(later.collect::<Vec<_>>());
+                    self.declarations[i] = declaration;
+                }
+                None => self.declarations.push(declaration),
+            }
+        }
+        true
+    }
 
-impl<'a> MatchedRule<'a> {
-    pub fn new(specificity: Specificity, rule: &'a Rule) -> Self {
-        Self { specificity, rule }
+    /// Removes the declarations of a property, or of every longhand of a shorthand.
+    /// Returns whether any were removed.
+    pub fn remove_property(&mut self, name: &str) -> bool {
+        let names = longhands(name).unwrap_or_else(|| vec![name.to_string()]);
+        let before = self.declarations.len();
+        self.declarations.retain(|d| !names.contains(&d.name));
+        self.declarations.len() != before
     }
 }
 
+/// An @import rule, for a stylesheet which is loaded once the importing one is parsed.
+/// Eg: @import url(theme.css) layer(theme) screen;
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import {
+    pub url: String,
+    /// The Some layer the imported rules are put in, or None to leave them unlayered.
+    pub layer: Option<String>,
+    /// The Some media queries which must match for the imported rules to apply.
+    pub media: Option<MediaQueryList>,
+}
+
+/// An @font-face rule, describing a font which can be loaded.
+/// Eg: @font-face { font-family: Lato; src: local(Lato), url(lato.ttf) format("truetype") }
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontFace {
+    /// The name the font is used by in `font-family`.
+    pub family: String,
+    /// Where the font can be loaded from, in order of preference.
+    pub sources: Vec<FontSource>,
+    /// The other descriptors, as they were written. Eg: font-weight: 100 900
+    pub descriptors: PropertyMap,
+    /// The Some cascade layer the rule is in, or None if it is unlayered. Eg: base.reset
+    pub layer: Option<String>,
+    /// The media queries of the @media rules it is in, which must all match for it to apply.
+    pub media: Vec<MediaQueryList>,
+}
+
+/// A place a font can be loaded from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FontSource {
+    /// A font file, with the Some format it is in. Eg: url(lato.woff2) format("woff2")
+    Url(String, Option<String>),
+    /// A font installed on the device, by its full name. Eg: local("Lato Bold")
+    Local(String),
+}
+
+/// An @page rule, styling the pages of a printed document. Eg: @page :first { margin: 1in }
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageRule {
+    /// The page selectors, or empty to match every page. Eg: :first, toc:left
+    pub selectors: Vec<String>,
+    /// The descriptors, as they were written. Eg: size: A4
+    pub declarations: Vec<Declaration>,
+    /// The rules for the boxes in the page margin. Eg: @top-center { content: "Title" }
+    pub margin_rules: Vec<(String, Vec<Declaration>)>,
+    /// The Some cascade layer the rule is in, or None if it is unlayered. Eg: base.reset
+    pub layer: Option<String>,
+    /// The media queries of the @media rules it is in, which must all match for it to apply.
+    pub media: Vec<MediaQueryList>,
+}
+
+/// An @keyframes rule, giving the styles at points through an animation.
+/// Eg: @keyframes fade { from { opacity: 0 } to { opacity: 1 } }
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframes {
+    pub name: String,
+    pub frames: Vec<Keyframe>,
+    /// The Some cascade layer the rule is in, or None if it is unlayered. Eg: base.reset
+    pub layer: Option<String>,
+    /// The media queries of the @media rules it is in, which must all match for it to apply.
+    pub media: Vec<MediaQueryList>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframe {
+    /// The points through the animation the styles apply at, from 0 to 1.
+    pub offsets: Vec<f32>,
+    pub declarations: Vec<Declaration>,
+}
+
 /// Different types of selectors for a css rule. TODO: More detail
 pub enum Selector {
     Simple(SimpleSelector),
+    /// Simple selectors joined by combinators. Eg: ul > li a
+    /// Each simple selector is paired with the combinator that follows it, and the last simple
+    /// selector is the subject, which the element itself has to match.
+    Complex(Vec<(SimpleSelector, Combinator)>, SimpleSelector),
+}
+
+/// How the simple selectors of a complex selector relate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Combinator {
+    /// Eg: ul li
+    Descendant,
+    /// Eg: ul > li
+    Child,
+    /// Eg: h1 + p
+    NextSibling,
+    /// Eg: h1 ~ p
+    SubsequentSibling,
+}
+
+impl Combinator {
+    /// Does the combinator relate an element to one of its siblings, rather than an ancestor?
+    pub fn is_sibling(self) -> bool {
+        matches!(
+            self,
+            Combinator::NextSibling | Combinator::SubsequentSibling
+        )
+    }
 }
 
 /// A simple selector for a rule.
@@ -36,13 +300,136 @@ pub struct SimpleSelector {
     pub tag_name: Option<String>,
     pub id: Option<String>,
     pub class: Vec<String>,
+    pub attributes: Vec<AttributeSelector>,
+    pub pseudo_element: Option<PseudoElement>,
+}
+
+/// Selects elements by an attribute. Eg: [hidden], [type="checkbox" i]
+pub struct AttributeSelector {
+    pub name: String,
+    /// The Some way the attribute's value must match, or None if it only has to be present.
+    pub matcher: Option<(AttributeOperator, String)>,
+    /// Should the value be matched ignoring ASCII case?
+    pub case_insensitive: bool,
+}
+
+/// How an attribute selector compares an attribute's value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeOperator {
+    /// The value is exactly equal. Eg: [lang=en]
+    Equals,
+    /// One of the whitespace separated words is equal. Eg: [class~=salad]
+    Includes,
+    /// The value is equal, or starts with it followed by a hyphen. Eg: [lang|=en] matches en-GB
+    DashMatch,
+    /// Eg: [href^="https"]
+    Prefix,
+    /// Eg: [href$=".pdf"]
+    Suffix,
+    /// Eg: [title*=salad]
+    Substring,
+}
+
+impl AttributeOperator {
+    /// Returns the Some operator written as `s`, or None if it isn't an operator. Eg: ~=
+    pub fn from_symbol(s: &str) -> Option<Self> {
+        match s {
+            "=" => Some(Self::Equals),
+            "~=" => Some(Self::Includes),
+            "|=" => Some(Self::DashMatch),
+            "^=" => Some(Self::Prefix),
+            "$=" => Some(Self::Suffix),
+            "*=" => Some(Self::Substring),
+            _ => None,
+        }
+    }
+}
+
+impl AttributeSelector {
+    /// Does an attribute's value, (or None if the element doesn't have it), match the selector?
+    pub fn matches(&self, value: Option<&String>) -> bool {
+        let Some(value) = value else {
+            return false;
+        };
+        let Some((operator, expected)) = &self.matcher else {
+            return true;
+        };
+
+        let (value, expected) = if self.case_insensitive {
+            (value.to_ascii_lowercase(), expected.to_ascii_lowercase())
+        } else {
+            (value.clone(), expected.clone())
+        };
+
+        // Selectors which match a part of the value never match an empty string.
+        match operator {
+            AttributeOperator::Equals => value == expected,
+            AttributeOperator::Includes => value.split_whitespace().any(|w| w == expected),
+            AttributeOperator::DashMatch => {
+                value == expected || value.starts_with(&format!("{expected}-"))
+            }
+            AttributeOperator::Prefix => !expected.is_empty() && value.starts_with(&expected),
+            AttributeOperator::Suffix => !expected.is_empty() && value.ends_with(&expected),
+            AttributeOperator::Substring => !expected.is_empty() && value.contains(&expected),
+        }
+    }
+}
+
+/// The pseudo-elements a selector can target. Eg: p::first-line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PseudoElement {
+    Before,
+    After,
+    FirstLine,
+    FirstLetter,
+    Marker,
+    /// The styles of ::placeholder and ::selection don't affect layout. They're kept for painting
+    /// form controls and selected text, which aren't supported yet.
+    Placeholder,
+    Selection,
+}
+
+impl PseudoElement {
+    pub const ALL: [PseudoElement; 7] = [
+        Self::Before,
+        Self::After,
+        Self::FirstLine,
+        Self::FirstLetter,
+        Self::Marker,
+        Self::Placeholder,
+        Self::Selection,
+    ];
+
+    /// Returns the [PseudoElement] with the given name, if one exists.
+    /// `legacy` only allows the names which may be written with a single colon.
+    pub fn from_name(name: &str, legacy: bool) -> Option<Self> {
+        let pseudo = match &*name.to_lowercase() {
+            "before" => Self::Before,
+            "after" => Self::After,
+            "first-line" => Self::FirstLine,
+            "first-letter" => Self::FirstLetter,
+            "marker" if !legacy => Self::Marker,
+            "placeholder" if !legacy => Self::Placeholder,
+            "selection" if !legacy => Self::Selection,
+            _ => return None,
+        };
+        Some(pseudo)
+    }
+
+    /// Does the pseudo-element generate its own box in the layout tree?
+    pub fn generates_box(&self) -> bool {
+        matches!(self, Self::Before | Self::After | Self::Marker)
+    }
 }
 
 /// A key-value pair of a css attribute. Eg: display: none;
 /// TODO: Link
+#[derive(Debug, Clone, PartialEq)]
 pub struct Declaration {
     pub name: String,
     pub value: Value,
+    /// Was the declaration marked `!important`?
+    pub important: bool,
 }
 
 // Map CSS properties to values.
@@ -53,22 +440,165 @@ pub type PropertyMap = HashMap<String, Value>;
 pub enum Value {
     Keyword(String),
     Length(f32, Unit),
-    ColourValue(Colour),
+    Percentage(f32),
+    Number(f32),
+    /// An angle in degrees.
+    Angle(f32),
+    /// A resolution in dots per px. Eg: 2dppx, 192dpi
+    Resolution(f32),
+    /// A flexible length, which is a share of the free space in a grid container. Eg: 1fr
+    Flex(f32),
+    /// The names of a line between grid tracks. Eg: [header-start main-start]
+    LineNames(Vec<String>),
+    /// A math function. Eg: calc(100% - 2em)
+    Calc(Box<CalcExpr>),
+    Colour(Colour),
+    Str(String),
+    /// A function and its comma separated arguments. Eg: attr(title)
+    Function(String, Vec<Value>),
+    /// Space separated values. Eg: "(" counter(item) ")"
+    List(Vec<Value>),
+    /// Comma separated values. Eg: Georgia, serif
+    CommaList(Vec<Value>),
+    /// A `/` between components. Eg: rgb(0 0 0 / 50%)
+    Slash,
+    /// The value of a custom property, kept as it was written. Eg: --gap: 1em 2em
+    Tokens(TokenList),
+    /// A value containing var(), which is parsed once it is substituted at computed-value time.
+    /// Longhands of a shorthand given var() have the Some name of the shorthand to expand.
+    /// Eg: color: var(--brand, black)
+    Unparsed(TokenList, Option<String>),
+}
+
+/// A token of a value which isn't parsed until it is used. Eg: the value of a custom property
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Whitespace,
+    Ident(String),
+    /// The name of a function. Its arguments are the tokens up to the matching `)`.
+    Function(String),
+    /// A hash, without its `#`. Eg: #336699
+    Hash(String),
+    Str(String),
+    /// An unquoted url. Eg: url(logo.png)
+    Url(String),
+    /// A number as it was written, with its unit or `%`. Eg: 1.5em
+    Number(String),
+    /// Any other character. Eg: ( , /
+    Delim(char),
+}
+
+/// A list of tokens, without whitespace at either end, or several whitespace tokens in a row.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TokenList(pub Vec<Token>);
+
+impl TokenList {
+    /// Does the list contain a var() function?
+    pub fn has_var(&self) -> bool {
+        self.0
+            .iter()
+            .any(|t| matches!(t, Token::Function(f) if f.eq_ignore_ascii_case("var")))
+    }
 }
 
 impl Value {
-    /// Return the size of a length in px, or zero for non-lengths.
+    /// Return the size of a length in px, or zero for non-lengths and relative lengths.
     pub fn to_px(&self) -> f32 {
         match *self {
-            Value::Length(f, Unit::Px) => f,
+            Value::Length(f, ref unit) => unit.absolute_px().map_or(0.0, |px| f * px),
             _ => 0.0,
         }
     }
+
+    /// Return the size of a computed length or percentage in px, with percentages relative to `base`.
+    pub fn to_px_of(&self, base: f32) -> f32 {
+        match self {
+            Value::Percentage(p) => base * p / 100.0,
+            Value::Calc(expr) => expr.eval(&LengthContext::for_percentages(base)),
+            v => v.to_px(),
+        }
+    }
+
+    /// Returns the Some colour of the value, or None if it isn't a colour.
+    /// `currentcolor` is replaced with `current`, (the element's `color`).
+    pub fn to_colour(&self, current: Option<&Colour>) -> Option<Colour> {
+        match self {
+            Value::Colour(c) => Some(c.clone()),
+            Value::Keyword(k) if k.eq_ignore_ascii_case("currentcolor") => current.cloned(),
+            Value::Keyword(k) => Colour::named(k),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a list of its space separated components.
+    pub fn components(&self) -> &[Value] {
+        match self {
+            Value::List(values) => values,
+            v => std::slice::from_ref(v),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Unit {
+    // Absolute lengths.
     Px,
+    Pt,
+    Pc,
+    In,
+    Cm,
+    Mm,
+    Q,
+    // Font relative lengths.
+    Em,
+    Rem,
+    Ex,
+    Ch,
+    // Viewport relative lengths.
+    Vw,
+    Vh,
+    Vmin,
+    Vmax,
+}
+
+impl Unit {
+    /// Returns the Some unit with the given name, or None if it isn't a known unit.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let unit = match &*name.to_lowercase() {
+            "px" => Unit::Px,
+            "pt" => Unit::Pt,
+            "pc" => Unit::Pc,
+            "in" => Unit::In,
+            "cm" => Unit::Cm,
+            "mm" => Unit::Mm,
+            "q" => Unit::Q,
+            "em" => Unit::Em,
+            "rem" => Unit::Rem,
+            "ex" => Unit::Ex,
+            "ch" => Unit::Ch,
+            "vw" => Unit::Vw,
+            "vh" => Unit::Vh,
+            "vmin" => Unit::Vmin,
+            "vmax" => Unit::Vmax,
+            _ => return None,
+        };
+        Some(unit)
+    }
+
+    /// Returns the Some size of one of this unit in px, or None if the unit is relative.
+    /// https://www.w3.org/TR/css-values-3/#absolute-lengths
+    pub fn absolute_px(&self) -> Option<f32> {
+        match self {
+            Unit::Px => Some(1.0),
+            Unit::Pt => Some(96.0 / 72.0),
+            Unit::Pc => Some(16.0),
+            Unit::In => Some(96.0),
+            Unit::Cm => Some(96.0 / 2.54),
+            Unit::Mm => Some(96.0 / 25.4),
+            Unit::Q => Some(96.0 / 101.6),
+            _ => None,
+        }
+    }
 }
 
 /// Colour in rgba
@@ -84,31 +614,57 @@ pub struct Colour {
 // # TODO: Merge styles into Node field (possibly).
 pub struct StyledNode<'a> {
     pub node: &'a Node, // DOM node
+    /// The values given by the cascade, as they were written.
     pub specified_values: PropertyMap,
+    /// The computed values of the node, which may be shared with other nodes.
+    pub style: Arc<ComputedStyle>,
     pub children: Vec<StyledNode<'a>>,
+    /// Set if this node was generated by a pseudo-element (Eg: ::before) of `node`.
+    pub pseudo: Option<PseudoElement>,
+    /// The text produced by the `content` property of a generated node.
+    pub generated_text: Option<String>,
+    /// Styles of the pseudo-elements which don't generate nodes, (Eg: ::first-line).
+    pub pseudo_styles: HashMap<PseudoElement, Arc<ComputedStyle>>,
 }
 
 /// TODO: Fix this garbage
 impl<'a> StyledNode<'a> {
+    /// Returns the Some specified value of a property, or None if it isn't specified.
     pub fn value(&self, name: &str) -> Option<Value> {
         self.specified_values.get(name).cloned()
     }
 
-    /// Returns the "display" attribute of the [StyledNode].
-    pub fn display(&self) -> Display {
-        match self.value("display") {
-            Some(Value::Keyword(v)) => match v.as_str() {
-                "block" => Display::Block,
-                "none" => Display::None,
-                _ => Display::Inline,
-            },
-            _ => Display::Inline,
+    /// Returns the Some computed value of a property, or None if the property is unknown.
+    /// Properties which aren't specified or inherited have their initial value.
+    pub fn computed_value(&self, name: &str) -> Option<Value> {
+        self.style.value(name)
+    }
+
+    /// Returns the value of a property for one of the node's non-generating pseudo-elements.
+    pub fn pseudo_value(&self, pseudo: PseudoElement, name: &str) -> Option<Value> {
+        self.pseudo_styles.get(&pseudo)?.value(name)
+    }
+
+    /// Returns the text of the node, whether it is a text node or generated content.
+    pub fn text(&self) -> Option<&str> {
+        match (&self.generated_text, &self.node.node_type) {
+            (Some(t), _) => Some(t),
+            (None, NodeType::Text(t)) if self.pseudo.is_none() => Some(t),
+            _ => None,
         }
     }
 
-    pub fn lookup(&self, key: &str, key_2: &str, default_val: &Value) -> Value {
-        self.value(key)
-            .unwrap_or_else(|| self.value(key_2).unwrap_or_else(|| default_val.clone()))
+    /// Returns the Some tokens of a custom property, or None if it has no value. Eg: --brand
+    pub fn custom_property(&self, name: &str) -> Option<&TokenList> {
+        match self.style.values.get(name) {
+            Some(Value::Tokens(tokens)) => Some(tokens),
+            _ => None,
+        }
+    }
+
+    /// Returns the "display" attribute of the [StyledNode].
+    pub fn display(&self) -> Display {
+        self.style.display
     }
 }
 
@@ -118,10 +674,32 @@ pub type Specificity = (usize, usize, usize);
 impl Selector {
     pub fn specificity(&self) -> Specificity {
         // http://www.w3.org/TR/selectors/#specificity
-        let Selector::Simple(ref simple) = *self;
-        let a = simple.id.iter().count();
-        let b = simple.class.len();
-        let c = simple.tag_name.iter().count();
-        (a, b, c)
+        self.simple_selectors()
+            .map(|simple| {
+                let a = simple.id.iter().count();
+                let b = simple.class.len() + simple.attributes.len();
+                let c = simple.tag_name.iter().count() + simple.pseudo_element.iter().count();
+                (a, b, c)
+            })
+            .fold((0, 0, 0), |(a, b, c), (x, y, z)| (a + x, b + y, c + z))
+    }
+
+    /// Returns the simple selector which the element itself has to match.
+    pub fn subject(&self) -> &SimpleSelector {
+        match self {
+            Selector::Simple(simple) | Selector::Complex(_, simple) => simple,
+        }
+    }
+
+    /// Returns every simple selector in the selector.
+    pub fn simple_selectors(&self) -> impl Iterator<Item = &SimpleSelector> {
+        let ancestors = match self {
+            Selector::Simple(_) => &[][..],
+            Selector::Complex(ancestors, _) => ancestors.as_slice(),
+        };
+        ancestors
+            .iter()
+            .map(|(simple, _)| simple)
+            .chain([self.subject()])
     }
 }
@@ -0,0 +1,256 @@
+// Generated content: the `content` property, counters and quotes.
+// https://www.w3.org/TR/CSS2/generate.html
+
+use crate::dom::ElementData;
+use crate::style::css::{PropertyMap, Value};
+
+/// The quotes used when the `quotes` property isn't specified.
+const DEFAULT_QUOTES: [(&str, &str); 2] = [("\u{201C}", "\u{201D}"), ("\u{2018}", "\u{2019}")];
+
+/// Tracks the counters and quote depth while walking the document in order.
+#[derive(Default)]
+pub struct GeneratedContent {
+    /// Every counter currently in scope, stored with the depth of the element that created it.
+    counters: Vec<(String, i32, usize)>,
+    quote_depth: usize,
+}
+
+impl GeneratedContent {
+    /// Applies `counter-reset`, `counter-set` and `counter-increment` for an element at a depth.
+    pub fn apply_counters(&mut self, values: &PropertyMap, depth: usize, list_item: bool) {
+        for (name, v) in counter_list(values.get("counter-reset"), 0) {
+            self.counters.push((name, v, depth));
+        }
+
+        for (name, v) in counter_list(values.get("counter-set"), 0) {
+            match self.innermost(&name) {
+                Some(c) => c.1 = v,
+                None => self.counters.push((name, v, depth)),
+            }
+        }
+
+        let mut increments = counter_list(values.get("counter-increment"), 1);
+        // List items increment the `list-item` counter unless told otherwise.
+        if list_item && !increments.iter().any(|(name, _)| name == "list-item") {
+            increments.push(("list-item".to_string(), 1));
+        }
+
+        for (name, v) in increments {
+            match self.innermost(&name) {
+                Some(c) => c.1 = c.1.saturating_add(v),
+                None => self.counters.push((name, v, depth)),
+            }
+        }
+    }
+
+    /// Leaves the children of an element at a depth, ending the scope of counters they created.
+    pub fn leave_children(&mut self, depth: usize) {
+        self.counters.retain(|c| c.2 <= depth);
+    }
+
+    fn innermost(&mut self, name: &str) -> Option<&mut (String, i32, usize)> {
+        self.counters.iter_mut().rev().find(|c| c.0 == name)
+    }
+
+    /// Returns the values of every counter in scope with a name, outermost first.
+    fn counter_values(&self, name: &str) -> Vec<i32> {
+        let values: Vec<i32> = self
+            .counters
+            .iter()
+            .filter(|c| c.0 == name)
+            .map(|c| c.1)
+            .collect();
+
+        // Counters which aren't in scope are treated as having a value of zero.
+        if values.is_empty() {
+            vec![0]
+        } else {
+            values
+        }
+    }
+
+    /// Returns the Some text produced by a `content` value, or None if no box should be generated.
+    pub fn content(
+        &mut self,
+        content: &Value,
+        values: &PropertyMap,
+        elem: &ElementData,
+    ) -> Option<String> {
+        if let Value::Keyword(k) = content {
+            if k == "none" || k == "normal" {
+                return None;
+            }
+        }
+
+        let mut res = String::new();
+        for item in content.components() {
+            match item {
+                Value::Str(s) => res.push_str(s),
+                Value::Function(f, args) => match (f.as_str(), args.as_slice()) {
+                    ("attr", [Value::Keyword(name), ..]) => {
+                        res.push_str(elem.attr(name).map(|a| a.as_str()).unwrap_or(""))
+                    }
+                    ("counter", [Value::Keyword(name), rest @ ..]) => {
+                        let value = *self.counter_values(name).last().unwrap();
+                        res.push_str(&format_counter(value, list_style(rest.first())));
+                    }
+                    ("counters", [Value::Keyword(name), Value::Str(sep), rest @ ..]) => {
+                        let style = list_style(rest.first());
+                        let formatted: Vec<String> = self
+                            .counter_values(name)
+                            .into_iter()
+                            .map(|v| format_counter(v, style))
+                            .collect();
+                        res.push_str(&formatted.join(sep));
+                    }
+                    _ => {}
+                },
+                Value::Keyword(k) => match k.as_str() {
+                    "open-quote" => {
+                        res.push_str(&quote(values, self.quote_depth, true));
+                        self.quote_depth += 1;
+                    }
+                    "close-quote" => {
+                        self.quote_depth = self.quote_depth.saturating_sub(1);
+                        res.push_str(&quote(values, self.quote_depth, false));
+                    }
+                    "no-open-quote" => self.quote_depth += 1,
+                    "no-close-quote" => self.quote_depth = self.quote_depth.saturating_sub(1),
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+
+        Some(res)
+    }
+
+    /// Returns the Some text of a list item's marker, or None if it has no marker.
+    pub fn marker(&mut self, list_style_type: Option<&Value>) -> Option<String> {
+        let style = list_style(list_style_type);
+        if style == "none" {
+            return None;
+        }
+
+        let value = *self.counter_values("list-item").last().unwrap();
+        Some(match style {
+            "disc" | "circle" | "square" => format!("{} ", format_counter(value, style)),
+            _ => format!("{}. ", format_counter(value, style)),
+        })
+    }
+}
+
+/// Parses a counter property, Eg: `counter-reset: chapter 2 section`, into names and values.
+fn counter_list(value: Option<&Value>, default: i32) -> Vec<(String, i32)> {
+    let mut res: Vec<(String, i32)> = Vec::new();
+
+    for v in value.map(|v| v.components()).unwrap_or(&[]) {
+        match v {
+            Value::Keyword(k) if k == "none" => {}
+            Value::Keyword(k) => res.push((k.clone(), default)),
+            Value::Number(n) => {
+                if let Some(last) = res.last_mut() {
+                    // A value too big for a counter is clamped to the biggest one.
+                    last.1 = *n as i32;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    res
+}
+
+/// Returns the name of a list style keyword, defaulting to decimal.
+fn list_style(value: Option<&Value>) -> &str {
+    match value {
+        Some(Value::Keyword(k)) => k.as_str(),
+        _ => "decimal",
+    }
+}
+
+/// Returns the quote for a depth of nesting, using the last pair when nested deeper than given.
+fn quote(values: &PropertyMap, depth: usize, open: bool) -> String {
+    let pick = |(o, c): (&str, &str)| if open { o.to_string() } else { c.to_string() };
+
+    match values.get("quotes") {
+        Some(Value::Keyword(k)) if k == "none" => String::new(),
+        Some(v @ Value::List(_)) => {
+            let strings: Vec<&str> = v
+                .components()
+                .iter()
+                .filter_map(|s| match s {
+                    Value::Str(s) => Some(s.as_str()),
+                    _ => None,
+                })
+                .collect();
+            let pairs: Vec<(&str, &str)> = strings.chunks_exact(2).map(|p| (p[0], p[1])).collect();
+            pairs
+                .get(depth)
+                .or(pairs.last())
+                .map(|p| pick(*p))
+                .unwrap_or_default()
+        }
+        _ => pick(DEFAULT_QUOTES[depth.min(DEFAULT_QUOTES.len() - 1)]),
+    }
+}
+
+/// Formats a counter value in a list style. Eg: 4 in upper-roman is IV.
+pub fn format_counter(value: i32, style: &str) -> String {
+    match style {
+        "none" => String::new(),
+        "disc" => "\u{2022}".to_string(),
+        "circle" => "\u{25E6}".to_string(),
+        "square" => "\u{25AA}".to_string(),
+        "decimal-leading-zero" if (0..10).contains(&value) => format!("0{value}"),
+        "lower-roman" if (1..4000).contains(&value) => roman(value).to_lowercase(),
+        "upper-roman" if (1..4000).contains(&value) => roman(value),
+        "lower-alpha" | "lower-latin" if value > 0 => alphabetic(value, 'a'..='z'),
+        "upper-alpha" | "upper-latin" if value > 0 => alphabetic(value, 'A'..='Z'),
+        "lower-greek" if value > 0 => alphabetic(value, '\u{3B1}'..='\u{3C9}'),
+        _ => value.to_string(),
+    }
+}
+
+/// Formats a positive number in roman numerals.
+fn roman(mut value: i32) -> String {
+    const NUMERALS: [(i32, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+
+    let mut res = String::new();
+    for (n, s) in NUMERALS {
+        while value >= n {
+            res.push_str(s);
+            value -= n;
+        }
+    }
+    res
+}
+
+/// Formats a positive number with an alphabet. Eg: 1 is a, 27 is aa.
+fn alphabetic(mut value: i32, alphabet: std::ops::RangeInclusive<char>) -> String {
+    // Final sigma is skipped in the greek alphabet.
+    let letters: Vec<char> = alphabet.filter(|c| *c != '\u{3C2}').collect();
+    let mut res = Vec::new();
+
+    while value > 0 {
+        value -= 1;
+        res.push(letters[value as usize % letters.len()]);
+        value /= letters.len() as i32;
+    }
+
+    res.iter().rev().collect()
+}
@@ -0,0 +1,286 @@
+// Selector matching, and the indexes that avoid testing every rule against every element.
+// https://www.w3.org/TR/selectors-4/
+
+use crate::dom::ElementData;
+use crate::style::css::{Combinator, PseudoElement, Rule, Selector, SimpleSelector, Specificity};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// Where an element is in the tree, as needed to match combinators.
+#[derive(Clone, Copy)]
+pub struct MatchContext<'a> {
+    /// The elements above the element, from the root down.
+    pub ancestors: &'a [&'a ElementData],
+    /// The preceding element siblings of each ancestor, then of the element itself.
+    pub preceding: &'a [Vec<&'a ElementData>],
+    /// The features of `ancestors`.
+    pub filter: &'a AncestorFilter,
+}
+
+/// Returns whether a [Selector] matches a given element, or one of its pseudo-elements.
+pub fn matches(
+    elem: &ElementData,
+    context: MatchContext,
+    selector: &Selector,
+    pseudo: Option<PseudoElement>,
+) -> bool {
+    let subject = selector.subject();
+    if subject.pseudo_element != pseudo || !matches_simple_selector(elem, subject) {
+        return false;
+    }
+
+    match selector {
+        Selector::Simple(_) => true,
+        Selector::Complex(parts, _) => {
+            let (preceding, siblings) = context.preceding.split_at(context.ancestors.len());
+            matches_parts(parts, context.ancestors, preceding, &siblings[0])
+        }
+    }
+}
+
+/// Returns whether the leading parts of a complex selector match, where the last part is related
+/// to an element by its combinator. The element's `ancestors` each have their `preceding`
+/// siblings, and `siblings` are the element's own preceding siblings.
+fn matches_parts(
+    parts: &[(SimpleSelector, Combinator)],
+    ancestors: &[&ElementData],
+    preceding: &[Vec<&ElementData>],
+    siblings: &[&ElementData],
+) -> bool {
+    let Some(((selector, combinator), rest)) = parts.split_last() else {
+        return true;
+    };
+
+    match combinator {
+        Combinator::Child => match ancestors.split_last() {
+            Some((parent, above)) => {
+                let i = above.len();
+                matches_simple_selector(parent, selector)
+                    && matches_parts(rest, above, &preceding[..i], &preceding[i])
+            }
+            None => false,
+        },
+        // Try each ancestor in turn, from the closest.
+        Combinator::Descendant => (0..ancestors.len()).rev().any(|i| {
+            matches_simple_selector(ancestors[i], selector)
+                && matches_parts(rest, &ancestors[..i], &preceding[..i], &preceding[i])
+        }),
+        Combinator::NextSibling => match siblings.split_last() {
+            Some((sibling, before)) => {
+                matches_simple_selector(sibling, selector)
+                    && matches_parts(rest, ancestors, preceding, before)
+            }
+            None => false,
+        },
+        // Try each preceding sibling in turn, from the closest.
+        Combinator::SubsequentSibling => (0..siblings.len()).rev().any(|i| {
+            matches_simple_selector(siblings[i], selector)
+                && matches_parts(rest, ancestors, preceding, &siblings[..i])
+        }),
+    }
+}
+
+/// Returns whether a [SimpleSelector] matches a given element.
+pub fn matches_simple_selector(elem: &ElementData, selector: &SimpleSelector) -> bool {
+    if selector.tag_name.iter().any(|x| *x != elem.tag_name) {
+        return false;
+    }
+
+    if selector.id.iter().any(|x| Some(x) != elem.id()) {
+        return false;
+    }
+
+    if selector
+        .class
+        .iter()
+        .any(|x| !elem.classes().contains(x.as_str()))
+    {
+        return false;
+    }
+
+    if selector
+        .attributes
+        .iter()
+        .any(|x| !x.matches(elem.attr(&x.name)))
+    {
+        return false;
+    }
+
+    true
+}
+
+/// A selector of a rule, indexed in a [RuleMap].
+pub struct RuleEntry<'a> {
+    pub selector: &'a Selector,
+    pub rule: &'a Rule,
+    pub specificity: Specificity,
+    /// The index of the stylesheet, and of the rule within it.
+    pub order: (usize, usize),
+    /// Hashes of some of the ids, classes and tags that the element's ancestors must have.
+    ancestor_hashes: Vec<u64>,
+}
+
+impl<'a> RuleEntry<'a> {
+    pub fn new(selector: &'a Selector, rule: &'a Rule, order: (usize, usize)) -> Self {
+        // A part followed by a child or descendant combinator matches an ancestor of the element,
+        // (since the siblings of an element's ancestors share their ancestors).
+        let ancestor_hashes = match selector {
+            Selector::Simple(_) => Vec::new(),
+            Selector::Complex(parts, _) => parts
+                .iter()
+                .filter(|(_, combinator)| !combinator.is_sibling())
+                .flat_map(|(simple, _)| simple_selector_hashes(simple))
+                .take(AncestorFilter::MAX_HASHES)
+                .collect(),
+        };
+
+        Self {
+            selector,
+            rule,
+            specificity: selector.specificity(),
+            order,
+            ancestor_hashes,
+        }
+    }
+}
+
+/// Rules indexed by the rightmost id, class or tag of each selector, (the rule hash). An element
+/// only has to be tested against the rules in the buckets of its own id, classes and tag.
+#[derive(Default)]
+pub struct RuleMap<'a> {
+    by_id: HashMap<&'a str, Vec<RuleEntry<'a>>>,
+    by_class: HashMap<&'a str, Vec<RuleEntry<'a>>>,
+    by_tag: HashMap<&'a str, Vec<RuleEntry<'a>>>,
+    /// Selectors without an id, class or tag. Eg: [hidden]
+    universal: Vec<RuleEntry<'a>>,
+}
+
+impl<'a> RuleMap<'a> {
+    pub fn insert(&mut self, entry: RuleEntry<'a>) {
+        let subject = entry.selector.subject();
+
+        let bucket = if let Some(id) = &subject.id {
+            self.by_id.entry(id).or_default()
+        } else if let Some(class) = subject.class.first() {
+            self.by_class.entry(class).or_default()
+        } else if let Some(tag) = &subject.tag_name {
+            self.by_tag.entry(tag).or_default()
+        } else {
+            &mut self.universal
+        };
+
+        bucket.push(entry);
+    }
+
+    /// Returns every entry, in no particular order.
+    pub fn entries(&self) -> impl Iterator<Item = &RuleEntry<'a>> {
+        let buckets = self.by_id.values().chain(self.by_class.values());
+        buckets
+            .chain(self.by_tag.values())
+            .flatten()
+            .chain(&self.universal)
+    }
+
+    /// Returns the entries which might match an element.
+    pub fn candidates<'b>(
+        &'b self,
+        elem: &'b ElementData,
+    ) -> impl Iterator<Item = &'b RuleEntry<'a>> {
+        let get = |map: &'b HashMap<&'a str, Vec<RuleEntry<'a>>>, key: &str| {
+            map.get(key).map_or(&[][..], Vec::as_slice)
+        };
+
+        let by_id = elem.id().map_or(&[][..], |id| get(&self.by_id, id));
+        let by_class = elem
+            .classes()
+            .into_iter()
+            .flat_map(move |class| get(&self.by_class, class));
+        let by_tag = get(&self.by_tag, &elem.tag_name);
+
+        by_id
+            .iter()
+            .chain(by_class)
+            .chain(by_tag)
+            .chain(&self.universal)
+    }
+}
+
+/// Returns hashes of the id, classes and tag a simple selector requires.
+fn simple_selector_hashes(selector: &SimpleSelector) -> Vec<u64> {
+    let id = selector.id.iter().map(|id| feature_hash('#', id));
+    let classes = selector.class.iter().map(|class| feature_hash('.', class));
+    let tag = selector.tag_name.iter().map(|tag| feature_hash(' ', tag));
+    id.chain(classes).chain(tag).collect()
+}
+
+/// Returns the hash of an element's id, class or tag, where `kind` tells them apart.
+fn feature_hash(kind: char, name: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (kind, name).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A counting Bloom filter of the ids, classes and tags of the current element's ancestors.
+/// It can say for sure that no ancestor has a feature, so descendant selectors that can't match
+/// are rejected without walking up the tree.
+pub struct AncestorFilter {
+    counters: Vec<u8>,
+}
+
+impl Default for AncestorFilter {
+    fn default() -> Self {
+        Self {
+            counters: vec![0; Self::SIZE],
+        }
+    }
+}
+
+impl AncestorFilter {
+    const SIZE: usize = 4096;
+
+    /// The most hashes of a selector to check.
+    const MAX_HASHES: usize = 4;
+
+    /// Returns the two counters a hash sets.
+    fn indices(hash: u64) -> [usize; 2] {
+        let mask = Self::SIZE as u64 - 1;
+        [(hash & mask) as usize, ((hash >> 32) & mask) as usize]
+    }
+
+    fn element_hashes(elem: &ElementData) -> Vec<u64> {
+        let id = elem.id().map(|id| feature_hash('#', id));
+        let classes = elem.classes().into_iter().map(|c| feature_hash('.', c));
+        let tag = feature_hash(' ', &elem.tag_name);
+        id.into_iter().chain(classes).chain([tag]).collect()
+    }
+
+    /// Adds an element, before styling its children.
+    pub fn push(&mut self, elem: &ElementData) {
+        for hash in Self::element_hashes(elem) {
+            for i in Self::indices(hash) {
+                self.counters[i] = self.counters[i].saturating_add(1);
+            }
+        }
+    }
+
+    /// Removes an element, after styling its children.
+    pub fn pop(&mut self, elem: &ElementData) {
+        for hash in Self::element_hashes(elem) {
+            for i in Self::indices(hash) {
+                // A full counter may count more elements than it can hold, so is never decreased.
+                if self.counters[i] != u8::MAX {
+                    self.counters[i] -= 1;
+                }
+            }
+        }
+    }
+
+    /// Might the ancestors have every feature an entry requires? False if they definitely don't.
+    pub fn might_match(&self, entry: &RuleEntry) -> bool {
+        entry
+            .ancestor_hashes
+            .iter()
+            .all(|&hash| Self::indices(hash).iter().all(|&i| self.counters[i] > 0))
+    }
+}
@@ -0,0 +1,192 @@
+// Media queries, which only apply rules when the viewport has some features.
+// https://www.w3.org/TR/mediaqueries-4/
+
+use crate::style::css::Value;
+use crate::style::values::{ColourScheme, LengthContext, Viewport, DEFAULT_FONT_SIZE};
+
+/// Comma separated media queries, which match if any of them do. Eg: screen, print
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaQueryList(pub Vec<MediaQuery>);
+
+/// A media query. Eg: not print and (min-width: 40em)
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaQuery {
+    /// Was the query negated by `not`?
+    pub negated: bool,
+    /// The Some media type, or None if the query applies to all media. Eg: screen
+    pub media_type: Option<String>,
+    pub condition: Option<MediaCondition>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaCondition {
+    Feature(MediaFeature),
+    Not(Box<MediaCondition>),
+    And(Vec<MediaCondition>),
+    Or(Vec<MediaCondition>),
+}
+
+/// A test of a feature of the viewport.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaFeature {
+    /// Matches if the feature isn't zero or none. Eg: (color)
+    Boolean(String),
+    /// Compares the feature to a value. `min-` and `max-` features are turned into comparisons.
+    /// Eg: (width >= 40em), (min-width: 40em), (orientation: portrait)
+    Range(String, Comparison, Value),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    /// Returns the comparison with its sides swapped. Eg: 40em < width is width > 40em.
+    pub fn flip(self) -> Self {
+        match self {
+            Self::Less => Self::Greater,
+            Self::LessOrEqual => Self::GreaterOrEqual,
+            Self::Equal => Self::Equal,
+            Self::GreaterOrEqual => Self::LessOrEqual,
+            Self::Greater => Self::Less,
+        }
+    }
+
+    fn compare(self, a: f32, b: f32) -> bool {
+        match self {
+            Self::Less => a < b,
+            Self::LessOrEqual => a <= b,
+            Self::Equal => a == b,
+            Self::GreaterOrEqual => a >= b,
+            Self::Greater => a > b,
+        }
+    }
+}
+
+/// The value of a feature of the viewport.
+enum FeatureValue {
+    Number(f32),
+    Keyword(&'static str),
+}
+
+/// Returns the Some value of a media feature on a viewport, or None if the feature is unknown.
+fn feature_value(name: &str, viewport: &Viewport) -> Option<FeatureValue> {
+    use FeatureValue::*;
+
+    let value = match name {
+        "width" => Number(viewport.width),
+        "height" => Number(viewport.height),
+        "aspect-ratio" => Number(viewport.width / viewport.height),
+        "orientation" if viewport.height >= viewport.width => Keyword("portrait"),
+        "orientation" => Keyword("landscape"),
+        "resolution" => Number(viewport.resolution),
+        "prefers-color-scheme" => Keyword(match viewport.colour_scheme {
+            ColourScheme::Light => "light",
+            ColourScheme::Dark => "dark",
+        }),
+        "prefers-reduced-motion" if viewport.reduced_motion => Keyword("reduce"),
+        "prefers-reduced-motion" => Keyword("no-preference"),
+        // The viewport is a colour screen with a mouse.
+        "color" => Number(8.0),
+        "monochrome" | "grid" => Number(0.0),
+        "hover" | "any-hover" => Keyword("hover"),
+        "pointer" | "any-pointer" => Keyword("fine"),
+        _ => return None,
+    };
+    Some(value)
+}
+
+impl MediaQueryList {
+    /// Does any of the queries match the viewport? An empty list matches every viewport.
+    pub fn matches(&self, viewport: &Viewport) -> bool {
+        self.0.is_empty() || self.0.iter().any(|q| q.matches(viewport))
+    }
+}
+
+impl MediaQuery {
+    /// Returns `not all`, which never matches. Invalid media queries are treated as it.
+    /// https://www.w3.org/TR/mediaqueries-4/#error-handling
+    pub fn not_all() -> Self {
+        Self {
+            negated: true,
+            media_type: Some("all".to_string()),
+            condition: None,
+        }
+    }
+
+    pub fn matches(&self, viewport: &Viewport) -> bool {
+        // Documents are only ever shown on screens.
+        let media_type = match self.media_type.as_deref() {
+            None => true,
+            Some(t) => t.eq_ignore_ascii_case("all") || t.eq_ignore_ascii_case("screen"),
+        };
+        let condition = self.condition.as_ref().is_none_or(|c| c.matches(viewport));
+
+        (media_type && condition) != self.negated
+    }
+}
+
+impl MediaCondition {
+    pub fn matches(&self, viewport: &Viewport) -> bool {
+        match self {
+            Self::Feature(feature) => feature.matches(viewport),
+            Self::Not(condition) => !condition.matches(viewport),
+            Self::And(conditions) => conditions.iter().all(|c| c.matches(viewport)),
+            Self::Or(conditions) => conditions.iter().any(|c| c.matches(viewport)),
+        }
+    }
+}
+
+impl MediaFeature {
+    /// Does the feature match the viewport? Unknown features and invalid values never match.
+    pub fn matches(&self, viewport: &Viewport) -> bool {
+        match self {
+            Self::Boolean(name) => match feature_value(name, viewport) {
+                Some(FeatureValue::Number(n)) => n != 0.0,
+                Some(FeatureValue::Keyword(k)) => k != "none" && k != "no-preference",
+                None => false,
+            },
+            Self::Range(name, comparison, value) => match feature_value(name, viewport) {
+                Some(FeatureValue::Number(n)) => match query_number(name, value, viewport) {
+                    Some(v) => comparison.compare(n, v),
+                    None => false,
+                },
+                Some(FeatureValue::Keyword(k)) => {
+                    *comparison == Comparison::Equal
+                        && matches!(value, Value::Keyword(v) if v.eq_ignore_ascii_case(k))
+                }
+                None => false,
+            },
+        }
+    }
+}
+
+/// Returns the Some number a feature is compared to, or None if the value doesn't suit it.
+fn query_number(name: &str, value: &Value, viewport: &Viewport) -> Option<f32> {
+    match (name, value) {
+        // Relative lengths are relative to the initial font size.
+        ("width" | "height", Value::Length(n, unit)) => {
+            let ctx = LengthContext {
+                font_size: DEFAULT_FONT_SIZE,
+                root_font_size: DEFAULT_FONT_SIZE,
+                viewport: *viewport,
+                percent_base: 0.0,
+            };
+            Some(ctx.length_to_px(*n, unit))
+        }
+        ("width" | "height", Value::Number(n)) if *n == 0.0 => Some(0.0),
+        ("aspect-ratio", Value::Number(n)) => Some(*n),
+        ("aspect-ratio", Value::List(ratio)) => match ratio.as_slice() {
+            [Value::Number(w), Value::Slash, Value::Number(h)] => Some(w / h),
+            _ => None,
+        },
+        ("resolution", Value::Resolution(dppx)) => Some(*dppx),
+        ("color" | "monochrome" | "grid", Value::Number(n)) => Some(*n),
+        _ => None,
+    }
+}
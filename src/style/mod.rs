@@ -1,2 +1,15 @@
+pub mod cascade;
+pub mod colour;
+pub mod computed;
 pub mod css;
+pub mod generated;
+pub mod matching;
+pub mod media;
+pub mod properties;
+pub mod restyle;
+pub mod serialize;
+pub mod shorthand;
 pub mod style_tree;
+pub mod user_agent;
+pub mod values;
+pub mod variables;
@@ -0,0 +1,959 @@
+// The registry of supported properties: What values they accept, their initial values, and
+// whether they are inherited.
+// https://www.w3.org/TR/CSS2/propidx.html
+
+use crate::parsing::css_parser::CssParser;
+use crate::style::css::{Declaration, Value};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Keywords which every property accepts.
+pub const CSS_WIDE_KEYWORDS: [&str; 5] = ["inherit", "initial", "unset", "revert", "revert-layer"];
+
+/// A type of component that a property's value can be made of.
+#[derive(Debug, Clone, Copy)]
+pub enum Component {
+    /// A length, including a unitless zero and math functions.
+    Length,
+    Percentage,
+    Number,
+    Integer,
+    Colour,
+    Str,
+    Url,
+    Angle,
+    /// Any identifier which isn't a CSS-wide keyword. Eg: A counter's name.
+    Ident,
+    Keywords(&'static [&'static str]),
+    Functions(&'static [&'static str]),
+    Slash,
+    /// Anything at all, for grammars too complex to describe here.
+    Any,
+}
+
+/// Describes the values a property accepts.
+#[derive(Debug, Clone, Copy)]
+pub struct Grammar {
+    pub components: &'static [Component],
+    /// The most space separated components the value can have.
+    pub max: usize,
+    /// Can the value be a comma separated list?
+    pub commas: bool,
+    /// Are negative numbers and lengths allowed?
+    pub negative: bool,
+}
+
+impl Grammar {
+    const fn one(components: &'static [Component]) -> Self {
+        Self {
+            components,
+            max: 1,
+            commas: false,
+            negative: true,
+        }
+    }
+
+    const fn many(components: &'static [Component], max: usize) -> Self {
+        Self {
+            components,
+            max,
+            commas: false,
+            negative: true,
+        }
+    }
+
+    const fn non_negative(self) -> Self {
+        Self {
+            negative: false,
+            ..self
+        }
+    }
+
+    const fn commas(self) -> Self {
+        Self {
+            commas: true,
+            ..self
+        }
+    }
+
+    /// Does the grammar accept a value?
+    pub fn accepts(&self, value: &Value) -> bool {
+        match value {
+            Value::CommaList(values) => self.commas && values.iter().all(|v| self.accepts(v)),
+            v => {
+                let c = v.components();
+                !c.is_empty()
+                    && c.len() <= self.max
+                    && c.iter()
+                        .all(|v| self.components.iter().any(|k| self.matches(*k, v)))
+            }
+        }
+    }
+
+    /// Does a single component match a component type?
+    fn matches(&self, component: Component, v: &Value) -> bool {
+        let sign_ok = |n: f32| self.negative || n >= 0.0;
+
+        match (component, v) {
+            (Component::Any, _) => true,
+            (Component::Length, Value::Length(n, _)) => sign_ok(*n),
+            (Component::Length, Value::Number(n)) => *n == 0.0,
+            (Component::Length | Component::Percentage | Component::Number, Value::Calc(_)) => true,
+            (Component::Percentage, Value::Percentage(n)) => sign_ok(*n),
+            (Component::Number, Value::Number(n)) => sign_ok(*n),
+            (Component::Integer, Value::Number(n)) => sign_ok(*n) && n.fract() == 0.0,
+            (Component::Colour, v) => {
+                v.to_colour(None).is_some() || is_keyword(v, &["currentcolor"])
+            }
+            (Component::Str, Value::Str(_)) => true,
+            (Component::Url, Value::Function(f, _)) => f == "url",
+            (Component::Angle, Value::Angle(_)) => true,
+            (Component::Angle, Value::Number(n)) => *n == 0.0,
+            (Component::Ident, Value::Keyword(k)) => {
+                !CSS_WIDE_KEYWORDS.contains(&k.to_lowercase().as_str())
+            }
+            (Component::Keywords(keywords), v) => is_keyword(v, keywords),
+            (Component::Functions(names), Value::Function(f, _)) => names.contains(&f.as_str()),
+            (Component::Slash, Value::Slash) => true,
+            _ => false,
+        }
+    }
+}
+
+fn is_keyword(v: &Value, keywords: &[&str]) -> bool {
+    matches!(v, Value::Keyword(k) if keywords.contains(&k.to_lowercase().as_str()))
+}
+
+/// The elements a property applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppliesTo {
+    All,
+    /// Everything except non-replaced inline boxes, table rows and row groups.
+    Sized,
+    BlockContainers,
+    ListItems,
+    Positioned,
+    FlexContainers,
+    FlexItems,
+    GridContainers,
+    GridItems,
+    /// Flex, grid and multi-column containers.
+    Containers,
+    /// Flex and grid items, and absolutely positioned boxes.
+    Items,
+}
+
+/// What percentages in a property's value are relative to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Percentages {
+    None,
+    ContainingBlockWidth,
+    ContainingBlockHeight,
+    FontSize,
+    /// The size of the box itself, (Eg: background-position).
+    OwnBox,
+    /// The size of the content box of the container, (Eg: flex-basis, gap).
+    ContainerContent,
+}
+
+/// A supported property.
+#[derive(Debug, Clone, Copy)]
+pub struct Property {
+    pub name: &'static str,
+    pub grammar: Grammar,
+    /// The initial value, as css text.
+    pub initial: &'static str,
+    pub inherited: bool,
+    pub applies_to: AppliesTo,
+    pub percentages: Percentages,
+    pub animatable: bool,
+}
+
+impl Property {
+    const fn new(name: &'static str, grammar: Grammar, initial: &'static str) -> Self {
+        Self {
+            name,
+            grammar,
+            initial,
+            inherited: false,
+            applies_to: AppliesTo::All,
+            percentages: Percentages::None,
+            animatable: true,
+        }
+    }
+
+    const fn inherited(self) -> Self {
+        Self {
+            inherited: true,
+            ..self
+        }
+    }
+
+    const fn applies(self, applies_to: AppliesTo) -> Self {
+        Self { applies_to, ..self }
+    }
+
+    const fn percent(self, percentages: Percentages) -> Self {
+        Self {
+            percentages,
+            ..self
+        }
+    }
+
+    /// Values which can't be interpolated between, so can't be animated.
+    const fn discrete(self) -> Self {
+        Self {
+            animatable: false,
+            ..self
+        }
+    }
+
+    /// Returns the initial value of the property.
+    pub fn initial_value(&self) -> Value {
+        initial_values()[self.name].clone()
+    }
+}
+
+use Component::*;
+
+const LENGTH: &[Component] = &[Length];
+const LENGTH_PERCENTAGE: &[Component] = &[Length, Percentage];
+const LENGTH_PERCENTAGE_AUTO: &[Component] = &[Length, Percentage, Keywords(&["auto"])];
+const SIZE: &[Component] = &[
+    Length,
+    Percentage,
+    Keywords(&["auto", "min-content", "max-content", "fit-content"]),
+];
+const MAX_SIZE: &[Component] = &[
+    Length,
+    Percentage,
+    Keywords(&["none", "min-content", "max-content", "fit-content"]),
+];
+const LINE_WIDTH: &[Component] = &[Length, Keywords(&["thin", "medium", "thick"])];
+const LINE_STYLE: &[Component] = &[Keywords(&[
+    "none", "hidden", "dotted", "dashed", "solid", "double", "groove", "ridge", "inset", "outset",
+])];
+const COLOUR: &[Component] = &[Colour];
+const IMAGE: &[Component] = &[
+    Url,
+    Functions(&[
+        "linear-gradient",
+        "radial-gradient",
+        "conic-gradient",
+        "repeating-linear-gradient",
+        "repeating-radial-gradient",
+        "repeating-conic-gradient",
+    ]),
+    Keywords(&["none"]),
+];
+const COUNTER: &[Component] = &[Ident, Integer, Keywords(&["none"])];
+const ALIGN: &[Component] = &[Keywords(&[
+    "auto",
+    "normal",
+    "stretch",
+    "center",
+    "start",
+    "end",
+    "flex-start",
+    "flex-end",
+    "self-start",
+    "self-end",
+    "left",
+    "right",
+    "baseline",
+    "first",
+    "last",
+    "safe",
+    "unsafe",
+    "space-between",
+    "space-around",
+    "space-evenly",
+    "legacy",
+])];
+const GRID_LINE: &[Component] = &[Integer, Ident, Keywords(&["auto", "span"])];
+const LIST_STYLE_TYPE: &[Component] = &[Ident, Str, Keywords(&["none"])];
+const TRACKS: &[Component] = &[Any];
+
+/// Every supported property.
+static PROPERTIES: &[Property] = &[
+    // Box model.
+    Property::new(
+        "display",
+        Grammar::many(
+            &[Keywords(&[
+                "inline",
+                "block",
+                "list-item",
+                "inline-block",
+                "flex",
+                "inline-flex",
+                "grid",
+                "inline-grid",
+                "flow-root",
+                "flow",
+                "contents",
+                "none",
+                "table",
+                "inline-table",
+                "table-row",
+                "table-cell",
+                "table-row-group",
+                "table-header-group",
+                "table-footer-group",
+                "table-column",
+                "table-column-group",
+                "table-caption",
+            ])],
+            2,
+        ),
+        "inline",
+    )
+    .discrete(),
+    Property::new("width", Grammar::one(SIZE).non_negative(), "auto")
+        .applies(AppliesTo::Sized)
+        .percent(Percentages::ContainingBlockWidth),
+    Property::new("height", Grammar::one(SIZE).non_negative(), "auto")
+        .applies(AppliesTo::Sized)
+        .percent(Percentages::ContainingBlockHeight),
+    Property::new("min-width", Grammar::one(SIZE).non_negative(), "auto")
+        .applies(AppliesTo::Sized)
+        .percent(Percentages::ContainingBlockWidth),
+    Property::new("min-height", Grammar::one(SIZE).non_negative(), "auto")
+        .applies(AppliesTo::Sized)
+        .percent(Percentages::ContainingBlockHeight),
+    Property::new("max-width", Grammar::one(MAX_SIZE).non_negative(), "none")
+        .applies(AppliesTo::Sized)
+        .percent(Percentages::ContainingBlockWidth),
+    Property::new("max-height", Grammar::one(MAX_SIZE).non_negative(), "none")
+        .applies(AppliesTo::Sized)
+        .percent(Percentages::ContainingBlockHeight),
+    Property::new(
+        "box-sizing",
+        Grammar::one(&[Keywords(&["content-box", "border-box"])]),
+        "content-box",
+    )
+    .applies(AppliesTo::Sized)
+    .discrete(),
+    Property::new("margin-top", Grammar::one(LENGTH_PERCENTAGE_AUTO), "0")
+        .percent(Percentages::ContainingBlockWidth),
+    Property::new("margin-right", Grammar::one(LENGTH_PERCENTAGE_AUTO), "0")
+        .percent(Percentages::ContainingBlockWidth),
+    Property::new("margin-bottom", Grammar::one(LENGTH_PERCENTAGE_AUTO), "0")
+        .percent(Percentages::ContainingBlockWidth),
+    Property::new("margin-left", Grammar::one(LENGTH_PERCENTAGE_AUTO), "0")
+        .percent(Percentages::ContainingBlockWidth),
+    Property::new(
+        "padding-top",
+        Grammar::one(LENGTH_PERCENTAGE).non_negative(),
+        "0",
+    )
+    .percent(Percentages::ContainingBlockWidth),
+    Property::new(
+        "padding-right",
+        Grammar::one(LENGTH_PERCENTAGE).non_negative(),
+        "0",
+    )
+    .percent(Percentages::ContainingBlockWidth),
+    Property::new(
+        "padding-bottom",
+        Grammar::one(LENGTH_PERCENTAGE).non_negative(),
+        "0",
+    )
+    .percent(Percentages::ContainingBlockWidth),
+    Property::new(
+        "padding-left",
+        Grammar::one(LENGTH_PERCENTAGE).non_negative(),
+        "0",
+    )
+    .percent(Percentages::ContainingBlockWidth),
+    // Borders and outlines.
+    Property::new(
+        "border-top-width",
+        Grammar::one(LINE_WIDTH).non_negative(),
+        "medium",
+    ),
+    Property::new(
+        "border-right-width",
+        Grammar::one(LINE_WIDTH).non_negative(),
+        "medium",
+    ),
+    Property::new(
+        "border-bottom-width",
+        Grammar::one(LINE_WIDTH).non_negative(),
+        "medium",
+    ),
+    Property::new(
+        "border-left-width",
+        Grammar::one(LINE_WIDTH).non_negative(),
+        "medium",
+    ),
+    Property::new("border-top-style", Grammar::one(LINE_STYLE), "none").discrete(),
+    Property::new("border-right-style", Grammar::one(LINE_STYLE), "none").discrete(),
+    Property::new("border-bottom-style", Grammar::one(LINE_STYLE), "none").discrete(),
+    Property::new("border-left-style", Grammar::one(LINE_STYLE), "none").discrete(),
+    Property::new("border-top-color", Grammar::one(COLOUR), "currentcolor"),
+    Property::new("border-right-color", Grammar::one(COLOUR), "currentcolor"),
+    Property::new("border-bottom-color", Grammar::one(COLOUR), "currentcolor"),
+    Property::new("border-left-color", Grammar::one(COLOUR), "currentcolor"),
+    Property::new(
+        "outline-width",
+        Grammar::one(LINE_WIDTH).non_negative(),
+        "medium",
+    ),
+    Property::new("outline-style", Grammar::one(LINE_STYLE), "none").discrete(),
+    Property::new("outline-color", Grammar::one(COLOUR), "currentcolor"),
+    // Colours and backgrounds.
+    Property::new("color", Grammar::one(COLOUR), "black").inherited(),
+    Property::new("opacity", Grammar::one(&[Number, Percentage]), "1"),
+    Property::new("background-color", Grammar::one(COLOUR), "transparent"),
+    Property::new("background-image", Grammar::one(IMAGE).commas(), "none").discrete(),
+    Property::new(
+        "background-repeat",
+        Grammar::many(
+            &[Keywords(&[
+                "repeat",
+                "repeat-x",
+                "repeat-y",
+                "no-repeat",
+                "space",
+                "round",
+            ])],
+            2,
+        )
+        .commas(),
+        "repeat",
+    )
+    .discrete(),
+    Property::new(
+        "background-attachment",
+        Grammar::one(&[Keywords(&["scroll", "fixed", "local"])]).commas(),
+        "scroll",
+    )
+    .discrete(),
+    Property::new(
+        "background-position",
+        Grammar::many(
+            &[
+                Length,
+                Percentage,
+                Keywords(&["left", "center", "right", "top", "bottom"]),
+            ],
+            4,
+        )
+        .commas(),
+        "0% 0%",
+    )
+    .percent(Percentages::OwnBox),
+    Property::new(
+        "background-size",
+        Grammar::many(
+            &[Length, Percentage, Keywords(&["auto", "cover", "contain"])],
+            2,
+        )
+        .commas()
+        .non_negative(),
+        "auto",
+    )
+    .percent(Percentages::OwnBox),
+    Property::new(
+        "background-origin",
+        Grammar::one(&[Keywords(&["border-box", "padding-box", "content-box"])]).commas(),
+        "padding-box",
+    )
+    .discrete(),
+    Property::new(
+        "background-clip",
+        Grammar::one(&[Keywords(&[
+            "border-box",
+            "padding-box",
+            "content-box",
+            "text",
+        ])])
+        .commas(),
+        "border-box",
+    )
+    .discrete(),
+    // Fonts and text.
+    Property::new(
+        "font-family",
+        Grammar::many(&[Ident, Str], usize::MAX).commas(),
+        "serif",
+    )
+    .inherited()
+    .discrete(),
+    Property::new(
+        "font-size",
+        Grammar::one(&[
+            Length,
+            Percentage,
+            Keywords(&[
+                "xx-small",
+                "x-small",
+                "small",
+                "medium",
+                "large",
+                "x-large",
+                "xx-large",
+                "xxx-large",
+                "smaller",
+                "larger",
+            ]),
+        ])
+        .non_negative(),
+        "medium",
+    )
+    .inherited()
+    .percent(Percentages::FontSize),
+    Property::new(
+        "font-style",
+        Grammar::many(&[Keywords(&["normal", "italic", "oblique"]), Angle], 2),
+        "normal",
+    )
+    .inherited()
+    .discrete(),
+    Property::new(
+        "font-weight",
+        Grammar::one(&[Number, Keywords(&["normal", "bold", "bolder", "lighter"])]).non_negative(),
+        "normal",
+    )
+    .inherited(),
+    Property::new(
+        "font-variant",
+        Grammar::one(&[Keywords(&["normal", "small-caps", "none"])]),
+        "normal",
+    )
+    .inherited()
+    .discrete(),
+    Property::new(
+        "font-stretch",
+        Grammar::one(&[
+            Percentage,
+            Keywords(&[
+                "normal",
+                "ultra-condensed",
+                "extra-condensed",
+                "condensed",
+                "semi-condensed",
+                "semi-expanded",
+                "expanded",
+                "extra-expanded",
+                "ultra-expanded",
+            ]),
+        ])
+        .non_negative(),
+        "normal",
+    )
+    .inherited(),
+    Property::new(
+        "line-height",
+        Grammar::one(&[Number, Length, Percentage, Keywords(&["normal"])]).non_negative(),
+        "normal",
+    )
+    .inherited()
+    .percent(Percentages::FontSize),
+    Property::new(
+        "text-align",
+        Grammar::one(&[Keywords(&[
+            "start",
+            "end",
+            "left",
+            "right",
+            "center",
+            "justify",
+            "match-parent",
+        ])]),
+        "start",
+    )
+    .inherited()
+    .applies(AppliesTo::BlockContainers)
+    .discrete(),
+    Property::new("text-indent", Grammar::one(LENGTH_PERCENTAGE), "0")
+        .inherited()
+        .applies(AppliesTo::BlockContainers)
+        .percent(Percentages::ContainingBlockWidth),
+    Property::new(
+        "text-transform",
+        Grammar::one(&[Keywords(&["none", "capitalize", "uppercase", "lowercase"])]),
+        "none",
+    )
+    .inherited()
+    .discrete(),
+    Property::new(
+        "text-decoration-line",
+        Grammar::many(
+            &[Keywords(&["none", "underline", "overline", "line-through"])],
+            3,
+        ),
+        "none",
+    )
+    .discrete(),
+    Property::new(
+        "text-decoration-color",
+        Grammar::one(COLOUR),
+        "currentcolor",
+    ),
+    Property::new(
+        "vertical-align",
+        Grammar::one(&[
+            Length,
+            Percentage,
+            Keywords(&[
+                "baseline",
+                "sub",
+                "super",
+                "top",
+                "text-top",
+                "middle",
+                "bottom",
+                "text-bottom",
+            ]),
+        ]),
+        "baseline",
+    )
+    .percent(Percentages::FontSize),
+    Property::new(
+        "white-space",
+        Grammar::one(&[Keywords(&[
+            "normal",
+            "pre",
+            "nowrap",
+            "pre-wrap",
+            "pre-line",
+            "break-spaces",
+        ])]),
+        "normal",
+    )
+    .inherited()
+    .discrete(),
+    Property::new(
+        "word-break",
+        Grammar::one(&[Keywords(&["normal", "break-all", "keep-all", "break-word"])]),
+        "normal",
+    )
+    .inherited()
+    .discrete(),
+    Property::new(
+        "overflow-wrap",
+        Grammar::one(&[Keywords(&["normal", "break-word", "anywhere"])]),
+        "normal",
+    )
+    .inherited()
+    .discrete(),
+    Property::new(
+        "letter-spacing",
+        Grammar::one(&[Length, Keywords(&["normal"])]),
+        "normal",
+    )
+    .inherited(),
+    Property::new(
+        "word-spacing",
+        Grammar::one(&[Length, Keywords(&["normal"])]),
+        "normal",
+    )
+    .inherited(),
+    // Generated content and lists.
+    Property::new("content", Grammar::many(&[Any], usize::MAX), "normal").discrete(),
+    Property::new(
+        "quotes",
+        Grammar::many(&[Str, Keywords(&["none", "auto"])], usize::MAX),
+        "auto",
+    )
+    .inherited()
+    .discrete(),
+    Property::new("counter-reset", Grammar::many(COUNTER, usize::MAX), "none").discrete(),
+    Property::new("counter-set", Grammar::many(COUNTER, usize::MAX), "none").discrete(),
+    Property::new(
+        "counter-increment",
+        Grammar::many(COUNTER, usize::MAX),
+        "none",
+    )
+    .discrete(),
+    Property::new("list-style-type", Grammar::one(LIST_STYLE_TYPE), "disc")
+        .inherited()
+        .applies(AppliesTo::ListItems)
+        .discrete(),
+    Property::new(
+        "list-style-position",
+        Grammar::one(&[Keywords(&["inside", "outside"])]),
+        "outside",
+    )
+    .inherited()
+    .applies(AppliesTo::ListItems)
+    .discrete(),
+    Property::new("list-style-image", Grammar::one(IMAGE), "none")
+        .inherited()
+        .applies(AppliesTo::ListItems)
+        .discrete(),
+    // Visual formatting.
+    Property::new(
+        "position",
+        Grammar::one(&[Keywords(&[
+            "static", "relative", "absolute", "fixed", "sticky",
+        ])]),
+        "static",
+    )
+    .discrete(),
+    Property::new("top", Grammar::one(LENGTH_PERCENTAGE_AUTO), "auto")
+        .applies(AppliesTo::Positioned)
+        .percent(Percentages::ContainingBlockHeight),
+    Property::new("right", Grammar::one(LENGTH_PERCENTAGE_AUTO), "auto")
+        .applies(AppliesTo::Positioned)
+        .percent(Percentages::ContainingBlockWidth),
+    Property::new("bottom", Grammar::one(LENGTH_PERCENTAGE_AUTO), "auto")
+        .applies(AppliesTo::Positioned)
+        .percent(Percentages::ContainingBlockHeight),
+    Property::new("left", Grammar::one(LENGTH_PERCENTAGE_AUTO), "auto")
+        .applies(AppliesTo::Positioned)
+        .percent(Percentages::ContainingBlockWidth),
+    Property::new(
+        "z-index",
+        Grammar::one(&[Integer, Keywords(&["auto"])]),
+        "auto",
+    )
+    .applies(AppliesTo::Positioned),
+    Property::new(
+        "float",
+        Grammar::one(&[Keywords(&[
+            "none",
+            "left",
+            "right",
+            "inline-start",
+            "inline-end",
+        ])]),
+        "none",
+    )
+    .discrete(),
+    Property::new(
+        "clear",
+        Grammar::one(&[Keywords(&[
+            "none",
+            "left",
+            "right",
+            "both",
+            "inline-start",
+            "inline-end",
+        ])]),
+        "none",
+    )
+    .discrete(),
+    Property::new(
+        "overflow-x",
+        Grammar::one(&[Keywords(&["visible", "hidden", "clip", "scroll", "auto"])]),
+        "visible",
+    )
+    .discrete(),
+    Property::new(
+        "overflow-y",
+        Grammar::one(&[Keywords(&["visible", "hidden", "clip", "scroll", "auto"])]),
+        "visible",
+    )
+    .discrete(),
+    Property::new(
+        "visibility",
+        Grammar::one(&[Keywords(&["visible", "hidden", "collapse"])]),
+        "visible",
+    )
+    .inherited(),
+    // Flexible box layout.
+    Property::new(
+        "flex-direction",
+        Grammar::one(&[Keywords(&[
+            "row",
+            "row-reverse",
+            "column",
+            "column-reverse",
+        ])]),
+        "row",
+    )
+    .applies(AppliesTo::FlexContainers)
+    .discrete(),
+    Property::new(
+        "flex-wrap",
+        Grammar::one(&[Keywords(&["nowrap", "wrap", "wrap-reverse"])]),
+        "nowrap",
+    )
+    .applies(AppliesTo::FlexContainers)
+    .discrete(),
+    Property::new("flex-grow", Grammar::one(&[Number]).non_negative(), "0")
+        .applies(AppliesTo::FlexItems),
+    Property::new("flex-shrink", Grammar::one(&[Number]).non_negative(), "1")
+        .applies(AppliesTo::FlexItems),
+    Property::new(
+        "flex-basis",
+        Grammar::one(&[Length, Percentage, Keywords(&["auto", "content"])]).non_negative(),
+        "auto",
+    )
+    .applies(AppliesTo::FlexItems)
+    .percent(Percentages::ContainerContent),
+    Property::new("order", Grammar::one(&[Integer]), "0").applies(AppliesTo::Items),
+    Property::new("justify-content", Grammar::many(ALIGN, 2), "normal")
+        .applies(AppliesTo::Containers)
+        .discrete(),
+    Property::new("align-content", Grammar::many(ALIGN, 2), "normal")
+        .applies(AppliesTo::Containers)
+        .discrete(),
+    Property::new("justify-items", Grammar::many(ALIGN, 2), "legacy").discrete(),
+    Property::new("align-items", Grammar::many(ALIGN, 2), "normal").discrete(),
+    Property::new("justify-self", Grammar::many(ALIGN, 2), "auto")
+        .applies(AppliesTo::Items)
+        .discrete(),
+    Property::new("align-self", Grammar::many(ALIGN, 2), "auto")
+        .applies(AppliesTo::Items)
+        .discrete(),
+    Property::new(
+        "row-gap",
+        Grammar::one(&[Length, Percentage, Keywords(&["normal"])]).non_negative(),
+        "normal",
+    )
+    .applies(AppliesTo::Containers)
+    .percent(Percentages::ContainerContent),
+    Property::new(
+        "column-gap",
+        Grammar::one(&[Length, Percentage, Keywords(&["normal"])]).non_negative(),
+        "normal",
+    )
+    .applies(AppliesTo::Containers)
+    .percent(Percentages::ContainerContent),
+    // Grid layout.
+    Property::new(
+        "grid-template-rows",
+        Grammar::many(TRACKS, usize::MAX),
+        "none",
+    )
+    .applies(AppliesTo::GridContainers)
+    .percent(Percentages::ContainerContent),
+    Property::new(
+        "grid-template-columns",
+        Grammar::many(TRACKS, usize::MAX),
+        "none",
+    )
+    .applies(AppliesTo::GridContainers)
+    .percent(Percentages::ContainerContent),
+    Property::new(
+        "grid-template-areas",
+        Grammar::many(&[Str, Keywords(&["none"])], usize::MAX),
+        "none",
+    )
+    .applies(AppliesTo::GridContainers)
+    .discrete(),
+    Property::new("grid-auto-rows", Grammar::many(TRACKS, usize::MAX), "auto")
+        .applies(AppliesTo::GridContainers)
+        .percent(Percentages::ContainerContent),
+    Property::new(
+        "grid-auto-columns",
+        Grammar::many(TRACKS, usize::MAX),
+        "auto",
+    )
+    .applies(AppliesTo::GridContainers)
+    .percent(Percentages::ContainerContent),
+    Property::new(
+        "grid-auto-flow",
+        Grammar::many(&[Keywords(&["row", "column", "dense"])], 2),
+        "row",
+    )
+    .applies(AppliesTo::GridContainers)
+    .discrete(),
+    Property::new("grid-row-start", Grammar::many(GRID_LINE, 3), "auto")
+        .applies(AppliesTo::GridItems)
+        .discrete(),
+    Property::new("grid-row-end", Grammar::many(GRID_LINE, 3), "auto")
+        .applies(AppliesTo::GridItems)
+        .discrete(),
+    Property::new("grid-column-start", Grammar::many(GRID_LINE, 3), "auto")
+        .applies(AppliesTo::GridItems)
+        .discrete(),
+    Property::new("grid-column-end", Grammar::many(GRID_LINE, 3), "auto")
+        .applies(AppliesTo::GridItems)
+        .discrete(),
+    // User interface.
+    Property::new(
+        "cursor",
+        Grammar::many(&[Ident, Url], usize::MAX).commas(),
+        "auto",
+    )
+    .inherited()
+    .discrete(),
+    Property::new(
+        "caret-color",
+        Grammar::one(&[Colour, Keywords(&["auto"])]),
+        "auto",
+    )
+    .inherited(),
+    Property::new(
+        "border-collapse",
+        Grammar::one(&[Keywords(&["separate", "collapse"])]),
+        "separate",
+    )
+    .inherited()
+    .discrete(),
+    Property::new(
+        "border-spacing",
+        Grammar::many(LENGTH, 2).non_negative(),
+        "0",
+    )
+    .inherited(),
+];
+
+/// Returns the registry, indexed by property name.
+fn registry() -> &'static HashMap<&'static str, &'static Property> {
+    static REGISTRY: OnceLock<HashMap<&'static str, &'static Property>> = OnceLock::new();
+    REGISTRY.get_or_init(|| PROPERTIES.iter().map(|p| (p.name, p)).collect())
+}
+
+/// Returns the parsed initial value of every property.
+fn initial_values() -> &'static HashMap<&'static str, Value> {
+    static INITIAL: OnceLock<HashMap<&'static str, Value>> = OnceLock::new();
+    INITIAL.get_or_init(|| {
+        PROPERTIES
+            .iter()
+            .map(|p| {
+                let value = CssParser::parse_declaration_value(p.initial);
+                (p.name, value.expect("Initial values are valid"))
+            })
+            .collect()
+    })
+}
+
+/// Returns the Some property with a name, or None if it isn't supported.
+pub fn property(name: &str) -> Option<&'static Property> {
+    registry().get(name).copied()
+}
+
+/// Returns every supported property.
+pub fn all_properties() -> &'static [Property] {
+    PROPERTIES
+}
+
+/// Returns the initial value of a property, or None if it isn't supported.
+pub fn initial_value(name: &str) -> Option<Value> {
+    property(name).map(|p| p.initial_value())
+}
+
+/// Is the property a custom property, which authors name themselves? Eg: --brand
+pub fn is_custom_property(name: &str) -> bool {
+    name.starts_with("--")
+}
+
+/// Is the property inherited by default? Custom properties always are.
+pub fn is_inherited(name: &str) -> bool {
+    is_custom_property(name) || property(name).is_some_and(|p| p.inherited)
+}
+
+/// Is the declaration a supported property with a valid value?
+/// Custom properties accept anything, and values with var() can't be checked until they are
+/// substituted.
+pub fn is_valid(declaration: &Declaration) -> bool {
+    if is_custom_property(&declaration.name) {
+        return true;
+    }
+    if let Value::Unparsed(..) = declaration.value {
+        return property(&declaration.name).is_some();
+    }
+    if let Value::Keyword(k) = &declaration.value {
+        if CSS_WIDE_KEYWORDS.contains(&k.to_lowercase().as_str()) {
+            return property(&declaration.name).is_some();
+        }
+    }
+
+    property(&declaration.name).is_some_and(|p| p.grammar.accepts(&declaration.value))
+}
@@ -0,0 +1,179 @@
+// Restyling only the parts of a document which changed since it was last styled.
+
+use crate::dom::ElementData;
+use crate::style::computed::ComputedStyle;
+use crate::style::css::{PropertyMap, PseudoElement, Selector, SimpleSelector};
+use crate::style::values::LengthContext;
+use std::collections::{HashMap, HashSet};
+use std::ops::BitOrAssign;
+use std::sync::Arc;
+
+/// The style of an element, and of its pseudo-elements.
+#[derive(Debug, Clone)]
+pub struct ElementStyle {
+    pub specified: PropertyMap,
+    pub style: Arc<ComputedStyle>,
+    /// The specified and computed styles of each pseudo-element which has declarations.
+    pub pseudo: HashMap<PseudoElement, (PropertyMap, Arc<ComputedStyle>)>,
+}
+
+/// The style of a node when it was last styled, with the inputs it was computed from.
+#[derive(Debug)]
+pub struct StyleData {
+    pub style: ElementStyle,
+    /// The style of the node's parent, or None for the root.
+    pub parent: Option<Arc<ComputedStyle>>,
+    pub context: LengthContext,
+}
+
+impl StyleData {
+    /// Can the computed values be reused, when the node's parent has a style and context?
+    pub fn is_current(&self, parent: Option<&Arc<ComputedStyle>>, context: &LengthContext) -> bool {
+        let same_parent = match (&self.parent, parent) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b) || a == b,
+            (None, None) => true,
+            _ => false,
+        };
+        same_parent && self.context == *context
+    }
+}
+
+/// What has changed about a node since it was last styled, with the style it had.
+/// Every DOM node has one.
+#[derive(Debug, Default)]
+pub struct RestyleData {
+    /// The element as it was when it was last styled, if its attributes have changed since.
+    pub snapshot: Option<ElementData>,
+    /// Have children been inserted or removed since the node was last styled?
+    pub children_changed: bool,
+    /// The node's style, or None if it has never been styled.
+    pub data: Option<StyleData>,
+}
+
+impl RestyleData {
+    /// Records that an element's attributes are about to change.
+    pub fn attributes_changing(&mut self, elem: &ElementData) {
+        if self.data.is_some() && self.snapshot.is_none() {
+            self.snapshot = Some(elem.clone());
+        }
+    }
+
+    /// Records that the node has been styled, with its new style.
+    pub fn styled(&mut self, data: StyleData) {
+        *self = Self {
+            data: Some(data),
+            ..Self::default()
+        }
+    }
+}
+
+/// The elements a change to an element may restyle.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Invalidation {
+    /// The element itself.
+    pub element: bool,
+    /// Every element below it.
+    pub descendants: bool,
+    /// Its later siblings, and every element below them.
+    pub siblings: bool,
+}
+
+impl BitOrAssign for Invalidation {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.element |= rhs.element;
+        self.descendants |= rhs.descendants;
+        self.siblings |= rhs.siblings;
+    }
+}
+
+/// An id, class or attribute that a selector depends on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Feature {
+    Id(String),
+    Class(String),
+    Attribute(String),
+}
+
+/// The invalidation sets of every selector: which elements have to be restyled when an element's
+/// id, classes or attributes change.
+#[derive(Debug, Default)]
+pub struct InvalidationMap {
+    features: HashMap<Feature, Invalidation>,
+    /// Do any selectors use sibling combinators? If so, an element's style can depend on its
+    /// siblings, so changes to a list of children restyle every child.
+    pub sibling_combinators: bool,
+}
+
+impl InvalidationMap {
+    pub fn insert(&mut self, selector: &Selector) {
+        if let Selector::Complex(parts, _) = selector {
+            for (simple, combinator) in parts {
+                // A part followed by a sibling combinator matches one of the element's preceding
+                // siblings, (or of its ancestors). Any other part matches an ancestor.
+                let invalidation = Invalidation {
+                    descendants: !combinator.is_sibling(),
+                    siblings: combinator.is_sibling(),
+                    ..Default::default()
+                };
+                self.sibling_combinators |= combinator.is_sibling();
+                self.insert_simple(simple, invalidation);
+            }
+        }
+
+        let invalidation = Invalidation {
+            element: true,
+            ..Default::default()
+        };
+        self.insert_simple(selector.subject(), invalidation);
+    }
+
+    fn insert_simple(&mut self, selector: &SimpleSelector, invalidation: Invalidation) {
+        let ids = selector.id.iter().cloned().map(Feature::Id);
+        let classes = selector.class.iter().cloned().map(Feature::Class);
+        let attributes = selector
+            .attributes
+            .iter()
+            .map(|a| Feature::Attribute(a.name.clone()));
+
+        for feature in ids.chain(classes).chain(attributes) {
+            *self.features.entry(feature).or_default() |= invalidation;
+        }
+    }
+
+    /// Returns the elements restyled by an element changing from `old` to `new`.
+    pub fn invalidation(&self, old: &ElementData, new: &ElementData) -> Invalidation {
+        let mut invalidation = Invalidation::default();
+
+        // Style attributes hold declarations for the element itself.
+        if old.attr("style") != new.attr("style") {
+            invalidation.element = true;
+        }
+
+        let mut features = Vec::new();
+        if old.id() != new.id() {
+            let ids = old.id().into_iter().chain(new.id());
+            features.extend(ids.map(|id| Feature::Id(id.clone())));
+        }
+
+        let (old_classes, new_classes) = (old.classes(), new.classes());
+        let classes = old_classes.symmetric_difference(&new_classes);
+        features.extend(classes.map(|class| Feature::Class(class.to_string())));
+
+        let names: HashSet<&String> = old
+            .attributes()
+            .keys()
+            .chain(new.attributes().keys())
+            .collect();
+        let changed = names
+            .into_iter()
+            .filter(|name| old.attr(name) != new.attr(name));
+        features.extend(changed.map(|name| Feature::Attribute(name.clone())));
+
+        for feature in &features {
+            if let Some(i) = self.features.get(feature) {
+                invalidation |= *i;
+            }
+        }
+        invalidation
+    }
+}
@@ -0,0 +1,611 @@
+// Serializing stylesheets back into css text.
+// https://www.w3.org/TR/cssom-1/#serializing-css-values
+
+use crate::style::css::{
+    AttributeOperator, AttributeSelector, Colour, Combinator, Declaration, FontFace, FontSource,
+    Import, Keyframes, PageRule, PseudoElement, Rule, Selector, SimpleSelector, Stylesheet, Token,
+    TokenList, Unit, Value,
+};
+use crate::style::media::{Comparison, MediaCondition, MediaFeature, MediaQuery, MediaQueryList};
+use crate::style::values::CalcExpr;
+use std::fmt::{self, Display, Formatter, Write};
+
+/// Writes items separated by `separator`.
+fn write_joined<T: Display>(f: &mut Formatter<'_>, items: &[T], separator: &str) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            f.write_str(separator)?;
+        }
+        write!(f, "{item}")?;
+    }
+    Ok(())
+}
+
+/// Writes a quoted string, escaping quotes and backslashes.
+fn write_string(f: &mut impl Write, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => write!(f, "\\{c}")?,
+            '\n' => f.write_str("\\a ")?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+/// Writes a name as an identifier if it can be written as one, otherwise as a string.
+/// Eg: The name of @keyframes
+fn write_name(f: &mut Formatter<'_>, name: &str) -> fmt::Result {
+    let identifier = name.chars().next().is_some_and(|c| !c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_') || !c.is_ascii());
+    if identifier {
+        f.write_str(name)
+    } else {
+        write_string(f, name)
+    }
+}
+
+impl Display for Stylesheet {
+    /// Writes the stylesheet as css. Rules are wrapped in the @layer and @media rules they're in,
+    /// with neighbouring rules in the same ones sharing them.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for import in &self.imports {
+            writeln!(f, "{import}")?;
+        }
+
+        // Declares the order of the layers, before any rules can change it.
+        let mut layers: Vec<&str> = Vec::new();
+        for layer in &self.layers {
+            if !is_anonymous(layer) && !layers.contains(&layer.as_str()) {
+                layers.push(layer);
+            }
+        }
+        if !layers.is_empty() {
+            writeln!(f, "@layer {};", layers.join(", "))?;
+        }
+
+        for font_face in &self.font_faces {
+            write_group(f, &font_face.layer, &font_face.media, &[font_face])?;
+        }
+
+        let mut rules = self.rules.iter().peekable();
+        while let Some(first) = rules.next() {
+            let mut group = vec![first];
+            while let Some(rule) =
+                rules.next_if(|r| r.layer == first.layer && r.media == first.media)
+            {
+                group.push(rule);
+            }
+            write_group(f, &first.layer, &first.media, &group)?;
+        }
+
+        for page in &self.pages {
+            write_group(f, &page.layer, &page.media, &[page])?;
+        }
+        for keyframes in &self.keyframes {
+            write_group(f, &keyframes.layer, &keyframes.media, &[keyframes])?;
+        }
+        Ok(())
+    }
+}
+
+/// Is a layer name made for a layer which wasn't named? Eg: @layer { ... }
+fn is_anonymous(layer: &str) -> bool {
+    layer.starts_with('<')
+}
+
+/// Writes rules which are in the same layer and media, inside the at-rules for them.
+fn write_group(
+    f: &mut Formatter<'_>,
+    layer: &Option<String>,
+    media: &[MediaQueryList],
+    rules: &[&impl Display],
+) -> fmt::Result {
+    let layers: Vec<&str> = layer.iter().flat_map(|l| l.split('.')).collect();
+
+    let mut depth = 0;
+    for layer in &layers {
+        // Anonymous layers are written without a name, in place of their generated one.
+        match is_anonymous(layer) {
+            true => writeln!(f, "{}@layer {{", "  ".repeat(depth))?,
+            false => writeln!(f, "{}@layer {layer} {{", "  ".repeat(depth))?,
+        }
+        depth += 1;
+    }
+    for media in media {
+        writeln!(f, "{}@media {media} {{", "  ".repeat(depth))?;
+        depth += 1;
+    }
+
+    for rule in rules {
+        writeln!(f, "{}{rule}", "  ".repeat(depth))?;
+    }
+
+    while depth > 0 {
+        depth -= 1;
+        writeln!(f, "{}}}", "  ".repeat(depth))?;
+    }
+    Ok(())
+}
+
+impl Display for Rule {
+    /// Writes the rule's selectors and declarations, without the at-rules it is in.
+    /// Eg: div > p { color: red; }
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_joined(f, &self.selectors, ", ")?;
+        match self.declarations.is_empty() {
+            true => f.write_str(" { }"),
+            false => write!(f, " {{ {} }}", declarations_text(&self.declarations)),
+        }
+    }
+}
+
+/// Returns the text of a block of declarations. The longhands of a shorthand which is waiting
+/// for var() to be substituted are written as the shorthand. Eg: color: red; margin: 0;
+pub fn declarations_text(declarations: &[Declaration]) -> String {
+    let mut text = String::new();
+    let mut written_shorthands: Vec<(&str, &TokenList)> = Vec::new();
+
+    for declaration in declarations {
+        if let Value::Unparsed(tokens, Some(shorthand)) = &declaration.value {
+            if written_shorthands.contains(&(shorthand.as_str(), tokens)) {
+                continue;
+            }
+            written_shorthands.push((shorthand, tokens));
+            let important = if declaration.important {
+                " !important"
+            } else {
+                ""
+            };
+            let _ = write!(text, "{shorthand}: {tokens}{important}; ");
+            continue;
+        }
+        let _ = write!(text, "{declaration}; ");
+    }
+
+    text.pop();
+    text
+}
+
+impl Display for Declaration {
+    /// Eg: margin-top: 1em !important
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.value)?;
+        if self.important {
+            f.write_str(" !important")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Selector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Selector::Complex(parts, _) = self {
+            for (simple, combinator) in parts {
+                write!(f, "{simple}{combinator}")?;
+            }
+        }
+        write!(f, "{}", self.subject())
+    }
+}
+
+impl Display for Combinator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Combinator::Descendant => " ",
+            Combinator::Child => " > ",
+            Combinator::NextSibling => " + ",
+            Combinator::SubsequentSibling => " ~ ",
+        })
+    }
+}
+
+impl Display for SimpleSelector {
+    /// Eg: a#home.nav[target]::before
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut empty = true;
+        if let Some(tag) = &self.tag_name {
+            f.write_str(tag)?;
+            empty = false;
+        }
+        if let Some(id) = &self.id {
+            write!(f, "#{id}")?;
+            empty = false;
+        }
+        for class in &self.class {
+            write!(f, ".{class}")?;
+            empty = false;
+        }
+        for attribute in &self.attributes {
+            write!(f, "{attribute}")?;
+            empty = false;
+        }
+
+        // A selector matching every element is written as the universal selector.
+        match self.pseudo_element {
+            Some(pseudo) => write!(f, "::{}", pseudo.name()),
+            None if empty => f.write_str("*"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Display for AttributeSelector {
+    /// Eg: [type="checkbox" i]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}", self.name)?;
+        if let Some((operator, value)) = &self.matcher {
+            f.write_str(operator.symbol())?;
+            write_string(f, value)?;
+            if self.case_insensitive {
+                f.write_str(" i")?;
+            }
+        }
+        f.write_str("]")
+    }
+}
+
+impl AttributeOperator {
+    /// Returns how the operator is written. Eg: ~=
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Self::Equals => "=",
+            Self::Includes => "~=",
+            Self::DashMatch => "|=",
+            Self::Prefix => "^=",
+            Self::Suffix => "$=",
+            Self::Substring => "*=",
+        }
+    }
+}
+
+impl PseudoElement {
+    /// Returns the name of the pseudo-element, without colons. Eg: first-line
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Before => "before",
+            Self::After => "after",
+            Self::FirstLine => "first-line",
+            Self::FirstLetter => "first-letter",
+            Self::Marker => "marker",
+            Self::Placeholder => "placeholder",
+            Self::Selection => "selection",
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Keyword(k) => f.write_str(k),
+            Value::Length(n, unit) => write!(f, "{n}{}", unit.name()),
+            Value::Percentage(p) => write!(f, "{p}%"),
+            Value::Number(n) => write!(f, "{n}"),
+            Value::Angle(degrees) => write!(f, "{degrees}deg"),
+            Value::Resolution(dppx) => write!(f, "{dppx}dppx"),
+            Value::Flex(fr) => write!(f, "{fr}fr"),
+            Value::LineNames(names) => {
+                f.write_str("[")?;
+                write_joined(f, names, " ")?;
+                f.write_str("]")
+            }
+            Value::Calc(expr) => write!(f, "{expr}"),
+            Value::Colour(colour) => write!(f, "{colour}"),
+            Value::Str(s) => write_string(f, s),
+            Value::Function(name, args) if name == "url" => match args.as_slice() {
+                [Value::Str(url)] => {
+                    f.write_str("url(")?;
+                    write_string(f, url)?;
+                    f.write_str(")")
+                }
+                _ => f.write_str("url()"),
+            },
+            Value::Function(name, args) => {
+                write!(f, "{name}(")?;
+                write_joined(f, args, ", ")?;
+                f.write_str(")")
+            }
+            Value::List(values) => write_joined(f, values, " "),
+            Value::CommaList(values) => write_joined(f, values, ", "),
+            Value::Slash => f.write_str("/"),
+            Value::Tokens(tokens) | Value::Unparsed(tokens, _) => write!(f, "{tokens}"),
+        }
+    }
+}
+
+impl Unit {
+    /// Returns how the unit is written. Eg: px
+    pub fn name(&self) -> &'static str {
+        match self {
+            Unit::Px => "px",
+            Unit::Pt => "pt",
+            Unit::Pc => "pc",
+            Unit::In => "in",
+            Unit::Cm => "cm",
+            Unit::Mm => "mm",
+            Unit::Q => "q",
+            Unit::Em => "em",
+            Unit::Rem => "rem",
+            Unit::Ex => "ex",
+            Unit::Ch => "ch",
+            Unit::Vw => "vw",
+            Unit::Vh => "vh",
+            Unit::Vmin => "vmin",
+            Unit::Vmax => "vmax",
+        }
+    }
+}
+
+impl Display for Colour {
+    /// Eg: rgb(51, 102, 153), rgba(0, 0, 0, 0.5)
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let Colour { r, g, b, a } = *self;
+        if a == 255 {
+            return write!(f, "rgb({r}, {g}, {b})");
+        }
+
+        // The alpha is written with the fewest decimal places, (2 or 3), that give the same value.
+        let alpha = a as f32 / 255.0;
+        let rounded = (alpha * 100.0).round() / 100.0;
+        let alpha = match (rounded * 255.0).round() as u8 == a {
+            true => rounded,
+            false => (alpha * 1000.0).round() / 1000.0,
+        };
+        write!(f, "rgba({r}, {g}, {b}, {alpha})")
+    }
+}
+
+impl Display for CalcExpr {
+    /// Eg: calc(100% - 2 * 1em), min(10px, 5vw)
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CalcExpr::Min(_) | CalcExpr::Max(_) | CalcExpr::Clamp(..) => write_calc(f, self, false),
+            expr => {
+                f.write_str("calc(")?;
+                write_calc(f, expr, false)?;
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+/// Writes a math expression without calc() around it. Sums within a product are `nested` in
+/// parentheses.
+fn write_calc(f: &mut Formatter<'_>, expr: &CalcExpr, nested: bool) -> fmt::Result {
+    let args = |f: &mut Formatter<'_>, name: &str, args: &[&CalcExpr]| {
+        write!(f, "{name}(")?;
+        for (i, arg) in args.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write_calc(f, arg, false)?;
+        }
+        f.write_str(")")
+    };
+    let sum = |f: &mut Formatter<'_>, a, operator, b| {
+        if nested {
+            f.write_str("(")?;
+        }
+        write_calc(f, a, false)?;
+        write!(f, " {operator} ")?;
+        write_calc(f, b, true)?;
+        if nested {
+            f.write_str(")")?;
+        }
+        Ok(())
+    };
+    let product = |f: &mut Formatter<'_>, a, operator, b| {
+        write_calc(f, a, true)?;
+        write!(f, " {operator} ")?;
+        write_calc(f, b, true)
+    };
+
+    match expr {
+        CalcExpr::Leaf(Value::Calc(inner)) => write_calc(f, inner, nested),
+        CalcExpr::Leaf(v) => write!(f, "{v}"),
+        CalcExpr::Sum(a, b) => sum(f, a, '+', b),
+        CalcExpr::Difference(a, b) => sum(f, a, '-', b),
+        CalcExpr::Product(a, b) => product(f, a, '*', b),
+        CalcExpr::Quotient(a, b) => product(f, a, '/', b),
+        CalcExpr::Min(values) => args(f, "min", &values.iter().collect::<Vec<_>>()),
+        CalcExpr::Max(values) => args(f, "max", &values.iter().collect::<Vec<_>>()),
+        CalcExpr::Clamp(min, value, max) => args(f, "clamp", &[min, value, max]),
+    }
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Whitespace => f.write_str(" "),
+            Token::Ident(s) | Token::Number(s) => f.write_str(s),
+            Token::Function(name) => write!(f, "{name}("),
+            Token::Hash(s) => write!(f, "#{s}"),
+            Token::Str(s) => write_string(f, s),
+            Token::Url(url) => write!(f, "url({url})"),
+            Token::Delim(c) => write!(f, "{c}"),
+        }
+    }
+}
+
+impl Display for TokenList {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|t| write!(f, "{t}"))
+    }
+}
+
+impl Display for MediaQueryList {
+    /// Eg: screen, (orientation: portrait)
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_joined(f, &self.0, ", ")
+    }
+}
+
+impl Display for MediaQuery {
+    /// Eg: not print and (min-width: 40em)
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.negated {
+            f.write_str("not ")?;
+        }
+        match (&self.media_type, &self.condition) {
+            (Some(media_type), Some(condition)) => write!(f, "{media_type} and {condition}"),
+            (Some(media_type), None) => f.write_str(media_type),
+            (None, Some(condition)) => write!(f, "{condition}"),
+            (None, None) => f.write_str("all"),
+        }
+    }
+}
+
+impl Display for MediaCondition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // Conditions joined by `and` or `or` are wrapped in parentheses when nested.
+        let nested = |f: &mut Formatter<'_>, c: &MediaCondition| match c {
+            MediaCondition::And(_) | MediaCondition::Or(_) | MediaCondition::Not(_) => {
+                write!(f, "({c})")
+            }
+            MediaCondition::Feature(_) => write!(f, "{c}"),
+        };
+        let joined = |f: &mut Formatter<'_>, conditions: &[MediaCondition], keyword| {
+            for (i, c) in conditions.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " {keyword} ")?;
+                }
+                nested(f, c)?;
+            }
+            Ok(())
+        };
+
+        match self {
+            MediaCondition::Feature(feature) => write!(f, "{feature}"),
+            MediaCondition::Not(c) => {
+                f.write_str("not ")?;
+                nested(f, c)
+            }
+            MediaCondition::And(conditions) => joined(f, conditions, "and"),
+            MediaCondition::Or(conditions) => joined(f, conditions, "or"),
+        }
+    }
+}
+
+impl Display for MediaFeature {
+    /// Eg: (color), (orientation: portrait), (width >= 40em)
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MediaFeature::Boolean(name) => write!(f, "({name})"),
+            MediaFeature::Range(name, Comparison::Equal, value) => write!(f, "({name}: {value})"),
+            MediaFeature::Range(name, comparison, value) => {
+                let symbol = match comparison {
+                    Comparison::Less => "<",
+                    Comparison::LessOrEqual => "<=",
+                    Comparison::Equal => "=",
+                    Comparison::GreaterOrEqual => ">=",
+                    Comparison::Greater => ">",
+                };
+                write!(f, "({name} {symbol} {value})")
+            }
+        }
+    }
+}
+
+impl Display for Import {
+    /// Eg: @import url("theme.css") layer(theme) screen;
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("@import url(")?;
+        write_string(f, &self.url)?;
+        f.write_str(")")?;
+        match &self.layer {
+            Some(layer) if is_anonymous(layer) => f.write_str(" layer")?,
+            Some(layer) => write!(f, " layer({layer})")?,
+            None => {}
+        }
+        if let Some(media) = &self.media {
+            write!(f, " {media}")?;
+        }
+        f.write_str(";")
+    }
+}
+
+impl Display for FontFace {
+    /// Eg: @font-face { font-family: "Lato"; src: url("lato.woff2") format("woff2"); }
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("@font-face { font-family: ")?;
+        write_string(f, &self.family)?;
+        f.write_str("; src: ")?;
+        write_joined(f, &self.sources, ", ")?;
+        f.write_str(";")?;
+
+        // Descriptors are sorted by name, so the same rule is always written the same way.
+        let mut descriptors: Vec<_> = self.descriptors.iter().collect();
+        descriptors.sort_by_key(|(name, _)| *name);
+        for (name, value) in descriptors {
+            write!(f, " {name}: {value};")?;
+        }
+        f.write_str(" }")
+    }
+}
+
+impl Display for FontSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FontSource::Url(url, format) => {
+                f.write_str("url(")?;
+                write_string(f, url)?;
+                f.write_str(")")?;
+                if let Some(format) = format {
+                    f.write_str(" format(")?;
+                    write_string(f, format)?;
+                    f.write_str(")")?;
+                }
+                Ok(())
+            }
+            FontSource::Local(name) => {
+                f.write_str("local(")?;
+                write_string(f, name)?;
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+impl Display for PageRule {
+    /// Eg: @page :first { margin: 1in; @top-center { content: "Title"; } }
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("@page ")?;
+        if !self.selectors.is_empty() {
+            write!(f, "{} ", self.selectors.join(", "))?;
+        }
+        f.write_str("{")?;
+        for declaration in &self.declarations {
+            write!(f, " {declaration};")?;
+        }
+        for (name, declarations) in &self.margin_rules {
+            write!(f, " @{name} {{ {} }}", declarations_text(declarations))?;
+        }
+        f.write_str(" }")
+    }
+}
+
+impl Display for Keyframes {
+    /// Eg: @keyframes fade { 0% { opacity: 0; } 100% { opacity: 1; } }
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("@keyframes ")?;
+        write_name(f, &self.name)?;
+        f.write_str(" {")?;
+        for frame in &self.frames {
+            let offsets: Vec<String> = frame
+                .offsets
+                .iter()
+                .map(|o| format!("{}%", o * 100.0))
+                .collect();
+            write!(
+                f,
+                " {} {{ {} }}",
+                offsets.join(", "),
+                declarations_text(&frame.declarations)
+            )?;
+        }
+        f.write_str(" }")
+    }
+}
@@ -0,0 +1,792 @@
+// Expanding shorthand properties into their longhands.
+// https://www.w3.org/TR/CSS2/about.html#shorthand
+
+use crate::style::css::{Declaration, Value};
+use crate::style::properties::{initial_value, CSS_WIDE_KEYWORDS};
+
+/// The sides of a box, in the order the 1 to 4 value syntax lists them.
+const SIDES: [&str; 4] = ["top", "right", "bottom", "left"];
+
+fn keyword(k: &str) -> Value {
+    Value::Keyword(k.to_string())
+}
+
+/// Returns the initial value of a longhand.
+fn initial(name: &str) -> Value {
+    initial_value(name).unwrap_or_else(|| panic!("{name} isn't a registered property"))
+}
+
+fn is_keyword(v: &Value, keywords: &[&str]) -> bool {
+    matches!(v, Value::Keyword(k) if keywords.contains(&k.to_lowercase().as_str()))
+}
+
+/// Is a value a length or a percentage? Only zero may be written without a unit.
+fn is_length(v: &Value) -> bool {
+    match v {
+        Value::Length(..) | Value::Percentage(_) | Value::Calc(_) => true,
+        Value::Number(n) => *n == 0.0,
+        _ => false,
+    }
+}
+
+fn is_colour(v: &Value) -> bool {
+    v.to_colour(None).is_some() || is_keyword(v, &["currentcolor"])
+}
+
+/// Splits components around the slashes between them.
+fn split_slash(values: &[Value]) -> Vec<&[Value]> {
+    values.split(|v| *v == Value::Slash).collect()
+}
+
+/// Joins components back into a single value.
+fn join(values: &[Value]) -> Value {
+    match values {
+        [v] => v.clone(),
+        vs => Value::List(vs.to_vec()),
+    }
+}
+
+/// Returns the Some declarations that a shorthand expands into, or None if `name` isn't a shorthand.
+/// Invalid shorthands expand into no declarations, so they are dropped.
+pub fn expand_shorthand(name: &str, value: &Value) -> Option<Vec<Declaration>> {
+    let longhands = longhands(name)?;
+
+    // A CSS-wide keyword sets every longhand to that keyword.
+    if is_keyword(value, &CSS_WIDE_KEYWORDS) {
+        return Some(
+            longhands
+                .into_iter()
+                .map(|name| Declaration {
+                    name,
+                    value: value.clone(),
+                    important: false,
+                })
+                .collect(),
+        );
+    }
+
+    // A value with var() can't be expanded until it is substituted, so every longhand waits.
+    if let Value::Unparsed(tokens, None) = value {
+        return Some(
+            longhands
+                .into_iter()
+                .map(|longhand| Declaration {
+                    name: longhand,
+                    value: Value::Unparsed(tokens.clone(), Some(name.to_string())),
+                    important: false,
+                })
+                .collect(),
+        );
+    }
+
+    let c = value.components();
+    let expanded = match name {
+        "margin" | "padding" | "inset" => four_sides(&longhands, c),
+        "border-width" | "border-style" | "border-color" => four_sides(&longhands, c),
+        "border" | "border-top" | "border-right" | "border-bottom" | "border-left" | "outline" => {
+            border(&longhands, c)
+        }
+        "background" => background(c),
+        "font" => font(value),
+        "list-style" => list_style(c),
+        "flex" => flex(c),
+        "flex-flow" => flex_flow(c),
+        "gap" | "grid-gap" | "overflow" => pair(&longhands, c),
+        "place-content" | "place-items" | "place-self" => place(&longhands, c),
+        "grid-row" | "grid-column" => grid_line(&longhands, c),
+        "grid-area" => grid_area(c),
+        "grid-template" => grid_template(c),
+        "grid" => grid(c),
+        _ => None,
+    };
+
+    let expanded = expanded.unwrap_or_default();
+    Some(
+        expanded
+            .into_iter()
+            .map(|(name, value)| Declaration {
+                name,
+                value,
+                important: false,
+            })
+            .collect(),
+    )
+}
+
+/// Returns the Some longhands set by a shorthand, or None if `name` isn't a shorthand.
+pub fn longhands(name: &str) -> Option<Vec<String>> {
+    let sides = |f: &dyn Fn(&str) -> String| SIDES.iter().map(|s| f(s)).collect::<Vec<_>>();
+
+    let names = match name {
+        "margin" | "padding" => sides(&|s| format!("{name}-{s}")),
+        "inset" => sides(&|s| s.to_string()),
+        "border-width" | "border-style" | "border-color" => {
+            let kind = &name["border-".len()..];
+            sides(&|s| format!("border-{s}-{kind}"))
+        }
+        "border" => SIDES
+            .iter()
+            .flat_map(|s| ["width", "style", "color"].map(|k| format!("border-{s}-{k}")))
+            .collect(),
+        "border-top" | "border-right" | "border-bottom" | "border-left" => {
+            ["width", "style", "color"]
+                .iter()
+                .map(|k| format!("{name}-{k}"))
+                .collect()
+        }
+        "outline" => vec![
+            "outline-width".into(),
+            "outline-style".into(),
+            "outline-color".into(),
+        ],
+        "background" => [
+            "background-color",
+            "background-image",
+            "background-repeat",
+            "background-attachment",
+            "background-position",
+            "background-size",
+            "background-origin",
+            "background-clip",
+        ]
+        .map(String::from)
+        .to_vec(),
+        "font" => [
+            "font-style",
+            "font-variant",
+            "font-weight",
+            "font-stretch",
+            "font-size",
+            "line-height",
+            "font-family",
+        ]
+        .map(String::from)
+        .to_vec(),
+        "list-style" => ["list-style-type", "list-style-position", "list-style-image"]
+            .map(String::from)
+            .to_vec(),
+        "flex" => ["flex-grow", "flex-shrink", "flex-basis"]
+            .map(String::from)
+            .to_vec(),
+        "flex-flow" => ["flex-direction", "flex-wrap"].map(String::from).to_vec(),
+        "gap" | "grid-gap" => ["row-gap", "column-gap"].map(String::from).to_vec(),
+        "overflow" => ["overflow-x", "overflow-y"].map(String::from).to_vec(),
+        "place-content" | "place-items" | "place-self" => {
+            let kind = &name["place-".len()..];
+            vec![format!("align-{kind}"), format!("justify-{kind}")]
+        }
+        "grid-row" | "grid-column" => vec![format!("{name}-start"), format!("{name}-end")],
+        "grid-area" => [
+            "grid-row-start",
+            "grid-column-start",
+            "grid-row-end",
+            "grid-column-end",
+        ]
+        .map(String::from)
+        .to_vec(),
+        "grid-template" => [
+            "grid-template-rows",
+            "grid-template-columns",
+            "grid-template-areas",
+        ]
+        .map(String::from)
+        .to_vec(),
+        "grid" => [
+            "grid-template-rows",
+            "grid-template-columns",
+            "grid-template-areas",
+            "grid-auto-rows",
+            "grid-auto-columns",
+            "grid-auto-flow",
+        ]
+        .map(String::from)
+        .to_vec(),
+        _ => return None,
+    };
+
+    Some(names)
+}
+
+type Longhands = Vec<(String, Value)>;
+
+/// Expands the 1 to 4 value syntax, Eg: `margin: 10px 20px`, into the top, right, bottom and left.
+fn four_sides(longhands: &[String], c: &[Value]) -> Option<Longhands> {
+    let [top, right, bottom, left] = match c {
+        [a] => [a, a, a, a],
+        [a, b] => [a, b, a, b],
+        [a, b, c] => [a, b, c, b],
+        [a, b, c, d] => [a, b, c, d],
+        _ => return None,
+    };
+
+    Some(
+        longhands
+            .iter()
+            .cloned()
+            .zip([top, right, bottom, left].map(Value::clone))
+            .collect(),
+    )
+}
+
+/// Expands a width, style and colour in any order, Eg: `border: 1px solid #000`.
+fn border(longhands: &[String], c: &[Value]) -> Option<Longhands> {
+    const STYLES: [&str; 10] = [
+        "none", "hidden", "dotted", "dashed", "solid", "double", "groove", "ridge", "inset",
+        "outset",
+    ];
+
+    let (mut width, mut style, mut colour) = (None, None, None);
+    for v in c {
+        if width.is_none() && (is_length(v) || is_keyword(v, &["thin", "medium", "thick"])) {
+            width = Some(v.clone());
+        } else if style.is_none() && is_keyword(v, &STYLES) {
+            style = Some(v.clone());
+        } else if colour.is_none() && is_colour(v) {
+            colour = Some(v.clone());
+        } else {
+            return None;
+        }
+    }
+
+    let width = width.unwrap_or_else(|| initial("border-top-width"));
+    let style = style.unwrap_or_else(|| initial("border-top-style"));
+    let colour = colour.unwrap_or_else(|| initial("border-top-color"));
+
+    Some(
+        longhands
+            .iter()
+            .map(|name| {
+                let value = if name.ends_with("width") {
+                    width.clone()
+                } else if name.ends_with("style") {
+                    style.clone()
+                } else {
+                    colour.clone()
+                };
+                (name.clone(), value)
+            })
+            .collect(),
+    )
+}
+
+/// Expands a single background layer, Eg: `background: #fff url(a.png) no-repeat center / cover`.
+fn background(c: &[Value]) -> Option<Longhands> {
+    const REPEATS: [&str; 6] = [
+        "repeat",
+        "repeat-x",
+        "repeat-y",
+        "no-repeat",
+        "space",
+        "round",
+    ];
+    const POSITIONS: [&str; 5] = ["left", "center", "right", "top", "bottom"];
+    const BOXES: [&str; 3] = ["border-box", "padding-box", "content-box"];
+
+    let mut colour = None;
+    let mut image = None;
+    let mut repeat = Vec::new();
+    let mut attachment = None;
+    let mut position = Vec::new();
+    let mut size = Vec::new();
+    let mut boxes = Vec::new();
+
+    let parts = split_slash(c);
+    let (before, after) = match parts.as_slice() {
+        [before] => (*before, &[][..]),
+        // The size follows the position, after a slash.
+        [before, after] => (*before, *after),
+        _ => return None,
+    };
+
+    for v in before {
+        let is_image = matches!(v, Value::Function(f, _) if f == "url" || f.ends_with("gradient"));
+
+        if image.is_none() && (is_image || is_keyword(v, &["none"])) {
+            image = Some(v.clone());
+        } else if is_keyword(v, &REPEATS) {
+            repeat.push(v.clone());
+        } else if attachment.is_none() && is_keyword(v, &["scroll", "fixed", "local"]) {
+            attachment = Some(v.clone());
+        } else if is_keyword(v, &BOXES) {
+            boxes.push(v.clone());
+        } else if is_length(v) || is_keyword(v, &POSITIONS) {
+            position.push(v.clone());
+        } else if colour.is_none() && is_colour(v) {
+            colour = Some(v.clone());
+        } else {
+            return None;
+        }
+    }
+
+    if !after.is_empty() {
+        if position.is_empty() {
+            return None;
+        }
+        size.extend(after.iter().cloned());
+    }
+
+    // A single box keyword sets both the origin and the clip.
+    let (origin, clip) = match boxes.as_slice() {
+        [] => (keyword("padding-box"), keyword("border-box")),
+        [b] => (b.clone(), b.clone()),
+        [o, c] => (o.clone(), c.clone()),
+        _ => return None,
+    };
+    let or_default = |v: Vec<Value>, default: Value| if v.is_empty() { default } else { join(&v) };
+
+    Some(vec![
+        (
+            "background-color".into(),
+            colour.unwrap_or_else(|| initial("background-color")),
+        ),
+        (
+            "background-image".into(),
+            image.unwrap_or_else(|| initial("background-image")),
+        ),
+        (
+            "background-repeat".into(),
+            or_default(repeat, initial("background-repeat")),
+        ),
+        (
+            "background-attachment".into(),
+            attachment.unwrap_or_else(|| initial("background-attachment")),
+        ),
+        (
+            "background-position".into(),
+            or_default(
+                position,
+                Value::List(vec![Value::Percentage(0.0), Value::Percentage(0.0)]),
+            ),
+        ),
+        (
+            "background-size".into(),
+            or_default(size, initial("background-size")),
+        ),
+        ("background-origin".into(), origin),
+        ("background-clip".into(), clip),
+    ])
+}
+
+/// Expands `font: [style variant weight stretch] size[/line-height] family`.
+fn font(value: &Value) -> Option<Longhands> {
+    const SIZES: [&str; 10] = [
+        "xx-small",
+        "x-small",
+        "small",
+        "medium",
+        "large",
+        "x-large",
+        "xx-large",
+        "xxx-large",
+        "smaller",
+        "larger",
+    ];
+    const STRETCHES: [&str; 8] = [
+        "ultra-condensed",
+        "extra-condensed",
+        "condensed",
+        "semi-condensed",
+        "semi-expanded",
+        "expanded",
+        "extra-expanded",
+        "ultra-expanded",
+    ];
+
+    // Font families are comma separated, and the rest of the shorthand precedes the first family.
+    let (first, other_families) = match value {
+        Value::CommaList(values) => (values.first()?, &values[1..]),
+        v => (v, &[][..]),
+    };
+    let c = first.components();
+
+    let (mut style, mut variant, mut weight, mut stretch) = (None, None, None, None);
+    let mut i = 0;
+
+    // Everything before the size is optional, and `normal` can reset any of them.
+    while let Some(v) = c.get(i) {
+        if is_keyword(v, &["normal"]) {
+        } else if style.is_none() && is_keyword(v, &["italic", "oblique"]) {
+            style = Some(v.clone());
+        } else if variant.is_none() && is_keyword(v, &["small-caps"]) {
+            variant = Some(v.clone());
+        } else if weight.is_none()
+            && (is_keyword(v, &["bold", "bolder", "lighter"]) || matches!(v, Value::Number(_)))
+        {
+            weight = Some(v.clone());
+        } else if stretch.is_none() && is_keyword(v, &STRETCHES) {
+            stretch = Some(v.clone());
+        } else {
+            break;
+        }
+        i += 1;
+    }
+
+    let size = c.get(i).filter(|v| is_length(v) || is_keyword(v, &SIZES))?;
+    i += 1;
+
+    let mut line_height = initial("line-height");
+    if c.get(i) == Some(&Value::Slash) {
+        line_height = c.get(i + 1)?.clone();
+        i += 2;
+    }
+
+    // The rest of the first component is the first family, which must be present.
+    let first_family = c.get(i..).filter(|f| !f.is_empty())?;
+    let family = if other_families.is_empty() {
+        join(first_family)
+    } else {
+        let mut families = vec![join(first_family)];
+        families.extend(other_families.iter().cloned());
+        Value::CommaList(families)
+    };
+
+    let or_initial = |v: Option<Value>, name: &str| v.unwrap_or_else(|| initial(name));
+    Some(vec![
+        ("font-style".into(), or_initial(style, "font-style")),
+        ("font-variant".into(), or_initial(variant, "font-variant")),
+        ("font-weight".into(), or_initial(weight, "font-weight")),
+        ("font-stretch".into(), or_initial(stretch, "font-stretch")),
+        ("font-size".into(), size.clone()),
+        ("line-height".into(), line_height),
+        ("font-family".into(), family),
+    ])
+}
+
+/// Expands a list style type, position and image in any order, Eg: `list-style: square inside`.
+fn list_style(c: &[Value]) -> Option<Longhands> {
+    let (mut kind, mut position, mut image) = (None, None, None);
+    let mut nones = 0;
+
+    for v in c {
+        if is_keyword(v, &["none"]) {
+            // `none` could be either the type or the image, so is resolved after the others.
+            nones += 1;
+        } else if position.is_none() && is_keyword(v, &["inside", "outside"]) {
+            position = Some(v.clone());
+        } else if image.is_none() && matches!(v, Value::Function(f, _) if f == "url") {
+            image = Some(v.clone());
+        } else if kind.is_none() && matches!(v, Value::Keyword(_) | Value::Str(_)) {
+            kind = Some(v.clone());
+        } else {
+            return None;
+        }
+    }
+
+    for _ in 0..nones {
+        if kind.is_none() {
+            kind = Some(keyword("none"));
+        } else if image.is_none() {
+            image = Some(keyword("none"));
+        } else {
+            return None;
+        }
+    }
+
+    Some(vec![
+        (
+            "list-style-type".into(),
+            kind.unwrap_or_else(|| initial("list-style-type")),
+        ),
+        (
+            "list-style-position".into(),
+            position.unwrap_or_else(|| initial("list-style-position")),
+        ),
+        (
+            "list-style-image".into(),
+            image.unwrap_or_else(|| initial("list-style-image")),
+        ),
+    ])
+}
+
+/// Expands `flex: grow [shrink] [basis]`, and its keywords.
+fn flex(c: &[Value]) -> Option<Longhands> {
+    let (grow, shrink, basis) = match c {
+        [v] if is_keyword(v, &["none"]) => (0.0, 0.0, keyword("auto")),
+        [v] if is_keyword(v, &["auto"]) => (1.0, 1.0, keyword("auto")),
+        // A lone number is the grow factor, with a basis of zero.
+        [Value::Number(g)] => (*g, 1.0, Value::Percentage(0.0)),
+        [Value::Number(g), Value::Number(s)] => (*g, *s, Value::Percentage(0.0)),
+        [Value::Number(g), Value::Number(s), b] => (*g, *s, b.clone()),
+        [Value::Number(g), b] => (*g, 1.0, b.clone()),
+        [b, Value::Number(g)] => (*g, 1.0, b.clone()),
+        [b, Value::Number(g), Value::Number(s)] => (*g, *s, b.clone()),
+        [b] => (1.0, 1.0, b.clone()),
+        _ => return None,
+    };
+
+    if !(is_length(&basis) || is_keyword(&basis, &["auto", "content"])) {
+        return None;
+    }
+
+    Some(vec![
+        ("flex-grow".into(), Value::Number(grow)),
+        ("flex-shrink".into(), Value::Number(shrink)),
+        ("flex-basis".into(), basis),
+    ])
+}
+
+/// Expands `flex-flow: direction wrap`, in either order.
+fn flex_flow(c: &[Value]) -> Option<Longhands> {
+    const DIRECTIONS: [&str; 4] = ["row", "row-reverse", "column", "column-reverse"];
+    const WRAPS: [&str; 3] = ["nowrap", "wrap", "wrap-reverse"];
+
+    let (mut direction, mut wrap) = (None, None);
+    for v in c {
+        if direction.is_none() && is_keyword(v, &DIRECTIONS) {
+            direction = Some(v.clone());
+        } else if wrap.is_none() && is_keyword(v, &WRAPS) {
+            wrap = Some(v.clone());
+        } else {
+            return None;
+        }
+    }
+
+    Some(vec![
+        (
+            "flex-direction".into(),
+            direction.unwrap_or_else(|| initial("flex-direction")),
+        ),
+        (
+            "flex-wrap".into(),
+            wrap.unwrap_or_else(|| initial("flex-wrap")),
+        ),
+    ])
+}
+
+/// Expands a shorthand for two longhands, where the second is the same as the first if it's
+/// omitted. Eg: `gap: row [column]`, `overflow: x [y]`
+fn pair(longhands: &[String], c: &[Value]) -> Option<Longhands> {
+    let (first, second) = match c {
+        [a] => (a, a),
+        [a, b] => (a, b),
+        _ => return None,
+    };
+
+    Some(vec![
+        (longhands[0].clone(), first.clone()),
+        (longhands[1].clone(), second.clone()),
+    ])
+}
+
+/// Expands `place-*: align [justify]`.
+fn place(longhands: &[String], c: &[Value]) -> Option<Longhands> {
+    let (align, justify) = match c {
+        [a] => (a.clone(), a.clone()),
+        [a, b] => (a.clone(), b.clone()),
+        // Baseline alignment is written as two keywords, Eg: first baseline.
+        [a, b, c] => (Value::List(vec![a.clone(), b.clone()]), c.clone()),
+        _ => return None,
+    };
+
+    Some(vec![
+        (longhands[0].clone(), align),
+        (longhands[1].clone(), justify),
+    ])
+}
+
+/// Returns the line a grid placement ends at when it's omitted.
+/// A named line is repeated, and anything else ends at auto.
+fn omitted_grid_line(start: &Value) -> Value {
+    match start {
+        Value::Keyword(k) if !CSS_WIDE_KEYWORDS.contains(&k.as_str()) && k != "auto" => {
+            start.clone()
+        }
+        _ => keyword("auto"),
+    }
+}
+
+/// Expands `grid-row` or `grid-column`, Eg: `grid-row: 1 / span 2`.
+fn grid_line(longhands: &[String], c: &[Value]) -> Option<Longhands> {
+    let (start, end) = match split_slash(c).as_slice() {
+        [s] => (join(s), omitted_grid_line(&join(s))),
+        [s, e] => (join(s), join(e)),
+        _ => return None,
+    };
+
+    Some(vec![
+        (longhands[0].clone(), start),
+        (longhands[1].clone(), end),
+    ])
+}
+
+/// Expands `grid-area: row-start / column-start / row-end / column-end`.
+fn grid_area(c: &[Value]) -> Option<Longhands> {
+    let parts: Vec<Value> = split_slash(c).into_iter().map(join).collect();
+    if parts.is_empty() || parts.len() > 4 {
+        return None;
+    }
+
+    let row_start = parts[0].clone();
+    let column_start = parts
+        .get(1)
+        .cloned()
+        .unwrap_or_else(|| omitted_grid_line(&row_start));
+    let row_end = parts
+        .get(2)
+        .cloned()
+        .unwrap_or_else(|| omitted_grid_line(&row_start));
+    let column_end = parts
+        .get(3)
+        .cloned()
+        .unwrap_or_else(|| omitted_grid_line(&column_start));
+
+    Some(vec![
+        ("grid-row-start".into(), row_start),
+        ("grid-column-start".into(), column_start),
+        ("grid-row-end".into(), row_end),
+        ("grid-column-end".into(), column_end),
+    ])
+}
+
+/// Expands `grid-template: rows / columns`, or the areas form: `"a a" 40px "b c" / 1fr 1fr`.
+fn grid_template(c: &[Value]) -> Option<Longhands> {
+    if is_keyword(c.first()?, &["none"]) && c.len() == 1 {
+        return Some(vec![
+            ("grid-template-rows".into(), keyword("none")),
+            ("grid-template-columns".into(), keyword("none")),
+            ("grid-template-areas".into(), keyword("none")),
+        ]);
+    }
+
+    let parts = split_slash(c);
+    let (rows, columns) = match parts.as_slice() {
+        [rows, columns] => (*rows, join(columns)),
+        [rows] if rows.iter().any(|v| matches!(v, Value::Str(_))) => (*rows, keyword("none")),
+        _ => return None,
+    };
+
+    if !rows.iter().any(|v| matches!(v, Value::Str(_))) {
+        return Some(vec![
+            ("grid-template-rows".into(), join(rows)),
+            ("grid-template-columns".into(), columns),
+            ("grid-template-areas".into(), keyword("none")),
+        ]);
+    }
+
+    // Each area string is a row, which may be followed by its size, and have line names before
+    // and after it. The names after a row and before the next are joined.
+    // Eg: [top] "a a" 40px [middle] "b b"
+    #[derive(PartialEq)]
+    enum Last {
+        Start,
+        NamesBefore,
+        Area,
+        Size,
+        NamesAfter,
+    }
+    let mut areas = Vec::new();
+    let mut sizes: Vec<Value> = Vec::new();
+    let mut last = Last::Start;
+    for v in rows {
+        last = match (v, last) {
+            (Value::Str(_), _) => {
+                areas.push(v.clone());
+                sizes.push(keyword("auto"));
+                Last::Area
+            }
+            (Value::LineNames(_), Last::Start) => {
+                sizes.push(v.clone());
+                Last::NamesBefore
+            }
+            (Value::LineNames(_), Last::Area | Last::Size) => {
+                sizes.push(v.clone());
+                Last::NamesAfter
+            }
+            (Value::LineNames(names), Last::NamesAfter) => {
+                if let Some(Value::LineNames(after)) = sizes.last_mut() {
+                    after.extend(names.iter().cloned());
+                }
+                Last::NamesBefore
+            }
+            (size, Last::Area) => {
+                *sizes.last_mut()? = size.clone();
+                Last::Size
+            }
+            _ => return None,
+        };
+    }
+    if last == Last::NamesBefore {
+        return None;
+    }
+
+    Some(vec![
+        ("grid-template-rows".into(), join(&sizes)),
+        ("grid-template-columns".into(), columns),
+        ("grid-template-areas".into(), join(&areas)),
+    ])
+}
+
+/// Expands `grid`, which is either a `grid-template`, or auto-flow rows or columns.
+/// Eg: `grid: auto-flow dense 40px / 1fr 1fr`
+fn grid(c: &[Value]) -> Option<Longhands> {
+    let reset = |names: &[&str]| -> Longhands {
+        names.iter().map(|n| (n.to_string(), initial(n))).collect()
+    };
+
+    let parts = split_slash(c);
+    let auto_flow = |side: &[Value]| side.iter().any(|v| is_keyword(v, &["auto-flow"]));
+    let (rows, columns) = match parts.as_slice() {
+        [rows, columns] => (*rows, *columns),
+        _ => {
+            let mut longhands = grid_template(c)?;
+            longhands.extend(reset(&[
+                "grid-auto-rows",
+                "grid-auto-columns",
+                "grid-auto-flow",
+            ]));
+            return Some(longhands);
+        }
+    };
+
+    // Splits `auto-flow [dense] sizes` into the flow and the track sizes.
+    let flow = |side: &[Value], direction: &str| {
+        let dense = side.iter().any(|v| is_keyword(v, &["dense"]));
+        let sizes: Vec<Value> = side
+            .iter()
+            .filter(|v| !is_keyword(v, &["auto-flow", "dense"]))
+            .cloned()
+            .collect();
+        let flow = if dense {
+            Value::List(vec![keyword(direction), keyword("dense")])
+        } else {
+            keyword(direction)
+        };
+        let sizes = if sizes.is_empty() {
+            keyword("auto")
+        } else {
+            join(&sizes)
+        };
+        (flow, sizes)
+    };
+
+    let mut longhands = reset(&["grid-template-areas"]);
+    if auto_flow(rows) {
+        let (flow, sizes) = flow(rows, "row");
+        longhands.extend([
+            ("grid-auto-flow".into(), flow),
+            ("grid-auto-rows".into(), sizes),
+            ("grid-auto-columns".into(), keyword("auto")),
+            ("grid-template-rows".into(), keyword("none")),
+            ("grid-template-columns".into(), join(columns)),
+        ]);
+    } else if auto_flow(columns) {
+        let (flow, sizes) = flow(columns, "column");
+        longhands.extend([
+            ("grid-auto-flow".into(), flow),
+            ("grid-auto-columns".into(), sizes),
+            ("grid-auto-rows".into(), keyword("auto")),
+            ("grid-template-columns".into(), keyword("none")),
+            ("grid-template-rows".into(), join(rows)),
+        ]);
+    } else {
+        let mut longhands = grid_template(c)?;
+        longhands.extend(reset(&[
+            "grid-auto-rows",
+            "grid-auto-columns",
+            "grid-auto-flow",
+        ]));
+        return Some(longhands);
+    }
+
+    Some(longhands)
+}
@@ -1,83 +1,685 @@
+use crate::box_model::Display;
 use crate::dom::{ElementData, Node, NodeType};
-use crate::style::css::{
-    MatchedRule, PropertyMap, Rule, Selector, SimpleSelector, StyledNode, Stylesheet,
-};
+use crate::parsing::css_parser::CssParser;
+use crate::style::cascade::{self, Cascade};
+use crate::style::computed::{ComputedStyle, Float, Position, StyleCache};
+use crate::style::css::{Origin, PropertyMap, PseudoElement, StyledNode, Stylesheet};
+use crate::style::generated::GeneratedContent;
+use crate::style::matching::{AncestorFilter, MatchContext, RuleEntry, RuleMap};
+use crate::style::restyle::{ElementStyle, Invalidation, RestyleData, StyleData};
+use crate::style::user_agent::user_agent_stylesheet;
+use crate::style::values::{compute_values, LengthContext, Viewport, DEFAULT_FONT_SIZE};
+use std::collections::HashMap;
+use std::sync::Arc;
 
-/// Returns whether a [Selector] matches a given element.
-fn matches(elem: &ElementData, selector: &Selector) -> bool {
-    match *selector {
-        Selector::Simple(ref simple_selector) => matches_simple_selector(elem, simple_selector),
+/// Returns a map of properties for a given element, from every declaration that applies to it.
+fn specified_values(
+    elem: &ElementData,
+    cascade: &Cascade,
+    context: MatchContext,
+    pseudo: Option<PseudoElement>,
+) -> PropertyMap {
+    let mut declarations = cascade.matching_declarations(elem, context, pseudo);
+
+    // Style attributes only apply to the element itself, not its pseudo-elements.
+    let inline = match (pseudo, elem.attr("style")) {
+        (None, Some(style)) => CssParser::parse_inline(style),
+        _ => Vec::new(),
+    };
+    for declaration in &inline {
+        declarations.push((
+            cascade.inline_precedence(declaration.important),
+            declaration,
+        ));
     }
+
+    cascade::cascade(declarations)
+}
+
+/// Returns the specified values of each of an element's pseudo-elements which has declarations.
+fn pseudo_specified_values(
+    elem: &ElementData,
+    cascade: &Cascade,
+    context: MatchContext,
+) -> HashMap<PseudoElement, PropertyMap> {
+    PseudoElement::ALL
+        .into_iter()
+        .map(|p| (p, specified_values(elem, cascade, context, Some(p))))
+        .filter(|(_, values)| !values.is_empty())
+        .collect()
 }
 
-/// Returns whether a [SimpleSelector] matches a given element.
-fn matches_simple_selector(elem: &ElementData, selector: &SimpleSelector) -> bool {
-    if selector.tag_name.iter().any(|x| *x != elem.tag_name) {
-        return false;
+/// Computes the style of an element and its pseudo-elements from their specified values.
+/// Pseudo-elements inherit from their element.
+fn compute_style(
+    specified: PropertyMap,
+    pseudo: HashMap<PseudoElement, PropertyMap>,
+    parent: Option<&Arc<ComputedStyle>>,
+    ctx: &LengthContext,
+    styles: &mut StyleCache,
+) -> ElementStyle {
+    let values = compute_values(&specified, parent.map(|p| &p.values), ctx);
+    let style = styles.share(values);
+    let pseudo = pseudo
+        .into_iter()
+        .map(|(p, specified)| {
+            let values = compute_values(&specified, Some(&style.values), ctx);
+            (p, (specified, styles.share(values)))
+        })
+        .collect();
+
+    ElementStyle {
+        specified,
+        style,
+        pseudo,
     }
+}
 
-    if selector.id.iter().any(|x| Some(x) != elem.id()) {
-        return false;
+/// The styles of recently matched elements. Siblings with the same tag and attributes match the
+/// same rules, (unless a selector depends on an element's siblings), and inherit from the same
+/// parent, so they always have the same style.
+#[derive(Default)]
+struct SharingCache<'a> {
+    /// Each element's parent, the element, and its style.
+    recent: Vec<(&'a ElementData, &'a ElementData, ElementStyle)>,
+}
+
+impl<'a> SharingCache<'a> {
+    /// The number of styles to remember.
+    const CAPACITY: usize = 8;
+
+    /// Returns the Some style of a sibling like `elem`, or None if there isn't one.
+    fn get(&self, parent: &ElementData, elem: &ElementData) -> Option<&ElementStyle> {
+        self.recent
+            .iter()
+            .rev()
+            .find(|(p, e, _)| std::ptr::eq(*p, parent) && *e == elem)
+            .map(|(_, _, style)| style)
     }
 
-    if selector
-        .class
-        .iter()
-        .any(|x| !elem.classes().contains(x.as_str()))
+    fn insert(&mut self, parent: &'a ElementData, elem: &'a ElementData, style: ElementStyle) {
+        if self.recent.len() == Self::CAPACITY {
+            self.recent.remove(0);
+        }
+        self.recent.push((parent, elem, style));
+    }
+}
+
+/// The stylesheets a document is styled with, and what changed since it was last styled.
+struct Stylist<'c> {
+    cascade: Cascade<'c>,
+    /// The rules of the stylesheets added since the document was last styled.
+    added: RuleMap<'c>,
+    /// Should every node be styled from scratch?
+    full: bool,
+}
+
+/// The state kept while styling a tree in document order.
+struct Traversal<'a> {
+    generated: GeneratedContent,
+    styles: StyleCache,
+    /// The elements above the current node, from the root down.
+    ancestors: Vec<&'a ElementData>,
+    /// The preceding element siblings of each ancestor, then of the current node.
+    preceding: Vec<Vec<&'a ElementData>>,
+    /// The ids, classes and tags of `ancestors`.
+    filter: AncestorFilter,
+    siblings: SharingCache<'a>,
+}
+
+impl<'a> Traversal<'a> {
+    fn new() -> Self {
+        Self {
+            generated: GeneratedContent::default(),
+            styles: StyleCache::default(),
+            ancestors: Vec::new(),
+            preceding: vec![Vec::new()],
+            filter: AncestorFilter::default(),
+            siblings: SharingCache::default(),
+        }
+    }
+
+    /// Returns where the current node is, for matching selectors.
+    fn context(&self) -> MatchContext<'_> {
+        MatchContext {
+            ancestors: &self.ancestors,
+            preceding: &self.preceding,
+            filter: &self.filter,
+        }
+    }
+}
+
+/// Returns which elements have to be matched again, because of changes to a node since it was
+/// last styled.
+fn node_invalidation(node: &Node, stylist: &Stylist) -> Invalidation {
+    let NodeType::Element(ref elem) = node.node_type else {
+        return Invalidation::default();
+    };
+    let restyle = node.restyle.borrow();
+    let invalidations = &stylist.cascade.invalidations;
+
+    let mut invalidation = Invalidation {
+        element: restyle.data.is_none() || stylist.added.candidates(elem).next().is_some(),
+        // Children which moved may now match different sibling combinators.
+        descendants: restyle.children_changed && invalidations.sibling_combinators,
+        siblings: false,
+    };
+
+    if let Some(old) = &restyle.snapshot {
+        invalidation |= invalidations.invalidation(old, elem);
+    }
+    invalidation
+}
+
+/// Forgets the styles of nodes which aren't styled, (since an ancestor isn't displayed), so they
+/// are styled from scratch once they are.
+fn forget_styles(nodes: &[Node]) {
+    for node in nodes {
+        let mut restyle = node.restyle.borrow_mut();
+        if restyle.data.is_some() {
+            *restyle = RestyleData::default();
+            forget_styles(&node.children);
+        }
+    }
+}
+
+/// Creates the node for a generated pseudo-element, if its `content` produces one.
+fn pseudo_node<'a>(
+    root: &'a Node,
+    elem: &ElementData,
+    pseudo: PseudoElement,
+    traversal: &mut Traversal<'a>,
+    (element, ctx): (&ElementStyle, &LengthContext),
+) -> Option<StyledNode<'a>> {
+    let parent = &element.style.values;
+    let (specified, style) = match element.pseudo.get(&pseudo) {
+        Some((specified, style)) => (specified.clone(), style.clone()),
+        // List items have markers, even without any declarations.
+        None if pseudo == PseudoElement::Marker => {
+            let values = compute_values(&PropertyMap::new(), Some(parent), ctx);
+            (PropertyMap::new(), traversal.styles.share(values))
+        }
+        None => return None,
+    };
+
+    let generated = &mut traversal.generated;
+    let text = match (pseudo, specified.get("content")) {
+        (PseudoElement::Marker, None) => generated.marker(parent.get("list-style-type")),
+        (_, Some(_)) => generated.content(&style.values["content"], &style.values, elem),
+        (_, None) => None,
+    }?;
+
+    Some(StyledNode {
+        node: root,
+        specified_values: specified,
+        style,
+        children: Vec::new(),
+        pseudo: Some(pseudo),
+        generated_text: Some(text),
+        pseudo_styles: HashMap::new(),
+    })
+}
+
+/// Returns the style of an element. It is reused from when the element was last styled unless
+/// `rematch` is set, or otherwise from a sibling like it if there is one.
+fn element_style<'a>(
+    elem: &'a ElementData,
+    data: Option<&StyleData>,
+    (stylist, traversal): (&Stylist, &mut Traversal<'a>),
+    (parent, ctx): (Option<&Arc<ComputedStyle>>, &LengthContext),
+    rematch: bool,
+) -> ElementStyle {
+    match data {
+        Some(data) if !rematch && data.is_current(parent, ctx) => return data.style.clone(),
+        // Only what the element inherits has changed, so the same declarations still apply.
+        Some(data) if !rematch => {
+            let pseudo = data.style.pseudo.iter();
+            return compute_style(
+                data.style.specified.clone(),
+                pseudo
+                    .map(|(p, (specified, _))| (*p, specified.clone()))
+                    .collect(),
+                parent,
+                ctx,
+                &mut traversal.styles,
+            );
+        }
+        _ => {}
+    }
+
+    let parent_elem = traversal.ancestors.last().copied();
+    let cascade = &stylist.cascade;
+    let sharing = cascade.optimized && !cascade.invalidations.sibling_combinators;
+    if let Some(shared) = parent_elem
+        .filter(|_| sharing)
+        .and_then(|p| traversal.siblings.get(p, elem))
     {
-        return false;
+        return shared.clone();
     }
 
-    true
+    let specified = specified_values(elem, cascade, traversal.context(), None);
+    let pseudo = pseudo_specified_values(elem, cascade, traversal.context());
+    let style = compute_style(specified, pseudo, parent, ctx, &mut traversal.styles);
+
+    // The root has no siblings to share with.
+    if let Some(parent) = parent_elem.filter(|_| sharing) {
+        traversal.siblings.insert(parent, elem, style.clone());
+    }
+    style
 }
 
-// If `rule` matches `elem`, return a `MatchedRule`. Otherwise return `None`.
-fn match_rule<'a>(elem: &ElementData, rule: &'a Rule) -> Option<MatchedRule<'a>> {
-    rule.selectors
+/// Styles a node and its children, generating content for pseudo-elements in document order.
+/// `parent` is the style of the node's parent, or None for the root, and `invalidation` says
+/// which elements have to be matched again.
+fn style_node<'a>(
+    root: &'a Node,
+    stylist: &Stylist,
+    traversal: &mut Traversal<'a>,
+    parent: Option<&Arc<ComputedStyle>>,
+    mut ctx: LengthContext,
+    invalidation: Invalidation,
+) -> StyledNode<'a> {
+    let mut restyle = root.restyle.borrow_mut();
+
+    let elem = match root.node_type {
+        NodeType::Element(ref elem) => elem,
+        NodeType::Text(_) => {
+            // Text has no style of its own, so only inherits.
+            let style = match &restyle.data {
+                Some(data) if data.is_current(parent, &ctx) => data.style.clone(),
+                _ => compute_style(
+                    PropertyMap::new(),
+                    HashMap::new(),
+                    parent,
+                    &ctx,
+                    &mut traversal.styles,
+                ),
+            };
+
+            restyle.styled(StyleData {
+                style: style.clone(),
+                parent: parent.cloned(),
+                context: ctx,
+            });
+            return StyledNode {
+                node: root,
+                specified_values: style.specified,
+                style: style.style,
+                children: Vec::new(),
+                pseudo: None,
+                generated_text: None,
+                pseudo_styles: HashMap::new(),
+            };
+        }
+    };
+
+    let element = element_style(
+        elem,
+        restyle.data.as_ref(),
+        (stylist, traversal),
+        (parent, &ctx),
+        invalidation.element,
+    );
+    restyle.styled(StyleData {
+        style: element.clone(),
+        parent: parent.cloned(),
+        context: ctx,
+    });
+    drop(restyle);
+
+    let style = element.style.clone();
+    // ::first-line and ::first-letter only apply to block containers.
+    let pseudo_styles = element
+        .pseudo
         .iter()
-        .find(|s| matches(elem, s))
-        .map(|s| MatchedRule::new(s.specificity(), rule))
+        .filter(|(p, _)| !p.generates_box())
+        .filter(|(p, _)| {
+            is_block_container(style.display)
+                || !matches!(p, PseudoElement::FirstLine | PseudoElement::FirstLetter)
+        })
+        .map(|(p, (_, style))| (*p, style.clone()))
+        .collect();
+
+    let mut node = StyledNode {
+        node: root,
+        specified_values: element.specified.clone(),
+        style: style.clone(),
+        children: Vec::new(),
+        pseudo: None,
+        generated_text: None,
+        pseudo_styles,
+    };
+
+    // `rem` lengths are relative to the font size of the root element.
+    let depth = traversal.ancestors.len();
+    if depth == 0 {
+        ctx.root_font_size = style.font_size;
+    }
+
+    if style.display == Display::None {
+        forget_styles(&root.children);
+        return node;
+    }
+
+    let list_item = style.display == Display::ListItem;
+    traversal
+        .generated
+        .apply_counters(&style.values, depth, list_item);
+
+    let pseudo = |p, t: &mut Traversal<'a>| pseudo_node(root, elem, p, t, (&element, &ctx));
+
+    if list_item {
+        node.children
+            .extend(pseudo(PseudoElement::Marker, traversal));
+    }
+    node.children
+        .extend(pseudo(PseudoElement::Before, traversal));
+
+    traversal.ancestors.push(elem);
+    traversal.preceding.push(Vec::new());
+    traversal.filter.push(elem);
+
+    // Set once a child's change restyles its later siblings.
+    let mut later_siblings = false;
+    for child in &root.children {
+        let mut child_invalidation = node_invalidation(child, stylist);
+        if invalidation.descendants || later_siblings {
+            child_invalidation.element = true;
+            child_invalidation.descendants = true;
+        }
+        later_siblings |= child_invalidation.siblings;
+
+        node.children.push(style_node(
+            child,
+            stylist,
+            traversal,
+            Some(&style),
+            ctx,
+            child_invalidation,
+        ));
+
+        if let NodeType::Element(ref child) = child.node_type {
+            traversal.preceding.last_mut().unwrap().push(child);
+        }
+    }
+
+    traversal.filter.pop(elem);
+    traversal.preceding.pop();
+    traversal.ancestors.pop();
+    traversal.generated.leave_children(depth);
+
+    node.children
+        .extend(pseudo(PseudoElement::After, traversal));
+
+    // The first letter is split out before the first line is styled, as it's on the first line.
+    if node.pseudo_styles.contains_key(&PseudoElement::FirstLetter) {
+        split_first_letter(
+            &mut node.children,
+            &element.pseudo[&PseudoElement::FirstLetter],
+        );
+    }
+    if let Some(line) = node.pseudo_styles.get(&PseudoElement::FirstLine) {
+        let line = line.clone();
+        style_first_line(&mut node.children, &line, &ctx, &mut traversal.styles);
+    }
+    node
 }
 
-/// Returns the [MatchedRule]s that match a given element within a given [Stylesheet].
-fn matching_rules<'a>(elem: &ElementData, stylesheet: &'a Stylesheet) -> Vec<MatchedRule<'a>> {
-    stylesheet
-        .rules
-        .iter()
-        .filter_map(|r| match_rule(elem, r))
-        .collect()
+/// Do boxes with this display contain lines of inline content?
+fn is_block_container(display: Display) -> bool {
+    matches!(
+        display,
+        Display::Block | Display::InlineBlock | Display::FlowRoot | Display::ListItem
+    )
+}
+
+/// Are boxes with this style taken out of the lines of their container?
+fn is_out_of_flow(style: &ComputedStyle) -> bool {
+    style.float != Float::None || matches!(style.position, Position::Absolute | Position::Fixed)
+}
+
+/// Returns the Some first letter of some text, with the punctuation around it, and the text
+/// after it, or None if it has no letter. Eg: "\"A\" is" -> ("\"A\"", " is")
+fn first_letter(text: &str) -> Option<(&str, &str)> {
+    let is_punctuation =
+        |c: char| c.is_ascii_punctuation() || matches!(c, '“' | '”' | '‘' | '’' | '«' | '»');
+
+    let text = text.trim_start();
+    let start = text.find(|c| !is_punctuation(c))?;
+    let end = start + text[start..].chars().next()?.len_utf8();
+    let end = text[end..]
+        .find(|c| !is_punctuation(c))
+        .map_or(text.len(), |i| end + i);
+    Some(text.split_at(end))
 }
 
-/// Returns a map of properties for a given element, sorted in order of [specificity].
-fn specified_values(elem: &ElementData, stylesheet: &Stylesheet) -> PropertyMap {
-    let mut values = PropertyMap::new();
-    let mut rules = matching_rules(elem, stylesheet);
+/// Splits the first letter of a block container's text into a ::first-letter node, with the
+/// specified and computed `letter` style. Returns true once the search ends, at the letter or
+/// at the first block, or false if `children` have no text.
+/// https://www.w3.org/TR/css-pseudo-4/#first-letter-pseudo
+fn split_first_letter<'a>(
+    children: &mut Vec<StyledNode<'a>>,
+    letter: &(PropertyMap, Arc<ComputedStyle>),
+) -> bool {
+    for i in 0..children.len() {
+        let child = &mut children[i];
+        // The content of an atomic inline is in lines of its own.
+        if child.display() == Display::None
+            || child.display().is_atomic_inline()
+            || is_out_of_flow(&child.style)
+            || child.pseudo == Some(PseudoElement::Marker)
+        {
+            continue;
+        }
 
-    // Most strongly specified rules go first.
-    rules.sort_by(|a, b| a.specificity.cmp(&b.specificity));
+        if let Some(text) = child.text() {
+            let Some((first, rest)) = first_letter(text) else {
+                continue;
+            };
+            let first = StyledNode {
+                node: child.node,
+                specified_values: letter.0.clone(),
+                style: letter.1.clone(),
+                children: Vec::new(),
+                pseudo: Some(PseudoElement::FirstLetter),
+                generated_text: Some(first.to_string()),
+                pseudo_styles: HashMap::new(),
+            };
+            child.generated_text = Some(rest.to_string());
+            children.insert(i, first);
+            return true;
+        }
+
+        if child.display() == Display::Inline {
+            if split_first_letter(&mut child.children, letter) {
+                return true;
+            }
+            continue;
+        }
+        // The letter may be in the first block, unless it has its own, but not after it.
+        if !child
+            .pseudo_styles
+            .contains_key(&PseudoElement::FirstLetter)
+        {
+            split_first_letter(&mut child.children, letter);
+        }
+        return true;
+    }
+    false
+}
+
+/// Gives the content on the first line of a block container the style it has there, as its
+/// ::first-line pseudo style. That content inherits from the container's ::first-line, `line`,
+/// so each node's first line style inherits from its parent's. Returns true once the first line
+/// ends, at the first block, or false if all of `children` can be on it.
+/// https://www.w3.org/TR/css-pseudo-4/#first-line-inheritance
+fn style_first_line(
+    children: &mut [StyledNode],
+    line: &Arc<ComputedStyle>,
+    ctx: &LengthContext,
+    styles: &mut StyleCache,
+) -> bool {
+    for child in children {
+        // The content of an atomic inline is in lines of its own.
+        if child.display() == Display::None
+            || child.display().is_atomic_inline()
+            || is_out_of_flow(&child.style)
+            || child.pseudo == Some(PseudoElement::Marker)
+        {
+            continue;
+        }
+        let is_block = child.display() != Display::Inline;
+        // A block's own ::first-line applies to its first line instead.
+        if is_block && child.pseudo_styles.contains_key(&PseudoElement::FirstLine) {
+            return true;
+        }
 
-    // Copy declarations into output.
-    for matched_rule in rules {
-        for declaration in &matched_rule.rule.declarations {
-            values.insert(declaration.name.clone(), declaration.value.clone());
+        let specified = child.specified_values.clone();
+        let style = compute_style(specified, HashMap::new(), Some(line), ctx, styles).style;
+        let ended = style_first_line(&mut child.children, &style, ctx, styles);
+        child.pseudo_styles.insert(PseudoElement::FirstLine, style);
+        if is_block || ended {
+            return true;
         }
     }
+    false
+}
+
+/// Styles a document from its root, matching the elements that `stylist` says have changed.
+fn style_document<'a>(root: &'a Node, stylist: &Stylist, viewport: Viewport) -> StyledNode<'a> {
+    let ctx = LengthContext {
+        font_size: DEFAULT_FONT_SIZE,
+        root_font_size: DEFAULT_FONT_SIZE,
+        viewport,
+        percent_base: 0.0,
+    };
+
+    let mut invalidation = node_invalidation(root, stylist);
+    if stylist.full {
+        invalidation.element = true;
+        invalidation.descendants = true;
+    }
 
-    values
+    style_node(
+        root,
+        stylist,
+        &mut Traversal::new(),
+        None,
+        ctx,
+        invalidation,
+    )
 }
 
-// Apply a stylesheet to an entire DOM tree, returning a StyledNode tree.
-pub fn style_tree<'a>(root: &'a Node, stylesheet: &'a Stylesheet) -> StyledNode<'a> {
-    StyledNode {
-        node: root,
-        specified_values: match root.node_type {
-            NodeType::Element(ref elem) => specified_values(elem, stylesheet),
-            NodeType::Text(_) => PropertyMap::new(),
-        },
-        children: root
-            .children
+/// Returns the stylesheets, with the built-in user agent stylesheet if none are from the user agent.
+fn with_user_agent(stylesheets: &[Stylesheet]) -> Vec<&Stylesheet> {
+    let mut sheets: Vec<&Stylesheet> = stylesheets.iter().collect();
+    if !sheets.iter().any(|s| s.origin == Origin::UserAgent) {
+        sheets.insert(0, user_agent_stylesheet());
+    }
+    sheets
+}
+
+// Apply stylesheets to an entire DOM tree, returning a StyledNode tree.
+// Stylesheets of the same origin cascade in the order they are given. The built-in user agent
+// stylesheet is used unless it is replaced, by giving another user agent stylesheet.
+pub fn style_tree<'a>(
+    root: &'a Node,
+    stylesheets: &[Stylesheet],
+    viewport: Viewport,
+) -> StyledNode<'a> {
+    let stylist = Stylist {
+        cascade: Cascade::new(with_user_agent(stylesheets), &viewport),
+        added: RuleMap::default(),
+        full: true,
+    };
+
+    style_document(root, &stylist, viewport)
+}
+
+/// Styles a tree like [style_tree], but tests every rule against every element, without the rule
+/// hash, the ancestor filter or style sharing. It's only for comparing with, (Eg: in benchmarks).
+pub fn style_tree_unoptimized<'a>(
+    root: &'a Node,
+    stylesheets: &[Stylesheet],
+    viewport: Viewport,
+) -> StyledNode<'a> {
+    let mut cascade = Cascade::new(with_user_agent(stylesheets), &viewport);
+    cascade.optimized = false;
+    let stylist = Stylist {
+        cascade,
+        added: RuleMap::default(),
+        full: true,
+    };
+
+    style_document(root, &stylist, viewport)
+}
+
+/// Styles a document as it changes, only restyling the nodes which changes affect.
+/// The document has to be changed through the methods of [Node], (Eg: [Node::set_attr]), so
+/// that they are noticed, and shouldn't be styled by anything else in between.
+pub struct Styler {
+    stylesheets: Vec<Stylesheet>,
+    viewport: Viewport,
+    /// The number of stylesheets the document was last styled with, and the viewport it was
+    /// styled for, or None if it hasn't been.
+    styled: Option<(usize, Viewport)>,
+}
+
+impl Styler {
+    pub fn new(stylesheets: Vec<Stylesheet>, viewport: Viewport) -> Self {
+        Self {
+            stylesheets,
+            viewport,
+            styled: None,
+        }
+    }
+
+    /// Adds a stylesheet, which cascades after the others of its origin.
+    pub fn add_stylesheet(&mut self, stylesheet: Stylesheet) {
+        self.stylesheets.push(stylesheet);
+    }
+
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.viewport = viewport;
+    }
+
+    /// Styles the document, reusing the style each node had when it was last styled, unless
+    /// something it depends on has changed.
+    pub fn style<'a>(&mut self, root: &'a Node) -> StyledNode<'a> {
+        let (previous, old_viewport) = self.styled.unwrap_or((0, self.viewport));
+        let (old, new) = self.stylesheets.split_at(previous);
+
+        // Adding the first user agent stylesheet replaces the built-in one.
+        let has_user_agent =
+            |sheets: &[Stylesheet]| sheets.iter().any(|s| s.origin == Origin::UserAgent);
+
+        // TODO: Only restyle the elements that rules which start or stop matching could match.
+        let media_changed = old
             .iter()
-            .map(|child| style_tree(child, stylesheet))
-            .collect(),
+            .flat_map(|s| &s.rules)
+            .any(|r| r.matches_media(&old_viewport) != r.matches_media(&self.viewport));
+        let full =
+            self.styled.is_none() || (!has_user_agent(old) && has_user_agent(new)) || media_changed;
+
+        let mut added = RuleMap::default();
+        for (i, sheet) in new.iter().enumerate() {
+            for (j, rule) in sheet.rules.iter().enumerate() {
+                if !rule.matches_media(&self.viewport) {
+                    continue;
+                }
+                for selector in &rule.selectors {
+                    added.insert(RuleEntry::new(selector, rule, (previous + i, j)));
+                }
+            }
+        }
+
+        let stylist = Stylist {
+            cascade: Cascade::new(with_user_agent(&self.stylesheets), &self.viewport),
+            added,
+            full,
+        };
+        self.styled = Some((self.stylesheets.len(), self.viewport));
+
+        style_document(root, &stylist, self.viewport)
     }
 }
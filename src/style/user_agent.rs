@@ -0,0 +1,15 @@
+// The built-in user agent stylesheet, which gives html elements their default rendering.
+
+use crate::parsing::css_parser::CssParser;
+use crate::style::css::{Origin, Stylesheet};
+use std::sync::OnceLock;
+
+/// The css of the user agent stylesheet.
+pub const USER_AGENT_CSS: &str = include_str!("user_agent.css");
+
+/// Returns the built-in user agent stylesheet. It is only parsed once.
+pub fn user_agent_stylesheet() -> &'static Stylesheet {
+    static STYLESHEET: OnceLock<Stylesheet> = OnceLock::new();
+    STYLESHEET
+        .get_or_init(|| CssParser::parse_with_origin(USER_AGENT_CSS.to_string(), Origin::UserAgent))
+}
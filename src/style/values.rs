@@ -0,0 +1,429 @@
+// Resolving relative values into absolute ones.
+// https://www.w3.org/TR/css-values-4/
+
+use crate::style::colour::is_colour_property;
+use crate::style::css::{PropertyMap, Unit, Value};
+use crate::style::properties::{
+    all_properties, initial_value, is_custom_property, is_inherited, CSS_WIDE_KEYWORDS,
+};
+use crate::style::variables::{substitute_custom_properties, substitute_value};
+use std::sync::OnceLock;
+
+/// The font size used by the root element when none is specified.
+pub const DEFAULT_FONT_SIZE: f32 = 16.0;
+
+/// The area the document is displayed in, and the device and user preferences that media
+/// queries test. Sizes are in px.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub width: f32,
+    pub height: f32,
+    /// The number of device pixels per px. Eg: 2 on a high density screen.
+    pub resolution: f32,
+    pub colour_scheme: ColourScheme,
+    /// Does the user prefer interfaces with less motion?
+    pub reduced_motion: bool,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self {
+            width: 800.0,
+            height: 600.0,
+            resolution: 1.0,
+            colour_scheme: ColourScheme::Light,
+            reduced_motion: false,
+        }
+    }
+}
+
+/// The colour scheme the user prefers. (prefers-color-scheme)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColourScheme {
+    Light,
+    Dark,
+}
+
+/// Everything a relative length can be relative to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LengthContext {
+    pub font_size: f32,
+    pub root_font_size: f32,
+    pub viewport: Viewport,
+    /// The size percentages resolve against. Eg: The width of the containing block.
+    pub percent_base: f32,
+}
+
+impl LengthContext {
+    /// A context for resolving values that only have percentages left to resolve.
+    pub fn for_percentages(percent_base: f32) -> Self {
+        Self {
+            font_size: DEFAULT_FONT_SIZE,
+            root_font_size: DEFAULT_FONT_SIZE,
+            viewport: Viewport::default(),
+            percent_base,
+        }
+    }
+
+    /// Returns the size of a length in px.
+    pub fn length_to_px(&self, n: f32, unit: &Unit) -> f32 {
+        if let Some(px) = unit.absolute_px() {
+            return n * px;
+        }
+
+        let vw = self.viewport.width / 100.0;
+        let vh = self.viewport.height / 100.0;
+
+        n * match unit {
+            Unit::Em => self.font_size,
+            Unit::Rem => self.root_font_size,
+            // Without font metrics, the x-height and "0" advance are both approximated as 0.5em.
+            Unit::Ex | Unit::Ch => self.font_size / 2.0,
+            Unit::Vw => vw,
+            Unit::Vh => vh,
+            Unit::Vmin => vw.min(vh),
+            Unit::Vmax => vw.max(vh),
+            _ => unreachable!("absolute units are handled above"),
+        }
+    }
+}
+
+/// A math expression, as used by calc(), min(), max() and clamp().
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalcExpr {
+    /// A number, length or percentage.
+    Leaf(Value),
+    Sum(Box<CalcExpr>, Box<CalcExpr>),
+    Difference(Box<CalcExpr>, Box<CalcExpr>),
+    Product(Box<CalcExpr>, Box<CalcExpr>),
+    Quotient(Box<CalcExpr>, Box<CalcExpr>),
+    Min(Vec<CalcExpr>),
+    Max(Vec<CalcExpr>),
+    Clamp(Box<CalcExpr>, Box<CalcExpr>, Box<CalcExpr>),
+}
+
+impl CalcExpr {
+    /// Evaluates the expression, with lengths and percentages in px.
+    pub fn eval(&self, ctx: &LengthContext) -> f32 {
+        match self {
+            CalcExpr::Leaf(v) => match v {
+                Value::Number(n) => *n,
+                Value::Percentage(p) => ctx.percent_base * p / 100.0,
+                Value::Length(n, unit) => ctx.length_to_px(*n, unit),
+                Value::Calc(expr) => expr.eval(ctx),
+                _ => 0.0,
+            },
+            CalcExpr::Sum(a, b) => a.eval(ctx) + b.eval(ctx),
+            CalcExpr::Difference(a, b) => a.eval(ctx) - b.eval(ctx),
+            CalcExpr::Product(a, b) => a.eval(ctx) * b.eval(ctx),
+            CalcExpr::Quotient(a, b) => a.eval(ctx) / b.eval(ctx),
+            CalcExpr::Min(args) => args
+                .iter()
+                .map(|a| a.eval(ctx))
+                .fold(f32::INFINITY, f32::min),
+            CalcExpr::Max(args) => args
+                .iter()
+                .map(|a| a.eval(ctx))
+                .fold(f32::NEG_INFINITY, f32::max),
+            // The minimum wins over the maximum if they conflict.
+            CalcExpr::Clamp(min, val, max) => val.eval(ctx).min(max.eval(ctx)).max(min.eval(ctx)),
+        }
+    }
+
+    /// Returns the children of the expression.
+    fn children(&self) -> Vec<&CalcExpr> {
+        match self {
+            CalcExpr::Leaf(_) => Vec::new(),
+            CalcExpr::Sum(a, b)
+            | CalcExpr::Difference(a, b)
+            | CalcExpr::Product(a, b)
+            | CalcExpr::Quotient(a, b) => vec![a, b],
+            CalcExpr::Min(args) | CalcExpr::Max(args) => args.iter().collect(),
+            CalcExpr::Clamp(a, b, c) => vec![a, b, c],
+        }
+    }
+
+    /// Does the expression contain a percentage, and so can't be resolved until layout?
+    pub fn has_percentage(&self) -> bool {
+        match self {
+            CalcExpr::Leaf(Value::Percentage(_)) => true,
+            CalcExpr::Leaf(_) => false,
+            e => e.children().iter().any(|c| c.has_percentage()),
+        }
+    }
+
+    /// Does the expression contain a number on its own, rather than a length?
+    fn is_number(&self) -> bool {
+        match self {
+            CalcExpr::Leaf(v) => matches!(v, Value::Number(_)),
+            CalcExpr::Product(a, b) => a.is_number() && b.is_number(),
+            CalcExpr::Quotient(a, _) => a.is_number(),
+            e => e.children().iter().all(|c| c.is_number()),
+        }
+    }
+
+    /// Replaces every relative length in the expression with its size in px.
+    fn absolutise(&self, ctx: &LengthContext) -> CalcExpr {
+        let abs = |e: &CalcExpr| Box::new(e.absolutise(ctx));
+        match self {
+            CalcExpr::Leaf(Value::Length(n, unit)) => {
+                CalcExpr::Leaf(Value::Length(ctx.length_to_px(*n, unit), Unit::Px))
+            }
+            CalcExpr::Leaf(v) => CalcExpr::Leaf(v.clone()),
+            CalcExpr::Sum(a, b) => CalcExpr::Sum(abs(a), abs(b)),
+            CalcExpr::Difference(a, b) => CalcExpr::Difference(abs(a), abs(b)),
+            CalcExpr::Product(a, b) => CalcExpr::Product(abs(a), abs(b)),
+            CalcExpr::Quotient(a, b) => CalcExpr::Quotient(abs(a), abs(b)),
+            CalcExpr::Min(args) => CalcExpr::Min(args.iter().map(|a| a.absolutise(ctx)).collect()),
+            CalcExpr::Max(args) => CalcExpr::Max(args.iter().map(|a| a.absolutise(ctx)).collect()),
+            CalcExpr::Clamp(a, b, c) => CalcExpr::Clamp(abs(a), abs(b), abs(c)),
+        }
+    }
+}
+
+/// Returns the computed value of a value: Relative lengths become px, and math functions are
+/// evaluated as far as possible. Percentages are kept, as they can't be resolved until layout.
+pub fn compute_value(value: &Value, ctx: &LengthContext) -> Value {
+    match value {
+        Value::Length(n, unit) if *unit != Unit::Px => {
+            Value::Length(ctx.length_to_px(*n, unit), Unit::Px)
+        }
+        Value::Calc(expr) if expr.has_percentage() => Value::Calc(Box::new(expr.absolutise(ctx))),
+        Value::Calc(expr) if expr.is_number() => Value::Number(expr.eval(ctx)),
+        Value::Calc(expr) => Value::Length(expr.eval(ctx), Unit::Px),
+        Value::List(values) => Value::List(values.iter().map(|v| compute_value(v, ctx)).collect()),
+        Value::CommaList(values) => {
+            Value::CommaList(values.iter().map(|v| compute_value(v, ctx)).collect())
+        }
+        Value::Function(name, args) if name != "url" => Value::Function(
+            name.clone(),
+            args.iter().map(|v| compute_value(v, ctx)).collect(),
+        ),
+        v => v.clone(),
+    }
+}
+
+/// Computes the font size of an element, relative to the font size of its parent.
+fn compute_font_size(value: Option<&Value>, parent_font_size: f32, ctx: &LengthContext) -> f32 {
+    // Font relative lengths in `font-size` are relative to the parent's font size.
+    let ctx = LengthContext {
+        font_size: parent_font_size,
+        percent_base: parent_font_size,
+        ..*ctx
+    };
+
+    match value {
+        Some(Value::Keyword(k)) => match k.as_str() {
+            "xx-small" => DEFAULT_FONT_SIZE * 3.0 / 5.0,
+            "x-small" => DEFAULT_FONT_SIZE * 3.0 / 4.0,
+            "small" => DEFAULT_FONT_SIZE * 8.0 / 9.0,
+            "medium" => DEFAULT_FONT_SIZE,
+            "large" => DEFAULT_FONT_SIZE * 6.0 / 5.0,
+            "x-large" => DEFAULT_FONT_SIZE * 3.0 / 2.0,
+            "xx-large" => DEFAULT_FONT_SIZE * 2.0,
+            "xxx-large" => DEFAULT_FONT_SIZE * 3.0,
+            "smaller" => parent_font_size / 1.2,
+            "larger" => parent_font_size * 1.2,
+            _ => parent_font_size,
+        },
+        Some(Value::Length(n, unit)) => ctx.length_to_px(*n, unit),
+        Some(Value::Percentage(p)) => parent_font_size * p / 100.0,
+        Some(Value::Calc(expr)) => expr.eval(&ctx),
+        _ => parent_font_size,
+    }
+}
+
+/// Returns the Some CSS-wide keyword of a value, or None if it isn't one. Eg: inherit
+fn css_wide_keyword(value: &Value) -> Option<&'static str> {
+    match value {
+        Value::Keyword(k) => CSS_WIDE_KEYWORDS
+            .into_iter()
+            .find(|w| k.eq_ignore_ascii_case(w)),
+        _ => None,
+    }
+}
+
+/// Returns the Some value of a property once CSS-wide keywords are resolved, or None if it has no
+/// value. `inherit` returns the value the property inherits.
+fn specified_value(
+    name: &str,
+    value: Value,
+    inherit: &dyn Fn(&str) -> Option<Value>,
+) -> Option<Value> {
+    match (name, css_wide_keyword(&value)) {
+        // `revert` and `revert-layer` are resolved by the cascade, so behave like `unset` here.
+        (name, Some("unset" | "revert" | "revert-layer")) if !is_inherited(name) => {
+            initial_value(name)
+        }
+        (name, Some("inherit" | "unset" | "revert" | "revert-layer")) => inherit(name),
+        (name, Some(_)) => initial_value(name),
+        // `currentcolor` in `color` itself refers to the parent's colour.
+        ("color", None) if matches!(&value, Value::Keyword(k) if k.eq_ignore_ascii_case("currentcolor")) => {
+            inherit("color")
+        }
+        (_, None) => Some(value),
+    }
+}
+
+/// Sets a property's value, or removes it if it has none.
+fn set(values: &mut PropertyMap, name: &str, value: Option<Value>) {
+    match value {
+        Some(value) => values.insert(name.to_string(), value),
+        None => values.remove(name),
+    };
+}
+
+/// Computes the values of an element from its specified values, and the computed values of its
+/// parent, (or None for the root element).
+/// Inherited properties which aren't specified take their parent's value, CSS-wide keywords are
+/// resolved, and var() references are substituted.
+pub fn compute_values(
+    specified: &PropertyMap,
+    parent: Option<&PropertyMap>,
+    ctx: &LengthContext,
+) -> PropertyMap {
+    let inherit = |name: &str| {
+        parent
+            .and_then(|p| p.get(name).cloned())
+            .or_else(|| initial_computed_value(name))
+    };
+
+    let mut values: PropertyMap = parent
+        .into_iter()
+        .flatten()
+        .filter(|(name, _)| is_inherited(name))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+
+    // Custom properties are computed first, as var() in other properties refers to them.
+    let (custom, others): (Vec<_>, Vec<_>) = specified
+        .iter()
+        .partition(|(name, _)| is_custom_property(name));
+    for (name, value) in custom {
+        let value = specified_value(name, value.clone(), &inherit);
+        set(&mut values, name, value);
+    }
+    substitute_custom_properties(&mut values);
+
+    for (name, value) in others {
+        let value = match value {
+            // A value which is invalid once substituted behaves as if it were `unset`.
+            Value::Unparsed(tokens, shorthand) => {
+                substitute_value(name, tokens, shorthand.as_deref(), &values)
+                    .unwrap_or_else(|| Value::Keyword("unset".to_string()))
+            }
+            v => v.clone(),
+        };
+        let value = specified_value(name, value, &inherit);
+        set(&mut values, name, value);
+    }
+
+    let parent_font_size = parent
+        .and_then(|p| p.get("font-size"))
+        .map_or(DEFAULT_FONT_SIZE, Value::to_px);
+    let font_size = compute_font_size(values.get("font-size"), parent_font_size, ctx);
+    let ctx = LengthContext { font_size, ..*ctx };
+
+    // `currentcolor` in other properties refers to the element's own `color`.
+    let current_colour = values.get("color").and_then(|c| c.to_colour(None));
+    let parent_weight = parent
+        .and_then(|p| p.get("font-weight"))
+        .map_or(400.0, |w| font_weight(w, 400.0));
+
+    for (name, value) in values.iter_mut() {
+        *value = match (name.as_str(), &*value) {
+            ("font-size", _) => Value::Length(font_size, Unit::Px),
+            ("font-weight", v) => Value::Number(font_weight(v, parent_weight)),
+            (name, v @ Value::Keyword(_)) if is_colour_property(name) => v
+                .to_colour(current_colour.as_ref())
+                .map_or_else(|| v.clone(), Value::Colour),
+            // Percentage line heights are relative to the element's font size.
+            ("line-height", Value::Percentage(p)) => Value::Length(font_size * p / 100.0, Unit::Px),
+            (_, v) => compute_value(v, &ctx),
+        };
+    }
+
+    // Border and outline widths are zero when there is no line to draw.
+    for prefix in LINES {
+        let (width, style) = (format!("{prefix}-width"), format!("{prefix}-style"));
+        if !values.contains_key(&width) && !values.contains_key(&style) {
+            continue;
+        }
+
+        let drawn =
+            matches!(values.get(&style), Some(Value::Keyword(k)) if k != "none" && k != "hidden");
+        let px = match values.get(&width) {
+            _ if !drawn => 0.0,
+            Some(Value::Keyword(k)) => line_width(k),
+            Some(v) => v.to_px(),
+            None => line_width("medium"),
+        };
+        values.insert(width, Value::Length(px, Unit::Px));
+    }
+
+    values
+}
+
+/// Returns the numeric weight of a `font-weight`, relative to the weight of the parent element.
+fn font_weight(value: &Value, parent_weight: f32) -> f32 {
+    // https://www.w3.org/TR/css-fonts-4/#relative-weights
+    match value {
+        Value::Number(n) => *n,
+        Value::Keyword(k) => match k.as_str() {
+            "bold" => 700.0,
+            "bolder" if parent_weight < 350.0 => 400.0,
+            "bolder" if parent_weight < 550.0 => 700.0,
+            "bolder" => parent_weight.max(900.0),
+            "lighter" if parent_weight < 550.0 => parent_weight.min(100.0),
+            "lighter" if parent_weight < 750.0 => 400.0,
+            "lighter" => 700.0,
+            _ => 400.0,
+        },
+        _ => 400.0,
+    }
+}
+
+/// Returns the Some computed initial value of a property, or None if it isn't registered.
+pub fn initial_computed_value(name: &str) -> Option<Value> {
+    static VALUES: OnceLock<PropertyMap> = OnceLock::new();
+    VALUES
+        .get_or_init(|| {
+            let initial: PropertyMap = all_properties()
+                .iter()
+                .map(|p| (p.name.to_string(), p.initial_value()))
+                .collect();
+            let ctx = LengthContext::for_percentages(0.0);
+
+            // Initial values aren't inherited, so aren't computed relative to a parent.
+            let mut values = PropertyMap::new();
+            for (name, value) in initial {
+                let single = PropertyMap::from([(name.clone(), value)]);
+                values.extend(
+                    compute_values(&single, None, &ctx)
+                        .remove(&name)
+                        .map(|v| (name, v)),
+                );
+            }
+            values
+        })
+        .get(name)
+        .cloned()
+}
+
+/// The properties that draw a line around a box, which have a width and style.
+const LINES: [&str; 5] = [
+    "border-top",
+    "border-right",
+    "border-bottom",
+    "border-left",
+    "outline",
+];
+
+/// Returns the size of a `thin`, `medium` or `thick` line in px.
+fn line_width(keyword: &str) -> f32 {
+    match keyword {
+        "thin" => 1.0,
+        "thick" => 5.0,
+        _ => 3.0,
+    }
+}
@@ -0,0 +1,220 @@
+// Custom properties, and substituting their values into var() references.
+// https://www.w3.org/TR/css-variables-1/
+
+use crate::parsing::css_parser::CssParser;
+use crate::style::css::{Declaration, PropertyMap, Token, TokenList, Value};
+use crate::style::properties::{is_custom_property, is_valid};
+use crate::style::shorthand::expand_shorthand;
+use std::collections::{HashMap, HashSet};
+
+/// Substitutes var() references in an element's custom properties, which may refer to each other.
+/// Custom properties which refer to a property without a value and have no fallback, or are in a
+/// cycle, are invalid, so have no value.
+pub fn substitute_custom_properties(values: &mut PropertyMap) {
+    let pending: Vec<String> = values
+        .iter()
+        .filter(|(name, v)| {
+            is_custom_property(name) && matches!(v, Value::Tokens(t) if t.has_var())
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+    if pending.is_empty() {
+        return;
+    }
+
+    let mut resolver = Resolver {
+        unresolved: pending
+            .iter()
+            .filter_map(|name| match values.remove(name) {
+                Some(Value::Tokens(tokens)) => Some((name.clone(), tokens)),
+                _ => None,
+            })
+            .collect(),
+        values: &*values,
+        resolved: HashMap::new(),
+        resolving: Vec::new(),
+        cyclic: HashSet::new(),
+    };
+
+    for name in &pending {
+        resolver.lookup(name);
+    }
+
+    let resolved = resolver.resolved;
+    for (name, tokens) in resolved {
+        if let Some(tokens) = tokens {
+            values.insert(name, Value::Tokens(tokens));
+        }
+    }
+}
+
+/// Resolves custom properties on demand, so that only the references which are used can form
+/// cycles. Eg: The fallback of var(--a, var(--b)) isn't used if --a has a value.
+struct Resolver<'a> {
+    /// The custom properties with var() references, which haven't been resolved yet.
+    unresolved: HashMap<String, TokenList>,
+    /// The custom properties without references, which are already resolved.
+    values: &'a PropertyMap,
+    /// The Some tokens of each resolved property, or None if it is invalid.
+    resolved: HashMap<String, Option<TokenList>>,
+    /// The properties being resolved, each referring to the next.
+    resolving: Vec<String>,
+    cyclic: HashSet<String>,
+}
+
+impl Resolver<'_> {
+    /// Returns the Some tokens of a custom property, or None if it has no valid value.
+    fn lookup(&mut self, name: &str) -> Option<TokenList> {
+        if let Some(tokens) = self.resolved.get(name) {
+            return tokens.clone();
+        }
+
+        // Every property in a cycle is invalid, even those with fallbacks.
+        if let Some(i) = self.resolving.iter().position(|n| n == name) {
+            self.cyclic.extend(self.resolving[i..].iter().cloned());
+            return None;
+        }
+
+        let Some(tokens) = self.unresolved.get(name).cloned() else {
+            return match self.values.get(name) {
+                Some(Value::Tokens(tokens)) => Some(tokens.clone()),
+                _ => None,
+            };
+        };
+
+        self.resolving.push(name.to_string());
+        let substituted = substitute(&tokens, &mut |n| self.lookup(n));
+        self.resolving.pop();
+
+        let tokens = substituted.filter(|_| !self.cyclic.contains(name));
+        self.resolved.insert(name.to_string(), tokens.clone());
+        tokens
+    }
+}
+
+/// Returns the Some tokens with each var() replaced by the value of its custom property, (or its
+/// fallback if the property has no value), or None if a var() has neither.
+fn substitute(
+    tokens: &TokenList,
+    lookup: &mut dyn FnMut(&str) -> Option<TokenList>,
+) -> Option<TokenList> {
+    let mut result = Vec::new();
+    let mut rest = tokens.0.as_slice();
+
+    while let Some((token, after)) = rest.split_first() {
+        rest = after;
+        match token {
+            Token::Function(f) if f.eq_ignore_ascii_case("var") => {
+                let (args, after) = split_arguments(rest);
+                rest = after;
+
+                let (name, fallback) = var_arguments(args)?;
+                let value = match lookup(&name) {
+                    Some(value) => value,
+                    None => substitute(&fallback?, lookup)?,
+                };
+                result.extend(value.0);
+            }
+            token => result.push(token.clone()),
+        }
+    }
+
+    Some(TokenList(result))
+}
+
+/// Splits the tokens after a function's name into its arguments, and the tokens after its `)`.
+fn split_arguments(tokens: &[Token]) -> (&[Token], &[Token]) {
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Function(_) | Token::Delim('(') => depth += 1,
+            Token::Delim(')') if depth == 0 => return (&tokens[..i], &tokens[i + 1..]),
+            Token::Delim(')') => depth -= 1,
+            _ => {}
+        }
+    }
+    // An unclosed function is closed by the end of the value.
+    (tokens, &[])
+}
+
+/// Returns the Some name of the custom property that var() refers to, and its Some fallback if it
+/// has one, or None if the arguments are invalid. Eg: var(--brand, black)
+fn var_arguments(args: &[Token]) -> Option<(String, Option<TokenList>)> {
+    let trim = |tokens: &[Token]| {
+        let start = tokens.iter().position(|t| *t != Token::Whitespace);
+        let end = tokens.iter().rposition(|t| *t != Token::Whitespace);
+        match (start, end) {
+            (Some(start), Some(end)) => TokenList(tokens[start..=end].to_vec()),
+            _ => TokenList::default(),
+        }
+    };
+
+    let comma = args.iter().position(|t| *t == Token::Delim(','));
+    let (name, fallback) = match comma {
+        Some(i) => (trim(&args[..i]), Some(trim(&args[i + 1..]))),
+        None => (trim(args), None),
+    };
+
+    match name.0.as_slice() {
+        [Token::Ident(name)] if is_custom_property(name) => Some((name.clone(), fallback)),
+        _ => None,
+    }
+}
+
+/// Returns the Some value of a property with var() references, once they are substituted with the
+/// custom properties in `values`, or None if it is invalid at computed-value time.
+/// `shorthand` is the Some shorthand the value was given to, which is expanded to find the value
+/// of the longhand `name`.
+pub fn substitute_value(
+    name: &str,
+    tokens: &TokenList,
+    shorthand: Option<&str>,
+    values: &PropertyMap,
+) -> Option<Value> {
+    let tokens = substitute(tokens, &mut |n| match values.get(n) {
+        Some(Value::Tokens(tokens)) => Some(tokens.clone()),
+        _ => None,
+    })?;
+    if !is_value_syntax(&tokens) {
+        return None;
+    }
+
+    let value = CssParser::parse_declaration_value(&tokens.to_string())?;
+    let declarations = match shorthand {
+        Some(shorthand) => expand_shorthand(shorthand, &value)?,
+        None => vec![Declaration {
+            name: name.to_string(),
+            value,
+            important: false,
+        }],
+    };
+
+    declarations
+        .into_iter()
+        .find(|d| d.name == name && is_valid(d))
+        .map(|d| d.value)
+}
+
+/// Can the tokens be parsed as a declaration's value? Brackets have to be balanced, and the only
+/// other characters allowed are separators, and operators within math functions.
+fn is_value_syntax(tokens: &TokenList) -> bool {
+    // Whether each function the tokens are in is a math function.
+    let mut math = Vec::new();
+
+    for token in &tokens.0 {
+        match token {
+            Token::Function(f) => {
+                let f = f.to_lowercase();
+                math.push(matches!(&*f, "calc" | "min" | "max" | "clamp"));
+            }
+            Token::Delim('(') if math.last() == Some(&true) => math.push(true),
+            Token::Delim(')') if math.pop().is_none() => return false,
+            Token::Delim(')' | ',' | '/') => {}
+            Token::Delim('+' | '*') if math.last() == Some(&true) => {}
+            Token::Delim(_) => return false,
+            _ => {}
+        }
+    }
+
+    math.is_empty()
+}
@@ -0,0 +1,279 @@
+// Reading TrueType and OpenType fonts. Only the tables needed to measure text are read.
+// https://learn.microsoft.com/en-us/typography/opentype/spec/otff
+
+use crate::style::computed::FontStyle;
+use crate::text::shaping::{Kerning, Ligatures};
+use std::collections::HashMap;
+
+/// Font data, which is read as big-endian numbers. Reads past the end return None.
+#[derive(Clone, Copy)]
+pub(super) struct Data<'a>(pub &'a [u8]);
+
+impl<'a> Data<'a> {
+    pub fn u16(self, at: usize) -> Option<u16> {
+        let b = self.0.get(at..at + 2)?;
+        Some(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    pub fn i16(self, at: usize) -> Option<i16> {
+        self.u16(at).map(|n| n as i16)
+    }
+
+    pub fn u32(self, at: usize) -> Option<u32> {
+        let b = self.0.get(at..at + 4)?;
+        Some(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub fn tag(self, at: usize) -> Option<[u8; 4]> {
+        self.0.get(at..at + 4)?.try_into().ok()
+    }
+
+    /// Returns the Some data from an offset, or None if it's past the end.
+    pub fn at(self, offset: usize) -> Option<Data<'a>> {
+        self.0.get(offset..).map(Data)
+    }
+}
+
+/// A font face, with the metrics used to lay out text. Sizes are in font units, of which there
+/// are `units_per_em` in the font size.
+pub struct Font {
+    pub family: String,
+    /// A weight from 1 to 1000, where normal is 400 and bold is 700.
+    pub weight: u16,
+    pub style: FontStyle,
+    pub units_per_em: u16,
+    /// The distance the glyphs reach above the baseline.
+    pub ascent: i16,
+    /// The distance the glyphs reach below the baseline, which is positive.
+    pub descent: i16,
+    /// The space the font suggests between lines.
+    pub line_gap: i16,
+    /// The height of lowercase letters.
+    pub x_height: i16,
+    /// The advance width of each glyph. Glyphs past the end have the same advance as the last.
+    advances: Vec<u16>,
+    /// The glyph of each character the font has.
+    glyphs: HashMap<char, u16>,
+    pub(super) ligatures: Ligatures,
+    pub(super) kerning: Kerning,
+}
+
+impl Font {
+    /// Returns the fonts in a font file, which has several if it's a collection. Eg: a .ttc file
+    /// Returns an empty list if it isn't a font file.
+    pub fn parse_file(data: &[u8]) -> Vec<Font> {
+        let d = Data(data);
+        if data.starts_with(b"ttcf") {
+            let count = d.u32(8).unwrap_or(0) as usize;
+            return (0..count)
+                .filter_map(|i| d.u32(12 + 4 * i))
+                .filter_map(|offset| Font::parse(data, offset as usize))
+                .collect();
+        }
+        Font::parse(data, 0).into_iter().collect()
+    }
+
+    /// Returns the Some font with its table directory at `offset`, or None if it isn't valid.
+    fn parse(data: &[u8], offset: usize) -> Option<Font> {
+        let d = Data(data);
+        // TrueType outlines, CFF outlines (OTTO), or old Apple TrueType.
+        if !matches!(&d.tag(offset)?, b"\0\x01\0\0" | b"OTTO" | b"true") {
+            return None;
+        }
+
+        let mut tables = HashMap::new();
+        for i in 0..d.u16(offset + 4)? as usize {
+            let record = offset + 12 + 16 * i;
+            let start = d.u32(record + 8)? as usize;
+            let len = d.u32(record + 12)? as usize;
+            tables.insert(d.tag(record)?, Data(data.get(start..start + len)?));
+        }
+        let table = |tag: &[u8; 4]| tables.get(tag).copied();
+
+        let head = table(b"head")?;
+        let hhea = table(b"hhea")?;
+        let units_per_em = head.u16(18)?;
+        let glyph_count = table(b"maxp")?.u16(4)?;
+
+        let metric_count = hhea.u16(34)?;
+        let hmtx = table(b"hmtx")?;
+        let advances = (0..metric_count as usize)
+            .map(|i| hmtx.u16(4 * i))
+            .collect::<Option<Vec<_>>>()?;
+
+        // The OS/2 table is required by OpenType, but not by Apple's TrueType.
+        let mac_style = head.u16(44)?;
+        let mut font = Font {
+            family: table(b"name").and_then(family_name).unwrap_or_default(),
+            weight: if mac_style & 1 != 0 { 700 } else { 400 },
+            style: match mac_style & 2 {
+                0 => FontStyle::Normal,
+                _ => FontStyle::Italic,
+            },
+            units_per_em,
+            ascent: hhea.i16(4)?,
+            descent: -hhea.i16(6)?,
+            line_gap: hhea.i16(8)?,
+            x_height: (units_per_em / 2) as i16,
+            advances,
+            glyphs: table(b"cmap").and_then(parse_cmap).unwrap_or_default(),
+            ligatures: Ligatures::default(),
+            kerning: Kerning::default(),
+        };
+        font.glyphs.retain(|_, g| *g < glyph_count);
+
+        if let Some(os2) = table(b"OS/2") {
+            font.weight = os2.u16(4)?.clamp(1, 1000);
+            let selection = os2.u16(62)?;
+            font.style = match selection {
+                s if s & 1 != 0 => FontStyle::Italic,
+                s if s & (1 << 9) != 0 => FontStyle::Oblique,
+                _ => FontStyle::Normal,
+            };
+            // Some fonts say their typographic metrics are the ones to use.
+            if selection & (1 << 7) != 0 {
+                font.ascent = os2.i16(68)?;
+                font.descent = -os2.i16(70)?;
+                font.line_gap = os2.i16(72)?;
+            }
+            if let Some(x_height) = os2.i16(86).filter(|h| os2.u16(0) >= Some(2) && *h > 0) {
+                font.x_height = x_height;
+            }
+        }
+
+        if let Some(gsub) = table(b"GSUB") {
+            font.ligatures = Ligatures::parse(gsub).unwrap_or_default();
+        }
+        font.kerning = match table(b"GPOS").and_then(Kerning::parse_gpos) {
+            Some(kerning) if !kerning.is_empty() => kerning,
+            _ => table(b"kern")
+                .and_then(Kerning::parse_kern)
+                .unwrap_or_default(),
+        };
+
+        Some(font)
+    }
+
+    /// Returns the Some glyph of a character, or None if the font doesn't have one.
+    pub fn glyph(&self, c: char) -> Option<u16> {
+        self.glyphs.get(&c).copied()
+    }
+
+    /// Returns the advance width of a glyph.
+    pub fn advance(&self, glyph: u16) -> u16 {
+        let advance = self.advances.get(glyph as usize);
+        advance.or(self.advances.last()).copied().unwrap_or(0)
+    }
+}
+
+/// Returns the Some family name from a `name` table, or None if it doesn't have one.
+fn family_name(name: Data) -> Option<String> {
+    let count = name.u16(2)? as usize;
+    let strings = name.u16(4)? as usize;
+
+    let records = (0..count).filter_map(|i| {
+        let record = 6 + 12 * i;
+        let platform = name.u16(record)?;
+        let id = name.u16(record + 6)?;
+        let len = name.u16(record + 8)? as usize;
+        let start = strings + name.u16(record + 10)? as usize;
+        Some((id, platform, name.0.get(start..start + len)?))
+    });
+
+    // The typographic family (16) groups more faces than the legacy family (1). Eg: "Light"
+    let mut best: Option<(u16, String)> = None;
+    for (id, platform, bytes) in records {
+        if !matches!(id, 1 | 16) || best.as_ref().is_some_and(|(b, _)| *b >= id) {
+            continue;
+        }
+        let name = match platform {
+            // Windows and Unicode names are UTF-16.
+            0 | 3 => String::from_utf16_lossy(
+                &bytes
+                    .chunks_exact(2)
+                    .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                    .collect::<Vec<_>>(),
+            ),
+            // Mac names are (mostly) ASCII.
+            1 => bytes.iter().map(|b| *b as char).collect(),
+            _ => continue,
+        };
+        best = Some((id, name));
+    }
+
+    best.map(|(_, name)| name)
+}
+
+/// Returns the Some glyph of each character from a `cmap` table, or None if it doesn't have a
+/// Unicode subtable we can read.
+fn parse_cmap(cmap: Data) -> Option<HashMap<char, u16>> {
+    let subtables = (0..cmap.u16(2)? as usize).filter_map(|i| {
+        let record = 4 + 8 * i;
+        let platform = cmap.u16(record)?;
+        let encoding = cmap.u16(record + 2)?;
+        let subtable = cmap.at(cmap.u32(record + 4)? as usize)?;
+        Some((platform, encoding, subtable, subtable.u16(0)?))
+    });
+
+    // Full Unicode subtables are preferred over the Basic Multilingual Plane.
+    let (_, _, subtable, format) = subtables
+        .filter(|(_, _, _, format)| matches!(format, 4 | 12))
+        .max_by_key(
+            |(platform, encoding, _, format)| match (platform, encoding, format) {
+                (3, 10, 12) | (0, _, 12) => 2,
+                (3, 1, 4) | (0, _, 4) => 1,
+                _ => 0,
+            },
+        )?;
+
+    let mut glyphs = HashMap::new();
+    match format {
+        4 => {
+            let segments = subtable.u16(6)? as usize / 2;
+            let (ends, starts) = (14, 16 + 2 * segments);
+            let (deltas, range_offsets) = (starts + 2 * segments, starts + 4 * segments);
+
+            for s in 0..segments {
+                let end = subtable.u16(ends + 2 * s)?;
+                let start = subtable.u16(starts + 2 * s)?;
+                let delta = subtable.u16(deltas + 2 * s)?;
+                let range_offset = subtable.u16(range_offsets + 2 * s)? as usize;
+
+                for code in start..=end.min(0xFFFE) {
+                    let glyph = match range_offset {
+                        0 => code.wrapping_add(delta),
+                        // The offset is from its own position in the subtable.
+                        _ => {
+                            let at = range_offsets + 2 * s + range_offset;
+                            match subtable.u16(at + 2 * (code - start) as usize)? {
+                                0 => 0,
+                                g => g.wrapping_add(delta),
+                            }
+                        }
+                    };
+                    if let (Some(c), 1..) = (char::from_u32(code as u32), glyph) {
+                        glyphs.insert(c, glyph);
+                    }
+                }
+            }
+        }
+        _ => {
+            for g in 0..subtable.u32(12)? as usize {
+                let group = 16 + 12 * g;
+                let start = subtable.u32(group)?;
+                let end = subtable.u32(group + 4)?;
+                let glyph = subtable.u32(group + 8)?;
+                for code in start..=end {
+                    let (Some(c), Ok(g)) =
+                        (char::from_u32(code), u16::try_from(glyph + code - start))
+                    else {
+                        continue;
+                    };
+                    glyphs.insert(c, g);
+                }
+            }
+        }
+    }
+
+    Some(glyphs)
+}
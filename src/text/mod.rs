@@ -0,0 +1,226 @@
+// Fonts, and measuring text with them.
+// https://www.w3.org/TR/css-fonts-4/#font-matching-algorithm
+
+pub mod font;
+pub mod shaping;
+
+use crate::style::computed::{ComputedStyle, FontStyle, LineHeight};
+use crate::text::font::Font;
+use crate::text::shaping::{shape_run, Glyph};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{OnceLock, RwLock};
+use std::{fs, io};
+
+/// The font used when no other font has a glyph, so that text can always be measured.
+const DEFAULT_FONT: &[u8] = include_bytes!("../../fonts/DejaVuSans.ttf");
+
+/// The families which stand for a kind of font, rather than a font.
+const GENERIC_FAMILIES: [&str; 9] = [
+    "serif",
+    "sans-serif",
+    "monospace",
+    "cursive",
+    "fantasy",
+    "system-ui",
+    "ui-serif",
+    "ui-sans-serif",
+    "ui-monospace",
+];
+
+/// The sizes of a font at a font size, in px.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FontMetrics {
+    pub ascent: f32,
+    /// The distance the glyphs reach below the baseline, which is positive.
+    pub descent: f32,
+    pub line_gap: f32,
+    pub x_height: f32,
+}
+
+/// Text shaped into glyphs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShapedText {
+    pub glyphs: Vec<Glyph>,
+    pub width: f32,
+}
+
+/// The fonts which text can be laid out with.
+pub struct FontDatabase {
+    fonts: Vec<Font>,
+    /// The family used for each generic family. Eg: serif -> DejaVu Serif
+    generic_families: HashMap<String, String>,
+}
+
+impl Default for FontDatabase {
+    fn default() -> Self {
+        FontDatabase::new()
+    }
+}
+
+impl FontDatabase {
+    /// Creates a database with only the default font, which every generic family uses.
+    pub fn new() -> Self {
+        let fonts = Font::parse_file(DEFAULT_FONT);
+        let family = fonts[0].family.clone();
+        FontDatabase {
+            fonts,
+            generic_families: GENERIC_FAMILIES
+                .iter()
+                .map(|g| (g.to_string(), family.clone()))
+                .collect(),
+        }
+    }
+
+    /// Adds the fonts in a file, returning the number of them.
+    /// Files which aren't TrueType or OpenType fonts have none.
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> io::Result<usize> {
+        let fonts = Font::parse_file(&fs::read(path)?);
+        let count = fonts.len();
+        self.fonts.extend(fonts);
+        Ok(count)
+    }
+
+    /// Adds the fonts in every file in a directory, and the directories in it, returning the
+    /// number of them.
+    pub fn load_directory(&mut self, path: impl AsRef<Path>) -> io::Result<usize> {
+        let mut count = 0;
+        let mut entries: Vec<_> = fs::read_dir(path)?.collect::<Result<_, _>>()?;
+        // Load the fonts in a consistent order, so the same fonts are always chosen.
+        entries.sort_by_key(|e| e.path());
+
+        for entry in entries {
+            let path = entry.path();
+            count += match path.is_dir() {
+                true => self.load_directory(&path)?,
+                false => self.load_file(&path)?,
+            };
+        }
+        Ok(count)
+    }
+
+    /// Sets the family a generic family uses. Eg: monospace -> DejaVu Sans Mono
+    pub fn set_generic_family(&mut self, generic: &str, family: &str) {
+        self.generic_families
+            .insert(generic.to_lowercase(), family.to_string());
+    }
+
+    pub fn font(&self, id: usize) -> &Font {
+        &self.fonts[id]
+    }
+
+    /// Returns the Some font of a family which best matches a weight and style, or None if there
+    /// are no fonts in the family.
+    /// https://www.w3.org/TR/css-fonts-4/#font-style-matching
+    fn match_family(&self, family: &str, weight: f32, style: FontStyle) -> Option<usize> {
+        let family = self
+            .generic_families
+            .get(&family.to_lowercase())
+            .map_or(family, |f| f.as_str());
+        let faces: Vec<usize> = (0..self.fonts.len())
+            .filter(|i| self.fonts[*i].family.eq_ignore_ascii_case(family))
+            .collect();
+
+        // Styles are tried in order, and only the faces of the first available style are kept.
+        let styles = match style {
+            FontStyle::Italic => [FontStyle::Italic, FontStyle::Oblique, FontStyle::Normal],
+            FontStyle::Oblique => [FontStyle::Oblique, FontStyle::Italic, FontStyle::Normal],
+            FontStyle::Normal => [FontStyle::Normal, FontStyle::Oblique, FontStyle::Italic],
+        };
+        let style = styles
+            .into_iter()
+            .find(|s| faces.iter().any(|i| self.fonts[*i].style == *s))?;
+
+        // Weights are tried in order of how close they are, with heavier or lighter weights
+        // first depending on the desired weight.
+        let weight = weight.round() as i32;
+        let rank = |w: i32| match weight {
+            400..=500 if (weight..=500).contains(&w) => (0, w - weight),
+            400..=500 if w < weight => (1, weight - w),
+            400..=500 => (2, w - weight),
+            _ if weight < 400 && w <= weight => (0, weight - w),
+            _ if weight < 400 => (1, w - weight),
+            _ if w >= weight => (0, w - weight),
+            _ => (1, weight - w),
+        };
+        faces
+            .into_iter()
+            .filter(|i| self.fonts[*i].style == style)
+            .min_by_key(|i| rank(self.fonts[*i].weight as i32))
+    }
+
+    /// Returns the fonts to use for a style, in the order they're tried for each character.
+    /// The default font is always last.
+    pub fn fallback_list(&self, style: &ComputedStyle) -> Vec<usize> {
+        let mut fonts: Vec<usize> = style
+            .font_family
+            .iter()
+            .filter_map(|f| self.match_family(f, style.font_weight, style.font_style))
+            .collect();
+        if !fonts.contains(&0) {
+            fonts.push(0);
+        }
+        fonts
+    }
+
+    /// Returns the metrics of the first font of a style.
+    pub fn metrics(&self, style: &ComputedStyle) -> FontMetrics {
+        let font = &self.fonts[self.fallback_list(style)[0]];
+        let scale = style.font_size / font.units_per_em as f32;
+        FontMetrics {
+            ascent: font.ascent as f32 * scale,
+            descent: font.descent as f32 * scale,
+            line_gap: font.line_gap as f32 * scale,
+            x_height: font.x_height as f32 * scale,
+        }
+    }
+
+    /// Returns the used line height of a style. `normal` is the spacing the first font suggests.
+    pub fn line_height(&self, style: &ComputedStyle) -> f32 {
+        match style.line_height {
+            LineHeight::Normal => {
+                let m = self.metrics(style);
+                m.ascent + m.descent + m.line_gap
+            }
+            LineHeight::Number(n) => style.font_size * n,
+            LineHeight::Px(px) => px,
+        }
+    }
+
+    /// Shapes text with the fonts of a style. Each character uses the first font which has it.
+    pub fn shape(&self, text: &str, style: &ComputedStyle) -> ShapedText {
+        let fonts = self.fallback_list(style);
+        let font_for = |c: char| {
+            let font = fonts.iter().find(|f| self.fonts[**f].glyph(c).is_some());
+            *font.unwrap_or(&fonts[0])
+        };
+
+        // Split the text into runs of characters which use the same font.
+        let mut glyphs = Vec::new();
+        let mut chars = text.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            let font = font_for(c);
+            let mut end = start + c.len_utf8();
+            while let Some((i, c)) = chars.next_if(|(_, c)| font_for(*c) == font) {
+                end = i + c.len_utf8();
+            }
+            glyphs.extend(shape_run(
+                &self.fonts[font],
+                font,
+                &text[start..end],
+                style.font_size,
+                start,
+            ));
+        }
+
+        let width = glyphs.iter().map(|g| g.advance).sum();
+        ShapedText { glyphs, width }
+    }
+}
+
+/// Returns the fonts layout uses, which start out with only the default font.
+/// Eg: `fonts().write().unwrap().load_directory("/usr/share/fonts")`
+pub fn fonts() -> &'static RwLock<FontDatabase> {
+    static FONTS: OnceLock<RwLock<FontDatabase>> = OnceLock::new();
+    FONTS.get_or_init(|| RwLock::new(FontDatabase::new()))
+}
@@ -0,0 +1,318 @@
+// Shaping text into glyphs, with ligatures and kerning.
+// https://learn.microsoft.com/en-us/typography/opentype/spec/gsub
+// https://learn.microsoft.com/en-us/typography/opentype/spec/gpos
+
+use crate::text::font::{Data, Font};
+use std::collections::{HashMap, HashSet};
+
+/// A glyph of shaped text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Glyph {
+    /// The index of the font in the [FontDatabase](crate::text::FontDatabase).
+    pub font: usize,
+    pub id: u16,
+    /// The distance to the next glyph, in px.
+    pub advance: f32,
+    /// The byte offset in the text of the first character the glyph shows.
+    pub cluster: usize,
+}
+
+/// The ligatures of a font, which replace sequences of glyphs. Eg: "fi"
+/// For each first glyph, the rest of each sequence, with the ligature that replaces it.
+#[derive(Default)]
+pub struct Ligatures(HashMap<u16, Vec<(Vec<u16>, u16)>>);
+
+/// Adjustments to the space between pairs of glyphs, in font units.
+#[derive(Default)]
+pub struct Kerning {
+    pairs: HashMap<(u16, u16), i16>,
+    /// Adjustments between classes of glyphs, which are tried in order if a pair has none.
+    classes: Vec<ClassKerning>,
+}
+
+/// An adjustment for each pair of classes, for the first glyphs in `coverage`.
+struct ClassKerning {
+    coverage: HashSet<u16>,
+    first: HashMap<u16, u16>,
+    second: HashMap<u16, u16>,
+    second_count: usize,
+    values: Vec<i16>,
+}
+
+/// Returns the lookup tables of the features with a tag in a GSUB or GPOS table. Extension
+/// lookups are replaced by the subtables they point to. Returns the type of each subtable.
+/// https://learn.microsoft.com/en-us/typography/opentype/spec/chapter2
+fn feature_lookups<'a>(
+    table: Data<'a>,
+    tag: &[u8; 4],
+    extension: u16,
+) -> Option<Vec<(u16, Data<'a>)>> {
+    let scripts = table.at(table.u16(4)? as usize)?;
+    let features = table.at(table.u16(6)? as usize)?;
+    let lookups = table.at(table.u16(8)? as usize)?;
+
+    // The features of the default language of the default and Latin scripts, or else all of them.
+    let mut indices: Vec<u16> = Vec::new();
+    for i in 0..scripts.u16(0)? as usize {
+        if !matches!(&scripts.tag(2 + 6 * i)?, b"DFLT" | b"latn") {
+            continue;
+        }
+        let script = scripts.at(scripts.u16(6 + 6 * i)? as usize)?;
+        let lang = match script.u16(0)? {
+            0 => continue,
+            offset => script.at(offset as usize)?,
+        };
+        indices.extend((0..lang.u16(4)? as usize).filter_map(|f| lang.u16(6 + 2 * f)));
+    }
+    if indices.is_empty() {
+        indices = (0..features.u16(0)?).collect();
+    }
+
+    let mut lookup_indices: Vec<u16> = Vec::new();
+    for f in indices {
+        let record = 2 + 6 * f as usize;
+        if features.tag(record)? != *tag {
+            continue;
+        }
+        let feature = features.at(features.u16(record + 4)? as usize)?;
+        lookup_indices.extend((0..feature.u16(2)? as usize).filter_map(|l| feature.u16(4 + 2 * l)));
+    }
+    // Lookups are applied in the order of the lookup list.
+    lookup_indices.sort();
+    lookup_indices.dedup();
+
+    let mut subtables = Vec::new();
+    for l in lookup_indices {
+        let lookup = lookups.at(lookups.u16(2 + 2 * l as usize)? as usize)?;
+        let kind = lookup.u16(0)?;
+        for s in 0..lookup.u16(4)? as usize {
+            let subtable = lookup.at(lookup.u16(6 + 2 * s)? as usize)?;
+            subtables.push(match kind {
+                k if k == extension => (subtable.u16(2)?, subtable.at(subtable.u32(4)? as usize)?),
+                k => (k, subtable),
+            });
+        }
+    }
+
+    Some(subtables)
+}
+
+/// Returns the glyphs of a coverage table, in the order of their coverage index.
+fn coverage(table: Data) -> Option<Vec<u16>> {
+    match table.u16(0)? {
+        1 => (0..table.u16(2)? as usize)
+            .map(|i| table.u16(4 + 2 * i))
+            .collect(),
+        2 => {
+            let mut glyphs = Vec::new();
+            for r in 0..table.u16(2)? as usize {
+                let (start, end) = (table.u16(4 + 6 * r)?, table.u16(6 + 6 * r)?);
+                glyphs.extend(start..=end);
+            }
+            Some(glyphs)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the class of each glyph in a class definition table. Other glyphs are in class 0.
+fn class_def(table: Data) -> Option<HashMap<u16, u16>> {
+    match table.u16(0)? {
+        1 => {
+            let start = table.u16(2)?;
+            (0..table.u16(4)?)
+                .map(|i| Some((start.wrapping_add(i), table.u16(6 + 2 * i as usize)?)))
+                .collect()
+        }
+        2 => {
+            let mut classes = HashMap::new();
+            for r in 0..table.u16(2)? as usize {
+                let (start, end) = (table.u16(4 + 6 * r)?, table.u16(6 + 6 * r)?);
+                let class = table.u16(8 + 6 * r)?;
+                classes.extend((start..=end).map(|g| (g, class)));
+            }
+            Some(classes)
+        }
+        _ => None,
+    }
+}
+
+impl Ligatures {
+    /// Returns the Some standard ligatures (the `liga` feature) of a GSUB table, or None if it
+    /// can't be read.
+    pub(super) fn parse(gsub: Data) -> Option<Self> {
+        let mut ligatures: HashMap<u16, Vec<(Vec<u16>, u16)>> = HashMap::new();
+
+        // Only ligature substitutions (4) are used. Extension substitutions (7) point to others.
+        for (_, subtable) in feature_lookups(gsub, b"liga", 7)?
+            .into_iter()
+            .filter(|(k, _)| *k == 4)
+        {
+            let first_glyphs = coverage(subtable.at(subtable.u16(2)? as usize)?)?;
+            for (i, first) in first_glyphs.into_iter().enumerate() {
+                let set = subtable.at(subtable.u16(6 + 2 * i)? as usize)?;
+                for l in 0..set.u16(0)? as usize {
+                    let ligature = set.at(set.u16(2 + 2 * l)? as usize)?;
+                    let glyph = ligature.u16(0)?;
+                    let rest = (1..ligature.u16(2)? as usize)
+                        .map(|c| ligature.u16(2 + 2 * c))
+                        .collect::<Option<Vec<_>>>()?;
+                    ligatures.entry(first).or_default().push((rest, glyph));
+                }
+            }
+        }
+
+        Some(Ligatures(ligatures))
+    }
+
+    /// Replaces sequences of glyphs with their ligatures. Each glyph is paired with the byte
+    /// offset of its character, which a ligature takes from its first glyph.
+    fn apply(&self, glyphs: &mut Vec<(u16, usize)>) {
+        let mut i = 0;
+        while i < glyphs.len() {
+            let rest = &glyphs[i + 1..];
+            let ligature = self.0.get(&glyphs[i].0).and_then(|ligatures| {
+                ligatures.iter().find(|(sequence, _)| {
+                    sequence.len() <= rest.len()
+                        && sequence.iter().zip(rest).all(|(a, (b, _))| a == b)
+                })
+            });
+            if let Some((sequence, ligature)) = ligature {
+                glyphs[i].0 = *ligature;
+                glyphs.drain(i + 1..i + 1 + sequence.len());
+            }
+            i += 1;
+        }
+    }
+}
+
+impl Kerning {
+    pub(super) fn is_empty(&self) -> bool {
+        self.pairs.is_empty() && self.classes.is_empty()
+    }
+
+    /// Returns the Some kerning (the `kern` feature) of a GPOS table, or None if it can't be read.
+    pub(super) fn parse_gpos(gpos: Data) -> Option<Self> {
+        let mut kerning = Kerning::default();
+
+        // Only pair adjustments (2) are used. Extension positioning (9) points to others.
+        for (_, subtable) in feature_lookups(gpos, b"kern", 9)?
+            .into_iter()
+            .filter(|(k, _)| *k == 2)
+        {
+            let first_glyphs = coverage(subtable.at(subtable.u16(2)? as usize)?)?;
+            let (format1, format2) = (subtable.u16(4)?, subtable.u16(6)?);
+            // Only the x advance of the first glyph is used. Each field of a value is 2 bytes.
+            let advance_at = 2 * (format1 & 0b11).count_ones() as usize;
+            let has_advance = format1 & 0b100 != 0;
+            let record_len = 2 * (format1.count_ones() + format2.count_ones()) as usize;
+
+            match subtable.u16(0)? {
+                1 => {
+                    for (i, first) in first_glyphs.into_iter().enumerate() {
+                        let set = subtable.at(subtable.u16(10 + 2 * i)? as usize)?;
+                        for p in 0..set.u16(0)? as usize {
+                            let record = 2 + p * (2 + record_len);
+                            let second = set.u16(record)?;
+                            let value = match has_advance {
+                                true => set.i16(record + 2 + advance_at)?,
+                                false => 0,
+                            };
+                            // The first subtable with the pair decides its adjustment.
+                            kerning.pairs.entry((first, second)).or_insert(value);
+                        }
+                    }
+                }
+                2 => {
+                    let first_count = subtable.u16(12)? as usize;
+                    let second_count = subtable.u16(14)? as usize;
+                    let values = (0..first_count * second_count)
+                        .map(|i| match has_advance {
+                            true => subtable.i16(16 + i * record_len + advance_at),
+                            false => Some(0),
+                        })
+                        .collect::<Option<Vec<_>>>()?;
+                    kerning.classes.push(ClassKerning {
+                        coverage: first_glyphs.into_iter().collect(),
+                        first: class_def(subtable.at(subtable.u16(8)? as usize)?)?,
+                        second: class_def(subtable.at(subtable.u16(10)? as usize)?)?,
+                        second_count,
+                        values,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        Some(kerning)
+    }
+
+    /// Returns the Some kerning of an old `kern` table, or None if it can't be read. Only
+    /// horizontal pair subtables are used.
+    pub(super) fn parse_kern(kern: Data) -> Option<Self> {
+        let mut kerning = Kerning::default();
+        let mut subtable = 4;
+
+        for _ in 0..kern.u16(2)? {
+            let len = kern.u16(subtable + 2)? as usize;
+            let coverage = kern.u16(subtable + 4)?;
+            // Format 0, horizontal, and not minimum values or cross-stream.
+            if coverage & 0xFF07 == 1 {
+                for p in 0..kern.u16(subtable + 6)? as usize {
+                    let pair = subtable + 14 + 6 * p;
+                    let glyphs = (kern.u16(pair)?, kern.u16(pair + 2)?);
+                    kerning.pairs.entry(glyphs).or_insert(kern.i16(pair + 4)?);
+                }
+            }
+            subtable += len;
+        }
+
+        Some(kerning)
+    }
+
+    /// Returns the adjustment to the advance of the first glyph of a pair.
+    fn adjustment(&self, first: u16, second: u16) -> i16 {
+        if let Some(value) = self.pairs.get(&(first, second)) {
+            return *value;
+        }
+        self.classes
+            .iter()
+            .find(|c| c.coverage.contains(&first))
+            .map_or(0, |c| {
+                let first = *c.first.get(&first).unwrap_or(&0) as usize;
+                let second = *c.second.get(&second).unwrap_or(&0) as usize;
+                c.values
+                    .get(first * c.second_count + second)
+                    .copied()
+                    .unwrap_or(0)
+            })
+    }
+}
+
+/// Shapes text in one font at a size in px, returning its glyphs. `offset` is added to the
+/// clusters of the glyphs, for text which is part of a longer run.
+pub fn shape_run(font: &Font, id: usize, text: &str, size: f32, offset: usize) -> Vec<Glyph> {
+    let mut glyphs: Vec<(u16, usize)> = text
+        .char_indices()
+        // Characters the font doesn't have use its missing glyph (0).
+        .map(|(i, c)| (font.glyph(c).unwrap_or(0), offset + i))
+        .collect();
+    font.ligatures.apply(&mut glyphs);
+
+    let scale = size / font.units_per_em as f32;
+    (0..glyphs.len())
+        .map(|i| {
+            let (glyph, cluster) = glyphs[i];
+            let kerning = match glyphs.get(i + 1) {
+                Some((next, _)) => font.kerning.adjustment(glyph, *next),
+                None => 0,
+            };
+            Glyph {
+                font: id,
+                id: glyph,
+                advance: (font.advance(glyph) as i32 + kerning as i32) as f32 * scale,
+                cluster,
+            }
+        })
+        .collect()
+}
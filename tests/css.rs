@@ -0,0 +1,747 @@
+// Tests for parsing and cascading css, which check that invalid css is dropped the way
+// CSS Syntax says.
+
+use parsnip::document::Document;
+use parsnip::dom::NodeType;
+use parsnip::loader::{resolve_url, FileLoader};
+use parsnip::parsing::css_parser::CssParser;
+use parsnip::parsing::html_parser::HtmlParser;
+use parsnip::style::css::{Origin, PseudoElement, Rule, RuleError, StyledNode, Value};
+use parsnip::style::properties;
+use parsnip::style::serialize::declarations_text;
+use parsnip::style::style_tree::style_tree;
+use parsnip::style::values::Viewport;
+use std::collections::HashMap;
+
+#[test]
+fn properties_have_initial_values_and_may_be_inherited() {
+    let keyword = |k: &str| Some(Value::Keyword(k.to_string()));
+    assert_eq!(properties::initial_value("display"), keyword("inline"));
+    assert_eq!(properties::initial_value("text-align"), keyword("start"));
+    assert_eq!(
+        properties::initial_value("margin-top"),
+        Some(Value::Number(0.0))
+    );
+    assert_eq!(properties::initial_value("margin"), None);
+    assert_eq!(properties::initial_value("foo"), None);
+
+    assert!(properties::is_inherited("color"));
+    assert!(properties::is_inherited("font-size"));
+    assert!(!properties::is_inherited("margin-top"));
+    assert!(!properties::is_inherited("foo"));
+}
+
+#[test]
+fn declarations_which_dont_match_the_grammar_of_their_property_are_dropped() {
+    let sheet = CssParser::parse(
+        "p { display: 10px; color: red; margin-top: auto; padding-top: -5px; foo: 1px; \
+         width: 1px 2px; height: inherit; text-align: CENTER; }"
+            .to_string(),
+    );
+    let names: Vec<_> = sheet.rules[0]
+        .declarations
+        .iter()
+        .map(|d| d.name.as_str())
+        .collect();
+    assert_eq!(names, ["color", "margin-top", "height", "text-align"]);
+}
+
+/// Returns the Some computed value of a property of the element with an id, or None if there
+/// isn't one.
+fn value_of(styled: &StyledNode, id: &str, name: &str) -> Option<Value> {
+    if let NodeType::Element(ref elem) = styled.node.node_type {
+        if styled.pseudo.is_none() && elem.id().map(String::as_str) == Some(id) {
+            return styled.computed_value(name);
+        }
+    }
+    styled
+        .children
+        .iter()
+        .find_map(|child| value_of(child, id, name))
+}
+
+/// Returns the text-align of the element with the id "a" in some html, styled by stylesheets
+/// given as (origin, css).
+fn text_align(html: &str, sheets: &[(Origin, &str)]) -> Option<Value> {
+    let sheets: Vec<_> = sheets
+        .iter()
+        .map(|&(origin, css)| CssParser::parse_with_origin(css.to_string(), origin))
+        .collect();
+    let root = HtmlParser::parse(html.to_string());
+    value_of(
+        &style_tree(&root, &sheets, Viewport::default()),
+        "a",
+        "text-align",
+    )
+}
+
+fn keyword(k: &str) -> Option<Value> {
+    Some(Value::Keyword(k.to_string()))
+}
+
+#[test]
+fn later_origins_win_and_important_declarations_reverse_them() {
+    let div = "<div id='a'></div>";
+    let user_agent = (Origin::UserAgent, "div { text-align: left; }");
+    let user = (Origin::User, "div { text-align: right; }");
+    let author = (Origin::Author, "div { text-align: center; }");
+    assert_eq!(
+        text_align(div, &[author, user, user_agent]),
+        keyword("center")
+    );
+    assert_eq!(text_align(div, &[user_agent, user]), keyword("right"));
+
+    let user_agent = (Origin::UserAgent, "div { text-align: left !important; }");
+    let user = (Origin::User, "div { text-align: right !important; }");
+    let author = (Origin::Author, "div { text-align: center !important; }");
+    assert_eq!(
+        text_align(div, &[user_agent, user, author]),
+        keyword("left")
+    );
+    assert_eq!(text_align(div, &[user, author]), keyword("right"));
+    let important = (Origin::Author, "div { text-align: center !important; }");
+    let normal = (Origin::User, "#a { text-align: right; }");
+    assert_eq!(text_align(div, &[important, normal]), keyword("center"));
+}
+
+#[test]
+fn more_specific_and_later_rules_win_within_an_origin() {
+    let div = "<div id='a' class='b'></div>";
+    let css = "#a { text-align: center; } .b { text-align: right; } div { text-align: left; }";
+    assert_eq!(text_align(div, &[(Origin::Author, css)]), keyword("center"));
+    let css = "div { text-align: left; } div { text-align: right; }";
+    assert_eq!(text_align(div, &[(Origin::Author, css)]), keyword("right"));
+    let css = "div { text-align: left !important; } #a { text-align: right; }";
+    assert_eq!(text_align(div, &[(Origin::Author, css)]), keyword("left"));
+}
+
+#[test]
+fn inline_declarations_win_over_author_rules() {
+    let div = "<div id='a' style='text-align: right'></div>";
+    let css = "#a { text-align: center; }";
+    assert_eq!(text_align(div, &[(Origin::Author, css)]), keyword("right"));
+    let css = "#a { text-align: center !important; }";
+    assert_eq!(text_align(div, &[(Origin::Author, css)]), keyword("center"));
+    let div = "<div id='a' style='text-align: right !important'></div>";
+    assert_eq!(text_align(div, &[(Origin::Author, css)]), keyword("right"));
+    let user = (Origin::User, "div { text-align: left !important; }");
+    assert_eq!(text_align(div, &[user]), keyword("left"));
+}
+
+#[test]
+fn later_layers_win_and_important_declarations_reverse_them() {
+    let css = "div { text-align: left; } div { text-align: right; } div { text-align: center; }";
+    let mut sheet = CssParser::parse(css.to_string());
+    sheet.layers = vec!["a".to_string(), "b".to_string()];
+    sheet.rules[0].layer = Some("b".to_string());
+    sheet.rules[1].layer = Some("a".to_string());
+    let root = HtmlParser::parse("<div id='a'></div>".to_string());
+    let styled = style_tree(&root, &[sheet], Viewport::default());
+    // Unlayered declarations come after every layer.
+    assert_eq!(value_of(&styled, "a", "text-align"), keyword("center"));
+
+    let css = "div { text-align: left !important; } div { text-align: right !important; } \
+               div { text-align: center !important; }";
+    let mut sheet = CssParser::parse(css.to_string());
+    sheet.layers = vec!["a".to_string(), "b".to_string()];
+    sheet.rules[0].layer = Some("b".to_string());
+    sheet.rules[1].layer = Some("a".to_string());
+    let styled = style_tree(&root, &[sheet], Viewport::default());
+    assert_eq!(value_of(&styled, "a", "text-align"), keyword("right"));
+}
+
+#[test]
+fn the_user_agent_stylesheet_gives_elements_their_default_styles() {
+    let root = HtmlParser::parse("<html><head id='h'></head><p id='p'></p></html>".to_string());
+    let styled = style_tree(&root, &[], Viewport::default());
+    assert_eq!(value_of(&styled, "p", "display"), keyword("block"));
+    assert_eq!(value_of(&styled, "h", "display"), keyword("none"));
+
+    // An author stylesheet doesn't replace it.
+    let author = CssParser::parse("span { color: red; }".to_string());
+    let styled = style_tree(&root, &[author], Viewport::default());
+    assert_eq!(value_of(&styled, "p", "display"), keyword("block"));
+}
+
+#[test]
+fn a_user_agent_stylesheet_replaces_the_built_in_one() {
+    let root = HtmlParser::parse("<div><p id='p'></p><span id='s'></span></div>".to_string());
+    let user_agent =
+        CssParser::parse_with_origin("span { display: block; }".to_string(), Origin::UserAgent);
+    let styled = style_tree(&root, &[user_agent], Viewport::default());
+    assert_ne!(value_of(&styled, "p", "display"), keyword("block"));
+    assert_eq!(value_of(&styled, "s", "display"), keyword("block"));
+}
+
+#[test]
+fn revert_rolls_back_to_the_previous_origin() {
+    let div = "<div style='text-align: justify'><div id='a'></div></div>";
+    let user_agent = (Origin::UserAgent, "#a { text-align: left; }");
+    let user = (Origin::User, "#a { text-align: right; }");
+    let author = (Origin::Author, "#a { text-align: revert; }");
+    assert_eq!(
+        text_align(div, &[user_agent, user, author]),
+        keyword("right")
+    );
+    assert_eq!(text_align(div, &[user_agent, author]), keyword("left"));
+    // Reverting the user agent origin gives an inherited property the parent's value.
+    let user_agent = (Origin::UserAgent, "#a { text-align: revert; }");
+    assert_eq!(text_align(div, &[user_agent]), keyword("justify"));
+}
+
+#[test]
+fn revert_layer_rolls_back_to_the_previous_layer() {
+    let css = "div { text-align: left; } div { text-align: right; } \
+               div { text-align: revert-layer; }";
+    let mut sheet = CssParser::parse(css.to_string());
+    sheet.layers = vec!["a".to_string(), "b".to_string()];
+    sheet.rules[0].layer = Some("a".to_string());
+    sheet.rules[1].layer = Some("b".to_string());
+    let root = HtmlParser::parse("<div id='a'></div>".to_string());
+    let styled = style_tree(&root, &[sheet], Viewport::default());
+    assert_eq!(value_of(&styled, "a", "text-align"), keyword("right"));
+
+    let css = "div { text-align: left; } div { text-align: revert-layer; }";
+    let mut sheet = CssParser::parse(css.to_string());
+    sheet.layers = vec!["a".to_string(), "b".to_string()];
+    sheet.rules[0].layer = Some("a".to_string());
+    sheet.rules[1].layer = Some("b".to_string());
+    let styled = style_tree(&root, &[sheet], Viewport::default());
+    assert_eq!(value_of(&styled, "a", "text-align"), keyword("left"));
+}
+
+#[test]
+fn layers_are_ordered_by_where_they_are_first_declared() {
+    let div = "<div id='a'></div>";
+    let css = "@layer b, a; @layer a { div { text-align: left; } } \
+               @layer b { div { text-align: right; } }";
+    assert_eq!(text_align(div, &[(Origin::Author, css)]), keyword("left"));
+    let css = "@layer b, a; @layer a { div { text-align: left !important; } } \
+               @layer b { div { text-align: right !important; } } \
+               div { text-align: center !important; }";
+    assert_eq!(text_align(div, &[(Origin::Author, css)]), keyword("right"));
+    let css = "@layer a { @layer x { div { text-align: left; } } } \
+               @layer a { div { text-align: right; } } @layer a.x { div { color: red; } }";
+    assert_eq!(text_align(div, &[(Origin::Author, css)]), keyword("right"));
+}
+
+#[test]
+fn var_is_replaced_by_the_custom_property_or_its_fallback() {
+    let div = "<div style='--x: right'><div id='a'></div></div>";
+    let css = "#a { text-align: var(--x); }";
+    assert_eq!(text_align(div, &[(Origin::Author, css)]), keyword("right"));
+    let css = "#a { --x: left; text-align: var(--x, center); }";
+    assert_eq!(text_align(div, &[(Origin::Author, css)]), keyword("left"));
+    let css = "#a { text-align: var(--y, center); }";
+    assert_eq!(text_align(div, &[(Origin::Author, css)]), keyword("center"));
+    let css = "#a { --y: var(--x); text-align: var(--y); }";
+    assert_eq!(text_align(div, &[(Origin::Author, css)]), keyword("right"));
+}
+
+#[test]
+fn custom_properties_in_a_cycle_are_invalid() {
+    let div = "<div style='text-align: justify'><div id='a'></div></div>";
+    let css = "#a { --a: var(--b); --b: var(--a); text-align: var(--a, center); }";
+    assert_eq!(text_align(div, &[(Origin::Author, css)]), keyword("center"));
+    // A var() without a fallback makes its declaration invalid at computed-value time, which
+    // inherits an inherited property.
+    let css = "#a { --a: var(--b); --b: var(--a); text-align: var(--a); }";
+    assert_eq!(
+        text_align(div, &[(Origin::Author, css)]),
+        keyword("justify")
+    );
+    let css = "#a { --a: var(--a); text-align: left; text-align: var(--a); }";
+    assert_eq!(
+        text_align(div, &[(Origin::Author, css)]),
+        keyword("justify")
+    );
+}
+
+fn inline(css: &str) -> String {
+    declarations_text(&CssParser::parse_inline(css))
+}
+
+/// Returns a stylesheet parsed from css, serialized again.
+fn parsed(css: &str) -> String {
+    CssParser::parse(css.to_string()).to_string()
+}
+
+#[test]
+fn rules_with_unsupported_selectors_are_dropped() {
+    assert_eq!(
+        parsed("a:hover { color: red; } p { color: blue; }"),
+        parsed("p { color: blue; }")
+    );
+    assert_eq!(
+        parsed("div:not(.x) { color: red; } p, q:first-child { margin: 0; } em { color: blue; }"),
+        parsed("em { color: blue; }")
+    );
+    assert_eq!(parsed("p[title~~x] { color: red; }"), "");
+}
+
+#[test]
+fn pages_with_unsupported_selectors_are_styled() {
+    let html = "<html><head><style>a:hover { color: red; } p { color: blue; }</style></head>\
+                <body><p>text</p></body></html>";
+    let document = Document::parse(html.to_string(), "", &FileLoader::new("."));
+    document.style(Default::default());
+}
+
+#[test]
+fn invalid_declarations_are_dropped_up_to_the_next_semicolon() {
+    assert_eq!(inline("foo"), "");
+    assert_eq!(inline("foo; color: red"), "color: red;");
+    assert_eq!(inline("color red; margin-top: 0"), "margin-top: 0;");
+    assert_eq!(inline("color: red !imp; margin-top: 0"), "margin-top: 0;");
+    assert_eq!(inline("width: url(a b); color: red"), "color: red;");
+    assert_eq!(inline("} color: blue; color: red"), "color: red;");
+    assert_eq!(inline("width: \"a;b\" 1px; color: red"), "color: red;");
+    // An unclosed block carries on to the end of input.
+    assert_eq!(inline("width: f(1px; color: red"), "");
+}
+
+#[test]
+fn invalid_math_functions_drop_their_declarations() {
+    assert_eq!(
+        parsed("div { width: calc(10px + ); color: red; }"),
+        parsed("div { color: red; }")
+    );
+    assert_eq!(
+        inline(
+            "width: calc(10px * ); color: red; height: clamp(1px, 2px); margin-top: min(1px, 2px)"
+        ),
+        inline("color: red; margin-top: min(1px, 2px)")
+    );
+    assert_eq!(inline("width: calc(1px + foo)"), "");
+    assert_eq!(inline("width: calc((1px + 2px)"), "");
+}
+
+#[test]
+fn invalid_values_are_dropped() {
+    assert_eq!(
+        CssParser::parse_declaration_value("1px solid"),
+        Some(Value::List(vec![
+            CssParser::parse_declaration_value("1px").unwrap(),
+            Value::Keyword("solid".to_string()),
+        ]))
+    );
+    assert_eq!(CssParser::parse_declaration_value("1px )"), None);
+    assert_eq!(CssParser::parse_declaration_value("rgb(1, 2"), None);
+    assert_eq!(
+        inline("grid-template-columns: [a, b] 1fr; color: red"),
+        "color: red;"
+    );
+}
+
+#[test]
+fn set_property_only_sets_one_valid_value() {
+    let mut rule = CssParser::parse("p { color: blue; }".to_string())
+        .rules
+        .remove(0);
+    let set = |rule: &mut Rule, name, value| rule.set_property(name, value, false);
+
+    assert!(!set(&mut rule, "color", "red; margin: 5px"));
+    assert!(!set(&mut rule, "color", "calc(1px +"));
+    assert!(!set(&mut rule, "color", "10px"));
+    assert!(!set(&mut rule, "color", "red !important"));
+    assert!(!set(&mut rule, "margin", "1px foo"));
+    assert_eq!(rule.css_text(), "color: blue;");
+
+    assert!(set(&mut rule, "color", " red "));
+    assert!(set(&mut rule, "margin", "1px 2px"));
+    assert!(set(&mut rule, "padding-top", "var(--gap)"));
+    assert_eq!(
+        rule.css_text(),
+        "color: red; margin-top: 1px; margin-right: 2px; margin-bottom: 1px; margin-left: 2px; \
+         padding-top: var(--gap);"
+    );
+}
+
+#[test]
+fn urls_are_resolved_without_dot_segments() {
+    assert_eq!(resolve_url("css/theme.css", "./fonts.css"), "css/fonts.css");
+    assert_eq!(
+        resolve_url("css/theme.css", "../a/./../fonts.css"),
+        "fonts.css"
+    );
+    assert_eq!(
+        resolve_url("theme.css", "../../fonts.css"),
+        "../../fonts.css"
+    );
+    assert_eq!(
+        resolve_url("/css/theme.css", "../../fonts.css"),
+        "/fonts.css"
+    );
+    assert_eq!(
+        resolve_url("https://example.com/css/theme.css", "../fonts.css"),
+        "https://example.com/fonts.css"
+    );
+}
+
+#[test]
+fn import_cycles_written_with_dot_segments_are_ignored() {
+    let files: HashMap<String, String> = [
+        (
+            "a.css",
+            "@import \"./a.css\"; @import \"css/b.css\"; a { color: red; }",
+        ),
+        (
+            "css/b.css",
+            "@import \"../css/./b.css\"; @import \"../a.css\"; b { color: blue; }",
+        ),
+    ]
+    .map(|(url, css)| (url.to_string(), css.to_string()))
+    .into();
+
+    let sheet =
+        CssParser::parse_with_loader(files["a.css"].clone(), Origin::Author, "a.css", &files);
+    assert_eq!(
+        sheet.to_string(),
+        parsed("b { color: blue; } a { color: red; }")
+    );
+}
+
+#[test]
+fn at_rules_keep_the_layer_and_media_they_are_in() {
+    let sheet = CssParser::parse(
+        "@media print { @font-face { font-family: A; src: local(A); } @page { margin: 0; } } \
+         @layer base { @media (min-width: 10px) { @keyframes fade { to { opacity: 0; } } } }"
+            .to_string(),
+    );
+    let print = vec![CssParser::parse_media("print")];
+    assert_eq!(
+        (&sheet.font_faces[0].layer, &sheet.font_faces[0].media),
+        (&None, &print)
+    );
+    assert_eq!(
+        (&sheet.pages[0].layer, &sheet.pages[0].media),
+        (&None, &print)
+    );
+    let keyframes = &sheet.keyframes[0];
+    assert_eq!(keyframes.layer.as_deref(), Some("base"));
+    assert_eq!(
+        keyframes.media,
+        vec![CssParser::parse_media("(min-width: 10px)")]
+    );
+
+    // They are written inside the at-rules they are in.
+    assert_eq!(parsed(&sheet.to_string()), sheet.to_string());
+    assert!(sheet.to_string().contains("@media print {\n  @page"));
+}
+
+#[test]
+fn an_escape_at_the_end_of_input_is_dropped() {
+    assert_eq!(
+        parsed("p { content: \"a\\"),
+        parsed("p { content: \"a\"; }")
+    );
+    assert_eq!(inline("content: 'a\\"), "content: \"a\";");
+}
+
+/// Returns the Some text generated by the ::before of the element with an id, or None if it has
+/// none.
+fn before_text(styled: &StyledNode, id: &str) -> Option<String> {
+    if let NodeType::Element(ref elem) = styled.node.node_type {
+        if styled.pseudo.is_none() && elem.id().map(String::as_str) == Some(id) {
+            let before = styled
+                .children
+                .iter()
+                .find(|c| c.pseudo == Some(PseudoElement::Before))?;
+            return before.text().map(str::to_string);
+        }
+    }
+    styled.children.iter().find_map(|c| before_text(c, id))
+}
+
+#[test]
+fn counters_are_clamped_instead_of_overflowing() {
+    let html = "<div><p id='a'></p><p id='b'></p><p id='c' class='reset'></p><p id='d'></p></div>";
+    let css = "p::before { content: counter(c); } p { counter-increment: c 2000000000; } \
+               .reset { counter-reset: c 99999999999; counter-increment: c -1; }";
+    let root = HtmlParser::parse(html.to_string());
+    let styled = style_tree(
+        &root,
+        &[CssParser::parse(css.to_string())],
+        Viewport::default(),
+    );
+    assert_eq!(before_text(&styled, "a").as_deref(), Some("2000000000"));
+    assert_eq!(before_text(&styled, "b").as_deref(), Some("2147483647"));
+    assert_eq!(before_text(&styled, "c").as_deref(), Some("2147483646"));
+    assert_eq!(before_text(&styled, "d").as_deref(), Some("2147483647"));
+}
+
+#[test]
+fn invalid_at_rules_are_dropped() {
+    let p = "p { color: red; }";
+    for css in [
+        "@layer a, b { p { color: blue; } }",
+        "@layer a b;",
+        "@import foo;",
+        "@import url(x) layer(a;",
+        "@import url(x) supports(;",
+        "@media screen;",
+        "@supports (display: grid);",
+        "@supports display: grid { p { color: blue; } }",
+        "@page;",
+        "@font-face;",
+        "@keyframes { to { opacity: 0; } }",
+        "@keyframes x;",
+    ] {
+        assert_eq!(parsed(&format!("{css} {p}")), parsed(p), "{css}");
+    }
+    // An invalid at-rule in a block doesn't end the block early, or go past its end.
+    assert_eq!(
+        parsed("@media print { @media screen } p { color: red; }"),
+        parsed("@media print {} p { color: red; }")
+    );
+    assert_eq!(
+        parsed("@media print { @layer a, b { p { color: blue; } } em { color: blue; } } q {}"),
+        parsed("@media print { em { color: blue; } } q {}")
+    );
+
+    for css in [
+        "@layer",
+        "@layer a.",
+        "@import",
+        "@import url(x) layer(",
+        "@import url(x) supports(display: grid",
+        "@media screen",
+        "@supports",
+        "@supports (display: grid)",
+        "@keyframes",
+        "@page",
+    ] {
+        assert_eq!(parsed(css), "", "{css}");
+    }
+}
+
+#[test]
+fn at_rules_at_the_end_of_input_are_closed() {
+    let sheet = CssParser::parse("@import \"a.css\"".to_string());
+    assert_eq!(sheet.imports[0].url, "a.css");
+    let sheet = CssParser::parse("@layer a, b".to_string());
+    assert_eq!(sheet.layers, ["a", "b"]);
+
+    let sheet = CssParser::parse("@keyframes x {".to_string());
+    assert!(sheet.keyframes[0].frames.is_empty());
+    let sheet = CssParser::parse("@page {".to_string());
+    assert!(sheet.pages[0].declarations.is_empty());
+    let sheet = CssParser::parse("@page { margin: 1in".to_string());
+    assert_eq!(sheet.pages[0].declarations[0].name, "margin");
+    // It has no font-family or src.
+    let sheet = CssParser::parse("@font-face {".to_string());
+    assert!(sheet.font_faces.is_empty());
+    let sheet = CssParser::parse("@font-face { font-family: A; src: local(A)".to_string());
+    assert_eq!(sheet.font_faces[0].family, "A");
+}
+
+#[test]
+fn invalid_media_queries_never_match() {
+    let not_all = CssParser::parse_media("not all");
+    for media in [
+        "(width foo)",
+        "(40em)",
+        "(min-width: 40em foo)",
+        "(width < 40em",
+        "screen foo",
+        "screen and",
+        "not",
+        "(color) and (hover) or (pointer)",
+    ] {
+        assert_eq!(CssParser::parse_media(media), not_all, "{media}");
+    }
+    assert_eq!(
+        CssParser::parse_media("(width foo, bar), screen, , (40em) print"),
+        CssParser::parse_media("not all, screen, not all, not all")
+    );
+
+    let sheet = CssParser::parse("@media (width foo), print { p { color: red; } }".to_string());
+    assert!(!sheet.rules[0].matches_media(&Viewport::default()));
+    let sheet = CssParser::parse("@media (width foo), screen { p { color: red; } }".to_string());
+    assert!(sheet.rules[0].matches_media(&Viewport::default()));
+}
+
+#[test]
+fn keyframes_with_invalid_selectors_are_dropped() {
+    let sheet = CssParser::parse(
+        "@keyframes x { 50px { opacity: 0; } bogus { opacity: 0; } 150% { opacity: 0; } \
+         from, 50% { opacity: 0.5; } to, x { opacity: 0; } to { opacity: 1; } 20% }"
+            .to_string(),
+    );
+    let offsets: Vec<_> = sheet.keyframes[0]
+        .frames
+        .iter()
+        .map(|f| f.offsets.clone())
+        .collect();
+    assert_eq!(offsets, [vec![0.0, 0.5], vec![1.0]]);
+}
+
+#[test]
+fn shorthands_expand_into_their_longhands() {
+    assert_eq!(
+        inline("margin: 1px 2px 3px"),
+        inline("margin-top: 1px; margin-right: 2px; margin-bottom: 3px; margin-left: 2px")
+    );
+    assert_eq!(
+        inline("flex: 2 3 0"),
+        inline("flex-grow: 2; flex-shrink: 3; flex-basis: 0")
+    );
+    assert_eq!(
+        inline("border-top: 0 solid"),
+        inline("border-top-width: 0; border-top-style: solid; border-top-color: currentcolor")
+    );
+    // Lengths other than zero need units.
+    assert_eq!(inline("border: 2 solid"), "");
+    assert_eq!(inline("border-top: 2 solid; color: red"), "color: red;");
+    assert_eq!(inline("font: 12 serif"), "");
+    assert_eq!(inline("flex: 2 3 4"), "");
+}
+
+#[test]
+fn grid_template_areas_have_line_names_between_their_rows() {
+    let longhands = |rows: &str, columns: &str, areas: &str| {
+        inline(&format!(
+            "grid-template-rows: {rows}; grid-template-columns: {columns}; \
+             grid-template-areas: {areas}"
+        ))
+    };
+    assert_eq!(
+        inline("grid-template: \"a\" 40px \"b\" / 1fr"),
+        longhands("40px auto", "1fr", "\"a\" \"b\"")
+    );
+    assert_eq!(
+        inline("grid-template: [a] \"x\" 40px"),
+        longhands("[a] 40px", "none", "\"x\"")
+    );
+    assert_eq!(
+        inline("grid-template: \"x\" 40px [b]"),
+        longhands("40px [b]", "none", "\"x\"")
+    );
+    // The names after a row and before the next are the same line.
+    assert_eq!(
+        inline("grid-template: [a] \"x\" [b] [c] \"y\" 10px [d] / [e] 1fr"),
+        longhands("[a] auto [b c] 10px [d]", "[e] 1fr", "\"x\" \"y\"")
+    );
+
+    assert_eq!(inline("grid-template: [a] [b] \"x\""), "");
+    assert_eq!(inline("grid-template: \"x\" [b] 40px"), "");
+    assert_eq!(inline("grid-template: \"x\" 40px 50px"), "");
+    // Names before a row have to be followed by it.
+    assert_eq!(inline("grid-template: \"x\" [a] [b]"), "");
+}
+
+#[test]
+fn rules_are_inserted_and_deleted_one_at_a_time() {
+    let mut sheet = CssParser::parse("a { color: red }".to_string());
+    assert_eq!(sheet.insert_rule(1, "b { color: blue }"), Ok(1));
+    assert_eq!(
+        sheet.insert_rule(0, "@media print { nav { display: none } }"),
+        Ok(0)
+    );
+    assert_eq!(sheet.rules.len(), 3);
+    assert_eq!(sheet.rules[0].media.len(), 1);
+
+    // Only a single valid rule can be inserted.
+    for css in [
+        "",
+        "a { color: red } b { color: blue }",
+        "a {} }",
+        "a, { }",
+        "@foo;",
+    ] {
+        assert_eq!(sheet.insert_rule(0, css), Err(RuleError::Syntax), "{css}");
+    }
+    assert_eq!(
+        sheet.insert_rule(4, "a { color: red }"),
+        Err(RuleError::IndexSize)
+    );
+    assert_eq!(
+        sheet.insert_rule(1, "@import url(a.css);"),
+        Err(RuleError::HierarchyRequest)
+    );
+    assert_eq!(sheet.rules.len(), 3);
+
+    assert!(matches!(sheet.delete_rule(3), Err(RuleError::IndexSize)));
+    assert_eq!(sheet.delete_rule(0).unwrap().media.len(), 1);
+    assert_eq!(sheet.rules.len(), 2);
+}
+
+#[test]
+fn unknown_properties_have_no_computed_value() {
+    let root = HtmlParser::parse("<div id='a' style='color: red'></div>".to_string());
+    let styled = style_tree(&root, &[], Viewport::default());
+    assert!(value_of(&styled, "a", "color").is_some());
+    assert!(value_of(&styled, "a", "margin-top").is_some());
+    assert_eq!(value_of(&styled, "a", "foo"), None);
+}
+
+/// Returns the Some red, green, blue and alpha channels of a css colour, or None if it isn't one.
+fn rgba(css: &str) -> Option<[u8; 4]> {
+    match CssParser::parse_property_value("color", css)? {
+        Value::Colour(c) => Some([c.r, c.g, c.b, c.a]),
+        _ => None,
+    }
+}
+
+#[test]
+fn colours_are_converted_to_srgb() {
+    assert_eq!(rgba("#0080ff80"), Some([0, 128, 255, 128]));
+    assert_eq!(rgba("rgb(0 128 255 / 50%)"), Some([0, 128, 255, 128]));
+    assert_eq!(rgba("rgba(0, 128, 255, 0.5)"), Some([0, 128, 255, 128]));
+    assert_eq!(rgba("rgb(0%, 50%, 100%)"), Some([0, 128, 255, 255]));
+    assert_eq!(rgba("hsl(120deg 100% 25%)"), Some([0, 128, 0, 255]));
+    assert_eq!(rgba("hsla(120, 100%, 25%, 0.5)"), Some([0, 128, 0, 128]));
+    assert_eq!(rgba("hwb(0 0% 0%)"), Some([255, 0, 0, 255]));
+
+    // The conversions aren't exact, so channels may be off by one.
+    let close = |css: &str, expected: [u8; 4]| {
+        let actual = rgba(css).unwrap_or_else(|| panic!("{css} isn't a colour"));
+        let off = |i: usize| actual[i].abs_diff(expected[i]) > 1;
+        assert!(!(0..4).any(off), "{css} is {actual:?}, not {expected:?}");
+    };
+    close("lab(54.29 80.8 69.89)", [255, 0, 0, 255]);
+    close("lab(100% 0 0 / 0.5)", [255, 255, 255, 128]);
+    close("lch(54.29 106.84 40.86)", [255, 0, 0, 255]);
+    close("lch(87.82 113.33 134.38)", [0, 255, 0, 255]);
+    close("oklab(0.628 0.2249 0.1258)", [255, 0, 0, 255]);
+    close("oklab(0% 0 0)", [0, 0, 0, 255]);
+    close("oklch(0.628 0.2577 29.23)", [255, 0, 0, 255]);
+    close("oklch(0.452 0.313 264.05deg)", [0, 0, 255, 255]);
+}
+
+#[test]
+fn invalid_colours_are_dropped() {
+    for css in [
+        "rgb(1, 2, 3, 4, 5)",
+        "rgb(1, 2)",
+        "rgb(0, 50%, 100%)",
+        "rgb(none, 0, 0)",
+        "rgb(1 2, 3)",
+        "hsl(120, 100, 25%)",
+        "hwb(0, 0%, 0%)",
+        "lab(50, 0, 0)",
+        "rgb(1 2 3 4)",
+        "rgb(1 2 3 /)",
+        "#12345",
+    ] {
+        assert_eq!(rgba(css), None, "{css}");
+    }
+}
+
+#[test]
+fn property_names_and_style_tags_ignore_case() {
+    assert_eq!(
+        inline("COLOR: red; Margin-Top: 0"),
+        "color: red; margin-top: 0;"
+    );
+    // Custom property names are case-sensitive.
+    assert_eq!(inline("--Gap: 1px"), "--Gap: 1px;");
+
+    let html = "<html><head><STYLE>#a { TEXT-ALIGN: center; }</Style></head>\
+                <body><p id='a'>text</p></body></html>";
+    let document = Document::parse(html.to_string(), "", &FileLoader::new("."));
+    assert_eq!(
+        value_of(&document.style(Default::default()), "a", "text-align"),
+        keyword("center")
+    );
+}
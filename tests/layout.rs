@@ -0,0 +1,1265 @@
+// Reference tests for layout, which check the border boxes of elements in small documents.
+
+use parsnip::box_model::{layout_tree, Dimensions, LayoutBox, Rect};
+use parsnip::document::Document;
+use parsnip::dom::NodeType;
+use parsnip::loader::FileLoader;
+use parsnip::style::values::Viewport;
+
+/// The styles every test starts with, so sizes are easy to work out.
+const CSS: &str = "body { margin: 0; line-height: 20px; }";
+
+/// The width of "a" in the default font, at the default font size.
+const A_WIDTH: f32 = 9.8046875;
+
+/// A test: its name, the body of the document, and the expected border box of each element by id,
+/// as x, y, width and height. The viewport is 800px wide.
+type Test = (
+    &'static str,
+    &'static str,
+    &'static [(&'static str, [f32; 4])],
+);
+
+/// https://www.w3.org/TR/CSS2/box.html#collapsing-margins
+const MARGIN_COLLAPSING: &[Test] = &[
+    (
+        "sibling margins collapse to the largest",
+        "<div id='a' style='height: 10px; margin-bottom: 20px'></div>\
+         <div id='b' style='height: 10px; margin-top: 30px'></div>",
+        &[("a", [0.0, 0.0, 800.0, 10.0]), ("b", [0.0, 40.0, 800.0, 10.0])],
+    ),
+    (
+        "a negative margin is added to the largest positive margin",
+        "<div id='a' style='height: 10px; margin-bottom: 20px'></div>\
+         <div id='b' style='height: 10px; margin-top: -5px'></div>",
+        &[("b", [0.0, 25.0, 800.0, 10.0])],
+    ),
+    (
+        "negative margins collapse to the most negative",
+        "<div id='a' style='height: 10px; margin-bottom: -2px'></div>\
+         <div id='b' style='height: 10px; margin-top: -5px'></div>",
+        &[("b", [0.0, 5.0, 800.0, 10.0])],
+    ),
+    (
+        "a parent's top margin collapses with its first child's",
+        "<div id='p' style='margin-top: 10px'><div id='c' style='height: 10px; margin-top: 30px'>\
+         </div></div>",
+        &[("p", [0.0, 30.0, 800.0, 10.0]), ("c", [0.0, 30.0, 800.0, 10.0])],
+    ),
+    (
+        "margins collapse through several generations",
+        "<div id='p' style='margin-top: 5px'><div style='margin-top: 15px'>\
+         <div id='c' style='height: 10px; margin-top: 10px'></div></div></div>",
+        &[("p", [0.0, 15.0, 800.0, 10.0]), ("c", [0.0, 15.0, 800.0, 10.0])],
+    ),
+    (
+        "a parent's bottom margin collapses with its last child's",
+        "<div id='p' style='margin-bottom: 10px'><div style='height: 10px; margin-bottom: 30px'>\
+         </div></div><div id='n' style='height: 10px; margin-top: 5px'></div>",
+        &[("p", [0.0, 0.0, 800.0, 10.0]), ("n", [0.0, 40.0, 800.0, 10.0])],
+    ),
+    (
+        "margins collapse through an empty block",
+        "<div id='a' style='height: 10px; margin-bottom: 10px'></div>\
+         <div id='e' style='margin-top: 20px; margin-bottom: 15px'></div>\
+         <div id='b' style='height: 10px; margin-top: 5px'></div>",
+        &[("e", [0.0, 30.0, 800.0, 0.0]), ("b", [0.0, 30.0, 800.0, 10.0])],
+    ),
+    (
+        "an empty first child doesn't stop the parent's margin collapsing with the next",
+        "<div id='p' style='margin-top: 10px'><div></div>\
+         <div id='c' style='height: 10px; margin-top: 20px'></div></div>",
+        &[("p", [0.0, 20.0, 800.0, 10.0]), ("c", [0.0, 20.0, 800.0, 10.0])],
+    ),
+    (
+        "collapsible white space doesn't separate margins",
+        "<div id='p' style='margin-top: 10px'> <div id='c' style='height: 10px; margin-top: 30px'>\
+         </div></div>",
+        &[("p", [0.0, 30.0, 800.0, 10.0]), ("c", [0.0, 30.0, 800.0, 10.0])],
+    ),
+    (
+        "a line box separates margins",
+        "<div id='p' style='margin-top: 10px'>Text<div id='c' style='height: 10px; margin-top: 30px'>\
+         </div></div>",
+        &[("p", [0.0, 10.0, 800.0, 60.0]), ("c", [0.0, 60.0, 800.0, 10.0])],
+    ),
+    (
+        "a top border separates a parent's margin from its child's",
+        "<div id='p' style='margin-top: 10px; border-top: 1px solid'>\
+         <div id='c' style='height: 10px; margin-top: 30px'></div></div>",
+        &[("p", [0.0, 10.0, 800.0, 41.0]), ("c", [0.0, 41.0, 800.0, 10.0])],
+    ),
+    (
+        "top padding separates a parent's margin from its child's",
+        "<div id='p' style='margin-top: 10px; padding-top: 2px'>\
+         <div id='c' style='height: 10px; margin-top: 30px'></div></div>",
+        &[("p", [0.0, 10.0, 800.0, 42.0]), ("c", [0.0, 42.0, 800.0, 10.0])],
+    ),
+    (
+        "bottom padding separates a parent's margin from its child's",
+        "<div id='p' style='padding-bottom: 5px'><div style='height: 10px; margin-bottom: 30px'>\
+         </div></div><div id='n' style='height: 10px; margin-top: 5px'></div>",
+        &[("p", [0.0, 0.0, 800.0, 45.0]), ("n", [0.0, 50.0, 800.0, 10.0])],
+    ),
+    (
+        "a height stops a parent's bottom margin collapsing with its child's",
+        "<div id='p' style='height: 50px'><div style='height: 10px; margin-bottom: 80px'>\
+         </div></div><div id='n' style='height: 10px; margin-top: 5px'></div>",
+        &[("p", [0.0, 0.0, 800.0, 50.0]), ("n", [0.0, 55.0, 800.0, 10.0])],
+    ),
+    (
+        "a block formatting context keeps its children's margins inside it",
+        "<div id='p' style='margin-top: 10px; overflow: hidden'>\
+         <div id='c' style='height: 10px; margin: 30px 0'></div></div>\
+         <div id='n' style='height: 10px; margin-top: 5px'></div>",
+        &[
+            ("p", [0.0, 10.0, 800.0, 70.0]),
+            ("c", [0.0, 40.0, 800.0, 10.0]),
+            ("n", [0.0, 85.0, 800.0, 10.0]),
+        ],
+    ),
+    (
+        "a block formatting context's own margins collapse with its siblings'",
+        "<div id='a' style='height: 10px; margin-bottom: 20px'></div>\
+         <div id='b' style='height: 10px; margin-top: 10px; overflow: hidden'></div>",
+        &[("b", [0.0, 30.0, 800.0, 10.0])],
+    ),
+    (
+        "the root's margins don't collapse with its children's",
+        "<div id='c' style='height: 10px; margin-top: 20px'></div>",
+        &[("c", [0.0, 20.0, 800.0, 10.0])],
+    ),
+];
+
+/// https://www.w3.org/TR/CSS2/visudet.html#min-max-widths
+/// https://www.w3.org/TR/CSS2/visudet.html#min-max-heights
+/// https://www.w3.org/TR/css-sizing-3/#box-sizing
+const SIZES: &[Test] = &[
+    (
+        "a width is no wider than the maximum",
+        "<div id='a' style='max-width: 300px; height: 10px'></div>",
+        &[("a", [0.0, 0.0, 300.0, 10.0])],
+    ),
+    (
+        "a maximum width is a percentage of the containing block",
+        "<div id='a' style='max-width: 25%; height: 10px'></div>",
+        &[("a", [0.0, 0.0, 200.0, 10.0])],
+    ),
+    (
+        "auto margins share the space left by a maximum width",
+        "<div id='a' style='max-width: 400px; height: 10px; margin: 0 auto'></div>",
+        &[("a", [200.0, 0.0, 400.0, 10.0])],
+    ),
+    (
+        "a width is no narrower than the minimum",
+        "<div id='a' style='width: 100px; min-width: 200px; height: 10px'></div>",
+        &[("a", [0.0, 0.0, 200.0, 10.0])],
+    ),
+    (
+        "a minimum width wins over a maximum",
+        "<div id='a' style='min-width: 400px; max-width: 300px; height: 10px'></div>",
+        &[("a", [0.0, 0.0, 400.0, 10.0])],
+    ),
+    (
+        "an empty block is as tall as its minimum height",
+        "<div id='a' style='min-height: 30px'></div>",
+        &[("a", [0.0, 0.0, 800.0, 30.0])],
+    ),
+    (
+        "a height is no taller than the maximum, even if the content is",
+        "<div id='a' style='max-height: 15px'><div id='c' style='height: 40px'></div></div>",
+        &[
+            ("a", [0.0, 0.0, 800.0, 15.0]),
+            ("c", [0.0, 0.0, 800.0, 40.0]),
+        ],
+    ),
+    (
+        "a minimum height wins over a maximum",
+        "<div id='a' style='height: 10px; min-height: 30px; max-height: 20px'></div>",
+        &[("a", [0.0, 0.0, 800.0, 30.0])],
+    ),
+    (
+        "a percentage height is relative to the containing block's height",
+        "<div style='height: 200px'><div id='c' style='height: 50%'></div></div>",
+        &[("c", [0.0, 0.0, 800.0, 100.0])],
+    ),
+    (
+        "percentage heights are relative to percentage heights",
+        "<div style='height: 200px'><div style='height: 50%'><div id='c' style='height: 50%'>\
+         </div></div></div>",
+        &[("c", [0.0, 0.0, 800.0, 50.0])],
+    ),
+    (
+        "a percentage height is relative to the containing block's maximum height",
+        "<div style='height: 400px; max-height: 200px'><div id='c' style='height: 50%'></div>\
+         </div>",
+        &[("c", [0.0, 0.0, 800.0, 100.0])],
+    ),
+    (
+        "a percentage of a height which depends on the content is auto",
+        "<div><div id='c' style='height: 50%; min-height: 10%; max-height: 5%'>\
+         <div style='height: 30px'></div></div></div>",
+        &[("c", [0.0, 0.0, 800.0, 30.0])],
+    ),
+    (
+        "a border-box width includes the padding and border",
+        "<div id='a' style='box-sizing: border-box; width: 200px; height: 10px; \
+         padding: 0 10px; border: 5px solid'></div>",
+        &[("a", [0.0, 0.0, 200.0, 10.0])],
+    ),
+    (
+        "a border-box height includes the padding and border",
+        "<div id='a' style='box-sizing: border-box; height: 100px; padding: 10px'></div>",
+        &[("a", [0.0, 0.0, 800.0, 100.0])],
+    ),
+    (
+        "a border-box size is never smaller than the padding and border",
+        "<div id='a' style='box-sizing: border-box; width: 10px; height: 10px; padding: 20px'>\
+         </div>",
+        &[("a", [0.0, 0.0, 40.0, 40.0])],
+    ),
+    (
+        "border-box minimum and maximum sizes include the padding and border",
+        "<div id='a' style='box-sizing: border-box; max-width: 300px; min-height: 50px; \
+         padding: 20px'></div>",
+        &[("a", [0.0, 0.0, 300.0, 50.0])],
+    ),
+    (
+        "a minimum height stops margins collapsing through a block",
+        "<div id='a' style='height: 10px; margin-bottom: 10px'></div>\
+         <div id='e' style='min-height: 5px; margin-top: 20px; margin-bottom: 15px'></div>\
+         <div id='b' style='height: 10px; margin-top: 5px'></div>",
+        &[
+            ("e", [0.0, 30.0, 800.0, 5.0]),
+            ("b", [0.0, 50.0, 800.0, 10.0]),
+        ],
+    ),
+    (
+        "margins collapse through a block with a height of 0",
+        "<div id='a' style='height: 10px; margin-bottom: 10px'></div>\
+         <div id='e' style='height: 0; margin-top: 20px; margin-bottom: 15px'></div>\
+         <div id='b' style='height: 10px; margin-top: 5px'></div>",
+        &[
+            ("e", [0.0, 30.0, 800.0, 0.0]),
+            ("b", [0.0, 30.0, 800.0, 10.0]),
+        ],
+    ),
+];
+
+/// https://www.w3.org/TR/CSS2/visuren.html#box-gen
+const BOX_GENERATION: &[Test] = &[
+    (
+        "a block in an inline box is laid out as a block between the parts of the inline box",
+        "Text <span>a<div id='d' style='height: 10px'></div>b</span>",
+        &[("d", [0.0, 20.0, 800.0, 10.0])],
+    ),
+    (
+        "inline boxes are split around blocks at every level",
+        "<b><i>a<div id='d' style='height: 10px'></div>b</i></b>\
+         <div id='n' style='height: 10px'></div>",
+        &[
+            ("d", [0.0, 20.0, 800.0, 10.0]),
+            ("n", [0.0, 50.0, 800.0, 10.0]),
+        ],
+    ),
+    (
+        "the empty parts of a split inline box don't make lines",
+        "<span><div id='d' style='height: 10px'></div></span>\
+         <div id='n' style='height: 10px'></div>",
+        &[
+            ("d", [0.0, 0.0, 800.0, 10.0]),
+            ("n", [0.0, 10.0, 800.0, 10.0]),
+        ],
+    ),
+    (
+        "a split inline box only has edges where it starts and ends",
+        "<span id='s' style='padding: 0 7px'>a<div style='height: 10px'></div>b</span>",
+        // The inline box is as tall as its glyphs, in the middle of the line.
+        &[("s", [0.0, 0.6875, 7.0 + A_WIDTH, 18.625])],
+    ),
+    (
+        "collapsible white space between blocks doesn't make lines",
+        "<div id='a' style='height: 10px'></div>\n  <div id='b' style='height: 10px'></div> ",
+        &[("b", [0.0, 10.0, 800.0, 10.0])],
+    ),
+    (
+        "white space between inline boxes separates them",
+        "<b>a</b> <i id='i'>a</i><div><span>a</span>\n<span id='s'>a</span></div>",
+        // A space is a little over half as wide as an "a".
+        &[
+            ("i", [A_WIDTH + 5.0859375, 0.6875, A_WIDTH, 18.625]),
+            ("s", [A_WIDTH + 5.0859375, 20.6875, A_WIDTH, 18.625]),
+        ],
+    ),
+    (
+        "an element with only collapsible white space in it has no lines",
+        "<div id='d'> <span> </span>\n</div><div id='n' style='height: 10px'></div>",
+        &[
+            ("d", [0.0, 0.0, 800.0, 0.0]),
+            ("n", [0.0, 0.0, 800.0, 10.0]),
+        ],
+    ),
+    (
+        "the generated content of a block-level pseudo-element is in a line in it",
+        "<style>#d::before { content: 'xyz'; display: block; padding: 5px }</style>\
+         <div id='d' style='float: left'></div>",
+        // "xyz" is 27.335938px wide.
+        &[("d", [0.0, 0.0, 37.335938, 30.0])],
+    ),
+    (
+        "the generated content of a pseudo-element which is an item is in a line in the item",
+        "<style>#f::before { content: 'xyz' } #g::before { content: 'xyz'; display: flex }\
+         </style>\
+         <div id='f' style='display: flex'><span id='s'>a</span></div>\
+         <div id='g'><span id='t'>a</span></div>",
+        &[
+            ("s", [27.335938, 0.0, A_WIDTH, 20.0]),
+            ("t", [0.0, 40.6875, A_WIDTH, 18.625]),
+        ],
+    ),
+    (
+        "an outside marker ends where the content of its list item starts",
+        "<ul style='margin: 0'><li id='l'><span id='s'>a</span></li></ul>",
+        // The marker is "• ", which is 14.5234375px wide.
+        &[
+            ("l::marker", [25.476563, 0.0, 14.5234375, 20.0]),
+            ("s", [40.0, 0.6875, A_WIDTH, 18.625]),
+        ],
+    ),
+    (
+        "an outside marker is on the baseline of the first line of its list item",
+        "<ol style='margin: 0; padding: 0'><li id='l'><p style='margin: 0; line-height: 40px'>\
+         a</p></li></ol>",
+        &[("l::marker", [-20.351563, 10.0, 20.351563, 20.0])],
+    ),
+    (
+        "an inside marker is at the start of the first line of its list item",
+        "<ul style='margin: 0; list-style-position: inside'><li><span id='s'>a</span></li></ul>",
+        &[("s", [54.523438, 0.6875, A_WIDTH, 18.625])],
+    ),
+    (
+        "the content of the first line has the style of ::first-line",
+        "<style>#p::first-line { font-size: 32px }</style>\
+         <p id='p' style='margin: 0'><span id='a'>a</span><br><span id='b'>a</span></p>",
+        // The glyphs are twice as big, but the line is still 20px tall.
+        &[
+            ("a", [0.0, -8.625, 2.0 * A_WIDTH, 37.25]),
+            ("b", [0.0, 20.6875, A_WIDTH, 18.625]),
+        ],
+    ),
+    (
+        "a floated ::first-letter is a float beside the rest of the text",
+        "<style>#p::first-letter { float: left; width: 30px }</style>\
+         <p id='p' style='margin: 0'><span id='s'>aa</span></p>",
+        &[("s", [30.0, 0.6875, A_WIDTH, 18.625])],
+    ),
+    (
+        "text between blocks is in an anonymous block with a line",
+        "<div id='a' style='height: 10px'></div>Text<div id='b' style='height: 10px'></div>",
+        &[("b", [0.0, 30.0, 800.0, 10.0])],
+    ),
+    (
+        "an element with display: none makes no boxes, and neither do its children",
+        "<div style='display: none; height: 50px'><div style='display: block; height: 5px'>\
+         </div></div><div id='b' style='height: 10px'></div>",
+        &[("b", [0.0, 0.0, 800.0, 10.0])],
+    ),
+    (
+        "a list item is a block",
+        "<span>a</span><li id='l' style='height: 10px'></li>",
+        &[("l", [0.0, 20.0, 800.0, 10.0])],
+    ),
+    (
+        "an inline-flex container stays in the line, with its first line on the baseline",
+        "<span id='a'>a</span><span id='b' style='display: inline-flex; height: 30px'>a</span>",
+        &[
+            ("a", [0.0, 0.6875, A_WIDTH, 18.625]),
+            ("b", [A_WIDTH, 0.0, A_WIDTH, 30.0]),
+        ],
+    ),
+    (
+        "table cells are beside each other in their row",
+        "<table><tr><td id='a'>a</td><td id='b'>aa</td></tr></table>",
+        // Cells have 1px of padding.
+        &[
+            ("a", [0.0, 0.0, A_WIDTH + 2.0, 22.0]),
+            ("b", [A_WIDTH + 2.0, 0.0, 2.0 * A_WIDTH + 2.0, 22.0]),
+        ],
+    ),
+];
+
+/// https://www.w3.org/TR/CSS2/visuren.html#floats
+/// https://www.w3.org/TR/CSS2/visuren.html#flow-control
+const FLOATS: &[Test] = &[
+    (
+        "a left float is at the left of its containing block, and lines are shortened beside it",
+        "<div id='f' style='float: left; width: 100px; height: 50px'></div><span id='s'>a</span>",
+        &[
+            ("f", [0.0, 0.0, 100.0, 50.0]),
+            ("s", [100.0, 0.6875, A_WIDTH, 18.625]),
+        ],
+    ),
+    (
+        "a right float is at the right of its containing block",
+        "<div id='f' style='float: right; width: 100px; height: 10px'></div>\
+         <span id='s'>a</span>",
+        &[
+            ("f", [700.0, 0.0, 100.0, 10.0]),
+            ("s", [0.0, 0.6875, A_WIDTH, 18.625]),
+        ],
+    ),
+    (
+        "floats on the same side are placed next to each other",
+        "<div id='a' style='float: left; width: 100px; height: 10px'></div>\
+         <div id='b' style='float: left; width: 100px; height: 20px'></div>\
+         <div id='c' style='float: right; width: 100px; height: 10px'></div>",
+        &[
+            ("a", [0.0, 0.0, 100.0, 10.0]),
+            ("b", [100.0, 0.0, 100.0, 20.0]),
+            ("c", [700.0, 0.0, 100.0, 10.0]),
+        ],
+    ),
+    (
+        "a float which doesn't fit beside the floats before it moves below them",
+        "<div id='a' style='float: left; width: 500px; height: 10px'></div>\
+         <div id='b' style='float: left; width: 400px; height: 10px'></div>",
+        &[("b", [0.0, 10.0, 400.0, 10.0])],
+    ),
+    (
+        "a float's margins push it away from its containing block",
+        "<div id='f' style='float: right; margin: 10px; width: 100px; height: 10px'></div>",
+        &[("f", [690.0, 10.0, 100.0, 10.0])],
+    ),
+    (
+        "a float with an auto width shrinks to fit its content",
+        "<div id='f' style='float: left'>a</div>",
+        &[("f", [0.0, 0.0, A_WIDTH, 20.0])],
+    ),
+    (
+        "a float with an auto width is no wider than its containing block",
+        "<div style='width: 50px'><div id='f' style='float: left'>a a a a a a</div></div>",
+        &[("f", [0.0, 0.0, 50.0, 40.0])],
+    ),
+    (
+        "a float in a line is placed beside it, and the text before it moves along",
+        "<span id='a'>a</span><span id='f' style='float: left; width: 100px; height: 10px'>\
+         </span><span id='b'>a</span>",
+        &[
+            ("f", [0.0, 0.0, 100.0, 10.0]),
+            ("a", [100.0, 0.6875, A_WIDTH, 18.625]),
+        ],
+    ),
+    (
+        "a float which doesn't fit beside a line is placed below it",
+        "<div style='width: 100px'><span id='a'>a a a a a</span>\
+         <span id='f' style='float: left; width: 60px; height: 10px'></span>b</div>",
+        &[("f", [0.0, 20.0, 60.0, 10.0])],
+    ),
+    (
+        "a line which doesn't fit beside a float moves below it",
+        "<div id='f' style='float: left; width: 795px; height: 30px'></div>\
+         <span id='s'>a</span>",
+        &[("s", [0.0, 30.6875, A_WIDTH, 18.625])],
+    ),
+    (
+        "the blocks after a float are placed as if it wasn't there",
+        "<div id='f' style='float: left; width: 100px; height: 50px'></div>\
+         <div id='b' style='height: 10px'></div>",
+        &[
+            ("f", [0.0, 0.0, 100.0, 50.0]),
+            ("b", [0.0, 0.0, 800.0, 10.0]),
+        ],
+    ),
+    (
+        "a block which clears a float is moved below it",
+        "<div style='float: left; width: 100px; height: 50px'></div>\
+         <div id='c' style='clear: left; margin-top: 10px; height: 10px'></div>",
+        &[("c", [0.0, 50.0, 800.0, 10.0])],
+    ),
+    (
+        "a block which is below a float anyway has no clearance",
+        "<div style='float: left; width: 100px; height: 50px'></div>\
+         <div id='c' style='clear: both; margin-top: 60px; height: 10px'></div>",
+        &[("c", [0.0, 60.0, 800.0, 10.0])],
+    ),
+    (
+        "clearing the other side leaves a block where it is",
+        "<div style='float: left; width: 100px; height: 50px'></div>\
+         <div id='c' style='clear: right; height: 10px'></div>",
+        &[("c", [0.0, 0.0, 800.0, 10.0])],
+    ),
+    (
+        "a float which clears the floats before it is placed below them",
+        "<div style='float: left; width: 100px; height: 50px'></div>\
+         <div id='f' style='float: left; clear: left; width: 100px; height: 10px'></div>",
+        &[("f", [0.0, 50.0, 100.0, 10.0])],
+    ),
+    (
+        "a block's floats overflow it, unless it starts a new formatting context",
+        "<div id='a'><div style='float: left; width: 100px; height: 50px'></div></div>\
+         <div id='b' style='overflow: hidden; clear: left'>\
+         <div style='float: left; width: 100px; height: 50px'></div></div>\
+         <div id='c' style='display: flow-root'>\
+         <div style='float: left; width: 100px; height: 30px'></div></div>",
+        &[
+            ("a", [0.0, 0.0, 800.0, 0.0]),
+            ("b", [0.0, 50.0, 800.0, 50.0]),
+            ("c", [0.0, 100.0, 800.0, 30.0]),
+        ],
+    ),
+    (
+        "a block which starts a new formatting context is placed beside floats",
+        "<div style='float: left; width: 100px; height: 50px'></div>\
+         <div id='b' style='overflow: hidden; height: 10px'></div>",
+        &[("b", [100.0, 0.0, 700.0, 10.0])],
+    ),
+    (
+        "a cleared block in a container keeps the container's top where it was",
+        "<div id='p'><div style='float: left; width: 100px; height: 50px'></div>\
+         <div id='c' style='clear: both; height: 10px'></div></div>",
+        &[
+            ("p", [0.0, 0.0, 800.0, 60.0]),
+            ("c", [0.0, 50.0, 800.0, 10.0]),
+        ],
+    ),
+];
+
+/// https://www.w3.org/TR/CSS2/visuren.html#positioning-scheme
+/// https://www.w3.org/TR/CSS2/visudet.html#abs-non-replaced-width
+/// https://www.w3.org/TR/CSS2/visudet.html#abs-non-replaced-height
+const POSITIONING: &[Test] = &[
+    (
+        "a relatively positioned box is moved, but the boxes after it aren't",
+        "<div id='r' style='position: relative; left: 10px; top: 5px; height: 10px'></div>\
+         <div id='n' style='height: 10px'></div>",
+        &[
+            ("r", [10.0, 5.0, 800.0, 10.0]),
+            ("n", [0.0, 10.0, 800.0, 10.0]),
+        ],
+    ),
+    (
+        "a relatively positioned box is moved back by its right and bottom",
+        "<div id='r' style='position: relative; right: 10px; bottom: 5px; height: 10px'></div>",
+        &[("r", [-10.0, -5.0, 800.0, 10.0])],
+    ),
+    (
+        "a relatively positioned inline box is moved with its text",
+        "<span id='s' style='position: relative; left: 3px; top: 2px'>a</span>",
+        &[("s", [3.0, 2.6875, A_WIDTH, 18.625])],
+    ),
+    (
+        "an absolutely positioned box is placed in its containing block by its insets",
+        "<div style='position: relative; height: 100px'>\
+         <div id='a' style='position: absolute; right: 20px; bottom: 10px; \
+         width: 50px; height: 30px'></div></div>",
+        &[("a", [730.0, 60.0, 50.0, 30.0])],
+    ),
+    (
+        "an absolutely positioned box doesn't move the boxes after it",
+        "<div id='a' style='position: absolute; width: 50px; height: 30px'></div>\
+         <div id='n' style='height: 10px'></div>",
+        &[
+            ("a", [0.0, 0.0, 50.0, 30.0]),
+            ("n", [0.0, 0.0, 800.0, 10.0]),
+        ],
+    ),
+    (
+        "an absolutely positioned box with auto insets is at its static position",
+        "<div style='height: 10px'></div>\
+         <div id='a' style='position: absolute; width: 50px; height: 5px'></div>",
+        &[("a", [0.0, 10.0, 50.0, 5.0])],
+    ),
+    (
+        "an absolutely positioned box in a line has its static position on the line",
+        "<span>a</span><span id='a' style='position: absolute; width: 5px; height: 5px'></span>",
+        &[("a", [A_WIDTH, 0.0, 5.0, 5.0])],
+    ),
+    (
+        "an absolutely positioned box with an auto width shrinks to fit its content",
+        "<div id='a' style='position: absolute'>a</div>",
+        &[("a", [0.0, 0.0, A_WIDTH, 20.0])],
+    ),
+    (
+        "an auto width stretches between the left and right",
+        "<div id='a' style='position: absolute; left: 10px; right: 20px; height: 5px'></div>",
+        &[("a", [10.0, 0.0, 770.0, 5.0])],
+    ),
+    (
+        "auto margins centre an absolutely positioned box",
+        "<div style='position: relative; height: 100px'>\
+         <div id='a' style='position: absolute; inset: 0; margin: auto; \
+         width: 100px; height: 20px'></div></div>",
+        &[("a", [350.0, 40.0, 100.0, 20.0])],
+    ),
+    (
+        "the right inset of an overconstrained box is ignored",
+        "<div id='a' style='position: absolute; left: 10px; right: 10px; width: 100px; \
+         height: 5px'></div>",
+        &[("a", [10.0, 0.0, 100.0, 5.0])],
+    ),
+    (
+        "percentages are of the padding box of the containing block",
+        "<div style='position: relative; padding: 10px; height: 80px'>\
+         <div id='a' style='position: absolute; left: 0; top: 0; width: 50%; height: 50%'>\
+         </div></div>",
+        &[("a", [0.0, 0.0, 400.0, 50.0])],
+    ),
+    (
+        "the containing block is the nearest positioned ancestor, not the parent",
+        "<div style='position: relative; height: 100px'><div style='margin: 0 20px; height: 20px'>\
+         <div id='a' style='position: absolute; left: 5px; bottom: 5px; width: 10px; \
+         height: 10px'></div></div></div>",
+        &[("a", [5.0, 85.0, 10.0, 10.0])],
+    ),
+    (
+        "a fixed box is placed in the viewport",
+        "<div style='position: relative; height: 100px'>\
+         <div id='f' style='position: fixed; right: 0; bottom: 0; width: 10px; height: 10px'>\
+         </div></div>",
+        // The viewport is 600px tall, and the root's margin isn't part of the tests.
+        &[("f", [790.0, 580.0, 10.0, 10.0])],
+    ),
+    (
+        "a sticky box is moved to its inset from the top of the viewport",
+        "<div id='s' style='position: sticky; top: 30px; height: 10px'></div>\
+         <div style='height: 100px'></div>",
+        &[("s", [0.0, 20.0, 800.0, 10.0])],
+    ),
+    (
+        "a sticky box stays in its containing block",
+        "<div style='height: 20px'>\
+         <div id='s' style='position: sticky; top: 50px; height: 10px'></div></div>",
+        &[("s", [0.0, 10.0, 800.0, 10.0])],
+    ),
+    (
+        "a sticky box is moved to its inset from the bottom of its scroll container",
+        "<div style='overflow: hidden; height: 50px'><div style='height: 100px'>\
+         <div style='height: 60px'></div>\
+         <div id='s' style='position: sticky; bottom: 0; height: 10px'></div></div></div>",
+        &[("s", [0.0, 40.0, 800.0, 10.0])],
+    ),
+];
+
+const FLEX: &[Test] = &[
+    (
+        "flex items share the free space by their grow factors",
+        "<div style='display: flex; width: 300px'>\
+         <div id='a' style='flex: 1'></div><div id='b' style='width: 50px'></div>\
+         <div id='c' style='flex: 2; padding: 5px'></div></div>",
+        &[
+            ("a", [0.0, 0.0, 80.0, 10.0]),
+            ("b", [80.0, 0.0, 50.0, 10.0]),
+            ("c", [130.0, 0.0, 170.0, 10.0]),
+        ],
+    ),
+    (
+        "flex items shrink by their shrink factors and their base sizes",
+        "<div style='display: flex; width: 200px'>\
+         <div id='a' style='width: 100px'></div><div id='b' style='width: 300px'></div></div>",
+        &[("a", [0.0, 0.0, 50.0, 0.0]), ("b", [50.0, 0.0, 150.0, 0.0])],
+    ),
+    (
+        "a flex item doesn't shrink below its minimum content width",
+        "<div style='display: flex; width: 50px'>\
+         <div id='a'><div style='width: 40px'></div></div>\
+         <div id='b' style='width: 100px; overflow: hidden'></div></div>",
+        &[("a", [0.0, 0.0, 40.0, 0.0]), ("b", [40.0, 0.0, 10.0, 0.0])],
+    ),
+    (
+        "a flex item is kept between its minimum and maximum while it grows",
+        "<div style='display: flex; width: 300px'>\
+         <div id='a' style='flex-grow: 1; max-width: 50px'></div>\
+         <div id='b' style='flex-grow: 1'></div></div>",
+        &[("a", [0.0, 0.0, 50.0, 0.0]), ("b", [50.0, 0.0, 250.0, 0.0])],
+    ),
+    (
+        "flex items are stretched to the height of their line",
+        "<div style='display: flex'><div id='a' style='width: 10px'></div>\
+         <div id='b' style='width: 10px; height: 30px'></div></div>",
+        &[
+            ("a", [0.0, 0.0, 10.0, 30.0]),
+            ("b", [10.0, 0.0, 10.0, 30.0]),
+        ],
+    ),
+    (
+        "flex items wrap onto new lines, with gaps between them",
+        "<div style='display: flex; flex-wrap: wrap; width: 200px; gap: 10px 20px'>\
+         <div id='a' style='width: 80px; height: 10px'></div>\
+         <div id='b' style='width: 80px; height: 30px'></div>\
+         <div id='c' style='width: 80px; height: 20px'></div></div>",
+        &[
+            ("a", [0.0, 0.0, 80.0, 10.0]),
+            ("b", [100.0, 0.0, 80.0, 30.0]),
+            ("c", [0.0, 40.0, 80.0, 20.0]),
+        ],
+    ),
+    (
+        "flex items are laid out in the order their order property gives",
+        "<div style='display: flex'><div id='a' style='width: 10px'></div>\
+         <div id='b' style='width: 10px; order: -1'></div></div>",
+        &[("a", [10.0, 0.0, 10.0, 0.0]), ("b", [0.0, 0.0, 10.0, 0.0])],
+    ),
+    (
+        "justify-content distributes the free space between flex items",
+        "<div style='display: flex; width: 100px; justify-content: space-between'>\
+         <div id='a' style='width: 10px'></div><div id='b' style='width: 10px'></div>\
+         <div id='c' style='width: 10px'></div></div>",
+        &[
+            ("a", [0.0, 0.0, 10.0, 0.0]),
+            ("b", [45.0, 0.0, 10.0, 0.0]),
+            ("c", [90.0, 0.0, 10.0, 0.0]),
+        ],
+    ),
+    (
+        "auto margins take the free space before justify-content does",
+        "<div style='display: flex; width: 100px; height: 50px; justify-content: center'>\
+         <div id='a' style='width: 10px; height: 10px'></div>\
+         <div id='b' style='width: 10px; height: 10px; margin: auto'></div></div>",
+        &[
+            ("a", [0.0, 0.0, 10.0, 10.0]),
+            ("b", [50.0, 20.0, 10.0, 10.0]),
+        ],
+    ),
+    (
+        "align-items and align-self align flex items in their line",
+        "<div style='display: flex; height: 100px; align-items: center'>\
+         <div id='a' style='width: 50px; height: 20px'></div>\
+         <div id='b' style='width: 50px; height: 40px; align-self: flex-end'></div></div>",
+        &[
+            ("a", [0.0, 40.0, 50.0, 20.0]),
+            ("b", [50.0, 60.0, 50.0, 40.0]),
+        ],
+    ),
+    (
+        "flex items aligned by their baselines share one",
+        "<div style='display: flex; align-items: baseline'>\
+         <div id='a' style='padding-top: 10px'>a</div><div id='b'>a</div></div>",
+        &[
+            ("a", [0.0, 0.0, A_WIDTH, 30.0]),
+            ("b", [A_WIDTH, 10.0, A_WIDTH, 20.0]),
+        ],
+    ),
+    (
+        "align-content packs the lines of a flex container",
+        "<div style='display: flex; flex-wrap: wrap; width: 100px; height: 100px; \
+         align-content: center'>\
+         <div id='a' style='width: 60px; height: 10px'></div>\
+         <div id='b' style='width: 60px; height: 20px'></div></div>",
+        &[
+            ("a", [0.0, 35.0, 60.0, 10.0]),
+            ("b", [0.0, 45.0, 60.0, 20.0]),
+        ],
+    ),
+    (
+        "a column of flex items is as tall as its items, which fill its width",
+        "<div id='f' style='display: flex; flex-direction: column'>\
+         <div id='a' style='height: 20px'></div>\
+         <div id='b' style='height: 30px; width: 40px; align-self: center'></div></div>",
+        &[
+            ("f", [0.0, 0.0, 800.0, 50.0]),
+            ("a", [0.0, 0.0, 800.0, 20.0]),
+            ("b", [380.0, 20.0, 40.0, 30.0]),
+        ],
+    ),
+    (
+        "reversed directions lay flex items out from the end",
+        "<div style='display: flex; flex-direction: row-reverse; width: 100px'>\
+         <div id='a' style='width: 10px'></div><div id='b' style='width: 20px'></div></div>\
+         <div style='display: flex; flex-wrap: wrap-reverse; width: 100px'>\
+         <div id='c' style='width: 60px; height: 10px'></div>\
+         <div id='d' style='width: 60px; height: 20px'></div></div>",
+        &[
+            ("a", [90.0, 0.0, 10.0, 0.0]),
+            ("b", [70.0, 0.0, 20.0, 0.0]),
+            ("c", [0.0, 20.0, 60.0, 10.0]),
+            ("d", [0.0, 0.0, 60.0, 20.0]),
+        ],
+    ),
+    (
+        "the children of a flex container are blocks, and its text is in anonymous items",
+        "<div style='display: flex'><span id='s' style='width: 20px'>a</span>a</div>",
+        &[("s", [0.0, 0.0, 20.0, 20.0])],
+    ),
+    (
+        "a floated flex container is as wide as its items side by side",
+        "<div id='f' style='float: left; display: flex; column-gap: 5px'>\
+         <div style='width: 20px'></div><div style='width: 30px'></div></div>",
+        &[("f", [0.0, 0.0, 55.0, 0.0])],
+    ),
+];
+
+const GRID: &[Test] = &[
+    (
+        "fr tracks share the space left by fixed tracks and gaps",
+        "<div style='display: grid; width: 380px; grid-template-columns: 100px 1fr 2fr; \
+         grid-auto-rows: 30px; gap: 10px 20px'>\
+         <div id='a'></div><div id='b'></div><div id='c'></div><div id='d'></div></div>",
+        &[
+            ("a", [0.0, 0.0, 100.0, 30.0]),
+            ("b", [120.0, 0.0, 80.0, 30.0]),
+            ("c", [220.0, 0.0, 160.0, 30.0]),
+            ("d", [0.0, 40.0, 100.0, 30.0]),
+        ],
+    ),
+    (
+        "a minmax() track is at least its minimum, and an fr track isn't smaller than its content",
+        "<div style='display: grid; width: 100px; grid-template-columns: minmax(80px, 1fr) 1fr'>\
+         <div id='a'></div><div id='b'><div style='width: 40px'></div></div></div>",
+        &[("a", [0.0, 0.0, 80.0, 0.0]), ("b", [80.0, 0.0, 40.0, 0.0])],
+    ),
+    (
+        "repeat() with auto-fill makes as many tracks as fit",
+        "<div style='display: grid; width: 330px; grid-template-columns: repeat(auto-fill, 100px); \
+         grid-auto-rows: 10px'><div></div><div></div><div id='a'></div><div id='b'></div></div>",
+        &[("a", [200.0, 0.0, 100.0, 10.0]), ("b", [0.0, 10.0, 100.0, 10.0])],
+    ),
+    (
+        "repeat() with auto-fit collapses the empty tracks",
+        "<div style='display: grid; width: 330px; \
+         grid-template-columns: repeat(auto-fit, minmax(100px, 1fr))'>\
+         <div id='a'></div><div id='b'></div></div>",
+        &[("a", [0.0, 0.0, 165.0, 0.0]), ("b", [165.0, 0.0, 165.0, 0.0])],
+    ),
+    (
+        "items are placed by line numbers, negative numbers and spans",
+        "<div style='display: grid; grid-template-columns: repeat(3, 50px); grid-auto-rows: 10px'>\
+         <div id='a' style='grid-column: 2 / -1'></div>\
+         <div id='b' style='grid-row: 2 / span 2; grid-column: 3'></div></div>",
+        &[("a", [50.0, 0.0, 100.0, 10.0]), ("b", [100.0, 10.0, 50.0, 20.0])],
+    ),
+    (
+        "items are placed by named lines, counting from either end",
+        "<div style='display: grid; grid-template-columns: [x] 50px [y] 50px [x] 50px [y]; \
+         grid-auto-rows: 10px'>\
+         <div id='a' style='grid-column: x 2 / y 2'></div>\
+         <div id='b' style='grid-column: span y / 3'></div>\
+         <div id='c' style='grid-column: x / y -1'></div></div>",
+        &[
+            ("a", [100.0, 0.0, 50.0, 10.0]),
+            ("b", [50.0, 10.0, 50.0, 10.0]),
+            ("c", [0.0, 20.0, 150.0, 10.0]),
+        ],
+    ),
+    (
+        "items are placed in named areas",
+        "<div style='display: grid; grid-template-areas: \"h h\" \"s m\"; \
+         grid-template-columns: 200px 1fr; grid-template-rows: 50px 30px'>\
+         <div id='m' style='grid-area: m'></div><div id='h' style='grid-area: h'></div>\
+         <div id='s' style='grid-column: s-start; grid-row: s'></div></div>",
+        &[
+            ("h", [0.0, 0.0, 800.0, 50.0]),
+            ("s", [0.0, 50.0, 200.0, 30.0]),
+            ("m", [200.0, 50.0, 600.0, 30.0]),
+        ],
+    ),
+    (
+        "auto-placed items skip occupied cells, and fill holes when dense",
+        "<div style='display: grid; grid-template-columns: repeat(3, 50px); grid-auto-rows: 10px'>\
+         <div style='grid-column: span 2'></div><div id='a' style='grid-column: span 2'></div>\
+         <div id='b'></div></div>\
+         <div style='display: grid; grid-template-columns: repeat(3, 50px); grid-auto-rows: 10px; \
+         grid-auto-flow: dense'>\
+         <div style='grid-column: span 2'></div><div style='grid-column: span 2'></div>\
+         <div id='c'></div></div>",
+        &[
+            ("a", [0.0, 10.0, 100.0, 10.0]),
+            ("b", [100.0, 10.0, 50.0, 10.0]),
+            ("c", [100.0, 20.0, 50.0, 10.0]),
+        ],
+    ),
+    (
+        "a column flow fills columns, making implicit columns",
+        "<div style='display: grid; grid-auto-flow: column; grid-template-rows: 10px 10px; \
+         grid-auto-columns: 40px'><div></div><div id='a'></div><div id='b'></div></div>",
+        &[("a", [0.0, 10.0, 40.0, 10.0]), ("b", [40.0, 0.0, 40.0, 10.0])],
+    ),
+    (
+        "implicit tracks are made before the explicit grid for lines before it",
+        "<div style='display: grid; grid-template-columns: 100px; grid-auto-columns: 30px'>\
+         <div id='a' style='grid-column: -3; height: 10px'></div><div id='b'></div></div>",
+        &[("a", [0.0, 0.0, 30.0, 10.0]), ("b", [30.0, 0.0, 100.0, 10.0])],
+    ),
+    (
+        "auto tracks fit their content and stretch into the space left",
+        "<div style='display: grid; width: 300px; grid-template-columns: auto auto'>\
+         <div id='a'><div style='width: 40px; height: 20px'></div></div>\
+         <div id='b'><div style='width: 60px'></div></div></div>",
+        &[("a", [0.0, 0.0, 140.0, 20.0]), ("b", [140.0, 0.0, 160.0, 20.0])],
+    ),
+    (
+        "the tracks are aligned in the container, and the items in their areas",
+        "<div style='display: grid; width: 200px; height: 100px; \
+         grid-template-columns: 50px 50px; grid-template-rows: 40px; \
+         justify-content: space-between; align-content: center; align-items: end'>\
+         <div id='a' style='height: 10px'></div>\
+         <div id='b' style='width: 20px; justify-self: center; align-self: stretch'></div>\
+         <div id='c' style='width: 10px; height: 10px; margin: auto'></div></div>",
+        &[
+            ("a", [0.0, 55.0, 50.0, 10.0]),
+            ("b", [165.0, 25.0, 20.0, 40.0]),
+            ("c", [20.0, 65.0, 10.0, 10.0]),
+        ],
+    ),
+    (
+        "a floated grid container is as wide as its columns",
+        "<div id='f' style='float: left; display: grid; column-gap: 5px; \
+         grid-template-columns: 20px auto fit-content(10px)'>\
+         <div></div><div style='width: 30px'></div><div style='width: 40px'></div></div>",
+        &[("f", [0.0, 0.0, 100.0, 0.0])],
+    ),
+];
+
+/// https://www.w3.org/TR/CSS2/visuren.html#inline-formatting
+const INLINE: &[Test] = &[
+    (
+        "text wraps at a space once the line is full, and a word wider than a line overflows it",
+        "<div style='width: 30px'><span id='a'>a</span> <span id='b'>a</span> \
+         <span id='c'>aaaa</span></div>",
+        // A space is a little over half as wide as an "a".
+        &[
+            ("a", [0.0, 0.6875, A_WIDTH, 18.625]),
+            ("b", [A_WIDTH + 5.0859375, 0.6875, A_WIDTH, 18.625]),
+            ("c", [0.0, 20.6875, 4.0 * A_WIDTH, 18.625]),
+        ],
+    ),
+    (
+        "spaces at the end of a line hang past it, so aren't aligned",
+        "<div style='width: 100px; text-align: right; white-space: pre-wrap'>\
+         <span id='a'>a   </span></div>",
+        &[("a", [100.0 - A_WIDTH, 0.6875, A_WIDTH, 18.625])],
+    ),
+    (
+        "text-align: justify stretches the spaces of every line but the last",
+        "<div style='width: 50px; text-align: justify'><span id='a'>a</span> \
+         <span id='b'>a</span> <span id='c'>aaa</span></div>",
+        &[
+            ("b", [50.0 - A_WIDTH, 0.6875, A_WIDTH, 18.625]),
+            ("c", [0.0, 20.6875, 3.0 * A_WIDTH, 18.625]),
+        ],
+    ),
+    (
+        "text-align: center centers the line in the block",
+        "<div style='width: 100px; text-align: center'><span id='a'>a</span></div>",
+        &[("a", [(100.0 - A_WIDTH) / 2.0, 0.6875, A_WIDTH, 18.625])],
+    ),
+    (
+        "a raised box makes the line taller, above the baseline",
+        "<div><span id='a'>a</span><span id='b' style='vertical-align: 10px'>a</span></div>",
+        &[
+            ("a", [0.0, 10.6875, A_WIDTH, 18.625]),
+            ("b", [A_WIDTH, 0.6875, A_WIDTH, 18.625]),
+        ],
+    ),
+    (
+        "a box aligned to the top of the line makes it taller below",
+        "<div id='d'><span id='a'>a</span>\
+         <span id='b' style='vertical-align: top; line-height: 40px'>a</span></div>",
+        &[
+            ("d", [0.0, 0.0, 800.0, 40.0]),
+            ("a", [0.0, 0.6875, A_WIDTH, 18.625]),
+            ("b", [A_WIDTH, 10.6875, A_WIDTH, 18.625]),
+        ],
+    ),
+    (
+        "an inline-block is placed in the line, with the bottom of an empty one on the baseline",
+        "<div><span id='a'>a</span>\
+         <span id='b' style='display: inline-block; width: 30px; height: 40px'></span></div>",
+        &[
+            ("a", [0.0, 25.148438, A_WIDTH, 18.625]),
+            ("b", [A_WIDTH, 0.0, 30.0, 40.0]),
+        ],
+    ),
+    (
+        "an inline-block shrinks to fit its content, and its last line is on the baseline",
+        "<div><span id='a'>a</span><span id='b' style='display: inline-block'>aa<br>a</span></div>",
+        &[
+            ("a", [0.0, 20.6875, A_WIDTH, 18.625]),
+            ("b", [A_WIDTH, 0.0, 2.0 * A_WIDTH, 40.0]),
+        ],
+    ),
+    (
+        "an inline-block which doesn't fit on the line wraps as a whole",
+        "<div style='width: 50px'>aaaa \
+         <span id='b' style='display: inline-block; width: 20px; height: 10px'></span></div>",
+        &[("b", [0.0, 25.539063, 20.0, 10.0])],
+    ),
+    (
+        "a <br> ends the line, and a <br> after it makes an empty line",
+        "<span id='a'>a</span><br><span id='b'>a</span><br><br><span id='c'>a</span>",
+        &[
+            ("b", [0.0, 20.6875, A_WIDTH, 18.625]),
+            ("c", [0.0, 60.6875, A_WIDTH, 18.625]),
+        ],
+    ),
+    (
+        "white-space: normal collapses spaces and newlines into one space",
+        "<span id='a'>a</span>  \n  <span id='b'>a</span>",
+        &[("b", [A_WIDTH + 5.0859375, 0.6875, A_WIDTH, 18.625])],
+    ),
+    (
+        "white-space: nowrap doesn't wrap lines",
+        "<div style='width: 10px; white-space: nowrap'><span>a</span> <span id='b'>a</span></div>",
+        &[("b", [A_WIDTH + 5.0859375, 0.6875, A_WIDTH, 18.625])],
+    ),
+    (
+        "white-space: pre keeps spaces, and breaks lines at newlines",
+        "<div style='white-space: pre'><span id='a'>a  \n</span><span id='b'>a</span></div>",
+        &[
+            ("a", [0.0, 0.6875, A_WIDTH + 2.0 * 5.0859375, 18.625]),
+            ("b", [0.0, 20.6875, A_WIDTH, 18.625]),
+        ],
+    ),
+    (
+        "white-space: pre-line collapses spaces, but breaks lines at newlines",
+        "<div style='white-space: pre-line'><span>a</span>   <span id='b'>a</span>\n\
+         <span id='c'>a</span></div>",
+        &[
+            ("b", [A_WIDTH + 5.0859375, 0.6875, A_WIDTH, 18.625]),
+            ("c", [0.0, 20.6875, A_WIDTH, 18.625]),
+        ],
+    ),
+];
+
+/// Only the default font, DejaVu Sans, is loaded.
+/// https://www.w3.org/TR/css-fonts-4/#font-matching-algorithm
+const TEXT: &[Test] = &[
+    (
+        "a family which isn't loaded falls back to the next family",
+        "<span id='a' style='font-family: Missing, sans-serif'>a</span>",
+        &[("a", [0.0, 0.6875, A_WIDTH, 18.625])],
+    ),
+    (
+        "a weight without a face of its own uses the nearest face",
+        "<span id='a' style='font-weight: 900'>a</span>",
+        &[("a", [0.0, 0.6875, A_WIDTH, 18.625])],
+    ),
+    (
+        "a character which no font has is the missing glyph of the first font",
+        "<span id='a'>中文</span>",
+        &[("a", [0.0, 0.6875, 2.0 * 9.6015625, 18.625])],
+    ),
+    (
+        "kerning moves pairs of glyphs closer together",
+        // "A" and "V" are 10.9453125px wide on their own.
+        "<span id='a'>AV</span>",
+        &[("a", [0.0, 0.6875, 20.867188, 18.625])],
+    ),
+    (
+        "a ligature replaces a sequence of glyphs",
+        // "f" is 5.6328125px wide and "i" is 4.4453125px wide on their own.
+        "<span id='a'>ffi</span>",
+        &[("a", [0.0, 0.6875, 15.46875, 18.625])],
+    ),
+    (
+        "glyphs are scaled by the font size, and the line is made tall enough for them",
+        "<span id='a' style='font-size: 32px'>a</span>",
+        &[("a", [0.0, -8.625, 2.0 * A_WIDTH, 37.25])],
+    ),
+    (
+        "line-height: normal is the ascent, descent and line gap of the first font",
+        "<div id='a' style='line-height: normal'>a</div>\
+         <div id='b' style='line-height: normal; font-size: 32px'>a</div>",
+        &[
+            ("a", [0.0, 0.0, 800.0, 18.625]),
+            ("b", [0.0, 18.625, 800.0, 37.25]),
+        ],
+    ),
+];
+
+/// A paint order test: its name, the body of the document, and the ids of the elements in the
+/// order they're painted. The boxes of elements without ids aren't checked.
+type PaintTest = (&'static str, &'static str, &'static [&'static str]);
+
+/// https://www.w3.org/TR/CSS2/zindex.html
+const PAINT_ORDER: &[PaintTest] = &[
+    (
+        "blocks are painted, then floats, then inline content, then positioned boxes by z-index",
+        "<div id='a' style='position: relative; z-index: 2'></div>\
+         <div id='b' style='position: relative; z-index: -1'></div>\
+         <div id='c'></div><div id='d' style='float: left'></div><span id='e'>a</span>\
+         <div id='f' style='position: absolute'></div>\
+         <div id='g' style='position: relative; z-index: 0'></div>\
+         <div id='h' style='position: relative; z-index: 1'></div>",
+        &["b", "c", "d", "e", "f", "g", "h", "a"],
+    ),
+    (
+        "an inline-block is painted with the inline content, and its blocks with it",
+        "<div id='a'><span id='b'>a</span><span id='c' style='display: inline-block'>\
+         <div id='d'></div><span id='e'>a</span></span></div>",
+        &["a", "b", "c", "d", "e"],
+    ),
+    (
+        "the boxes of a stacking context are painted together",
+        "<div id='a' style='position: relative; z-index: 1'>\
+         <div id='b' style='position: relative; z-index: 10'></div></div>\
+         <div id='c' style='position: relative; z-index: 2'></div>",
+        &["a", "b", "c"],
+    ),
+    (
+        "the positioned boxes in a box with z-index: auto are in the stacking context it's in",
+        "<div id='a' style='position: relative'><div id='b' style='position: relative; \
+         z-index: -1'></div><div id='c'></div></div><div id='d'></div>",
+        &["b", "d", "a", "c"],
+    ),
+    (
+        "flex items with a z-index start a stacking context",
+        "<div style='display: flex'><div id='a' style='z-index: 1'></div><div id='b'></div></div>",
+        &["b", "a"],
+    ),
+    (
+        "a transparent box starts a stacking context",
+        "<div id='a' style='opacity: 0.5'><div id='b' style='position: relative'></div></div>\
+         <div id='c' style='position: relative'></div>",
+        &["a", "b", "c"],
+    ),
+];
+
+/// Returns the Some id of the element a box was generated for, or None if it has none.
+fn id<'a>(b: &LayoutBox<'a>) -> Option<&'a str> {
+    match &b.style_node()?.node.node_type {
+        NodeType::Element(e) => e.id().map(String::as_str),
+        NodeType::Text(_) => None,
+    }
+}
+
+/// Returns the Some box of the element with an id, or None if there isn't one. The outside
+/// marker of a list item is found by its id and ::marker. Eg: li::marker
+fn find<'a, 'b>(b: &'b LayoutBox<'a>, id: &str) -> Option<&'b LayoutBox<'a>> {
+    if let Some(id) = id.strip_suffix("::marker") {
+        return find(b, id)?.marker.as_deref();
+    }
+    match self::id(b) == Some(id) {
+        true => Some(b),
+        false => b.children.iter().find_map(|c| find(c, id)),
+    }
+}
+
+/// Lays out a document with a body, returning the Some result of `f` with the root box, or None if
+/// the root wasn't laid out.
+fn with_layout<T>(body: &str, f: impl FnOnce(&LayoutBox) -> T) -> Option<T> {
+    let html = format!(
+        "<html style='margin-top: 10px'><head><style>{CSS}</style></head><body>{body}</body></html>"
+    );
+    let document = Document::parse(html, "", &FileLoader::new("."));
+    let styled = document.style(Viewport::default());
+    let root = layout_tree(&styled, Dimensions::of_size(800.0, 600.0))?;
+    Some(f(&root))
+}
+
+/// Returns the Some differences between the laid out and expected boxes, or None if they match.
+fn run((_, body, expected): &Test) -> Option<String> {
+    with_layout(body, |root| differences(root, expected))
+        .unwrap_or_else(|| Some("The root wasn't laid out".to_string()))
+}
+
+/// Returns the Some differences between the boxes in `root` and the `expected` boxes, or None if
+/// they match.
+fn differences(root: &LayoutBox, expected: &[(&str, [f32; 4])]) -> Option<String> {
+    let mut errors = Vec::new();
+    for (id, [x, y, width, height]) in expected.iter() {
+        // The root's margin moves everything down, but isn't part of the tests.
+        let expected = Rect {
+            x: *x,
+            y: y + 10.0,
+            width: *width,
+            height: *height,
+        };
+        match find(root, id) {
+            None => errors.push(format!("#{id} wasn't laid out")),
+            Some(b) if b.border_box() != expected => {
+                errors.push(format!("#{id} is {:?}, not {:?}", b.border_box(), expected))
+            }
+            _ => {}
+        }
+    }
+    (!errors.is_empty()).then(|| errors.join("\n    "))
+}
+
+/// Returns the Some difference between the order the boxes are painted in and the expected order,
+/// or None if they match.
+fn run_paint_order((_, body, expected): &PaintTest) -> Option<String> {
+    let order = with_layout(body, |root| {
+        let order = root.paint_order().into_iter().filter_map(id);
+        order.map(str::to_string).collect::<Vec<_>>()
+    });
+    match order {
+        None => Some("The root wasn't laid out".to_string()),
+        Some(order) if order != *expected => {
+            Some(format!("{order:?} are painted, not {expected:?}"))
+        }
+        _ => None,
+    }
+}
+
+/// Runs a suite of tests, and panics with the differences if any of them fail.
+fn check(tests: &[Test]) {
+    let failures = tests
+        .iter()
+        .filter_map(|test| run(test).map(|errors| format!("{}\n    {errors}", test.0)));
+    report(failures.collect(), tests.len());
+}
+
+/// Panics with the `failures` of a suite of `count` tests, if there are any.
+fn report(failures: Vec<String>, count: usize) {
+    if !failures.is_empty() {
+        panic!(
+            "{} of {} tests failed:\n  {}",
+            failures.len(),
+            count,
+            failures.join("\n  ")
+        );
+    }
+}
+
+#[test]
+fn margin_collapsing() {
+    check(MARGIN_COLLAPSING);
+}
+
+#[test]
+fn sizes() {
+    check(SIZES);
+}
+
+#[test]
+fn box_generation() {
+    check(BOX_GENERATION);
+}
+
+#[test]
+fn floats() {
+    check(FLOATS);
+}
+
+#[test]
+fn positioning() {
+    check(POSITIONING);
+}
+
+#[test]
+fn flex() {
+    check(FLEX);
+}
+
+#[test]
+fn grid() {
+    check(GRID);
+}
+
+#[test]
+fn inline() {
+    check(INLINE);
+}
+
+#[test]
+fn text() {
+    check(TEXT);
+}
+
+#[test]
+fn paint_order() {
+    let failures = PAINT_ORDER
+        .iter()
+        .filter_map(|test| run_paint_order(test).map(|error| format!("{}\n    {error}", test.0)));
+    report(failures.collect(), PAINT_ORDER.len());
+}
@@ -0,0 +1,189 @@
+// Checks that incremental restyles give the same styles as styling from scratch, after random
+// changes to a document.
+
+use parsnip::dom::{Node, NodeType};
+use parsnip::parsing::css_parser::CssParser;
+use parsnip::parsing::html_parser::HtmlParser;
+use parsnip::style::css::{StyledNode, Stylesheet};
+use parsnip::style::style_tree::{style_tree, style_tree_unoptimized, Styler};
+use parsnip::style::values::Viewport;
+
+const PAGE: &str = "<html><body>\
+    <div class=\"section\" id=\"intro\"><h1 class=\"title\">Title</h1><p>Some <em>text</em></p></div>\
+    <div class=\"section wide\"><ul><li class=\"item\">One</li><li class=\"item first\">Two</li>\
+    <li class=\"item\">Three<ul><li>Nested</li></ul></li></ul><p class=\"note\">Note</p></div>\
+    <ol><li>A</li><li>B</li><li data-state=\"done\">C</li></ol>\
+    <div class=\"section\"><h2>Heading</h2><p class=\"note\">More</p><p>Last</p></div>\
+    </body></html>";
+
+const CSS: &str = "
+    .section { margin: 1em; counter-reset: para; }
+    .section p { counter-increment: para; }
+    .section p::before { content: counter(para) \". \"; }
+    .wide { font-size: 20px; }
+    #intro .title { color: red; }
+    #main > p { padding: 2px; }
+    .item + .item { margin-top: 4px; }
+    h2 ~ .note { font-weight: bold; }
+    .first ~ li { color: green; }
+    [data-state] { text-decoration-line: line-through; }
+    [data-state=\"done\"] { color: gray; }
+    .hidden { display: none; }
+    .big .note { font-size: 2rem; }
+    p::first-line { color: blue; }
+    @media (min-width: 600px) { .item { margin-left: 1em; } }
+    .wide { --accent: orange; } .note { border-color: var(--accent, black); }
+";
+
+/// The stylesheets added part way through the changes.
+const ADDED_CSS: [&str; 2] = [
+    ".note { color: purple; } li:first-line { color: red; }",
+    "div > ul li { padding-left: 1em; } .extra ~ p { margin: 0; }",
+];
+
+const CLASSES: [&str; 8] = [
+    "section", "wide", "item", "first", "note", "hidden", "big", "extra",
+];
+
+/// A small pseudo-random number generator, so runs are repeatable.
+struct Random(u64);
+
+impl Random {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((self.0 >> 33) as usize) % n
+    }
+}
+
+/// Returns the paths of every element in the document, as the indices of children to follow.
+fn element_paths(node: &Node, path: Vec<usize>, paths: &mut Vec<Vec<usize>>) {
+    for (i, c) in node.children.iter().enumerate() {
+        if let NodeType::Element(_) = c.node_type {
+            let mut child = path.clone();
+            child.push(i);
+            element_paths(c, child.clone(), paths);
+            paths.push(child);
+        }
+    }
+}
+
+fn node_at<'a>(root: &'a mut Node, path: &[usize]) -> &'a mut Node {
+    path.iter().fold(root, |node, &i| &mut node.children[i])
+}
+
+/// Makes a random change to an element. The same change is made to both documents.
+fn change(documents: [&mut Node; 2], random: &mut Random) -> String {
+    let mut paths = Vec::new();
+    element_paths(documents[0], Vec::new(), &mut paths);
+    let path = &paths[random.below(paths.len())];
+    let kind = random.below(7);
+    let class = CLASSES[random.below(CLASSES.len())];
+    let index = random.below(4);
+
+    for root in documents {
+        let node = node_at(root, path);
+        match kind {
+            0 => node.set_attr("class", class),
+            1 => {
+                node.remove_attr("class");
+            }
+            2 => node.set_attr("id", ["intro", "main"][index % 2]),
+            3 => node.set_attr("data-state", ["done", "open"][index % 2]),
+            4 => node.set_attr(
+                "style",
+                &format!("font-size: {}px; --accent: red", 10 + index),
+            ),
+            5 => {
+                let html = format!("<p class=\"{class}\">New <em>node</em></p>");
+                node.insert_child(index.min(node.children.len()), HtmlParser::parse(html));
+            }
+            _ => {
+                if !node.children.is_empty() {
+                    node.remove_child(index % node.children.len());
+                }
+            }
+        }
+    }
+    format!("change {kind} at {path:?}")
+}
+
+/// Returns the Some path of the first node whose style differs, or None if the trees match.
+fn difference(a: &StyledNode, b: &StyledNode) -> Option<String> {
+    let same = a.pseudo == b.pseudo
+        && a.generated_text == b.generated_text
+        && a.specified_values == b.specified_values
+        && a.style.values == b.style.values
+        && a.children.len() == b.children.len()
+        && a.pseudo_styles.len() == b.pseudo_styles.len()
+        && a.pseudo_styles
+            .iter()
+            .all(|(p, s)| b.pseudo_styles.get(p).is_some_and(|t| s.values == t.values));
+    if !same {
+        return Some(format!("{} ({:?})", a.node.node_type, a.pseudo));
+    }
+
+    a.children
+        .iter()
+        .zip(&b.children)
+        .find_map(|(a, b)| difference(a, b))
+}
+
+#[test]
+fn incremental_restyles_match_full_restyles() {
+    let mut incremental = HtmlParser::parse(PAGE.to_string());
+    let mut reference = HtmlParser::parse(PAGE.to_string());
+    let mut random = Random(7);
+
+    let mut styler = Styler::new(vec![CssParser::parse(CSS.to_string())], Viewport::default());
+    let mut sheets: Vec<Stylesheet> = vec![CssParser::parse(CSS.to_string())];
+    let mut viewport = Viewport::default();
+    styler.style(&incremental);
+
+    let changes = 500;
+    for step in 0..changes {
+        let description = match step {
+            100 | 300 => {
+                let css = ADDED_CSS[step / 200];
+                styler.add_stylesheet(CssParser::parse(css.to_string()));
+                sheets.push(CssParser::parse(css.to_string()));
+                format!("added stylesheet {css:?}")
+            }
+            200 => {
+                viewport.width /= 2.0;
+                styler.set_viewport(viewport);
+                "resized the viewport".to_string()
+            }
+            _ => change([&mut incremental, &mut reference], &mut random),
+        };
+
+        let styled = styler.style(&incremental);
+        let expected = style_tree(&reference, &sheets, viewport);
+
+        if let Some(node) = difference(&styled, &expected) {
+            panic!("Styles differ at {node} after step {step}: {description}");
+        }
+    }
+}
+
+#[test]
+fn optimized_styles_match_unoptimized_styles() {
+    let mut documents = [PAGE, PAGE].map(|p| HtmlParser::parse(p.to_string()));
+    let sheets: Vec<Stylesheet> = [CSS, ADDED_CSS[0], ADDED_CSS[1]]
+        .map(|css| CssParser::parse(css.to_string()))
+        .into();
+    let mut random = Random(11);
+
+    for step in 0..100 {
+        let [a, b] = &mut documents;
+        let description = change([a, b], &mut random);
+
+        let styled = style_tree(&documents[0], &sheets, Viewport::default());
+        let expected = style_tree_unoptimized(&documents[0], &sheets, Viewport::default());
+        if let Some(node) = difference(&styled, &expected) {
+            panic!("Styles differ at {node} after step {step}: {description}");
+        }
+    }
+}